@@ -0,0 +1,197 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Error};
+use chip8::core::audio::{self, AudioSink, Scope, Synth};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    BufferSize, Device, FromSample, SampleFormat, SampleRate, SizedSample, Stream, StreamConfig,
+    SupportedBufferSize, SupportedStreamConfig,
+};
+use shared::config::config::Audio;
+use tracing::{info, warn};
+
+/// cpal buzzer for the frontends without SDL. The stream plays on the default output device
+/// for as long as the buzzer lives, its callback shares the synth with the emulation loop.
+pub struct CpalBuzzer {
+    synth: Arc<Mutex<Synth>>,
+    scope: Scope,
+    _stream: Stream,
+    active: bool,
+    volume: u8,
+    muted: bool,
+}
+
+impl CpalBuzzer {
+    pub fn new(settings: &Audio) -> Result<Self, Error> {
+        let device = output_device(settings)?;
+        let supported = output_config(&device, settings)?;
+        let format = supported.sample_format();
+        let buffer_size = match supported.buffer_size() {
+            SupportedBufferSize::Range { min, max } => {
+                BufferSize::Fixed(u32::from(settings.buffer_size).clamp(*min, *max))
+            }
+            SupportedBufferSize::Unknown => BufferSize::Default,
+        };
+        let config = StreamConfig {
+            buffer_size,
+            ..supported.into()
+        };
+        info!(
+            "Opened audio device {} at {} Hz with {} channels and {:?} buffers",
+            device.name().unwrap_or_default(),
+            config.sample_rate.0,
+            config.channels,
+            config.buffer_size
+        );
+
+        let synth = Synth::new(settings, config.sample_rate.0);
+        let scope = synth.scope().clone();
+        let synth = Arc::new(Mutex::new(synth));
+        let stream = match format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, &synth),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, &synth),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, &synth),
+            format => Err(anyhow!("Unsupported audio sample format {:?}", format)),
+        }?;
+        stream
+            .play()
+            .map_err(|e| anyhow!("Failed to start the audio stream: {}", e))?;
+
+        Ok(Self {
+            synth,
+            scope,
+            _stream: stream,
+            active: false,
+            volume: settings.volume.min(100),
+            muted: false,
+        })
+    }
+
+    fn with_synth<T>(&self, f: impl FnOnce(&mut Synth) -> T) -> Option<T> {
+        // A poisoned lock means the callback panicked, the stream is silent from then on
+        self.synth.lock().ok().map(|mut synth| f(&mut synth))
+    }
+
+    /// Fades the stream to the current volume, or to silence while muted.
+    fn update_gain(&mut self) {
+        let gain = audio::gain(self.volume, self.muted);
+        self.with_synth(|synth| synth.set_gain(gain));
+    }
+}
+
+/// Finds the configured output device by name, or the system default.
+fn output_device(settings: &Audio) -> Result<Device, Error> {
+    let host = cpal::default_host();
+    match &settings.device {
+        Some(name) => host
+            .output_devices()
+            .map_err(|e| anyhow!("Failed to list audio output devices: {}", e))?
+            .find(|device| device.name().is_ok_and(|device_name| device_name == *name))
+            .ok_or_else(|| anyhow!("No audio output device named '{}'", name)),
+        None => host
+            .default_output_device()
+            .ok_or_else(|| anyhow!("No audio output device available")),
+    }
+}
+
+/// Picks a config playing at the configured sample rate in a supported sample format, or
+/// the device default when there is none.
+fn output_config(device: &Device, settings: &Audio) -> Result<SupportedStreamConfig, Error> {
+    let sample_rate = SampleRate(settings.sample_rate);
+    let matching = device
+        .supported_output_configs()
+        .map_err(|e| anyhow!("Failed to query the audio output configs: {}", e))?
+        .filter(|range| {
+            matches!(
+                range.sample_format(),
+                SampleFormat::F32 | SampleFormat::I16 | SampleFormat::U16
+            )
+        })
+        .find_map(|range| range.try_with_sample_rate(sample_rate));
+    match matching {
+        Some(config) => Ok(config),
+        None => {
+            warn!(
+                "The audio device does not play at {} Hz, using its default rate",
+                settings.sample_rate
+            );
+            device
+                .default_output_config()
+                .map_err(|e| anyhow!("Failed to query the audio output config: {}", e))
+        }
+    }
+}
+
+/// Opens an output stream of `T` samples, copying the mono synth output to every channel.
+fn build_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    synth: &Arc<Mutex<Synth>>,
+) -> Result<Stream, Error>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let synth = Arc::clone(synth);
+    let mut mono = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                mono.resize(data.len() / channels, 0.0);
+                match synth.lock() {
+                    Ok(mut synth) => synth.fill(&mut mono),
+                    Err(_) => mono.fill(0.0),
+                }
+                for (frame, &sample) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            |e| warn!("Audio stream error: {}", e),
+            None,
+        )
+        .map_err(|e| anyhow!("Failed to open the audio stream: {}", e))
+}
+
+impl AudioSink for CpalBuzzer {
+    fn set_beep(&mut self, on: bool) {
+        if on != self.active {
+            self.with_synth(|synth| synth.set_beep(on));
+            self.active = on;
+        }
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.with_synth(|synth| synth.push_samples(samples));
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        info!("Volume set to {}", self.volume);
+        self.update_gain();
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        info!("Sound muted: {}", self.muted);
+        self.update_gain();
+    }
+
+    fn scope(&self) -> Option<Scope> {
+        Some(self.scope.clone())
+    }
+
+    fn cycle_waveform(&mut self) {
+        if let Some(waveform) = self.with_synth(Synth::cycle_waveform) {
+            info!("Switched buzzer waveform to {:?}", waveform);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+/*
+ * C interface to the CHIP-8 emulator core, implemented by the chip8-ffi crate
+ * (libchip8_ffi.so, chip8_ffi.dll or libchip8_ffi.a).
+ *
+ * The host owns the loop: call chip8_step_frame 60 times per second, draw the
+ * screen from chip8_get_display_ptr and forward the keypad with chip8_set_key.
+ * Functions returning int give 0 on success and -1 on failure, with the reason
+ * in chip8_last_error. An instance must only be used by one thread at a time.
+ */
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CHIP8_DISPLAY_WIDTH 64
+#define CHIP8_DISPLAY_HEIGHT 32
+
+typedef struct Chip8 Chip8;
+
+/* Creates an emulator with nothing loaded, to free with chip8_destroy. */
+Chip8 *chip8_create(void);
+/* Frees an emulator, NULL is ignored. */
+void chip8_destroy(Chip8 *chip8);
+
+/* Loads a ROM and starts it from power-on. */
+int chip8_load_rom(Chip8 *chip8, const uint8_t *rom, size_t len);
+/* Seeds the RNG of CXNN now and for every ROM loaded later. */
+void chip8_set_seed(Chip8 *chip8, uint64_t seed);
+/* Runs one 60Hz frame of cycles_per_frame instructions, 10 being about the
+ * original speed, and counts the timers down once. */
+int chip8_step_frame(Chip8 *chip8, uint32_t cycles_per_frame);
+
+/* CHIP8_DISPLAY_WIDTH x CHIP8_DISPLAY_HEIGHT bytes row by row, the color index
+ * of each pixel from 0 (off) to 3. Valid until chip8_destroy, the bytes change
+ * with every call that runs the emulator. */
+const uint8_t *chip8_get_display_ptr(const Chip8 *chip8);
+/* Presses or releases keypad key 0 to 15. */
+int chip8_set_key(Chip8 *chip8, uint8_t key, bool pressed);
+/* Whether the buzzer sounds this frame. */
+bool chip8_is_buzzing(const Chip8 *chip8);
+
+/* Bytes chip8_save_state needs for the loaded ROM. */
+size_t chip8_state_size(const Chip8 *chip8);
+/* Writes the machine and its ROM into buffer, returns the bytes written or -1
+ * when the buffer is smaller than chip8_state_size. */
+ptrdiff_t chip8_save_state(Chip8 *chip8, uint8_t *buffer, size_t len);
+/* Continues from a state written by chip8_save_state, a state that fails to
+ * load leaves the emulator as it was. */
+int chip8_load_state(Chip8 *chip8, const uint8_t *buffer, size_t len);
+
+/* Why the last failed call failed, owned by the emulator until its next call.
+ * NULL after a successful call. */
+const char *chip8_last_error(const Chip8 *chip8);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHIP8_H */
@@ -0,0 +1,266 @@
+//! C interface to the CHIP-8 core for C, C++ and C# frontends and game engines, declared in
+//! `include/chip8.h`. The host owns the loop: it calls `chip8_step_frame` 60 times per
+//! second, draws the screen from `chip8_get_display_ptr` and forwards the keypad with
+//! `chip8_set_key`.
+//!
+//! Functions returning `int` give 0 on success and -1 on failure, with the reason left in
+//! `chip8_last_error`. Panics never cross the boundary, they fail the call the same way.
+
+use std::ffi::{c_char, c_int, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::state::SaveState;
+
+/// An emulator instance, opaque to the host.
+pub struct Chip8 {
+    emulator: Emulator,
+    cpu: CpuController,
+    // Set by `chip8_set_seed`, random otherwise
+    seed: Option<u64>,
+    error: Option<CString>,
+}
+
+impl Chip8 {
+    /// Runs `f`, recording its error or panic for `chip8_last_error`.
+    fn call(&mut self, f: impl FnOnce(&mut Self) -> Result<(), Error>) -> c_int {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(self))).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Err(anyhow!("Emulator panicked: {}", message))
+        });
+        match result {
+            Ok(()) => {
+                self.error = None;
+                0
+            }
+            Err(e) => {
+                // Interior NULs would cut the message short, drop them
+                let message = format!("{:#}", e).replace('\0', "");
+                self.error = CString::new(message).ok();
+                -1
+            }
+        }
+    }
+}
+
+/// Reads `len` bytes at `data`, an empty slice for a null pointer.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if data.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+/// Creates an emulator with nothing loaded, to free with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8 {
+    let instance = Chip8 {
+        emulator: Emulator::new(CHIP8::default()),
+        cpu: CpuController,
+        seed: None,
+        error: None,
+    };
+    Box::into_raw(Box::new(instance))
+}
+
+/// Frees an emulator, null is ignored.
+///
+/// # Safety
+///
+/// `chip8` must be null or come from `chip8_create` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(chip8: *mut Chip8) {
+    if !chip8.is_null() {
+        drop(Box::from_raw(chip8));
+    }
+}
+
+/// Loads the `len` byte ROM at `rom` and starts it from power-on, keeping the quirks.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create` and `rom` point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(chip8: *mut Chip8, rom: *const u8, len: usize) -> c_int {
+    let Some(chip8) = chip8.as_mut() else {
+        return -1;
+    };
+    let rom = bytes(rom, len);
+    chip8.call(|chip8| {
+        let mut emulator = Emulator::new(CHIP8::default());
+        if let Some(seed) = chip8.seed {
+            emulator.set_seed(seed);
+        }
+        emulator.set_quirks(chip8.emulator.quirks());
+        emulator.init_ram_from_bytes(rom)?;
+        chip8.emulator = emulator;
+        Ok(())
+    })
+}
+
+/// Seeds the RNG of `CXNN` now and for every ROM loaded later, so runs given the same input
+/// are the same.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_seed(chip8: *mut Chip8, seed: u64) {
+    if let Some(chip8) = chip8.as_mut() {
+        chip8.seed = Some(seed);
+        chip8.emulator.set_seed(seed);
+    }
+}
+
+/// Runs one 60Hz frame of `cycles_per_frame` instructions, 10 being about the original
+/// speed, and counts the timers down once.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step_frame(chip8: *mut Chip8, cycles_per_frame: u32) -> c_int {
+    let Some(chip8) = chip8.as_mut() else {
+        return -1;
+    };
+    chip8.call(|chip8| chip8.cpu.step_frame(&mut chip8.emulator, cycles_per_frame))
+}
+
+/// The screen, `CHIP8_DISPLAY_WIDTH` x `CHIP8_DISPLAY_HEIGHT` bytes row by row, each the
+/// color index of a pixel from 0 (off) to 3. The pointer stays valid until `chip8_destroy`,
+/// the bytes change with every call that runs the emulator.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_get_display_ptr(chip8: *const Chip8) -> *const u8 {
+    match chip8.as_ref() {
+        // The screen lives inside the boxed instance, so it never moves
+        Some(chip8) => chip8.emulator.get_display().as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Presses or releases keypad key `key`, 0 to 15.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(chip8: *mut Chip8, key: u8, pressed: bool) -> c_int {
+    let Some(chip8) = chip8.as_mut() else {
+        return -1;
+    };
+    chip8.call(|chip8| {
+        if pressed {
+            chip8.emulator.key_press(key)
+        } else {
+            chip8.emulator.key_release(key)
+        }
+    })
+}
+
+/// Whether the buzzer sounds this frame, for the host's audio.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_is_buzzing(chip8: *const Chip8) -> bool {
+    chip8
+        .as_ref()
+        .is_some_and(|chip8| chip8.emulator.is_buzzing())
+}
+
+/// Bytes `chip8_save_state` needs for the loaded ROM.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_state_size(chip8: *const Chip8) -> usize {
+    chip8.as_ref().map_or(0, |chip8| {
+        SaveState::len_for(chip8.emulator.get_rom().len())
+    })
+}
+
+/// Writes the machine and its ROM into `buffer`, which holds `len` bytes and must be at
+/// least `chip8_state_size` long. Returns the bytes written, or -1.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create` and `buffer` point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(chip8: *mut Chip8, buffer: *mut u8, len: usize) -> isize {
+    let Some(chip8) = chip8.as_mut() else {
+        return -1;
+    };
+    let state = chip8.emulator.save_state().to_bytes();
+    let result = chip8.call(|_| {
+        if buffer.is_null() || len < state.len() {
+            return Err(anyhow!(
+                "The save state needs {} bytes, the buffer holds {}",
+                state.len(),
+                len
+            ));
+        }
+        slice::from_raw_parts_mut(buffer, state.len()).copy_from_slice(&state);
+        Ok(())
+    });
+    if result == 0 {
+        state.len() as isize
+    } else {
+        -1
+    }
+}
+
+/// Continues from a state written by `chip8_save_state`, `len` bytes at `buffer`. A state
+/// that fails to load leaves the emulator as it was.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create` and `buffer` point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(
+    chip8: *mut Chip8,
+    buffer: *const u8,
+    len: usize,
+) -> c_int {
+    let Some(chip8) = chip8.as_mut() else {
+        return -1;
+    };
+    let state = bytes(buffer, len);
+    chip8.call(|chip8| {
+        chip8.emulator.load_state(SaveState::from_bytes(state)?);
+        Ok(())
+    })
+}
+
+/// Why the last failed call failed, NUL-terminated and owned by the emulator until its next
+/// call. Null after a successful call.
+///
+/// # Safety
+///
+/// `chip8` must come from `chip8_create`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_last_error(chip8: *const Chip8) -> *const c_char {
+    chip8
+        .as_ref()
+        .and_then(|chip8| chip8.error.as_ref())
+        .map_or(ptr::null(), |error| error.as_ptr())
+}
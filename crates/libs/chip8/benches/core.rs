@@ -0,0 +1,93 @@
+use std::hint::black_box;
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::dynarec::DynarecMode;
+use chip8::core::emulator::Emulator;
+use chip8::core::instruction::Instruction;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const BRIX: &[u8] = include_bytes!("../../../../roms/BRIX");
+
+fn emulator(program: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(program).unwrap();
+    emulator.set_seed(0);
+    emulator
+}
+
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+
+    group.throughput(Throughput::Elements(1 << 16));
+    group.bench_function("decode_all_words", |b| {
+        b.iter(|| {
+            for word in 0..=u16::MAX {
+                black_box(Instruction::decode(black_box(word)));
+            }
+        })
+    });
+
+    // Arithmetic on registers in a loop, so ticks are mostly fetch and dispatch
+    let program = [
+        0x70, 0x01, 0x81, 0x04, 0x82, 0x13, 0x83, 0x26, 0xF1, 0x1E, 0x12, 0x00,
+    ];
+    let mut emulator = emulator(&program);
+    let cpu = CpuController;
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("tick", |b| {
+        b.iter(|| cpu.tick(black_box(&mut emulator)).unwrap())
+    });
+    group.finish();
+}
+
+fn sprites(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dxyn");
+    let mut emulator = emulator(&[]);
+    emulator.set_i(0x000);
+    // One sprite inside the screen and one spilling over the right and bottom edges
+    for (name, x, y) in [("inside", 8, 8), ("wrapping", 60, 28)] {
+        emulator.set_v(0, x).unwrap();
+        emulator.set_v(1, y).unwrap();
+        for rows in [1u8, 5, 15] {
+            group.bench_with_input(BenchmarkId::new(name, rows), &rows, |b, &rows| {
+                b.iter(|| {
+                    Instruction::OpDXYN(0, 1, rows)
+                        .call(black_box(&mut emulator))
+                        .unwrap()
+                })
+            });
+        }
+    }
+    emulator.set_plane_mask(0b11).unwrap();
+    group.bench_function("both_planes/15", |b| {
+        b.iter(|| {
+            Instruction::OpDXYN(0, 1, 15)
+                .call(black_box(&mut emulator))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn frames(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step_frame");
+    let cpu = CpuController;
+    for (name, mode) in [
+        ("brix", DynarecMode::Off),
+        ("brix_dynarec", DynarecMode::On),
+    ] {
+        for cycles in [10u32, 100, 1000] {
+            let mut emulator = emulator(BRIX);
+            emulator.set_dynarec(mode);
+            group.throughput(Throughput::Elements(cycles as u64));
+            group.bench_with_input(BenchmarkId::new(name, cycles), &cycles, |b, &cycles| {
+                b.iter(|| cpu.step_frame(black_box(&mut emulator), cycles).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dispatch, sprites, frames);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+#![no_main]
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::emulator::Emulator;
+use chip8::core::instruction::Instruction;
+use libfuzzer_sys::fuzz_target;
+
+// Decodes and executes single instructions on a machine with random registers, which may
+// fail with an error but never panic.
+fuzz_target!(|input: (u16, [u8; 16], u16, u16, u8)| {
+    let (word, registers, i, pc, sp_calls) = input;
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.set_seed(0);
+    for (x, &value) in registers.iter().enumerate() {
+        emulator.set_v(x as u8, value).unwrap();
+    }
+    emulator.set_i(i);
+    emulator.set_pc(pc);
+    for _ in 0..sp_calls % 20 {
+        let _ = emulator.stack_push(pc);
+    }
+    let Some(instruction) = Instruction::decode(word) else {
+        return;
+    };
+    let _ = instruction.to_string();
+    let _ = instruction.call(&mut emulator);
+});
@@ -0,0 +1,40 @@
+#![no_main]
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::replay::Quirks;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    rom: Vec<u8>,
+    keys: u16,
+    use_vy: bool,
+    change_i: bool,
+    frames: u8,
+}
+
+// Runs random RAM images for a few frames, every failure has to surface as an error.
+fuzz_target!(|input: Input| {
+    let mut emulator = Emulator::new(CHIP8::default());
+    if emulator.init_ram_from_bytes(&input.rom).is_err() {
+        return;
+    }
+    emulator.set_seed(0);
+    emulator.set_quirks(Quirks {
+        bit_shift_instructions_use_vy: input.use_vy,
+        store_read_instructions_change_i: input.change_i,
+        ..Quirks::default()
+    });
+    for key in (0..16).filter(|key| input.keys & 1 << key != 0) {
+        emulator.key_press(key).unwrap();
+    }
+    let cpu = CpuController;
+    for _ in 0..input.frames % 16 {
+        if cpu.step_frame(&mut emulator, 32).is_err() {
+            break;
+        }
+    }
+});
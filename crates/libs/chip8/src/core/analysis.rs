@@ -0,0 +1,91 @@
+use std::collections::BTreeSet;
+
+use super::instruction::Instruction;
+
+/// Address the ROM is loaded at and execution starts from.
+pub const START_ADDR: u16 = 0x200;
+
+/// Which bytes of a ROM hold reachable instructions, the rest being sprites, tables or
+/// padding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeMap {
+    /// Addresses of the reachable instructions
+    pub instructions: BTreeSet<u16>,
+}
+
+/// The opcode of `rom`, as loaded at 0x200, starting at `address`.
+pub fn word_at(rom: &[u8], address: u16) -> Option<u16> {
+    let offset = address.checked_sub(START_ADDR)? as usize;
+    Some(u16::from_be_bytes([
+        *rom.get(offset)?,
+        *rom.get(offset + 1)?,
+    ]))
+}
+
+/// The instruction of `rom`, as loaded at 0x200, starting at `address`.
+pub fn instruction_at(rom: &[u8], address: u16) -> Option<Instruction> {
+    word_at(rom, address).and_then(Instruction::decode)
+}
+
+/// Where execution can go after `instruction` at `address`: the next instruction, both sides
+/// of a skip, a jump target, or a call target and the return site. A `JP V0` goes to its base
+/// address and the jumps or calls directly following it, the usual jump table, as the value
+/// of V0 is not known without running the ROM.
+pub fn successors(rom: &[u8], address: u16, instruction: &Instruction) -> Vec<u16> {
+    let next = address.wrapping_add(2);
+    match *instruction {
+        Instruction::Op00EE => Vec::new(),
+        Instruction::Op1NNN(addr) => vec![addr],
+        Instruction::Op2NNN(addr) => vec![next, addr],
+        Instruction::OpBNNN(addr) => {
+            // V0 is at most 0xFF, so a table holds up to 128 entries
+            let table = (1..128)
+                .map(|entry| addr.wrapping_add(entry * 2))
+                .take_while(|&entry| {
+                    matches!(
+                        instruction_at(rom, entry),
+                        Some(Instruction::Op1NNN(_) | Instruction::Op2NNN(_))
+                    )
+                });
+            std::iter::once(addr).chain(table).collect()
+        }
+        Instruction::Op3XNN(..)
+        | Instruction::Op4XNN(..)
+        | Instruction::Op5XY0(..)
+        | Instruction::Op9XY0(..)
+        | Instruction::OpEX9E(_)
+        | Instruction::OpEXA1(_) => vec![next, next.wrapping_add(2)],
+        _ => vec![next],
+    }
+}
+
+impl CodeMap {
+    /// Follows every path execution can take from 0x200 through `rom`, see `successors`.
+    /// Paths stop at opcodes that do not decode and at addresses outside the ROM; code the ROM
+    /// writes at run time is not seen.
+    pub fn new(rom: &[u8]) -> Self {
+        let mut instructions = BTreeSet::new();
+        let mut pending = vec![START_ADDR];
+        while let Some(address) = pending.pop() {
+            if instructions.contains(&address) {
+                continue;
+            }
+            let Some(instruction) = instruction_at(rom, address) else {
+                continue;
+            };
+            instructions.insert(address);
+            pending.extend(successors(rom, address, &instruction));
+        }
+        Self { instructions }
+    }
+
+    /// Whether a reachable instruction starts at `address`.
+    pub fn is_instruction(&self, address: u16) -> bool {
+        self.instructions.contains(&address)
+    }
+
+    /// Whether `address` is one of the two bytes of a reachable instruction.
+    pub fn is_code(&self, address: u16) -> bool {
+        self.is_instruction(address) || self.is_instruction(address.wrapping_sub(1))
+    }
+}
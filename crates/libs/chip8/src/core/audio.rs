@@ -0,0 +1,394 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use shared::config::config::{Audio, Waveform};
+
+use super::controller::Hotkey;
+use tracing::info;
+
+/// Peak amplitude of the generated samples, leaves headroom below full scale.
+const AMPLITUDE: f32 = 0.25;
+
+/// Time a volume change takes to settle, long enough to avoid audible clicks.
+const FADE_SECONDS: f32 = 0.01;
+
+/// Longest backlog of pushed samples, older ones are dropped so sound keeps up with the game.
+const MAX_QUEUED_SECONDS: f32 = 0.25;
+
+/// Length of the signal kept for the oscilloscope overlay.
+const SCOPE_SECONDS: f32 = 0.1;
+
+/// Volume steps of the volume hotkeys.
+pub const VOLUME_STEP: u8 = 10;
+
+/// An audio output driven by the emulation loop, implemented by every sound backend.
+pub trait AudioSink {
+    /// Starts or stops the buzzer tone, called every frame with the sound timer state.
+    fn set_beep(&mut self, on: bool);
+
+    /// Queues mono samples at the sink's sample rate, mixed over the buzzer tone. Sinks
+    /// without a sample stream ignore them.
+    fn push_samples(&mut self, _samples: &[f32]) {}
+
+    /// Called after every emulated 60Hz frame, sinks rendering audio offline produce one
+    /// frame of samples here.
+    fn end_frame(&mut self) {}
+
+    /// Master volume from 0 to 100.
+    fn volume(&self) -> u8;
+
+    fn set_volume(&mut self, volume: u8);
+
+    fn is_muted(&self) -> bool;
+
+    fn set_muted(&mut self, muted: bool);
+
+    /// Switches to the next buzzer waveform, ignored by sinks with a fixed tone.
+    fn cycle_waveform(&mut self) {}
+
+    /// Last generated samples for the oscilloscope overlay, `None` by default.
+    fn scope(&self) -> Option<Scope> {
+        None
+    }
+
+    /// Applies the waveform, volume and mute hotkeys, others are ignored.
+    fn handle_hotkey(&mut self, hotkey: Hotkey) {
+        match hotkey {
+            Hotkey::CycleWaveform => self.cycle_waveform(),
+            Hotkey::VolumeUp => self.set_volume(self.volume().saturating_add(VOLUME_STEP)),
+            Hotkey::VolumeDown => self.set_volume(self.volume().saturating_sub(VOLUME_STEP)),
+            Hotkey::ToggleMute => self.set_muted(!self.is_muted()),
+            _ => {}
+        }
+    }
+}
+
+/// Sink of frontends running without sound.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Silence;
+
+impl AudioSink for Silence {
+    fn set_beep(&mut self, _on: bool) {}
+
+    fn volume(&self) -> u8 {
+        0
+    }
+
+    fn set_volume(&mut self, _volume: u8) {}
+
+    fn is_muted(&self) -> bool {
+        true
+    }
+
+    fn set_muted(&mut self, _muted: bool) {}
+}
+
+/// A sound timer activation seen by `SoundLog`, in emulated 60Hz frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundEvent {
+    /// Frame the buzzer started on, counted from the first emulated frame.
+    pub frame: u64,
+    /// Frames the buzzer sounded for so far.
+    pub duration: u64,
+}
+
+/// Silent sink logging every buzzer activation instead of playing it, so headless runs can
+/// check that a game beeped when expected.
+#[derive(Debug, Clone)]
+pub struct SoundLog {
+    frame: u64,
+    beeping: bool,
+    events: Vec<SoundEvent>,
+    volume: u8,
+    muted: bool,
+}
+
+impl SoundLog {
+    pub fn new(volume: u8) -> Self {
+        Self {
+            frame: 0,
+            beeping: false,
+            events: Vec::new(),
+            volume,
+            muted: false,
+        }
+    }
+
+    /// Activations so far, oldest first.
+    pub fn events(&self) -> &[SoundEvent] {
+        &self.events
+    }
+
+    /// Emulated frames seen so far.
+    pub fn frames(&self) -> u64 {
+        self.frame
+    }
+}
+
+impl AudioSink for SoundLog {
+    fn set_beep(&mut self, on: bool) {
+        if on == self.beeping {
+            return;
+        }
+        self.beeping = on;
+        if on {
+            self.events.push(SoundEvent {
+                frame: self.frame,
+                duration: 0,
+            });
+        } else if let Some(event) = self.events.last() {
+            info!(
+                "Sound at frame {} lasted {} frames",
+                event.frame, event.duration
+            );
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.frame += 1;
+        if self.beeping {
+            if let Some(event) = self.events.last_mut() {
+                event.duration += 1;
+            }
+        }
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+}
+
+/// Buzzer tone generator. Produces mono `f32` samples for any audio backend, the phase is
+/// kept between calls so the tone is continuous across buffers and waveform switches.
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    waveform: Waveform,
+    phase: f32,
+    phase_inc: f32,
+    duty_cycle: f32,
+    // xorshift state and the level held for the current period of the noise waveform
+    noise_state: u32,
+    noise_level: f32,
+}
+
+impl Oscillator {
+    pub fn new(waveform: Waveform, frequency: f32, duty_cycle: f32, sample_rate: u32) -> Self {
+        Self {
+            waveform,
+            phase: 0.0,
+            phase_inc: frequency / sample_rate as f32,
+            duty_cycle: duty_cycle.clamp(0.0, 1.0),
+            noise_state: 0x2545_F491,
+            noise_level: AMPLITUDE,
+        }
+    }
+
+    pub fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        let sample = match self.waveform {
+            Waveform::Square => {
+                if self.phase < self.duty_cycle {
+                    AMPLITUDE
+                } else {
+                    -AMPLITUDE
+                }
+            }
+            Waveform::Sine => AMPLITUDE * (self.phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => AMPLITUDE * (1.0 - 4.0 * (self.phase - 0.5).abs()),
+            Waveform::Noise => self.noise_level,
+        };
+        self.phase += self.phase_inc;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.next_noise_level();
+        }
+        sample
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = self.next_sample();
+        }
+    }
+
+    /// Picks a random level once per period, so the noise keeps the configured pitch.
+    fn next_noise_level(&mut self) {
+        let mut x = self.noise_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.noise_state = x;
+        self.noise_level = (x as f32 / u32::MAX as f32 * 2.0 - 1.0) * AMPLITUDE;
+    }
+}
+
+/// Gain applied to generated samples, ramping linearly to its target so volume changes and
+/// muting don't click.
+#[derive(Debug, Clone)]
+pub struct Fader {
+    current: f32,
+    target: f32,
+    step: f32,
+}
+
+impl Fader {
+    pub fn new(gain: f32, sample_rate: u32) -> Self {
+        Self {
+            current: gain,
+            target: gain,
+            step: 1.0 / (FADE_SECONDS * sample_rate as f32),
+        }
+    }
+
+    pub fn set_target(&mut self, gain: f32) {
+        self.target = gain.clamp(0.0, 1.0);
+    }
+
+    pub fn apply(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            if self.current < self.target {
+                self.current = (self.current + self.step).min(self.target);
+            } else if self.current > self.target {
+                self.current = (self.current - self.step).max(self.target);
+            }
+            *sample *= self.current;
+        }
+    }
+}
+
+/// The last `SCOPE_SECONDS` of generated audio, before the master volume. Clones share the
+/// buffer, so an audio callback can feed it while a display draws it.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    capacity: usize,
+}
+
+impl Scope {
+    pub fn new(sample_rate: u32) -> Self {
+        let capacity = (SCOPE_SECONDS * sample_rate as f32) as usize;
+        Self {
+            samples: Arc::new(Mutex::new(VecDeque::from(vec![0.0; capacity]))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, samples: &[f32]) {
+        if let Ok(mut buffer) = self.samples.lock() {
+            buffer.extend(samples);
+            let excess = buffer.len().saturating_sub(self.capacity);
+            buffer.drain(..excess);
+        }
+    }
+
+    /// Copies the kept signal, oldest sample first, into `out`.
+    pub fn snapshot(&self, out: &mut Vec<f32>) {
+        out.clear();
+        if let Ok(buffer) = self.samples.lock() {
+            out.extend(buffer.iter());
+        }
+    }
+}
+
+/// Sample generator of the callback driven backends: the buzzer tone while beeping mixed with
+/// the pushed samples, at the master gain.
+#[derive(Debug, Clone)]
+pub struct Synth {
+    wave: Oscillator,
+    fader: Fader,
+    beep: bool,
+    queue: VecDeque<f32>,
+    max_queued: usize,
+    scope: Scope,
+}
+
+impl Synth {
+    pub fn new(settings: &Audio, sample_rate: u32) -> Self {
+        Self {
+            wave: Oscillator::new(
+                settings.waveform,
+                settings.frequency,
+                settings.duty_cycle,
+                sample_rate,
+            ),
+            fader: Fader::new(volume_gain(settings.volume), sample_rate),
+            beep: false,
+            queue: VecDeque::new(),
+            max_queued: (MAX_QUEUED_SECONDS * sample_rate as f32) as usize,
+            scope: Scope::new(sample_rate),
+        }
+    }
+
+    pub fn set_beep(&mut self, on: bool) {
+        self.beep = on;
+    }
+
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        self.queue.extend(samples);
+        let excess = self.queue.len().saturating_sub(self.max_queued);
+        self.queue.drain(..excess);
+    }
+
+    /// Switches to the next waveform and returns it.
+    pub fn cycle_waveform(&mut self) -> Waveform {
+        let waveform = self.wave.waveform().next();
+        self.wave.set_waveform(waveform);
+        waveform
+    }
+
+    /// Fades the output to `gain`, e.g. the result of `gain(volume, muted)`.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.fader.set_target(gain);
+    }
+
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            let tone = if self.beep {
+                self.wave.next_sample()
+            } else {
+                0.0
+            };
+            *sample = tone + self.queue.pop_front().unwrap_or(0.0);
+        }
+        self.scope.push(out);
+        self.fader.apply(out);
+    }
+
+    pub fn scope(&self) -> &Scope {
+        &self.scope
+    }
+}
+
+/// Gain of a sink at `volume`, silent while muted.
+pub fn gain(volume: u8, muted: bool) -> f32 {
+    if muted {
+        0.0
+    } else {
+        volume_gain(volume)
+    }
+}
+
+/// Converts a 0-100 volume to a gain, squared so the steps sound even.
+pub fn volume_gain(volume: u8) -> f32 {
+    let level = volume.min(100) as f32 / 100.0;
+    level * level
+}
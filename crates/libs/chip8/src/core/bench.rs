@@ -0,0 +1,285 @@
+use std::fmt::Write as _;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error};
+
+use super::chip8::{Framebuffer, CHIP8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::cpu::CpuController;
+#[cfg(feature = "dynarec")]
+use super::dynarec::DynarecMode;
+use super::emulator::Emulator;
+use super::instruction::Instruction;
+
+/// Runs of each configuration, the fastest one is kept.
+pub const ROUNDS: usize = 3;
+/// Sprites drawn by the framebuffer configurations.
+const SPRITES: u64 = 200_000;
+
+/// Settings of a `desktop bench` run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchSettings {
+    /// Emulated 60 Hz frames run per configuration
+    pub frames: u32,
+    pub cycles_per_frame: Vec<u32>,
+}
+
+impl Default for BenchSettings {
+    fn default() -> Self {
+        Self {
+            frames: 3600,
+            cycles_per_frame: vec![10, 100, 1000],
+        }
+    }
+}
+
+/// How instructions are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    /// Fetches, decodes and dispatches every instruction
+    Interpreter,
+    /// `CpuController::step_frame`, dispatching the instructions decoded before by address
+    CachedDecode,
+    #[cfg(feature = "dynarec")]
+    Dynarec,
+}
+
+impl Engine {
+    const ALL: &'static [Engine] = &[
+        Engine::Interpreter,
+        Engine::CachedDecode,
+        #[cfg(feature = "dynarec")]
+        Engine::Dynarec,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Engine::Interpreter => "interpreter",
+            Engine::CachedDecode => "cached decode",
+            #[cfg(feature = "dynarec")]
+            Engine::Dynarec => "block compiler",
+        }
+    }
+}
+
+/// The time a configuration took for its work, the fastest of `ROUNDS` runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    /// What `count` counts, e.g. instructions
+    pub unit: &'static str,
+    pub count: u64,
+    pub elapsed: Duration,
+    /// Time of the first configuration doing the same work, the speedup is measured against it
+    pub baseline: Duration,
+}
+
+impl Measurement {
+    pub fn nanos_each(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.count.max(1) as f64
+    }
+
+    pub fn speedup(&self) -> f64 {
+        self.baseline.as_secs_f64() / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+}
+
+/// Runs `rom` headlessly under every engine at every `cycles_per_frame`, then draws sprites
+/// a pixel at a time into a byte per pixel and a row at a time into packed rows. Fails when
+/// an engine ends in another machine state than the interpreter or the layouts disagree on
+/// the collisions, so the numbers compare the same work.
+pub fn run(rom: &[u8], settings: &BenchSettings) -> Result<Vec<Measurement>, Error> {
+    let mut measurements = Vec::new();
+    for &cycles in &settings.cycles_per_frame {
+        let mut baseline = None;
+        let mut expected = None;
+        for &engine in Engine::ALL {
+            let mut elapsed = Duration::MAX;
+            let mut state = 0;
+            for _ in 0..ROUNDS {
+                let (time, hash) = run_engine(rom, engine, settings.frames, cycles)?;
+                elapsed = elapsed.min(time);
+                state = hash;
+            }
+            if *expected.get_or_insert(state) != state {
+                return Err(anyhow!(
+                    "The {} ended in another state than the interpreter at {} cycles per frame",
+                    engine.label(),
+                    cycles
+                ));
+            }
+            measurements.push(Measurement {
+                name: format!("{}, {} cycles per frame", engine.label(), cycles),
+                unit: "instruction",
+                count: settings.frames as u64 * cycles as u64,
+                elapsed,
+                baseline: *baseline.get_or_insert(elapsed),
+            });
+        }
+    }
+
+    let sprites = sprites();
+    let (mut baseline, mut expected) = (None, None);
+    for (name, draw) in [
+        (
+            "sprites, a pixel at a time",
+            draw_pixels as fn(&[Sprite]) -> u64,
+        ),
+        ("sprites, packed rows", draw_rows),
+    ] {
+        let mut elapsed = Duration::MAX;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            let collisions = black_box(draw(&sprites));
+            elapsed = elapsed.min(start.elapsed());
+            if *expected.get_or_insert(collisions) != collisions {
+                return Err(anyhow!(
+                    "Drawing {} found {} collisions instead of {}",
+                    name,
+                    collisions,
+                    expected.unwrap_or_default()
+                ));
+            }
+        }
+        measurements.push(Measurement {
+            name: name.to_string(),
+            unit: "sprite",
+            count: SPRITES,
+            elapsed,
+            baseline: *baseline.get_or_insert(elapsed),
+        });
+    }
+    Ok(measurements)
+}
+
+/// Runs `frames` frames of `rom` from power-on, returning the time taken and the final
+/// `Emulator::state_hash`.
+fn run_engine(
+    rom: &[u8],
+    engine: Engine,
+    frames: u32,
+    cycles: u32,
+) -> Result<(Duration, u64), Error> {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(rom)?;
+    emulator.set_seed(0);
+    #[cfg(feature = "dynarec")]
+    if engine == Engine::Dynarec {
+        emulator.set_dynarec(DynarecMode::On);
+    }
+    let cpu = CpuController;
+    let start = Instant::now();
+    for _ in 0..frames {
+        match engine {
+            Engine::Interpreter => {
+                // `CpuController::tick` and `step_frame` but decoding every word again
+                for _ in 0..cycles {
+                    if emulator.at_breakpoint() {
+                        break;
+                    }
+                    let pc = emulator.get_pc();
+                    emulator.apply_cheats();
+                    let word = cpu.fetch(&mut emulator)?;
+                    emulator.record_instruction(pc, word);
+                    Instruction::decode(word)
+                        .ok_or_else(|| {
+                            anyhow!("Unsupported instruction {:04X} at {:#05X}", word, pc)
+                        })?
+                        .call(&mut emulator)?;
+                    emulator.notify_instruction(pc, word);
+                }
+                emulator.dec_all_timers();
+                emulator.advance_frame();
+                emulator.notify_frame();
+            }
+            _ => cpu.step_frame(&mut emulator, cycles)?,
+        }
+    }
+    Ok((start.elapsed(), emulator.state_hash()))
+}
+
+/// A sprite row and where it lands.
+struct Sprite {
+    byte: u8,
+    x: usize,
+    y: usize,
+}
+
+/// `SPRITES` rows of the hex digit glyphs over the whole screen, many of them wrapping
+/// around the edges.
+fn sprites() -> Vec<Sprite> {
+    let mut emulator = Emulator::new(CHIP8::default());
+    let _ = emulator.load_hex_digits();
+    let glyphs = &emulator.get_ram()[..16 * 5];
+    (0..SPRITES as usize)
+        .map(|n| Sprite {
+            byte: glyphs[n % glyphs.len()],
+            x: n * 7 % SCREEN_WIDTH,
+            y: n * 3 % SCREEN_HEIGHT,
+        })
+        .collect()
+}
+
+/// Draws the way the screen was drawn before it was packed: every lit bit of a row flips
+/// one byte and is checked for a collision on its own. Returns the rows that collided.
+fn draw_pixels(sprites: &[Sprite]) -> u64 {
+    let mut pixels = [0u8; SCREEN_WIDTH * SCREEN_HEIGHT];
+    let mut collisions = 0;
+    for sprite in sprites {
+        let mut collided = false;
+        for bit in 0..8 {
+            if sprite.byte & 0x80 >> bit == 0 {
+                continue;
+            }
+            let index = sprite.y * SCREEN_WIDTH + (sprite.x + bit) % SCREEN_WIDTH;
+            collided |= pixels[index] == 1;
+            pixels[index] ^= 1;
+        }
+        collisions += collided as u64;
+    }
+    collisions
+}
+
+/// Draws as DXYN does: a row is rotated into place and XORed with the packed screen row.
+/// Returns the rows that collided.
+fn draw_rows(sprites: &[Sprite]) -> u64 {
+    let mut screen = Framebuffer::from_pixels(&[0; SCREEN_WIDTH * SCREEN_HEIGHT]);
+    let mut collisions = 0;
+    for sprite in sprites {
+        let bits = ((sprite.byte as u64) << 56).rotate_right(sprite.x as u32);
+        collisions += screen.xor_row(sprite.y, bits, 0b01) as u64;
+    }
+    collisions
+}
+
+/// Formats the measurements as an aligned table, the speedup of each configuration against
+/// the first one doing the same work.
+pub fn table(measurements: &[Measurement]) -> String {
+    let width = measurements
+        .iter()
+        .map(|measurement| measurement.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>10}  {:>9}  {:>16}  {:>7}",
+        "Configuration", "Time", "ns each", "per second", "Speedup"
+    );
+    for measurement in measurements {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>8.2}ms  {:>9.2}  {:>16}  {:>6.2}x",
+            measurement.name,
+            measurement.elapsed.as_secs_f64() * 1000.0,
+            measurement.nanos_each(),
+            format!(
+                "{:.1}M {}s",
+                measurement.count as f64 / measurement.elapsed.as_secs_f64() / 1e6,
+                measurement.unit
+            ),
+            measurement.speedup()
+        );
+    }
+    out
+}
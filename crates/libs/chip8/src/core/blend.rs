@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use shared::config::config::Color;
+
+use super::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Motion blur helper for the frontends: keeps the last two presented frames so every pixel
+/// can be drawn with the average of its previous and current color. Unlike phosphor decay
+/// a pixel settles after one frame, which hides XOR flicker without trailing.
+pub struct FrameBlend {
+    previous: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    current: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // Pixels changed by the previous update, their blend settles on the next one
+    pending: Vec<usize>,
+}
+
+impl Default for FrameBlend {
+    fn default() -> Self {
+        Self {
+            previous: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            current: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl FrameBlend {
+    /// Advances to the next presented frame and returns the pixels whose blended color
+    /// changed: the `dirty` ones and the ones changed on the previous frame.
+    pub fn update(&mut self, display: &[u8], dirty: &[usize]) -> Vec<usize> {
+        let mut repaint = std::mem::replace(&mut self.pending, dirty.to_vec());
+        for &index in &repaint {
+            self.previous[index] = self.current[index];
+        }
+        for &index in dirty {
+            self.previous[index] = self.current[index];
+            self.current[index] = display[index];
+        }
+        repaint.extend_from_slice(dirty);
+        repaint
+    }
+
+    /// Forgets the history and starts over from `display`, e.g. after the blend was enabled
+    /// mid-game or a new ROM was loaded.
+    pub fn sync(&mut self, display: &[u8]) {
+        self.previous.copy_from_slice(display);
+        self.current.copy_from_slice(display);
+        self.pending.clear();
+    }
+
+    /// Returns the `(previous, current)` values of a pixel.
+    pub fn get(&self, index: usize) -> (u8, u8) {
+        (self.previous[index], self.current[index])
+    }
+
+    /// Blended color of a pixel, `colors` is indexed by pixel value.
+    pub fn color(&self, colors: &[Color; 4], index: usize) -> Color {
+        let (previous, current) = self.get(index);
+        colors[previous as usize].mix(colors[current as usize])
+    }
+}
+
+/// Returns the pixels to repaint this frame: `dirty` as is without motion blur, otherwise the
+/// ones whose blended color changed.
+pub fn repaint_list<'a>(
+    blend: Option<&mut FrameBlend>,
+    display: &[u8],
+    dirty: &'a [usize],
+) -> Cow<'a, [usize]> {
+    match blend {
+        Some(blend) => Cow::Owned(blend.update(display, dirty)),
+        None => Cow::Borrowed(dirty),
+    }
+}
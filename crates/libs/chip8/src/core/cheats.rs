@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use serde_derive::Deserialize;
+
+/// A RAM byte to compare against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Condition {
+    pub address: u16,
+    pub equals: u8,
+}
+
+/// What a cheat writes into RAM, and when.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    /// Writes `value` before every instruction, e.g. to keep the lives from going down
+    Freeze { address: u16, value: u8 },
+    /// Writes `value` once, each time the cheat is turned on
+    Patch { address: u16, value: u8 },
+    /// Writes `value` before every instruction that finds the `when` byte equal to its value
+    PatchIf {
+        address: u16,
+        value: u8,
+        when: Condition,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Cheat {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub effect: Effect,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// The cheats of the running ROM, applied between instructions by `apply` while both the
+/// list and the cheat are turned on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+    enabled: bool,
+    // Whether each `Patch` cheat has been written since it was last turned on
+    patched: Vec<bool>,
+}
+
+impl Cheats {
+    pub fn new(cheats: Vec<Cheat>) -> Self {
+        Self {
+            patched: vec![false; cheats.len()],
+            cheats,
+            enabled: true,
+        }
+    }
+
+    /// Reads a cheat file, a YAML list of cheats.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let cheats: Vec<Cheat> = serde_yaml::from_str::<Option<_>>(&text)
+            .map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?
+            .unwrap_or_default();
+        Ok(Self::new(cheats))
+    }
+
+    /// Reads the cheats of the ROM with SHA-1 `hash` from `<hash>.yaml` or `<hash>.yml` in
+    /// `folder`, returning the file they came from.
+    pub fn find(folder: &Path, hash: &str) -> Result<Option<(PathBuf, Self)>, Error> {
+        let Some(path) = ["yaml", "yml"]
+            .iter()
+            .map(|extension| folder.join(format!("{hash}.{extension}")))
+            .find(|path| path.exists())
+        else {
+            return Ok(None);
+        };
+        let cheats = Self::load(&path)?;
+        Ok(Some((path, cheats)))
+    }
+
+    /// Adds `cheat` at the end of the list.
+    pub fn push(&mut self, cheat: Cheat) {
+        if self.cheats.is_empty() {
+            self.enabled = true;
+        }
+        self.cheats.push(cheat);
+        self.patched.push(false);
+    }
+
+    pub fn list(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turns all the cheats on or off at once, keeping which ones are on.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled && !self.enabled {
+            self.patched.fill(false);
+        }
+        self.enabled = enabled;
+    }
+
+    /// Turns the cheat at `index` on or off.
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) -> Result<(), Error> {
+        let cheat = self
+            .cheats
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("No cheat at index {}!", index))?;
+        if enabled && !cheat.enabled {
+            self.patched[index] = false;
+        }
+        cheat.enabled = enabled;
+        Ok(())
+    }
+
+    /// Writes the active cheats into `ram`. Addresses past the end of it are left alone.
+    pub fn apply(&mut self, ram: &mut [u8]) {
+        self.apply_with(ram, |_| {});
+    }
+
+    /// `apply`, calling `written` with the address of every byte it changed.
+    pub fn apply_with(&mut self, ram: &mut [u8], mut written: impl FnMut(usize)) {
+        if !self.enabled {
+            return;
+        }
+        for (cheat, patched) in self.cheats.iter().zip(self.patched.iter_mut()) {
+            if !cheat.enabled {
+                continue;
+            }
+            let (address, value) = match cheat.effect {
+                Effect::Freeze { address, value } => (address, value),
+                Effect::Patch { .. } if *patched => continue,
+                Effect::Patch { address, value } => {
+                    *patched = true;
+                    (address, value)
+                }
+                Effect::PatchIf {
+                    address,
+                    value,
+                    when,
+                } => match ram.get(when.address as usize) {
+                    Some(&byte) if byte == when.equals => (address, value),
+                    _ => continue,
+                },
+            };
+            if let Some(byte) = ram.get_mut(address as usize) {
+                if *byte != value {
+                    *byte = value;
+                    written(address as usize);
+                }
+            }
+        }
+    }
+}
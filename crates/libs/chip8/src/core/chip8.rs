@@ -1,13 +1,16 @@
 use std::default::Default;
 
-const RAM_SIZE: usize = 4096;
-const STACK_SIZE: usize = 16;
+pub const RAM_SIZE: usize = 4096;
+pub const STACK_SIZE: usize = 16;
 const NUM_REGS: usize = 16;
-const REFRESH_RATE: usize = 60;
+pub const REFRESH_RATE: u32 = 60;
 const START_ADDR: u16 = 0x200;
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+/// Planes drawn to until an XO-CHIP ROM selects others with `FN01`.
+const DEFAULT_PLANE_MASK: u8 = 0b01;
 
+#[derive(Clone)]
 pub struct CHIP8 {
     pub ram: [u8; RAM_SIZE],
     pub stack: [u16; STACK_SIZE],
@@ -18,7 +21,8 @@ pub struct CHIP8 {
     pub dt: u8,
     pub st: u8,
     pub keys: [bool; 16],
-    pub display: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    pub display: Framebuffer,
+    pub plane_mask: u8,
 }
 
 impl Default for CHIP8 {
@@ -33,7 +37,8 @@ impl Default for CHIP8 {
             dt: 0,
             st: 0,
             keys: [false; 16],
-            display: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            display: Framebuffer::default(),
+            plane_mask: DEFAULT_PLANE_MASK,
         }
     }
 }
@@ -43,3 +48,134 @@ impl CHIP8 {
         *self = Self::default();
     }
 }
+
+/// The screen, stored twice: as packed rows, one `u64` per row and XO-CHIP plane with the
+/// leftmost pixel in the highest bit, so drawing a sprite row is a rotate, an AND and an XOR;
+/// and as one color index from 0 to 3 per pixel, the view frontends read, rebuilt a row at a
+/// time from the packed ones.
+#[derive(Clone)]
+pub struct Framebuffer {
+    rows: [[u64; SCREEN_HEIGHT]; 2],
+    pixels: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+}
+
+/// The 8 pixels of a byte of a packed row, one per byte of the `u64` in memory order.
+const SPREAD: [u64; 256] = spread();
+
+const fn spread() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut bit = 0;
+        while bit < 8 {
+            if byte & 0x80 >> bit != 0 {
+                table[byte] |= 1 << (bit * 8);
+            }
+            bit += 1;
+        }
+        byte += 1;
+    }
+    table
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self {
+            rows: [[0; SCREEN_HEIGHT]; 2],
+            pixels: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+}
+
+impl Framebuffer {
+    /// Builds the screen from color indices, only their two low bits are kept.
+    pub fn from_pixels(pixels: &[u8; SCREEN_WIDTH * SCREEN_HEIGHT]) -> Self {
+        let mut framebuffer = Self::default();
+        for (index, &pixel) in pixels.iter().enumerate() {
+            framebuffer.set(index, pixel & 0b11);
+        }
+        framebuffer
+    }
+
+    /// The color indices of the pixels, row by row.
+    pub fn pixels(&self) -> &[u8; SCREEN_WIDTH * SCREEN_HEIGHT] {
+        &self.pixels
+    }
+
+    /// The packed row `y` of the planes in `planes`, ORed together.
+    pub fn row(&self, y: usize, planes: u8) -> u64 {
+        let mut row = 0;
+        for (plane, rows) in self.rows.iter().enumerate() {
+            if planes & 1 << plane != 0 {
+                row |= rows[y];
+            }
+        }
+        row
+    }
+
+    /// Sets the color index of the pixel at `index` and returns whether it changed.
+    pub fn set(&mut self, index: usize, value: u8) -> bool {
+        if self.pixels[index] == value {
+            return false;
+        }
+        let (y, bit) = (index / SCREEN_WIDTH, 1 << 63 >> (index % SCREEN_WIDTH));
+        for (plane, rows) in self.rows.iter_mut().enumerate() {
+            if value & 1 << plane != 0 {
+                rows[y] |= bit;
+            } else {
+                rows[y] &= !bit;
+            }
+        }
+        self.pixels[index] = value;
+        true
+    }
+
+    /// Flips the `bits` of row `y` in `planes`, as a sprite row drawn over it does, and
+    /// returns whether one of them was lit, i.e. whether the sprite collided there.
+    pub fn xor_row(&mut self, y: usize, bits: u64, planes: u8) -> bool {
+        let mut collision = false;
+        for (plane, rows) in self.rows.iter_mut().enumerate() {
+            if planes & 1 << plane != 0 {
+                collision |= rows[y] & bits != 0;
+                rows[y] ^= bits;
+            }
+        }
+        self.sync(y, bits);
+        collision
+    }
+
+    /// Clears `planes`, the others keep their pixels.
+    pub fn clear(&mut self, planes: u8) {
+        for (plane, rows) in self.rows.iter_mut().enumerate() {
+            if planes & 1 << plane != 0 {
+                *rows = [0; SCREEN_HEIGHT];
+            }
+        }
+        for y in 0..SCREEN_HEIGHT {
+            self.sync(y, u64::MAX);
+        }
+    }
+
+    /// Rebuilds the color indices of the groups of 8 pixels of row `y` that `bits` touches, at
+    /// most two for a sprite row.
+    fn sync(&mut self, y: usize, mut bits: u64) {
+        let [plane1, plane2] = [self.rows[0][y], self.rows[1][y]];
+        while bits != 0 {
+            let shift = 56 - bits.leading_zeros() / 8 * 8;
+            let pixels = SPREAD[(plane1 >> shift) as u8 as usize]
+                | SPREAD[(plane2 >> shift) as u8 as usize] << 1;
+            let start = y * SCREEN_WIDTH + (56 - shift as usize);
+            self.pixels[start..start + 8].copy_from_slice(&pixels.to_le_bytes());
+            bits &= !(0xFF << shift);
+        }
+    }
+}
+
+/// Calls `f` with the column of every set bit of a packed row, from left to right.
+pub fn for_each_bit(mut bits: u64, mut f: impl FnMut(usize)) {
+    while bits != 0 {
+        let x = bits.leading_zeros() as usize;
+        f(x);
+        bits &= !(1 << 63 >> x);
+    }
+}
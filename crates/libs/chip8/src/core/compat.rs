@@ -0,0 +1,281 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+
+use super::chip8::{CHIP8, REFRESH_RATE};
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::instruction::Instruction;
+use super::replay::Quirks;
+
+/// File extensions taken for ROMs besides files without any, like the bundled games.
+const EXTENSIONS: [&str; 5] = ["ch8", "c8", "hc8", "sc8", "xo8"];
+
+/// Settings of a compatibility run over a folder of ROMs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatSettings {
+    /// Name of the `Quirks::PRESETS` entry the ROMs run under
+    pub preset: String,
+    pub seconds: u32,
+    pub cycles_per_frame: u32,
+}
+
+impl Default for CompatSettings {
+    fn default() -> Self {
+        Self {
+            preset: "default".to_string(),
+            seconds: 10,
+            cycles_per_frame: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Ran for the whole time and drew something
+    Ok,
+    /// Ran for the whole time without lighting a single pixel
+    Blank,
+    /// Reached an opcode no supported interpreter defines
+    UnknownOpcode { pc: u16, opcode: u16 },
+    /// Failed to load, stopped on an error or panicked
+    Crashed(String),
+}
+
+impl Verdict {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verdict::Ok => "ok",
+            Verdict::Blank => "blank",
+            Verdict::UnknownOpcode { .. } => "unknown opcode",
+            Verdict::Crashed(_) => "crashed",
+        }
+    }
+
+    fn details(&self) -> String {
+        match self {
+            Verdict::Ok | Verdict::Blank => String::new(),
+            Verdict::UnknownOpcode { pc, opcode } => format!("{:04X} at 0x{:03X}", opcode, pc),
+            Verdict::Crashed(error) => error.clone(),
+        }
+    }
+}
+
+/// How one ROM of the folder ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomReport {
+    pub name: String,
+    pub verdict: Verdict,
+    pub frames: u64,
+    /// `Emulator::display_to_string` when the run stopped
+    pub screen: String,
+}
+
+/// Lists the ROMs of `folder` by name.
+pub fn list_roms(folder: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries =
+        fs::read_dir(folder).map_err(|e| anyhow!("Failed to read {}: {}", folder.display(), e))?;
+    let mut roms: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            let rom = path.extension().is_none_or(|extension| {
+                EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+            });
+            !hidden && rom
+        })
+        .collect();
+    roms.sort();
+    Ok(roms)
+}
+
+/// Runs `rom` headlessly from power-on for `settings.seconds`, with a fixed RNG seed and no
+/// key pressed.
+pub fn check_rom(name: &str, rom: &[u8], quirks: Quirks, settings: &CompatSettings) -> RomReport {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.set_seed(0);
+    emulator.set_quirks(quirks);
+    let frames = settings.seconds as u64 * REFRESH_RATE as u64;
+    let verdict = match emulator.init_ram_from_bytes(rom) {
+        Ok(()) => panic::catch_unwind(AssertUnwindSafe(|| {
+            run(&mut emulator, frames, settings.cycles_per_frame)
+        }))
+        .unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            Verdict::Crashed(format!("panicked: {}", message))
+        }),
+        Err(e) => Verdict::Crashed(format!("failed to load: {}", e)),
+    };
+    RomReport {
+        name: name.to_string(),
+        verdict,
+        frames: emulator.frame(),
+        screen: emulator.display_to_string(),
+    }
+}
+
+fn run(emulator: &mut Emulator, frames: u64, cycles_per_frame: u32) -> Verdict {
+    let cpu = CpuController;
+    let mut drew = false;
+    for _ in 0..frames {
+        for _ in 0..cycles_per_frame {
+            let pc = emulator.get_pc();
+            let opcode = match (
+                emulator.get_from_ram(pc as usize),
+                emulator.get_from_ram(pc as usize + 1),
+            ) {
+                (Ok(high), Ok(low)) => u16::from_be_bytes([high, low]),
+                _ => return Verdict::Crashed(format!("PC 0x{:03X} ran past the RAM", pc)),
+            };
+            if Instruction::decode(opcode).is_none() {
+                return Verdict::UnknownOpcode { pc, opcode };
+            }
+            if let Err(e) = cpu.tick(emulator) {
+                return Verdict::Crashed(format!("{:04X} at 0x{:03X}: {}", opcode, pc, e));
+            }
+        }
+        emulator.dec_all_timers();
+        emulator.advance_frame();
+        drew = drew || emulator.get_display().iter().any(|&pixel| pixel != 0);
+    }
+    if drew {
+        Verdict::Ok
+    } else {
+        Verdict::Blank
+    }
+}
+
+/// Runs every ROM of `folder`, see `check_rom`.
+pub fn check_dir(folder: &Path, settings: &CompatSettings) -> Result<Vec<RomReport>, Error> {
+    let quirks = Quirks::preset(&settings.preset)?;
+    list_roms(folder)?
+        .into_iter()
+        .map(|path| {
+            let rom =
+                fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Ok(check_rom(&name, &rom, quirks, settings))
+        })
+        .collect()
+}
+
+/// Counts of the reports per verdict, in the order ok, blank, unknown opcode, crashed.
+fn tally(reports: &[RomReport]) -> [usize; 4] {
+    let mut counts = [0; 4];
+    for report in reports {
+        counts[match report.verdict {
+            Verdict::Ok => 0,
+            Verdict::Blank => 1,
+            Verdict::UnknownOpcode { .. } => 2,
+            Verdict::Crashed(_) => 3,
+        }] += 1;
+    }
+    counts
+}
+
+/// Formats the reports as a Markdown table.
+pub fn markdown(reports: &[RomReport], settings: &CompatSettings) -> String {
+    let [ok, blank, unknown, crashed] = tally(reports);
+    let mut out = String::new();
+    let _ = writeln!(out, "# CHIP-8 compatibility report\n");
+    let _ = writeln!(
+        out,
+        "{} ROMs, {} seconds each at {} instructions per frame, `{}` quirks: {} ok, {} blank, \
+         {} unknown opcode, {} crashed.\n",
+        reports.len(),
+        settings.seconds,
+        settings.cycles_per_frame,
+        settings.preset,
+        ok,
+        blank,
+        unknown,
+        crashed
+    );
+    let _ = writeln!(out, "| ROM | Result | Frames | Details |");
+    let _ = writeln!(out, "| --- | --- | ---: | --- |");
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            report.name.replace('|', "\\|"),
+            report.verdict.label(),
+            report.frames,
+            report.verdict.details().replace('|', "\\|")
+        );
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Formats the reports as a standalone HTML page, with the last screen of every ROM.
+pub fn html(reports: &[RomReport], settings: &CompatSettings) -> String {
+    let [ok, blank, unknown, crashed] = tally(reports);
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>CHIP-8 compatibility report</title>\n<style>\n\
+         body {{ font-family: sans-serif; }}\n\
+         td, th {{ padding: 2px 8px; text-align: left; vertical-align: top; }}\n\
+         pre {{ font-size: 6px; line-height: 6px; }}\n\
+         .ok {{ color: green; }} .blank {{ color: orange; }} .bad {{ color: red; }}\n\
+         </style>\n</head>\n<body>\n<h1>CHIP-8 compatibility report</h1>"
+    );
+    let _ = writeln!(
+        out,
+        "<p>{} ROMs, {} seconds each at {} instructions per frame, <code>{}</code> quirks: \
+         {} ok, {} blank, {} unknown opcode, {} crashed.</p>",
+        reports.len(),
+        settings.seconds,
+        settings.cycles_per_frame,
+        escape_html(&settings.preset),
+        ok,
+        blank,
+        unknown,
+        crashed
+    );
+    let _ = writeln!(
+        out,
+        "<table>\n<tr><th>ROM</th><th>Result</th><th>Frames</th><th>Details</th>\
+         <th>Screen</th></tr>"
+    );
+    for report in reports {
+        let class = match report.verdict {
+            Verdict::Ok => "ok",
+            Verdict::Blank => "blank",
+            _ => "bad",
+        };
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td>\
+             <td><pre>{}</pre></td></tr>",
+            escape_html(&report.name),
+            class,
+            report.verdict.label(),
+            report.frames,
+            escape_html(&report.verdict.details()),
+            report.screen
+        );
+    }
+    let _ = writeln!(out, "</table>\n</body>\n</html>");
+    out
+}
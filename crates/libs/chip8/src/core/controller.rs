@@ -0,0 +1,386 @@
+use anyhow::Error;
+use shared::config::config::{ChipSettings, ConfigUpdate, Palette};
+use tracing::{info, trace_span};
+
+use super::audio::AudioSink;
+use super::chip8::REFRESH_RATE;
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::pacer::FramePacer;
+use super::plugin::Panel;
+
+/// Speaker shown by frontends that can't flash their border while the buzzer sounds.
+pub const SOUND_ICON: char = '\u{1F50A}';
+
+/// Frontend actions that are not CHIP-8 keypad presses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    CyclePalette,
+    ToggleCrt,
+    ToggleGrid,
+    TogglePause,
+    CycleSpeed,
+    CycleWaveform,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    ToggleScope,
+    ToggleRecording,
+    ToggleCheats,
+    ToggleLogConsole,
+    ScrollLogUp,
+    ScrollLogDown,
+    FollowLog,
+    /// Held down to step back through the last frames, reported as `InputEvent::Rewind`
+    Rewind,
+    SaveToSlot,
+    LoadFromSlot,
+    /// Picks the save slot of the two above, 0 to 9
+    SelectSlot(u8),
+}
+
+/// Events reported by an `InputController`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    KeyDown(u8),
+    KeyUp(u8),
+    /// Autofire key held down, pressed and released the given number of times per second
+    TurboDown(u8, u32),
+    TurboUp(u8),
+    /// Rewind key pressed or released
+    Rewind(bool),
+    Hotkey(Hotkey),
+    /// The window was uncovered or resized and has to be drawn again
+    Redraw,
+    Quit,
+}
+
+/// Emulation state shown by the frontends, e.g. in the window title.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Status {
+    pub rom: Option<String>,
+    pub paused: bool,
+    pub speed: u32,
+    /// Cheats are loaded and turned on
+    pub cheats: bool,
+}
+
+impl Status {
+    /// Formats a caption such as "Chip-8 Emulator — BRIX [paused] [cheats] 2x".
+    pub fn caption(&self, title: &str) -> String {
+        let mut caption = title.to_string();
+        if let Some(rom) = &self.rom {
+            caption.push_str(&format!(" \u{2014} {}", rom));
+        }
+        if self.paused {
+            caption.push_str(" [paused]");
+        }
+        if self.cheats {
+            caption.push_str(" [cheats]");
+        }
+        if self.speed != 1 {
+            caption.push_str(&format!(" {}x", self.speed));
+        }
+        caption
+    }
+}
+
+/// A frontend surface able to show the CHIP-8 framebuffer.
+pub trait DisplayController {
+    /// Shows `display`, whose pixels are palette indices from 0 to 3 (one bit per XO-CHIP
+    /// plane), `dirty` lists the pixel indices changed since the previous call. Called every
+    /// frame, frontends skip the upload and present when nothing they show changed.
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error>;
+
+    /// Draws everything again on the next `present`, e.g. after the window was uncovered.
+    /// Ignored by default.
+    fn request_repaint(&mut self) {}
+
+    /// Applies a renderer hotkey such as a palette switch, ignored by default.
+    fn handle_hotkey(&mut self, _hotkey: Hotkey) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Shows the emulation state, called on start and whenever it changes. Ignored by default.
+    fn show_status(&mut self, _status: &Status) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Shows whether the buzzer sounds, called every frame before `present`. Ignored by
+    /// default.
+    fn show_sound(&mut self, _on: bool) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Switches to a palette changed in the reloaded config. Ignored by default.
+    fn set_palette(&mut self, _palette: &Palette) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Shows the panels of the plugins over the screen, called whenever they change. Ignored
+    /// by default.
+    fn show_panels(&mut self, _panels: &[Panel]) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A frontend source of keypad and hotkey events.
+pub trait InputController {
+    /// Drains the events received since the previous poll.
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error>;
+
+    /// Applies the key bindings of a reloaded config (keyboard layout, autofire and player
+    /// keys) for the ROM `rom`. Ignored by default.
+    fn set_keymap(&mut self, _settings: &ChipSettings, _rom: Option<&str>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Runs a single frame: applies pending input, executes the CPU and presents the display.
+/// Returns `false` once the frontend asked to quit.
+pub fn run_frame<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<bool, Error> {
+    run_frames(emulator, cpu, cycles_per_frame, 1, display, input, audio)
+}
+
+/// Applies pending input, emulates `frames` 60Hz frames (times the speed multiplier, none
+/// while paused), presents the result once and beeps while the sound timer runs. With
+/// `frames == 0` only input and window events are processed.
+/// Returns `false` once the frontend asked to quit.
+pub fn run_frames<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    frames: u32,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<bool, Error> {
+    let _span = trace_span!("run_frames", frames).entered();
+    if let Some(update) = emulator.poll_config_update() {
+        apply_emulator_config(emulator, &update);
+        apply_frontend_config(&update, emulator.get_rom_name(), display, input, audio)?;
+    }
+    let cycles_per_frame = poll_services(emulator, cpu, cycles_per_frame);
+
+    let mut events = trace_span!("input").in_scope(|| input.poll())?;
+    events.extend(emulator.poll_remote_input());
+    for event in events {
+        if is_frontend_event(event) {
+            apply_frontend_event(event, display, audio)?;
+        } else if !apply_event(emulator, event)? {
+            return Ok(false);
+        }
+    }
+    if emulator.take_status_changed() {
+        let status = emulator.status();
+        display.show_status(&status)?;
+        emulator.publish_status(&status);
+    }
+
+    let emulated = emulate(emulator, cpu, cycles_per_frame, frames, |buzzing| {
+        trace_span!("audio").in_scope(|| {
+            audio.set_beep(buzzing);
+            audio.end_frame();
+        })
+    })?;
+    let buzzing = !emulator.is_paused() && emulator.is_buzzing();
+    audio.set_beep(buzzing);
+    display.show_sound(buzzing)?;
+    if let Some(panels) = emulator.take_panels() {
+        display.show_panels(panels)?;
+    }
+
+    let dirty = emulator.take_dirty();
+    trace_span!("render").in_scope(|| display.present(emulator.get_display(), &dirty))?;
+    emulator.publish_services();
+    emulator.record_frame(emulated, emulated as u64 * cycles_per_frame as u64);
+    Ok(true)
+}
+
+/// Serves the search console and the services, e.g. the remote control and the debuggers,
+/// and returns the cycles per frame to run, `cycles_per_frame` unless changed at runtime.
+pub(crate) fn poll_services(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+) -> u32 {
+    emulator.poll_search_console();
+    emulator.poll_services(cpu);
+    emulator.cycles_per_frame().unwrap_or(cycles_per_frame)
+}
+
+/// Whether `event` is handled by the frontend itself (renderer and audio hotkeys, repaints)
+/// rather than by the emulator.
+pub(crate) fn is_frontend_event(event: InputEvent) -> bool {
+    match event {
+        InputEvent::Hotkey(
+            Hotkey::TogglePause
+            | Hotkey::CycleSpeed
+            | Hotkey::ToggleRecording
+            | Hotkey::ToggleCheats
+            | Hotkey::SaveToSlot
+            | Hotkey::LoadFromSlot
+            | Hotkey::SelectSlot(_),
+        ) => false,
+        InputEvent::Hotkey(_) | InputEvent::Redraw => true,
+        _ => false,
+    }
+}
+
+/// Applies an event for which `is_frontend_event` holds.
+pub(crate) fn apply_frontend_event<D: DisplayController, A: AudioSink + ?Sized>(
+    event: InputEvent,
+    display: &mut D,
+    audio: &mut A,
+) -> Result<(), Error> {
+    match event {
+        InputEvent::Hotkey(
+            hotkey @ (Hotkey::CycleWaveform
+            | Hotkey::VolumeUp
+            | Hotkey::VolumeDown
+            | Hotkey::ToggleMute),
+        ) => audio.handle_hotkey(hotkey),
+        InputEvent::Hotkey(hotkey) => display.handle_hotkey(hotkey)?,
+        InputEvent::Redraw => display.request_repaint(),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Applies a keypad, autofire or emulation hotkey event. Returns `false` for `Quit`.
+pub(crate) fn apply_event(emulator: &mut Emulator, event: InputEvent) -> Result<bool, Error> {
+    match event {
+        // Pressing a key during a replay takes over live control
+        InputEvent::KeyDown(key) => {
+            emulator.take_over();
+            emulator.key_press(key)?
+        }
+        InputEvent::KeyUp(_) if emulator.is_replaying() => {}
+        InputEvent::KeyUp(key) => emulator.key_release(key)?,
+        InputEvent::TurboDown(key, rate) => {
+            emulator.take_over();
+            emulator.turbo_press(key, rate)?
+        }
+        InputEvent::TurboUp(_) if emulator.is_replaying() => {}
+        InputEvent::TurboUp(key) => emulator.turbo_release(key)?,
+        InputEvent::Hotkey(Hotkey::TogglePause) => emulator.set_paused(!emulator.is_paused()),
+        InputEvent::Hotkey(Hotkey::CycleSpeed) => emulator.cycle_speed(),
+        InputEvent::Hotkey(Hotkey::ToggleRecording) => emulator.toggle_recording()?,
+        InputEvent::Hotkey(Hotkey::ToggleCheats) => emulator.toggle_cheats(),
+        InputEvent::Hotkey(Hotkey::SaveToSlot) => emulator.quick_save(),
+        InputEvent::Hotkey(Hotkey::LoadFromSlot) => emulator.quick_load(),
+        InputEvent::Hotkey(Hotkey::SelectSlot(slot)) => emulator.select_save_slot(slot),
+        InputEvent::Rewind(held) => emulator.set_rewinding(held),
+        InputEvent::Hotkey(_) | InputEvent::Redraw => {}
+        InputEvent::Quit => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Emulates `frames` 60Hz frames times the speed multiplier, none while paused, calling
+/// `on_frame` after each with whether the buzzer sounds. While rewinding, each frame steps
+/// back one instead, silently. Returns the number of frames run for the metrics: frames
+/// stepped back run no instructions and are left out, as are the frames a breakpoint or the
+/// netplay peer stopped.
+pub(crate) fn emulate(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    frames: u32,
+    mut on_frame: impl FnMut(bool),
+) -> Result<u32, Error> {
+    if emulator.is_paused() {
+        return Ok(0);
+    }
+    let mut ran = 0;
+    for _ in 0..frames * emulator.get_speed() {
+        if emulator.is_rewinding() {
+            emulator.rewind_frame()?;
+            on_frame(false);
+            continue;
+        }
+        // Stopped at a breakpoint, or waiting for the netplay peer's keys
+        if emulator.is_paused() || !emulator.apply_netplay()? {
+            break;
+        }
+        emulator.apply_replay();
+        emulator.apply_turbo();
+        emulator.apply_services();
+        emulator.record_rewind();
+        cpu.step_frame(emulator, cycles_per_frame)?;
+        ran += 1;
+        on_frame(emulator.is_buzzing());
+    }
+    Ok(ran)
+}
+
+/// Applies the emulator settings of a reloaded config that can change while running.
+pub(crate) fn apply_emulator_config(emulator: &mut Emulator, update: &ConfigUpdate) {
+    let (previous, current) = (&update.previous.chip8, &update.current.chip8);
+    if current.cycles_per_frame != previous.cycles_per_frame {
+        info!("Running {} cycles per frame", current.cycles_per_frame);
+        emulator.set_cycles_per_frame(current.cycles_per_frame);
+    }
+}
+
+/// Applies the frontend settings of a reloaded config that can change while running, for the
+/// ROM `rom`. The others are reported by `config::watch` as needing a restart.
+pub(crate) fn apply_frontend_config<
+    D: DisplayController,
+    I: InputController,
+    A: AudioSink + ?Sized,
+>(
+    update: &ConfigUpdate,
+    rom: Option<&str>,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<(), Error> {
+    let (previous, current) = (&update.previous.chip8, &update.current.chip8);
+    if current.palette != previous.palette {
+        display.set_palette(&current.palette)?;
+    }
+    if current.audio.volume != previous.audio.volume {
+        audio.set_volume(current.audio.volume);
+    }
+    input.set_keymap(current, rom)
+}
+
+/// Drives the emulator at a fixed `REFRESH_RATE` until the frontend quits.
+pub fn run<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<(), Error> {
+    let mut pacer = FramePacer::new(REFRESH_RATE);
+    info!(
+        "Entering main loop with {} cycles per frame",
+        cycles_per_frame
+    );
+
+    loop {
+        let frames = pacer.advance();
+        if !run_frames(
+            emulator,
+            cpu,
+            cycles_per_frame,
+            frames,
+            display,
+            input,
+            audio,
+        )? {
+            return Ok(());
+        }
+        pacer.wait();
+    }
+}
@@ -1,8 +1,12 @@
-use super::{emulator::Emulator, instruction::Instruction};
+use std::ops::Range;
+
+#[cfg(feature = "dynarec")]
+use super::dynarec::{self, BlockCache, DynarecMode};
+use super::instruction::{self, Instruction};
+use super::{chip8::RAM_SIZE, emulator::Emulator};
 use anyhow::{anyhow, Error};
-use log::{debug, error};
 use shared::data::bit::BitManipulation;
-use tracing::info;
+use tracing::{debug, error, field, trace_span, Level};
 
 pub struct CpuController;
 
@@ -24,227 +28,421 @@ impl CpuController {
         // Combine the two bytes into a 16-bit word (instruction)
         let word = BitManipulation::combine_bytes_to_16bit_instruction(first_byte, second_byte);
         emulator.inc_pc_by(2);
-        info!("CPU initialized with instruction word: {:#04x}", word);
 
         Ok(word)
     }
 
     pub fn tick(&self, emulator: &mut Emulator) -> Result<(), Error> {
-        // Fetch the next instruction
-        let word = self.fetch(emulator)?;
+        let pc = emulator.get_pc();
+        let span = trace_span!("tick", pc, opcode = field::Empty).entered();
+        emulator.apply_cheats();
+        // Fetch and decode the next instruction unless it was decoded before
+        let decoded = match emulator.decode_cache().get(pc) {
+            Some(decoded) => {
+                emulator.inc_pc_by(2);
+                decoded
+            }
+            None => {
+                let decoded = Decoded::new(self.fetch(emulator)?);
+                emulator.decode_cache().insert(pc, decoded);
+                decoded
+            }
+        };
+        span.record("opcode", decoded.word);
+        // Checked before building the event, disassembling costs more than running the opcode
+        if tracing::enabled!(Level::DEBUG) {
+            trace_instruction(pc, decoded.word);
+        }
+        emulator.record_instruction(pc, decoded.word);
         // Execute the instruction
-        self.exec(emulator, word)?;
+        (decoded.handler)(emulator, decoded.word)?;
+        emulator.notify_instruction(pc, decoded.word);
         Ok(())
     }
 
-    // [xxxx 0000 0000 0000]
-    fn first_nibble(word: u16) -> u8 {
-        (word >> 12) as u8
+    /// Executes one 60Hz frame worth of instructions and decrements the timers once.
+    pub fn step_frame(&self, emulator: &mut Emulator, cycles_per_frame: u32) -> Result<(), Error> {
+        let _span = trace_span!(
+            "step_frame",
+            frame = emulator.frame(),
+            cycles = cycles_per_frame
+        )
+        .entered();
+        // Plugins, breakpoints and cheats only change between frames
+        #[cfg(feature = "dynarec")]
+        let blocks = emulator.dynarec() != DynarecMode::Off && emulator.can_run_blocks();
+        let mut cycles = cycles_per_frame;
+        while cycles > 0 {
+            // The rest of the frame is skipped, the timers still count down once
+            if emulator.at_breakpoint() {
+                break;
+            }
+            #[cfg(feature = "dynarec")]
+            if blocks {
+                let ran = dynarec::run_block(self, emulator, cycles)?;
+                if ran > 0 {
+                    cycles -= ran;
+                    continue;
+                }
+            }
+            self.tick(emulator)?;
+            cycles -= 1;
+        }
+        emulator.dec_all_timers();
+        emulator.advance_frame();
+        emulator.notify_frame();
+        Ok(())
     }
+}
 
-    // [0000 0000 xxxx xxxx]
-    fn second_byte(word: u16) -> u8 {
-        word as u8
+/// Logs an executed instruction with its disassembly, the instruction trace shown with
+/// `chip8::core::cpu=debug` in `logger.override_filter`.
+#[cold]
+fn trace_instruction(pc: u16, word: u16) {
+    match Instruction::decode(word) {
+        Some(instruction) => debug!(pc, opcode = word, "{}", instruction),
+        None => debug!(pc, opcode = word, "Unsupported instruction"),
     }
+}
 
-    // [0000 xxxx 0000 0000]
-    fn x(word: u16) -> u8 {
-        ((word >> 8) & 0x0F) as u8
-    }
+/// An opcode with the handler running it.
+#[derive(Clone, Copy)]
+pub(crate) struct Decoded {
+    word: u16,
+    handler: Handler,
+}
 
-    // [0000 0000 xxxx 0000]
-    fn y(word: u16) -> u8 {
-        ((word >> 4) & 0x0F) as u8
+impl Decoded {
+    fn new(word: u16) -> Self {
+        Self {
+            word,
+            handler: HANDLERS[(word >> 12) as usize],
+        }
     }
+}
 
-    // [0000 0000 0000 xxxx]
-    fn fourth_nibble(word: u16) -> u8 {
-        (word & 0x0F) as u8
+/// The instructions decoded so far by address, so loops do not decode the same words again.
+/// Whatever writes RAM invalidates the instructions over the bytes it wrote, which keeps
+/// self-modifying code working.
+pub(crate) struct DecodeCache {
+    entries: Box<[Option<Decoded>]>,
+    #[cfg(feature = "dynarec")]
+    blocks: BlockCache,
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        Self {
+            entries: vec![None; RAM_SIZE].into_boxed_slice(),
+            #[cfg(feature = "dynarec")]
+            blocks: BlockCache::default(),
+        }
     }
+}
 
-    fn extract_12bit_address(word: u16) -> u16 {
-        word & 0x0FFF
+impl DecodeCache {
+    fn get(&self, pc: u16) -> Option<Decoded> {
+        self.entries.get(pc as usize).copied().flatten()
     }
 
-    fn exec(&self, emulator: &mut Emulator, word: u16) -> Result<(), anyhow::Error> {
-        let first_nibble = CpuController::first_nibble(word);
-        let x = CpuController::x(word);
-        let y = CpuController::y(word);
-        let nibble = CpuController::fourth_nibble(word);
-        let addr = CpuController::extract_12bit_address(word);
-        let byte = CpuController::second_byte(word);
-
-        match first_nibble {
-            0x0 => match word {
-                0x0000 => {
-                    debug!("NOP executed: No operation performed.");
-                    Instruction::Op0000.call(emulator)?;
-                }
-                0x00E0 => {
-                    debug!("Screen cleared!");
-                    Instruction::Op00E0.call(emulator)?;
-                }
-                0x00EE => {
-                    debug!("Returned from subroutine!");
-                    Instruction::Op00EE.call(emulator)?;
-                }
-                _ => {
-                    error!("Unsupported instruction: {:#04x}", word);
-                    return Err(anyhow!("Unsupported instruction"));
-                }
-            },
-            0x1 => {
-                debug!("Jump to address: {:#04x}", addr);
-                Instruction::Op1NNN(addr).call(emulator)?;
-            }
-            0x2 => {
-                debug!("Call subroutine at address: {:#04x}", addr);
-                Instruction::Op2NNN(addr).call(emulator)?;
-            }
-            0x3 => {
-                debug!("Skip next instruction if V{:X} == {:#04x}", x, byte);
-                Instruction::Op3XNN(x, byte).call(emulator)?;
-            }
-            0x4 => {
-                debug!("Skip next instruction if V{:X} != {:#04x}", x, byte);
-                Instruction::Op4XNN(x, byte).call(emulator)?;
-            }
-            0x5 => {
-                debug!("Skip next instruction if V{:X} == V{:X}", x, y);
-                Instruction::Op5XY0(x, y).call(emulator)?;
-            }
-            0x6 => {
-                debug!("Set V{:X} = {:#04x}", x, byte);
-                Instruction::Op6XNN(x, byte).call(emulator)?;
-            }
-            0x7 => {
-                debug!("Add {:#04x} to V{:X}", byte, x);
-                Instruction::Op7XNN(x, byte).call(emulator)?;
-            }
-            0x8 => match nibble {
-                0x0 => {
-                    debug!("Set V{:X} = V{:X}", x, y);
-                    Instruction::Op8XY0(x, y).call(emulator)?;
-                }
-                0x1 => {
-                    debug!("Set V{:X} = V{:X} | V{:X}", x, y, y);
-                    Instruction::Op8XY1(x, y).call(emulator)?;
-                }
-                0x2 => {
-                    debug!("Set V{:X} = V{:X} & V{:X}", x, y, y);
-                    Instruction::Op8XY2(x, y).call(emulator)?;
-                }
-                0x3 => {
-                    debug!("Set V{:X} = V{:X} ^ V{:X}", x, y, y);
-                    Instruction::Op8XY3(x, y).call(emulator)?;
-                }
-                0x4 => {
-                    debug!("Add V{:X} to V{:X} with carry", x, y);
-                    Instruction::Op8XY4(x, y).call(emulator)?;
-                }
-                0x5 => {
-                    debug!("Subtract V{:X} from V{:X} with borrow", y, x);
-                    Instruction::Op8XY5(x, y).call(emulator)?;
-                }
-                0x6 => {
-                    debug!("Right shift V{:X} by 1", x);
-                    Instruction::Op8XY6(x).call(emulator)?;
-                }
-                0x7 => {
-                    debug!("Set V{:X} = V{:X} - V{:X} with borrow", x, y, x);
-                    Instruction::Op8XY7(x, y).call(emulator)?;
-                }
-                0xE => {
-                    debug!("Left shift V{:X} by 1", x);
-                    Instruction::Op8XYE(x).call(emulator)?;
-                }
-                _ => return Err(anyhow!("Unsupported instruction")),
-            },
-            0x9 => {
-                debug!("Skip next instruction if V{:X} != V{:X}", x, y);
-                Instruction::Op9XY0(x, y).call(emulator)?;
-            }
-            0xA => {
-                debug!("Set I = {:#04x}", addr);
-                Instruction::OpANNN(addr).call(emulator)?;
-            }
-            0xB => {
-                debug!("Jump to address V0 + {:#04x}", addr);
-                Instruction::OpBNNN(addr).call(emulator)?;
-            }
-            0xC => {
-                debug!("Set V{:X} = random byte AND {:#04x}", x, byte);
-                Instruction::OpCXNN(x, byte).call(emulator)?;
-            }
-            0xD => {
-                debug!(
-                    "Draw sprite at V{:X}, V{:X} with height {:#X}",
-                    x, y, nibble
-                );
-                Instruction::OpDXYN(x, y, nibble).call(emulator)?;
-            }
-            0xE => match byte {
-                0x9E => {
-                    debug!("Skip next instruction if key V{:X} is pressed", x);
-                    Instruction::OpEX9E(x).call(emulator)?;
-                }
-                0xA1 => {
-                    debug!("Skip next instruction if key V{:X} is not pressed", x);
-                    Instruction::OpEXA1(x).call(emulator)?;
-                }
-                _ => return Err(anyhow!("Unsupported instruction")),
-            },
-            0xF => match byte {
-                0x07 => {
-                    debug!("Set V{:X} = delay timer value", x);
-                    Instruction::OpFX07(x).call(emulator)?;
-                }
-                0x0A => {
-                    debug!("Wait for a key press, store in V{:X}", x);
-                    Instruction::OpFX0A(x).call(emulator)?;
-                }
-                0x15 => {
-                    debug!("Set delay timer = V{:X}", x);
-                    Instruction::OpFX15(x).call(emulator)?;
-                }
-                0x18 => {
-                    debug!("Set sound timer = V{:X}", x);
-                    Instruction::OpFX18(x).call(emulator)?;
-                }
-                0x1E => {
-                    debug!("Set I = I + V{:X}", x);
-                    Instruction::OpFX1E(x).call(emulator)?;
-                }
-                0x29 => {
-                    debug!("Set I = location of sprite for digit V{:X}", x);
-                    Instruction::OpFX29(x).call(emulator)?;
-                }
-                0x33 => {
-                    debug!("Store BCD of V{:X} in memory locations I, I+1, I+2", x);
-                    Instruction::OpFX33(x).call(emulator)?;
-                }
-                0x55 => {
-                    debug!(
-                        "Store registers V0 through V{:X} in memory starting at I",
-                        x
-                    );
-                    Instruction::OpFX55(x).call(emulator)?;
-                }
-                0x65 => {
-                    debug!(
-                        "Read registers V0 through V{:X} from memory starting at I",
-                        x
-                    );
-                    Instruction::OpFX65(x).call(emulator)?;
-                }
-                _ => return Err(anyhow!("Unsupported instruction")),
-            },
-            _ => {
-                error!("Unsupported instruction: {:#04x}", word);
-                return Err(anyhow!("Unsupported instruction"));
-            }
+    fn insert(&mut self, pc: u16, decoded: Decoded) {
+        if let Some(entry) = self.entries.get_mut(pc as usize) {
+            *entry = Some(decoded);
         }
+    }
 
-        Ok(())
+    /// Forgets the instructions overlapping `address`, the one starting there and the one
+    /// starting the byte before.
+    pub(crate) fn invalidate(&mut self, address: usize) {
+        self.invalidate_range(address..address + 1);
+    }
+
+    /// Forgets the instructions overlapping the bytes of `range`.
+    pub(crate) fn invalidate_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.entries.len());
+        let start = range.start.saturating_sub(1).min(end);
+        self.entries[start..end].fill(None);
+        #[cfg(feature = "dynarec")]
+        self.blocks.invalidate_range(range);
     }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.fill(None);
+        #[cfg(feature = "dynarec")]
+        self.blocks.clear();
+    }
+
+    /// The compiled blocks, invalidated together with the decoded instructions.
+    #[cfg(feature = "dynarec")]
+    pub(crate) fn blocks(&mut self) -> &mut BlockCache {
+        &mut self.blocks
+    }
+}
+
+/// Decodes and runs one instruction whose opcode matched its table slot.
+type Handler = fn(&mut Emulator, u16) -> Result<(), Error>;
+
+/// Handlers by the first nibble of the opcode. The groups sharing a first nibble index a
+/// table of their own, so every opcode takes at most two indirect jumps instead of a chain
+/// of compares.
+const HANDLERS: [Handler; 16] = [
+    exec_0nnn, exec_1nnn, exec_2nnn, exec_3xnn, exec_4xnn, exec_5xy0, exec_6xnn, exec_7xnn,
+    exec_8xyn, exec_9xy0, exec_annn, exec_bnnn, exec_cxnn, exec_dxyn, exec_exnn, exec_fxnn,
+];
+
+/// `8XYN` handlers by N.
+const ARITHMETIC: [Handler; 16] = table(&[
+    (0x0, exec_8xy0),
+    (0x1, exec_8xy1),
+    (0x2, exec_8xy2),
+    (0x3, exec_8xy3),
+    (0x4, exec_8xy4),
+    (0x5, exec_8xy5),
+    (0x6, exec_8xy6),
+    (0x7, exec_8xy7),
+    (0xE, exec_8xye),
+]);
+
+/// `EXNN` handlers by NN.
+const KEYS: [Handler; 256] = table(&[(0x9E, exec_ex9e), (0xA1, exec_exa1)]);
+
+/// `FXNN` handlers by NN.
+const MISC: [Handler; 256] = table(&[
+    (0x01, exec_fn01),
+    (0x07, exec_fx07),
+    (0x0A, exec_fx0a),
+    (0x15, exec_fx15),
+    (0x18, exec_fx18),
+    (0x1E, exec_fx1e),
+    (0x29, exec_fx29),
+    (0x33, exec_fx33),
+    (0x55, exec_fx55),
+    (0x65, exec_fx65),
+]);
+
+/// A table with the handlers of `entries` in their slots and `unsupported` everywhere else,
+/// built at compile time.
+const fn table<const N: usize>(entries: &[(u8, Handler)]) -> [Handler; N] {
+    let mut table = [unsupported as Handler; N];
+    let mut n = 0;
+    while n < entries.len() {
+        table[entries[n].0 as usize] = entries[n].1;
+        n += 1;
+    }
+    table
+}
+
+// [0000 xxxx 0000 0000]
+fn x(word: u16) -> u8 {
+    ((word >> 8) & 0x0F) as u8
+}
+
+// [0000 0000 xxxx 0000]
+fn y(word: u16) -> u8 {
+    ((word >> 4) & 0x0F) as u8
+}
+
+// [0000 0000 0000 xxxx]
+fn fourth_nibble(word: u16) -> u8 {
+    (word & 0x0F) as u8
+}
+
+// [0000 0000 xxxx xxxx]
+fn second_byte(word: u16) -> u8 {
+    word as u8
+}
+
+fn extract_12bit_address(word: u16) -> u16 {
+    word & 0x0FFF
+}
+
+fn unsupported(_emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    error!(opcode = word, "Unsupported instruction");
+    Err(anyhow!("Unsupported instruction"))
+}
+
+fn exec_0nnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    match word {
+        0x0000 => instruction::op_0000(emulator),
+        0x00E0 => instruction::op_00e0(emulator),
+        0x00EE => instruction::op_00ee(emulator),
+        _ => unsupported(emulator, word),
+    }
+}
+
+fn exec_1nnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let addr = extract_12bit_address(word);
+    instruction::op_1nnn(emulator, addr)
+}
+
+fn exec_2nnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let addr = extract_12bit_address(word);
+    instruction::op_2nnn(emulator, addr)
+}
+
+fn exec_3xnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, byte) = (x(word), second_byte(word));
+    instruction::op_3xnn(emulator, x, byte)
+}
+
+fn exec_4xnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, byte) = (x(word), second_byte(word));
+    instruction::op_4xnn(emulator, x, byte)
+}
+
+fn exec_5xy0(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_5xy0(emulator, x, y)
+}
+
+fn exec_6xnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, byte) = (x(word), second_byte(word));
+    instruction::op_6xnn(emulator, x, byte)
+}
+
+fn exec_7xnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, byte) = (x(word), second_byte(word));
+    instruction::op_7xnn(emulator, x, byte)
+}
+
+fn exec_8xyn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    ARITHMETIC[fourth_nibble(word) as usize](emulator, word)
+}
+
+fn exec_8xy0(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy0(emulator, x, y)
+}
+
+fn exec_8xy1(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy1(emulator, x, y)
+}
+
+fn exec_8xy2(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy2(emulator, x, y)
+}
+
+fn exec_8xy3(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy3(emulator, x, y)
+}
+
+fn exec_8xy4(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy4(emulator, x, y)
+}
+
+fn exec_8xy5(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy5(emulator, x, y)
+}
+
+fn exec_8xy6(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy6(emulator, x, y)
+}
+
+fn exec_8xy7(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xy7(emulator, x, y)
+}
+
+fn exec_8xye(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_8xye(emulator, x, y)
+}
+
+fn exec_9xy0(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y) = (x(word), y(word));
+    instruction::op_9xy0(emulator, x, y)
+}
+
+fn exec_annn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let addr = extract_12bit_address(word);
+    instruction::op_annn(emulator, addr)
+}
+
+fn exec_bnnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let addr = extract_12bit_address(word);
+    instruction::op_bnnn(emulator, addr)
+}
+
+fn exec_cxnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, byte) = (x(word), second_byte(word));
+    instruction::op_cxnn(emulator, x, byte)
+}
+
+fn exec_dxyn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let (x, y, nibble) = (x(word), y(word), fourth_nibble(word));
+    instruction::op_dxyn(emulator, x, y, nibble)
+}
+
+fn exec_exnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    KEYS[second_byte(word) as usize](emulator, word)
+}
+
+fn exec_ex9e(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_ex9e(emulator, x)
+}
+
+fn exec_exa1(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_exa1(emulator, x)
+}
+
+fn exec_fxnn(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    MISC[second_byte(word) as usize](emulator, word)
+}
+
+fn exec_fn01(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fn01(emulator, x)
+}
+
+fn exec_fx07(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx07(emulator, x)
+}
+
+fn exec_fx0a(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx0a(emulator, x)
+}
+
+fn exec_fx15(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx15(emulator, x)
+}
+
+fn exec_fx18(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx18(emulator, x)
+}
+
+fn exec_fx1e(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx1e(emulator, x)
+}
+
+fn exec_fx29(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx29(emulator, x)
+}
+
+fn exec_fx33(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx33(emulator, x)
+}
+
+fn exec_fx55(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx55(emulator, x)
+}
+
+fn exec_fx65(emulator: &mut Emulator, word: u16) -> Result<(), Error> {
+    let x = x(word);
+    instruction::op_fx65(emulator, x)
 }
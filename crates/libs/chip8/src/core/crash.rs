@@ -0,0 +1,81 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Error};
+
+use super::emulator::Emulator;
+use super::instruction::Instruction;
+
+/// Describes the machine state at the time of `error`: the last executed instructions, the
+/// registers, stack and timers, and a dump of the whole RAM.
+pub fn report(emulator: &Emulator, error: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "CHIP-8 crash report\n");
+    let _ = writeln!(out, "Error: {}", error);
+    let _ = writeln!(out, "ROM: {}", emulator.get_rom_name().unwrap_or("unknown"));
+    let _ = writeln!(
+        out,
+        "Frame: {}, seed: {}, {:?}",
+        emulator.frame(),
+        emulator.get_seed(),
+        emulator.quirks()
+    );
+
+    let _ = writeln!(out, "\nLast instructions, oldest first:");
+    for (pc, opcode) in emulator.history() {
+        let mnemonic = Instruction::decode(opcode)
+            .map(|instruction| instruction.to_string())
+            .unwrap_or_else(|| "???".to_string());
+        let _ = writeln!(out, "  0x{:03X}  {:04X}  {}", pc, opcode, mnemonic);
+    }
+
+    let _ = writeln!(out, "\nRegisters:");
+    for x in 0..16u8 {
+        let _ = write!(out, "  V{:X}=0x{:02X}", x, emulator.get_v(x).unwrap_or(0));
+        if x % 8 == 7 {
+            let _ = writeln!(out);
+        }
+    }
+    let _ = writeln!(
+        out,
+        "  I=0x{:03X}  PC=0x{:03X}  SP={}",
+        emulator.get_i(),
+        emulator.get_pc(),
+        emulator.get_stack().len()
+    );
+    let _ = writeln!(out, "\nStack, innermost call last:");
+    for address in emulator.get_stack() {
+        let _ = writeln!(out, "  0x{:03X}", address);
+    }
+    let _ = writeln!(
+        out,
+        "\nTimers:\n  DT={}  ST={}",
+        emulator.get_dt(),
+        emulator.get_st()
+    );
+
+    let _ = writeln!(out, "\nScreen:\n{}", emulator.display_to_string());
+    let _ = writeln!(out, "RAM:");
+    for (row, bytes) in emulator.get_ram().chunks(16).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let _ = writeln!(out, "  0x{:03X}  {}", row * 16, hex.join(" "));
+    }
+    out
+}
+
+/// Writes the crash report of `error` to a new `crash-<unix time>.txt` in `folder` and returns
+/// its path.
+pub fn write(emulator: &Emulator, error: &str, folder: &Path) -> Result<PathBuf, Error> {
+    fs::create_dir_all(folder)
+        .map_err(|e| anyhow!("Failed to create {}: {}", folder.display(), e))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let path = folder.join(format!("crash-{}.txt", timestamp));
+    fs::write(&path, report(emulator, error))
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use super::analysis::{CodeMap, START_ADDR};
+use super::instruction::Instruction;
+
+/// What a label marks, which also picks its prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LabelKind {
+    /// Target of a `CALL`
+    Sub,
+    /// Target of a `JP` or `JP V0`
+    Jump,
+    /// Target of a `LD I`, usually a sprite or a table
+    Data,
+}
+
+impl LabelKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            LabelKind::Sub => "sub",
+            LabelKind::Jump => "label",
+            LabelKind::Data => "data",
+        }
+    }
+}
+
+/// One line of the listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Code { address: u16, opcode: u16 },
+    Data { address: u16, byte: u8 },
+}
+
+/// Annotated disassembly of a ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    pub items: Vec<Item>,
+    /// The reachable instructions, everything else is listed as data
+    pub code: CodeMap,
+    /// Labelled addresses, a call target wins over a jump target over a data one
+    pub labels: BTreeMap<u16, LabelKind>,
+    /// Addresses of the instructions referring to each label
+    pub xrefs: BTreeMap<u16, BTreeSet<u16>>,
+}
+
+fn word_at(rom: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([
+        *rom.get(offset)?,
+        *rom.get(offset + 1)?,
+    ]))
+}
+
+impl Listing {
+    /// Disassembles `rom` as loaded at 0x200. Only the instructions `CodeMap` finds reachable
+    /// are listed as code, every address they name with `JP`, `CALL` or `LD I` gets a label.
+    pub fn new(rom: &[u8]) -> Self {
+        let code = CodeMap::new(rom);
+        let end = START_ADDR as usize + rom.len();
+        let mut labels = BTreeMap::new();
+        let mut xrefs: BTreeMap<u16, BTreeSet<u16>> = BTreeMap::new();
+        for &address in &code.instructions {
+            let offset = (address - START_ADDR) as usize;
+            let Some(instruction) = word_at(rom, offset).and_then(Instruction::decode) else {
+                continue;
+            };
+            let (target, kind) = match instruction {
+                Instruction::Op2NNN(addr) => (addr, LabelKind::Sub),
+                Instruction::Op1NNN(addr) | Instruction::OpBNNN(addr) => (addr, LabelKind::Jump),
+                Instruction::OpANNN(addr) => (addr, LabelKind::Data),
+                _ => continue,
+            };
+            if (START_ADDR as usize..end).contains(&(target as usize)) {
+                let label = labels.entry(target).or_insert(kind);
+                *label = (*label).min(kind);
+                xrefs.entry(target).or_default().insert(address);
+            }
+        }
+
+        let mut items = Vec::new();
+        let mut offset = 0;
+        while offset < rom.len() {
+            let address = START_ADDR + offset as u16;
+            match word_at(rom, offset) {
+                Some(opcode) if code.is_instruction(address) => {
+                    items.push(Item::Code { address, opcode });
+                    offset += 2;
+                }
+                _ => {
+                    items.push(Item::Data {
+                        address,
+                        byte: rom[offset],
+                    });
+                    offset += 1;
+                }
+            }
+        }
+        Self {
+            items,
+            code,
+            labels,
+            xrefs,
+        }
+    }
+
+    /// Name of the label at `address`, if any.
+    pub fn label(&self, address: u16) -> Option<String> {
+        self.labels
+            .get(&address)
+            .map(|kind| format!("{}_{:03X}", kind.prefix(), address))
+    }
+
+    /// The mnemonic of `instruction`, naming labelled addresses instead of printing them.
+    fn mnemonic(&self, instruction: &Instruction) -> String {
+        let name = |addr: u16| {
+            self.label(addr)
+                .unwrap_or_else(|| format!("0x{:03X}", addr))
+        };
+        match *instruction {
+            Instruction::Op1NNN(addr) => format!("JP {}", name(addr)),
+            Instruction::Op2NNN(addr) => format!("CALL {}", name(addr)),
+            Instruction::OpANNN(addr) => format!("LD I, {}", name(addr)),
+            Instruction::OpBNNN(addr) => format!("JP V0, {}", name(addr)),
+            ref other => other.to_string(),
+        }
+    }
+
+    /// Formats the listing as assembly, one instruction or data byte per line, with the
+    /// bits of data bytes drawn like a sprite row. With `xref` a table of the instructions
+    /// referring to each label follows.
+    pub fn render(&self, xref: bool) -> String {
+        let mut out = String::new();
+        for item in &self.items {
+            let address = match *item {
+                Item::Code { address, .. } | Item::Data { address, .. } => address,
+            };
+            if let Some(label) = self.label(address) {
+                let _ = writeln!(out, "{}:", label);
+            }
+            match *item {
+                Item::Code { address, opcode } => {
+                    let mnemonic = Instruction::decode(opcode)
+                        .map(|instruction| self.mnemonic(&instruction))
+                        .unwrap_or_default();
+                    let _ = writeln!(out, "    0x{:03X}  {:04X}  {}", address, opcode, mnemonic);
+                }
+                Item::Data { address, byte } => {
+                    let bits: String = (0..8)
+                        .map(|bit| {
+                            if byte & (0x80 >> bit) != 0 {
+                                '█'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect();
+                    let _ = writeln!(
+                        out,
+                        "    0x{:03X}  {:02X}    DB 0x{:02X}  ; {}",
+                        address, byte, byte, bits
+                    );
+                }
+            }
+        }
+        if xref {
+            let _ = writeln!(out, "\n; Cross-references");
+            for (address, callers) in &self.xrefs {
+                let callers: Vec<String> = callers
+                    .iter()
+                    .map(|caller| format!("0x{:03X}", caller))
+                    .collect();
+                let _ = writeln!(
+                    out,
+                    "; {:<10} <- {}",
+                    self.label(*address).unwrap_or_default(),
+                    callers.join(", ")
+                );
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,236 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+
+use super::chip8::{CHIP8, RAM_SIZE};
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::instruction::{self, Instruction};
+
+/// Instructions compiled into one block at most.
+const MAX_BLOCK_LEN: usize = 32;
+
+/// How `CpuController::step_frame` runs the straight-line runs of instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DynarecMode {
+    /// Every instruction goes through the interpreter
+    #[default]
+    Off,
+    /// Compiled blocks run in place of the interpreter
+    On,
+    /// Every block runs compiled, then again through the interpreter from the same state, and
+    /// a difference stops the emulation with an error
+    Verify,
+}
+
+/// An instruction compiled into a closure, its operands decoded once.
+type Op = Box<dyn Fn(&mut Emulator) -> Result<(), Error> + Send + Sync>;
+
+/// A straight-line run of instructions compiled into closures. It ends before the first
+/// instruction that may jump, skip or wait for a key, or right after one that writes RAM,
+/// since that write may be over the block itself.
+pub(crate) struct Block {
+    // Opcode and compiled instruction, in address order
+    ops: Vec<(u16, Op)>,
+}
+
+impl Block {
+    /// Compiles the block starting at `pc`, `None` when its first instruction ends blocks.
+    fn compile(ram: &[u8], pc: u16) -> Option<Self> {
+        let mut ops = Vec::new();
+        let mut address = pc as usize;
+        while ops.len() < MAX_BLOCK_LEN && address + 1 < ram.len() {
+            let word = u16::from_be_bytes([ram[address], ram[address + 1]]);
+            let Some((op, writes_ram)) = Instruction::decode(word).and_then(compile) else {
+                break;
+            };
+            ops.push((word, op));
+            address += 2;
+            if writes_ram {
+                break;
+            }
+        }
+        (!ops.is_empty()).then_some(Self { ops })
+    }
+
+    /// Bytes of RAM the block was compiled from.
+    fn byte_len(&self) -> usize {
+        self.ops.len() * 2
+    }
+}
+
+/// The closure running `instruction` and whether it writes RAM, `None` for the instructions
+/// ending a block.
+fn compile(instruction: Instruction) -> Option<(Op, bool)> {
+    let op: Op = match instruction {
+        Instruction::Op0000 => Box::new(instruction::op_0000),
+        Instruction::Op00E0 => Box::new(instruction::op_00e0),
+        Instruction::Op6XNN(x, byte) => Box::new(move |emu| instruction::op_6xnn(emu, x, byte)),
+        Instruction::Op7XNN(x, byte) => Box::new(move |emu| instruction::op_7xnn(emu, x, byte)),
+        Instruction::Op8XY0(x, y) => Box::new(move |emu| instruction::op_8xy0(emu, x, y)),
+        Instruction::Op8XY1(x, y) => Box::new(move |emu| instruction::op_8xy1(emu, x, y)),
+        Instruction::Op8XY2(x, y) => Box::new(move |emu| instruction::op_8xy2(emu, x, y)),
+        Instruction::Op8XY3(x, y) => Box::new(move |emu| instruction::op_8xy3(emu, x, y)),
+        Instruction::Op8XY4(x, y) => Box::new(move |emu| instruction::op_8xy4(emu, x, y)),
+        Instruction::Op8XY5(x, y) => Box::new(move |emu| instruction::op_8xy5(emu, x, y)),
+        Instruction::Op8XY6(x, y) => Box::new(move |emu| instruction::op_8xy6(emu, x, y)),
+        Instruction::Op8XY7(x, y) => Box::new(move |emu| instruction::op_8xy7(emu, x, y)),
+        Instruction::Op8XYE(x, y) => Box::new(move |emu| instruction::op_8xye(emu, x, y)),
+        Instruction::OpANNN(addr) => Box::new(move |emu| instruction::op_annn(emu, addr)),
+        Instruction::OpCXNN(x, byte) => Box::new(move |emu| instruction::op_cxnn(emu, x, byte)),
+        Instruction::OpDXYN(x, y, n) => Box::new(move |emu| instruction::op_dxyn(emu, x, y, n)),
+        Instruction::OpFN01(mask) => Box::new(move |emu| instruction::op_fn01(emu, mask)),
+        Instruction::OpFX07(x) => Box::new(move |emu| instruction::op_fx07(emu, x)),
+        Instruction::OpFX15(x) => Box::new(move |emu| instruction::op_fx15(emu, x)),
+        Instruction::OpFX18(x) => Box::new(move |emu| instruction::op_fx18(emu, x)),
+        Instruction::OpFX1E(x) => Box::new(move |emu| instruction::op_fx1e(emu, x)),
+        Instruction::OpFX29(x) => Box::new(move |emu| instruction::op_fx29(emu, x)),
+        Instruction::OpFX65(x) => Box::new(move |emu| instruction::op_fx65(emu, x)),
+        Instruction::OpFX33(x) => {
+            return Some((Box::new(move |emu| instruction::op_fx33(emu, x)), true))
+        }
+        Instruction::OpFX55(x) => {
+            return Some((Box::new(move |emu| instruction::op_fx55(emu, x)), true))
+        }
+        _ => return None,
+    };
+    Some((op, false))
+}
+
+#[derive(Clone, Default)]
+enum Slot {
+    #[default]
+    Unknown,
+    // The instruction at this address ends blocks, it runs through the interpreter
+    Interpreted,
+    Compiled(Arc<Block>),
+}
+
+/// The blocks compiled so far by start address. Writing RAM drops the blocks compiled from the
+/// bytes written, and the addresses found not to start a block, so they are compiled again.
+pub(crate) struct BlockCache {
+    slots: Box<[Slot]>,
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self {
+            slots: vec![Slot::Unknown; RAM_SIZE].into_boxed_slice(),
+        }
+    }
+}
+
+impl BlockCache {
+    /// The block starting at `pc`, compiled from `ram` on first use.
+    pub(crate) fn get_or_compile(&mut self, ram: &[u8], pc: u16) -> Option<Arc<Block>> {
+        let slot = self.slots.get_mut(pc as usize)?;
+        if let Slot::Unknown = slot {
+            *slot = match Block::compile(ram, pc) {
+                Some(block) => Slot::Compiled(Arc::new(block)),
+                None => Slot::Interpreted,
+            };
+        }
+        match slot {
+            Slot::Compiled(block) => Some(block.clone()),
+            _ => None,
+        }
+    }
+
+    /// Forgets what was compiled from the bytes of `range`.
+    pub(crate) fn invalidate_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.slots.len());
+        let first = range.start.saturating_sub(MAX_BLOCK_LEN * 2 - 1).min(end);
+        for start in first..end {
+            let len = match &self.slots[start] {
+                Slot::Unknown => continue,
+                Slot::Interpreted => 2,
+                Slot::Compiled(block) => block.byte_len(),
+            };
+            if start + len > range.start {
+                self.slots[start] = Slot::Unknown;
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.slots.fill(Slot::Unknown);
+    }
+}
+
+/// Runs the block at the program counter, at most `cycles` instructions of it, and returns
+/// the instructions run, 0 when the instruction there has to go through the interpreter.
+/// Only called while `Emulator::can_run_blocks` holds.
+pub(crate) fn run_block(
+    cpu: &CpuController,
+    emulator: &mut Emulator,
+    cycles: u32,
+) -> Result<u32, Error> {
+    let pc = emulator.get_pc();
+    let Some(block) = emulator.block_at(pc) else {
+        return Ok(0);
+    };
+    let ops = &block.ops[..block.ops.len().min(cycles as usize)];
+    match emulator.dynarec() {
+        DynarecMode::Verify => verify(cpu, emulator, pc, ops)?,
+        _ => {
+            for (n, (word, op)) in ops.iter().enumerate() {
+                emulator.inc_pc_by(2);
+                emulator.record_instruction(pc + 2 * n as u16, *word);
+                op(emulator)?;
+            }
+        }
+    }
+    Ok(ops.len() as u32)
+}
+
+/// Runs `ops` compiled, then the same instructions through the interpreter from the same
+/// state, keeping the interpreter's result and failing when the two differ.
+fn verify(
+    cpu: &CpuController,
+    emulator: &mut Emulator,
+    pc: u16,
+    ops: &[(u16, Op)],
+) -> Result<(), Error> {
+    let checkpoint = emulator.checkpoint();
+    let compiled = ops.iter().try_for_each(|(_, op)| {
+        emulator.inc_pc_by(2);
+        op(emulator)
+    });
+    let compiled = compiled.map(|()| emulator.checkpoint());
+    emulator.restore(checkpoint);
+    for _ in ops {
+        cpu.tick(emulator)?;
+    }
+    let compiled = compiled.map_err(|e| {
+        anyhow!(
+            "The block at 0x{:03X} failed compiled but not interpreted: {}",
+            pc,
+            e
+        )
+    })?;
+    match difference(compiled.machine(), emulator.checkpoint().machine()) {
+        Some(what) => Err(anyhow!(
+            "The block at 0x{:03X} diverged from the interpreter: {} differs",
+            pc,
+            what
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The first part of the machine state that differs between `a` and `b`.
+fn difference(a: &CHIP8, b: &CHIP8) -> Option<&'static str> {
+    [
+        (a.v_reg != b.v_reg, "V registers"),
+        (a.i_reg != b.i_reg, "I"),
+        (a.pc != b.pc, "PC"),
+        (a.sp != b.sp || a.stack != b.stack, "stack"),
+        (a.dt != b.dt || a.st != b.st, "timers"),
+        (a.ram != b.ram, "RAM"),
+        (a.display.pixels() != b.display.pixels(), "screen"),
+        (a.plane_mask != b.plane_mask, "plane mask"),
+    ]
+    .into_iter()
+    .find_map(|(differs, what)| differs.then_some(what))
+}
@@ -1,10 +1,49 @@
-use crate::core::chip8::{CHIP8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::core::cheats::{Cheat, Cheats, Effect};
+use crate::core::chip8::{
+    for_each_bit, CHIP8, RAM_SIZE, REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+use crate::core::controller::{InputEvent, Status};
+use crate::core::cpu::{CpuController, DecodeCache};
+#[cfg(feature = "dynarec")]
+use crate::core::dynarec::{Block, DynarecMode};
+#[cfg(feature = "image")]
+use crate::core::export::{self, ExportFormat};
+use crate::core::metrics::{Metrics, MetricsRecorder};
+use crate::core::netplay::Lockstep;
+use crate::core::plugin::{MachineView, Panel, Plugin};
+use crate::core::replay::{Playback, Quirks, Recording};
+use crate::core::rewind::{RewindBuffer, Rewound};
+use crate::core::search::{self, MemorySearch, SearchCommand};
+use crate::core::service::Service;
+use crate::core::state::{SaveState, StateRef};
 use anyhow::{anyhow, Error};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use shared::config::config::ConfigUpdate;
+#[cfg(feature = "image")]
+use shared::config::config::Palette;
+use shared::config::profile::rom_hash;
+use std::collections::{BTreeSet, VecDeque};
 use std::fs::File;
 use std::io::Read;
-use tracing::{error, info};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error, info, warn};
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
-const HEX_DIGITS: [u8; 80] = [
+/// Numbered save states per ROM, picked with shift and a digit.
+pub const SAVE_SLOTS: u8 = 10;
+/// Executed instructions kept for crash reports.
+pub const HISTORY_LEN: usize = 64;
+/// Keypad events a recording has room for when it starts, hours of play at a few presses per
+/// second, so pressing keys while recording does not allocate.
+const RECORDING_EVENTS: usize = 1 << 16;
+
+/// Sprites of the hex digits 0 to F loaded at the start of RAM, 5 rows of 4 pixels each in
+/// the high nibbles.
+pub const HEX_DIGITS: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -23,22 +62,1081 @@ const HEX_DIGITS: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+/// Speed multipliers cycled through by `cycle_speed`.
+const SPEEDS: [u32; 4] = [1, 2, 4, 8];
+
+/// Frames of the beep played per sound timer activation when the buzzer does not follow the
+/// timer.
+const ONE_SHOT_BEEP_FRAMES: u8 = 6;
+
 pub struct Emulator {
     chip8: CHIP8,
+    // Kept to restore the power-on state when an input recording starts
+    rom: Vec<u8>,
+    rom_name: Option<String>,
+    // Proper title from the ROM database, shown instead of the name
+    rom_title: Option<String>,
+    paused: bool,
+    speed: u32,
+    quirks: Quirks,
+    // CXKK draws from `rng`, seeded with `seed` at power-on so runs can be reproduced
+    seed: u64,
+    rng: StdRng,
+    // Bytes drawn from `rng` since it was seeded, which with the seed pins down its state
+    random_draws: u64,
+    // Emulated frames since power-on
+    frame: u64,
+    recording: Option<Recording>,
+    recording_folder: Option<PathBuf>,
+    // Folder of the numbered save states and the slot F5 and F9 save to and load from
+    save_slot_folder: Option<PathBuf>,
+    save_slot: u8,
+    playback: Option<Playback>,
+    // Autofire keys as the frame they were pressed on and the frames between toggles
+    turbo: [Option<(u64, u64)>; 16],
+    // Events injected from outside the frontend, e.g. the remote input endpoint
+    remote_input: Option<Receiver<InputEvent>>,
+    // Remote control, debuggers, chat votes and scripts, dropped when they fail
+    services: Vec<Box<dyn Service>>,
+    // Tools watching the frames and instructions, dropped when they fail
+    plugins: Vec<Box<dyn Plugin>>,
+    // Panels of the plugins as last shown, and whether they changed since `take_panels`
+    panels: Vec<Panel>,
+    panels_changed: bool,
+    config_updates: Option<Receiver<ConfigUpdate>>,
+    // Set when a reloaded config changed the instructions per frame
+    cycles_per_frame: Option<u32>,
+    // Frames left of the one-shot beep, counted down with the timers
+    one_shot_frames: u8,
+    // Set when the ROM, pause state or speed changed since the last `take_status_changed`
+    status_changed: bool,
+    // Pixels changed since the last `take_dirty`, packed like the framebuffer rows
+    dirty: [u64; SCREEN_HEIGHT],
+    // The same since the last `take_display_delta`, so a streamer and a display can both take
+    delta: [u64; SCREEN_HEIGHT],
+    metrics: MetricsRecorder,
+    // Shared with the metrics endpoint, refreshed every metrics window
+    published_metrics: Option<Arc<Mutex<Metrics>>>,
+    // Told about every status change, e.g. the Discord presence
+    status_target: Option<Sender<Status>>,
+    // Address and opcode of the last `HISTORY_LEN` instructions, oldest first
+    history: VecDeque<(u16, u16)>,
+    cheats: Cheats,
+    search: MemorySearch,
+    // Lines typed into the memory search console and where its replies go
+    search_console: Option<(Receiver<String>, Sender<String>)>,
+    breakpoints: BTreeSet<u16>,
+    // Breakpoint execution stopped at, until it resumes
+    stopped_at: Option<u16>,
+    // Breakpoint to run over once, the one execution stopped at before resuming
+    break_skip: Option<u16>,
+    // Keys held here go to the peer and the keypad is set from both before every frame
+    netplay: Option<Lockstep>,
+    // Instructions decoded so far, invalidated by every write to RAM
+    decoded: DecodeCache,
+    #[cfg(feature = "dynarec")]
+    dynarec: DynarecMode,
+    // The last frames emulated, stepped back through while `rewinding` is held
+    rewind: Option<RewindBuffer>,
+    rewinding: bool,
+}
+
+/// The machine and random number generator state, to run instructions again from it.
+#[cfg(feature = "dynarec")]
+pub(crate) struct Checkpoint {
+    chip8: CHIP8,
+    rng: StdRng,
+    random_draws: u64,
+}
+
+#[cfg(feature = "dynarec")]
+impl Checkpoint {
+    pub(crate) fn machine(&self) -> &CHIP8 {
+        &self.chip8
+    }
 }
 
 impl Emulator {
     pub fn new(chip8: CHIP8) -> Self {
-        Self { chip8 }
+        let seed = rand::thread_rng().gen();
+        Self {
+            chip8,
+            rom: Vec::new(),
+            rom_name: None,
+            rom_title: None,
+            paused: false,
+            speed: 1,
+            quirks: Quirks::default(),
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            random_draws: 0,
+            frame: 0,
+            recording: None,
+            recording_folder: None,
+            save_slot_folder: None,
+            save_slot: 0,
+            playback: None,
+            remote_input: None,
+            services: Vec::new(),
+            plugins: Vec::new(),
+            panels: Vec::new(),
+            panels_changed: false,
+            config_updates: None,
+            cycles_per_frame: None,
+            turbo: [None; 16],
+            one_shot_frames: 0,
+            status_changed: true,
+            dirty: [0; SCREEN_HEIGHT],
+            delta: [0; SCREEN_HEIGHT],
+            metrics: MetricsRecorder::default(),
+            published_metrics: None,
+            status_target: None,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            cheats: Cheats::default(),
+            search: MemorySearch::default(),
+            search_console: None,
+            breakpoints: BTreeSet::new(),
+            stopped_at: None,
+            break_skip: None,
+            netplay: None,
+            decoded: DecodeCache::default(),
+            #[cfg(feature = "dynarec")]
+            dynarec: DynarecMode::default(),
+            rewind: None,
+            rewinding: false,
+        }
     }
 
     pub fn init_ram(&mut self, rom_path: &str) -> Result<(), Error> {
         info!("Initializing RAM with ROM file: {}", rom_path);
-        self.load_rom_file(rom_path)?;
+        let rom = Self::read_rom_file(rom_path)?;
+        self.init_ram_from_bytes(&rom)?;
+        let name = Path::new(rom_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        self.set_rom_name(name);
+        Ok(())
+    }
+
+    /// Loads an in-memory ROM, used by frontends without file system access such as the web.
+    pub fn init_ram_from_bytes(&mut self, rom: &[u8]) -> Result<(), Error> {
+        self.load_rom(rom)?;
         self.load_hex_digits()?;
+        self.rom = rom.to_vec();
+        Ok(())
+    }
+
+    /// Restores the power-on state of the loaded ROM: clears the machine, reloads the ROM and
+    /// reseeds the random number generator. Settings such as the speed are kept.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        info!("Resetting the machine");
+        self.mark_lit_dirty(0b11);
+        self.chip8 = CHIP8::default();
+        self.decoded.clear();
+        let rom = std::mem::take(&mut self.rom);
+        self.init_ram_from_bytes(&rom)?;
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.random_draws = 0;
+        self.frame = 0;
+        self.one_shot_frames = 0;
+        self.turbo = [None; 16];
+        self.history.clear();
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        Ok(())
+    }
+
+    /// The loaded ROM as read from the file.
+    pub fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    pub fn get_rom_name(&self) -> Option<&str> {
+        self.rom_name.as_deref()
+    }
+
+    /// Names the loaded ROM for captions, `init_ram` uses the file name.
+    pub fn set_rom_name(&mut self, name: Option<String>) {
+        self.rom_name = name;
+        self.status_changed = true;
+    }
+
+    /// Shows `title` in captions instead of the ROM name, which still picks the per-ROM
+    /// settings.
+    pub fn set_rom_title(&mut self, title: Option<String>) {
+        self.rom_title = title;
+        self.status_changed = true;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Reseeds the random number generator used by CXKK, e.g. to replay a recording.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+        self.random_draws = 0;
+    }
+
+    pub fn random_byte(&mut self) -> u8 {
+        self.random_draws += 1;
+        self.rng.gen()
+    }
+
+    /// Emulated frames since power-on.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn advance_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Enables the input recording hotkey, recordings are saved into `folder`.
+    pub fn set_recording_folder(&mut self, folder: Option<PathBuf>) {
+        self.recording_folder = folder;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts recording the keypad from power-on, so the machine is reset first.
+    pub fn start_recording(&mut self) -> Result<(), Error> {
+        self.playback = None;
+        self.reset()?;
+        let mut recording = Recording::new(self.seed, self.quirks);
+        recording.events.reserve(RECORDING_EVENTS);
+        self.recording = Some(recording);
+        info!("Input recording started with seed {:#018x}", self.seed);
+        Ok(())
+    }
+
+    /// Ends the recording and saves it, returning the path of the file.
+    pub fn stop_recording(&mut self) -> Result<Option<PathBuf>, Error> {
+        let (Some(recording), Some(folder)) = (self.recording.take(), &self.recording_folder)
+        else {
+            return Ok(None);
+        };
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        let name = format!(
+            "{}-{}.c8r",
+            self.rom_name.as_deref().unwrap_or("rom"),
+            seconds
+        );
+        let path = folder.join(name);
+        recording.save(&path)?;
+        info!(
+            "Input recording of {} frames saved to {}",
+            self.frame,
+            path.display()
+        );
+        Ok(Some(path))
+    }
+
+    /// Replays `recording` from power-on with its seed and quirks, the keypad follows the
+    /// recorded events until they run out or `take_over` is called.
+    pub fn start_replay(&mut self, recording: Recording) -> Result<(), Error> {
+        if recording.quirks != self.quirks {
+            warn!("The replay was recorded with other quirk settings, using the recorded ones");
+            self.quirks = recording.quirks;
+        }
+        self.set_seed(recording.seed);
+        self.reset()?;
+        info!(
+            "Replaying {} input events over {} frames",
+            recording.events.len(),
+            recording.frames()
+        );
+        self.playback = Some(Playback::new(recording));
+        Ok(())
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Applies the replayed keypad events due before the next frame runs.
+    pub fn apply_replay(&mut self) {
+        let Some(mut playback) = self.playback.take() else {
+            return;
+        };
+        for event in playback.due(self.frame).to_vec() {
+            self.set_key(event.key, event.pressed);
+        }
+        if playback.is_finished() {
+            info!(
+                "Replay finished at frame {}, live input enabled",
+                self.frame
+            );
+        } else {
+            self.playback = Some(playback);
+        }
+    }
+
+    /// Feeds events from another thread into `run_frames` next to the frontend input.
+    pub fn set_remote_input(&mut self, receiver: Receiver<InputEvent>) {
+        self.remote_input = Some(receiver);
+    }
+
+    /// Returns the remote input events received since the previous call.
+    pub fn poll_remote_input(&mut self) -> Vec<InputEvent> {
+        self.remote_input
+            .as_ref()
+            .map(|receiver| receiver.try_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Calls `service` from the frame loop from now on, see `Service`.
+    pub fn add_service(&mut self, service: Box<dyn Service>) {
+        self.services.push(service);
+    }
+
+    /// Lets the services serve what they received since the previous call, between frames.
+    pub fn poll_services(&mut self, cpu: &CpuController) {
+        self.call_services(|service, emulator| service.poll(emulator, cpu));
+    }
+
+    /// Lets the services change the machine before the next frame runs, e.g. press keys.
+    pub fn apply_services(&mut self) {
+        self.call_services(|service, emulator| service.apply(emulator));
+    }
+
+    /// Lets the services send out the frames just presented.
+    pub fn publish_services(&mut self) {
+        self.call_services(|service, emulator| service.publish(emulator));
+    }
+
+    /// Calls every service, dropping the ones that fail. Services added meanwhile are kept.
+    fn call_services(
+        &mut self,
+        mut call: impl FnMut(&mut dyn Service, &mut Self) -> Result<(), Error>,
+    ) {
+        if self.services.is_empty() {
+            return;
+        }
+        let mut services = std::mem::take(&mut self.services);
+        services.retain_mut(|service| match call(service.as_mut(), self) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(
+                    "Stopped the {} at frame {}: {:#}",
+                    service.name(),
+                    self.frame,
+                    e
+                );
+                false
+            }
+        });
+        services.append(&mut self.services);
+        self.services = services;
+    }
+
+    /// Tells `plugin` about every frame from now on, see `Plugin`.
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// The machine as plugins see it.
+    pub fn machine_view(&self) -> MachineView<'_> {
+        MachineView {
+            frame: self.frame,
+            ram: &self.chip8.ram,
+            v: &self.chip8.v_reg,
+            i: self.chip8.i_reg,
+            pc: self.chip8.pc,
+            dt: self.chip8.dt,
+            st: self.chip8.st,
+            display: self.chip8.display.pixels(),
+        }
+    }
+
+    /// Tells the plugins asking for instructions that `opcode` at `pc` ran.
+    pub fn notify_instruction(&mut self, pc: u16, opcode: u16) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let mut plugins = std::mem::take(&mut self.plugins);
+        let machine = self.machine_view();
+        plugins.retain_mut(|plugin| {
+            !plugin.wants_instructions()
+                || Self::keep_plugin(plugin.on_instruction(&machine, pc, opcode), plugin.name())
+        });
+        self.plugins = plugins;
+    }
+
+    /// Tells the plugins a frame ended and collects their panels.
+    pub fn notify_frame(&mut self) {
+        if self.plugins.is_empty() && self.panels.is_empty() {
+            return;
+        }
+        let mut plugins = std::mem::take(&mut self.plugins);
+        let machine = self.machine_view();
+        plugins.retain_mut(|plugin| Self::keep_plugin(plugin.on_frame(&machine), plugin.name()));
+        let panels: Vec<Panel> = plugins
+            .iter()
+            .filter_map(|plugin| plugin.panel().cloned())
+            .collect();
+        if panels != self.panels {
+            self.panels = panels;
+            self.panels_changed = true;
+        }
+        self.plugins = plugins;
+    }
+
+    fn keep_plugin(result: Result<(), Error>, name: &str) -> bool {
+        if let Err(e) = &result {
+            error!("Stopped the plugin {}: {:#}", name, e);
+        }
+        result.is_ok()
+    }
+
+    /// The plugin panels when they changed since the previous call.
+    pub fn take_panels(&mut self) -> Option<&[Panel]> {
+        if !std::mem::take(&mut self.panels_changed) {
+            return None;
+        }
+        Some(&self.panels)
+    }
+
+    /// Receives reloaded configs, applied by `run_frames` between frames.
+    pub fn set_config_updates(&mut self, receiver: Receiver<ConfigUpdate>) {
+        self.config_updates = Some(receiver);
+    }
+
+    /// Returns the config changes received since the previous call, merged into one update.
+    pub fn poll_config_update(&mut self) -> Option<ConfigUpdate> {
+        self.config_updates
+            .as_ref()?
+            .try_iter()
+            .reduce(|first, last| ConfigUpdate {
+                previous: first.previous,
+                current: last.current,
+            })
+    }
+
+    /// Instructions per frame set by a reloaded config, overriding the frontend's value.
+    pub fn cycles_per_frame(&self) -> Option<u32> {
+        self.cycles_per_frame
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles_per_frame: u32) {
+        self.cycles_per_frame = Some(cycles_per_frame);
+    }
+
+    /// Ends the replay and hands the keypad to the player, releasing the replayed keys.
+    pub fn take_over(&mut self) {
+        if self.playback.take().is_some() {
+            info!("Live input took over the replay at frame {}", self.frame);
+            for key in 0..16 {
+                self.set_key(key, false);
+            }
+        }
+    }
+
+    /// Starts or stops the input recording, a no-op unless a recording folder is set. Saving
+    /// errors are logged so a full disk doesn't end the game.
+    pub fn toggle_recording(&mut self) -> Result<(), Error> {
+        if self.recording_folder.is_none() {
+            info!("Input recording is not available in this frontend");
+        } else if self.recording.is_some() {
+            if let Err(e) = self.stop_recording() {
+                error!("{}", e);
+            }
+        } else {
+            self.start_recording()?;
+        }
+        Ok(())
+    }
+
+    /// Enables the save slot hotkeys, the states are saved into `folder` as
+    /// `<rom sha1>-<slot>.c8s`.
+    pub fn set_save_slot_folder(&mut self, folder: Option<PathBuf>) {
+        self.save_slot_folder = folder;
+    }
+
+    /// The slot `save_to_slot` and `load_from_slot` use when the hotkeys don't name one.
+    pub fn save_slot(&self) -> u8 {
+        self.save_slot
+    }
+
+    pub fn select_save_slot(&mut self, slot: u8) {
+        if slot >= SAVE_SLOTS {
+            warn!("There is no save slot {}", slot);
+            return;
+        }
+        info!("Save slot {} selected", slot);
+        self.save_slot = slot;
+    }
+
+    /// The file of save slot `slot` for the loaded ROM, if a save slot folder is set.
+    pub fn save_slot_path(&self, slot: u8) -> Option<PathBuf> {
+        let folder = self.save_slot_folder.as_ref()?;
+        Some(folder.join(format!("{}-{}.c8s", rom_hash(&self.rom), slot)))
+    }
+
+    /// Saves the machine to save slot `slot`, returning the path of the file.
+    pub fn save_to_slot(&self, slot: u8) -> Result<Option<PathBuf>, Error> {
+        let Some(path) = self.save_slot_path(slot) else {
+            return Ok(None);
+        };
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)
+                .map_err(|e| anyhow!("Failed to create {}: {}", folder.display(), e))?;
+        }
+        std::fs::write(&path, self.save_state().to_bytes())
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        info!(
+            "Saved frame {} to slot {}, {}",
+            self.frame,
+            slot,
+            path.display()
+        );
+        Ok(Some(path))
+    }
+
+    /// Continues from save slot `slot`, returning false when it is empty. Not available during
+    /// netplay, where the peer keeps running, nor while recording input, since recordings
+    /// replay from power-on. Ends a replay like a key press does.
+    pub fn load_from_slot(&mut self, slot: u8) -> Result<bool, Error> {
+        if self.netplay.is_some() {
+            warn!("Loading a save slot is not available during netplay");
+            return Ok(false);
+        }
+        if self.recording.is_some() {
+            warn!("Loading a save slot is not available while recording input");
+            return Ok(false);
+        }
+        let Some(path) = self.save_slot_path(slot) else {
+            return Ok(false);
+        };
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!("Save slot {} is empty", slot);
+                return Ok(false);
+            }
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+        };
+        let state = SaveState::from_bytes(&bytes)
+            .map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))?;
+        self.take_over();
+        self.load_state(state);
+        Ok(true)
+    }
+
+    /// Saves to the selected slot for the F5 hotkey, a no-op unless a save slot folder is set.
+    /// Errors are logged so a full disk doesn't end the game.
+    pub fn quick_save(&mut self) {
+        if self.save_slot_folder.is_none() {
+            info!("Save slots are not available in this frontend");
+        } else if let Err(e) = self.save_to_slot(self.save_slot) {
+            error!("{}", e);
+        }
+    }
+
+    /// Loads the selected slot for the F9 hotkey, see `quick_save`.
+    pub fn quick_load(&mut self) {
+        if self.save_slot_folder.is_none() {
+            info!("Save slots are not available in this frontend");
+        } else if let Err(e) = self.load_from_slot(self.save_slot) {
+            error!("{}", e);
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            info!("Emulation {}", if paused { "paused" } else { "resumed" });
+            self.paused = paused;
+            self.status_changed = true;
+            if !paused {
+                self.stopped_at = None;
+                self.break_skip = Some(self.chip8.pc);
+            }
+        }
+    }
+
+    pub fn get_speed(&self) -> u32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: u32) -> Result<(), Error> {
+        if speed == 0 {
+            error!("Invalid speed multiplier!");
+            return Err(anyhow!("Speed multiplier must be at least 1!"));
+        }
+        if self.speed != speed {
+            self.speed = speed;
+            self.status_changed = true;
+        }
+        Ok(())
+    }
+
+    /// Switches to the next speed multiplier, wrapping back to 1x after the fastest one.
+    pub fn cycle_speed(&mut self) {
+        let position = SPEEDS
+            .iter()
+            .position(|speed| *speed == self.speed)
+            .unwrap_or(0);
+        self.speed = SPEEDS[(position + 1) % SPEEDS.len()];
+        info!("Emulation speed set to {}x", self.speed);
+        self.status_changed = true;
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            rom: self.rom_title.clone().or_else(|| self.rom_name.clone()),
+            paused: self.paused,
+            speed: self.speed,
+            cheats: !self.cheats.is_empty() && self.cheats.is_enabled(),
+        }
+    }
+
+    /// Sets the cheats of the running ROM, see `Cheats::find`.
+    pub fn set_cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+        self.status_changed = true;
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        &self.cheats
+    }
+
+    pub fn cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.cheats
+    }
+
+    /// Turns all the cheats on or off, bound to the J hotkey.
+    pub fn toggle_cheats(&mut self) {
+        if self.cheats.is_empty() {
+            info!("No cheats loaded for this ROM");
+            return;
+        }
+        let enabled = !self.cheats.is_enabled();
+        self.cheats.set_enabled(enabled);
+        info!("Cheats {}", if enabled { "on" } else { "off" });
+        self.status_changed = true;
+    }
+
+    /// Writes the active cheats into RAM, run by `CpuController::tick` before every
+    /// instruction.
+    pub fn apply_cheats(&mut self) {
+        let decoded = &mut self.decoded;
+        self.cheats
+            .apply_with(&mut self.chip8.ram, |address| decoded.invalidate(address));
+    }
+
+    /// Runs one memory search console command against the RAM, returning the reply.
+    pub fn search_command(&mut self, line: &str) -> Result<String, Error> {
+        Ok(match SearchCommand::parse(line)? {
+            SearchCommand::New => {
+                self.search.start(&self.chip8.ram);
+                self.search.summary(0)
+            }
+            SearchCommand::Filter(filter) => {
+                self.search.refine(&self.chip8.ram, filter)?;
+                self.search.summary(search::LIST_LIMIT)
+            }
+            SearchCommand::List => self.search.summary(usize::MAX),
+            SearchCommand::Freeze { address, value } => {
+                if address as usize >= self.chip8.ram.len() {
+                    return Err(anyhow!("Address 0x{:03X} is outside the RAM!", address));
+                }
+                let name = format!("Freeze 0x{:03X}", address);
+                self.cheats.push(Cheat {
+                    name: name.clone(),
+                    enabled: true,
+                    effect: Effect::Freeze { address, value },
+                });
+                self.status_changed = true;
+                format!(
+                    "Cheat added, to keep it add to the cheat file:\n- name: {}\n  freeze: \
+                     {{ address: 0x{:03X}, value: {} }}",
+                    name, address, value
+                )
+            }
+            SearchCommand::Help => search::HELP.to_string(),
+        })
+    }
+
+    /// Takes memory search commands from `commands` and sends the replies to `replies`,
+    /// between frames.
+    pub fn set_search_console(&mut self, commands: Receiver<String>, replies: Sender<String>) {
+        self.search_console = Some((commands, replies));
+    }
+
+    /// Runs the search console commands received since the previous call.
+    pub fn poll_search_console(&mut self) {
+        let Some((commands, replies)) = self.search_console.take() else {
+            return;
+        };
+        for line in commands.try_iter() {
+            let reply = self
+                .search_command(&line)
+                .unwrap_or_else(|e| format!("{:#}", e));
+            let _ = replies.send(reply);
+        }
+        self.search_console = Some((commands, replies));
+    }
+
+    /// Loads the ROM file at `path` and starts it from power-on, see `start_rom`.
+    pub fn start_rom_file(&mut self, path: &str) -> Result<(), Error> {
+        let name = Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned());
+        self.start_rom(Self::read_rom_file(path)?, name)
+    }
+
+    /// Replaces the ROM and starts it from power-on, keeping the previous one when `rom` does
+    /// not fit in RAM.
+    pub fn start_rom(&mut self, rom: Vec<u8>, name: Option<String>) -> Result<(), Error> {
+        if rom.len() > RAM_SIZE - 0x200 {
+            return Err(anyhow!(
+                "The selected ROM size will overflow beyond the limit of RAM!"
+            ));
+        }
+        self.rom = rom;
+        self.reset()?;
+        self.set_rom_title(None);
+        self.set_rom_name(name);
+        Ok(())
+    }
+
+    /// The `length` bytes of RAM from `address`, failing past the end of the RAM.
+    pub fn read_ram(&self, address: u16, length: usize) -> Result<&[u8], Error> {
+        Ok(&self.chip8.ram[Self::ram_range(address, length)?])
+    }
+
+    /// Writes `bytes` into the RAM from `address`, failing past the end of the RAM.
+    pub fn write_ram(&mut self, address: u16, bytes: &[u8]) -> Result<(), Error> {
+        let range = Self::ram_range(address, bytes.len())?;
+        self.chip8.ram[range.clone()].copy_from_slice(bytes);
+        self.decoded.invalidate_range(range);
+        Ok(())
+    }
+
+    /// The RAM addresses of `length` bytes from `address`, failing past the end of the RAM.
+    fn ram_range(address: u16, length: usize) -> Result<std::ops::Range<usize>, Error> {
+        let start = address as usize;
+        let end = start + length;
+        if end > RAM_SIZE {
+            return Err(anyhow!(
+                "0x{:03X}-0x{:03X} is outside the RAM!",
+                start,
+                end - 1
+            ));
+        }
+        Ok(start..end)
+    }
+
+    /// Replaces the breakpoints, execution pauses before running an instruction at one.
+    pub fn set_breakpoints(&mut self, addresses: impl IntoIterator<Item = u16>) {
+        self.breakpoints = addresses.into_iter().collect();
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// The breakpoint execution stopped at, until it resumes.
+    pub fn stopped_at(&self) -> Option<u16> {
+        self.stopped_at
+    }
+
+    /// Plays with the peer of `lockstep`: takes over the seed, quirks and speed both agreed on
+    /// and starts the ROM from power-on, so the two machines start out the same.
+    pub fn start_netplay(&mut self, lockstep: Lockstep) -> Result<(), Error> {
+        let session = *lockstep.session();
+        info!(
+            "Netplay started with seed {} and an input delay of {} frames",
+            session.seed, session.input_delay
+        );
+        self.set_seed(session.seed);
+        self.set_quirks(session.quirks);
+        self.set_cycles_per_frame(session.cycles_per_frame);
+        self.reset()?;
+        self.netplay = Some(lockstep);
         Ok(())
     }
 
+    pub fn is_netplay(&self) -> bool {
+        self.netplay.is_some()
+    }
+
+    /// Exchanges the keypad with the netplay peer before the next frame and sets it to the
+    /// keys of both. `false` while the peer's keys for the frame have not arrived, the frame
+    /// must wait. Always `true` without netplay.
+    pub fn apply_netplay(&mut self) -> Result<bool, Error> {
+        if self.netplay.is_none() {
+            return Ok(true);
+        }
+        let (frame, hash) = (self.frame, self.state_hash());
+        let keys = match &mut self.netplay {
+            Some(netplay) => netplay.exchange(frame, hash)?,
+            None => None,
+        };
+        let Some(keys) = keys else {
+            return Ok(false);
+        };
+        for (key, pressed) in self.chip8.keys.iter_mut().enumerate() {
+            *pressed = keys & 1 << key != 0;
+        }
+        Ok(true)
+    }
+
+    /// Whether execution should stop before the instruction at PC, pausing when it does.
+    /// Checked before every instruction of a frame.
+    pub fn at_breakpoint(&mut self) -> bool {
+        let skip = self.break_skip.take();
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+        let pc = self.chip8.pc;
+        if skip == Some(pc) || !self.breakpoints.contains(&pc) {
+            return false;
+        }
+        info!("Breakpoint hit at 0x{:03X}", pc);
+        self.paused = true;
+        self.status_changed = true;
+        self.stopped_at = Some(pc);
+        true
+    }
+
+    /// FPS, instructions per second and frame time percentiles of the last couple of seconds.
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.metrics()
+    }
+
+    /// Publishes the metrics into `target` every couple of seconds, for readers on other
+    /// threads such as the metrics endpoint.
+    pub fn set_metrics_target(&mut self, target: Arc<Mutex<Metrics>>) {
+        self.published_metrics = Some(target);
+    }
+
+    /// Records a frame presented by `run_frames` after running `emulated_frames` frames of
+    /// `instructions` in total. The metrics are logged at debug level every couple of seconds.
+    pub fn record_frame(&mut self, emulated_frames: u32, instructions: u64) {
+        self.metrics
+            .record(Instant::now(), emulated_frames, instructions);
+        if self.metrics.window_completed() {
+            let metrics = self.metrics.metrics();
+            debug!(
+                fps = metrics.fps,
+                emulated_fps = metrics.emulated_fps,
+                ips = metrics.ips,
+                p50_ms = metrics.frame_time_p50.as_secs_f64() * 1000.0,
+                p95_ms = metrics.frame_time_p95.as_secs_f64() * 1000.0,
+                p99_ms = metrics.frame_time_p99.as_secs_f64() * 1000.0,
+                "Frame metrics"
+            );
+            if let Some(target) = &self.published_metrics {
+                *target.lock().unwrap() = metrics;
+            }
+        }
+    }
+
+    /// Returns whether the status changed since the previous call and resets the flag.
+    pub fn take_status_changed(&mut self) -> bool {
+        std::mem::take(&mut self.status_changed)
+    }
+
+    /// Sends the status to `target` whenever the frontends are told about a change.
+    pub fn set_status_target(&mut self, target: Sender<Status>) {
+        self.status_target = Some(target);
+    }
+
+    /// Passes a changed status on to the status target, dropped once its receiver is gone.
+    pub fn publish_status(&mut self, status: &Status) {
+        if let Some(target) = &self.status_target {
+            if target.send(status.clone()).is_err() {
+                self.status_target = None;
+            }
+        }
+    }
+
+    /// The instructions decoded so far, for `CpuController::tick`.
+    pub(crate) fn decode_cache(&mut self) -> &mut DecodeCache {
+        &mut self.decoded
+    }
+
+    #[cfg(feature = "dynarec")]
+    pub fn dynarec(&self) -> DynarecMode {
+        self.dynarec
+    }
+
+    /// Chooses whether `CpuController::step_frame` runs compiled blocks, see `DynarecMode`.
+    #[cfg(feature = "dynarec")]
+    pub fn set_dynarec(&mut self, mode: DynarecMode) {
+        info!("Block compiler {:?}", mode);
+        self.dynarec = mode;
+    }
+
+    /// Whether nothing has to see the instructions one by one, so compiled blocks may run.
+    #[cfg(feature = "dynarec")]
+    pub(crate) fn can_run_blocks(&self) -> bool {
+        self.plugins.is_empty()
+            && self.breakpoints.is_empty()
+            && (self.cheats.is_empty() || !self.cheats.is_enabled())
+            && !tracing::enabled!(target: "chip8::core::cpu", tracing::Level::DEBUG)
+    }
+
+    /// The block compiled from the instructions at `pc`, see `DynarecMode`.
+    #[cfg(feature = "dynarec")]
+    pub(crate) fn block_at(&mut self, pc: u16) -> Option<std::sync::Arc<Block>> {
+        self.decoded.blocks().get_or_compile(&self.chip8.ram, pc)
+    }
+
+    #[cfg(feature = "dynarec")]
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            chip8: self.chip8.clone(),
+            rng: self.rng.clone(),
+            random_draws: self.random_draws,
+        }
+    }
+
+    /// Returns to `checkpoint`, forgetting the instructions decoded from the bytes it restores
+    /// and marking the pixels it restores as changed.
+    #[cfg(feature = "dynarec")]
+    pub(crate) fn restore(&mut self, checkpoint: Checkpoint) {
+        self.replace_machine(checkpoint.chip8);
+        self.rng = checkpoint.rng;
+        self.random_draws = checkpoint.random_draws;
+    }
+
+    /// Switches to `chip8`, forgetting the instructions decoded from the RAM bytes that differ
+    /// and marking the pixels that differ as changed.
+    fn replace_machine(&mut self, chip8: CHIP8) {
+        for (address, (now, then)) in self.chip8.ram.iter().zip(&chip8.ram).enumerate() {
+            if now != then {
+                self.decoded.invalidate(address);
+            }
+        }
+        for y in 0..SCREEN_HEIGHT {
+            for plane in [0b01, 0b10] {
+                self.mark_changed(
+                    y,
+                    self.chip8.display.row(y, plane) ^ chip8.display.row(y, plane),
+                );
+            }
+        }
+        self.chip8 = chip8;
+    }
+
+    /// Keeps the last `seconds` of emulated frames to rewind through, 0 turns rewinding off.
+    pub fn set_rewind(&mut self, seconds: u32) {
+        if seconds == 0 {
+            self.rewind = None;
+            self.rewinding = false;
+            return;
+        }
+        info!("Keeping {} seconds of frames to rewind", seconds);
+        self.rewind = Some(RewindBuffer::new(seconds));
+    }
+
+    pub fn rewind_buffer(&self) -> Option<&RewindBuffer> {
+        self.rewind.as_ref()
+    }
+
+    pub fn is_rewinding(&self) -> bool {
+        self.rewinding
+    }
+
+    /// Steps back one frame per emulated frame while `held`. Not available during netplay,
+    /// where the peer keeps running, and ends a replay like a key press does.
+    pub fn set_rewinding(&mut self, held: bool) {
+        if self.rewind.is_none() || held == self.rewinding {
+            return;
+        }
+        if held && self.netplay.is_some() {
+            warn!("Rewinding is not available during netplay");
+            return;
+        }
+        if held {
+            self.take_over();
+        }
+        info!(
+            "Rewinding {} at frame {}",
+            if held { "started" } else { "stopped" },
+            self.frame
+        );
+        self.rewinding = held;
+    }
+
+    /// Stores the machine in the rewind buffer, if rewinding is enabled, called with the input
+    /// of the frame about to run applied.
+    pub fn record_rewind(&mut self) {
+        let Some(rewind) = &mut self.rewind else {
+            return;
+        };
+        let state = StateRef {
+            chip8: &self.chip8,
+            rom: &self.rom,
+            quirks: self.quirks,
+            one_shot_frames: self.one_shot_frames,
+            seed: self.seed,
+            random_draws: self.random_draws,
+            frame: self.frame,
+        };
+        rewind.push(&self.rng, self.random_draws, |bytes| state.write(bytes));
+    }
+
+    /// Returns to the machine before the last frame stored in the rewind buffer, `false` once
+    /// it is empty. An input recording forgets the keys of the frames undone.
+    pub fn rewind_frame(&mut self) -> Result<bool, Error> {
+        let Some(rewind) = &mut self.rewind else {
+            return Ok(false);
+        };
+        let Some(Rewound {
+            state,
+            mut rng,
+            random_draws: keyframe_draws,
+        }) = rewind.pop()?
+        else {
+            return Ok(false);
+        };
+        let mut chip8 = CHIP8::default();
+        let state = StateRef::read(state, &mut chip8)?;
+        if self.rom != state.rom {
+            self.rom.clear();
+            self.rom.extend_from_slice(state.rom);
+        }
+        self.quirks = state.quirks;
+        self.one_shot_frames = state.one_shot_frames;
+        self.seed = state.seed;
+        self.frame = state.frame;
+        let random_draws = state.random_draws;
+        for _ in keyframe_draws..random_draws {
+            rng.gen::<u8>();
+        }
+        self.replace_machine(chip8);
+        self.rng = rng;
+        self.random_draws = random_draws;
+        self.turbo = [None; 16];
+        self.history.clear();
+        if let Some(recording) = &mut self.recording {
+            recording.truncate(self.frame);
+        }
+        Ok(true)
+    }
+
     pub fn get_ram(&self) -> &[u8] {
         &self.chip8.ram
     }
@@ -57,6 +1155,7 @@ impl Emulator {
             return Err(anyhow!("Index out of bounds for RAM!"));
         }
         self.chip8.ram[index] = val;
+        self.decoded.invalidate(index);
         Ok(())
     }
 
@@ -96,9 +1195,27 @@ impl Emulator {
     }
 
     pub fn set_st(&mut self, val: u8) {
+        if val > 0 && self.chip8.st == 0 {
+            self.one_shot_frames = ONE_SHOT_BEEP_FRAMES;
+        }
         self.chip8.st = val;
     }
 
+    /// With `true` (the default) the buzzer sounds exactly while the sound timer is non-zero,
+    /// otherwise every activation of the timer plays a fixed short beep.
+    pub fn set_st_equals_buzzer(&mut self, enable: bool) {
+        self.quirks.st_equals_buzzer = enable;
+    }
+
+    /// Whether the buzzer should sound, for audio sinks and on-screen indicators.
+    pub fn is_buzzing(&self) -> bool {
+        if self.quirks.st_equals_buzzer {
+            self.chip8.st > 0
+        } else {
+            self.one_shot_frames > 0
+        }
+    }
+
     pub fn dec_st(&mut self) {
         if self.chip8.st > 0 {
             self.chip8.st -= 1;
@@ -108,6 +1225,7 @@ impl Emulator {
     pub fn dec_all_timers(&mut self) {
         self.dec_dt();
         self.dec_st();
+        self.one_shot_frames = self.one_shot_frames.saturating_sub(1);
     }
 
     pub fn get_pc(&self) -> u16 {
@@ -119,13 +1237,12 @@ impl Emulator {
     }
 
     pub fn inc_pc_by(&mut self, val: u16) {
-        self.chip8.pc += val;
+        // Out of range addresses fail on the next fetch
+        self.chip8.pc = self.chip8.pc.wrapping_add(val);
     }
 
     pub fn dec_pc_by(&mut self, val: u16) {
-        if self.chip8.pc > 0 {
-            self.chip8.pc -= val;
-        }
+        self.chip8.pc = self.chip8.pc.wrapping_sub(val);
     }
 
     pub fn get_i(&self) -> u16 {
@@ -137,7 +1254,25 @@ impl Emulator {
     }
 
     pub fn inc_i_by(&mut self, val: u16) {
-        self.chip8.i_reg += val;
+        self.chip8.i_reg = self.chip8.i_reg.wrapping_add(val);
+    }
+
+    /// Remembers an instruction fetched from `pc`, for `history`.
+    pub fn record_instruction(&mut self, pc: u16, opcode: u16) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back((pc, opcode));
+    }
+
+    /// Address and opcode of the last executed instructions, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Return addresses on the stack, the innermost call last.
+    pub fn get_stack(&self) -> &[u16] {
+        &self.chip8.stack[..self.chip8.sp as usize]
     }
 
     pub fn stack_pop(&mut self) -> Result<(), Error> {
@@ -171,14 +1306,13 @@ impl Emulator {
             return Err(anyhow!("HEX_DIGITS exceeds RAM size!"));
         }
 
-        for i in 0..HEX_DIGITS.len() {
-            self.chip8.ram[i] = HEX_DIGITS[i];
-        }
+        self.chip8.ram[..HEX_DIGITS.len()].copy_from_slice(&HEX_DIGITS);
+        self.decoded.invalidate_range(0..HEX_DIGITS.len());
 
         Ok(())
     }
 
-    fn load_rom_file(&mut self, path: &str) -> Result<(), Error> {
+    fn read_rom_file(path: &str) -> Result<Vec<u8>, Error> {
         info!("Loading ROM file from path: {}", path);
         let mut byte_vec: Vec<u8> = Vec::new();
         File::open(path)
@@ -187,9 +1321,12 @@ impl Emulator {
                 error!("Failed to read ROM file: {}", e);
                 anyhow!("Failed to read ROM file: {}", e)
             })?;
+        Ok(byte_vec)
+    }
 
+    fn load_rom(&mut self, rom: &[u8]) -> Result<(), Error> {
         // 4096 (RAM size) - 512 (Reserved RAM)
-        if byte_vec.len() > 3584 {
+        if rom.len() > 3584 {
             error!("The selected ROM size will overflow beyond the limit of RAM!");
             return Err(anyhow!(
                 "The selected ROM size will overflow beyond the limit of RAM!"
@@ -197,29 +1334,205 @@ impl Emulator {
         }
 
         let start_addr = 0x200;
-        for (i, byte) in byte_vec.iter().enumerate() {
+        for (i, byte) in rom.iter().enumerate() {
             self.chip8.ram[start_addr + i] = *byte;
         }
+        self.decoded
+            .invalidate_range(start_addr..start_addr + rom.len());
 
         Ok(())
     }
 
-    pub fn get_display(&self) -> [bool; SCREEN_WIDTH * SCREEN_HEIGHT] {
-        self.chip8.display
+    /// The palette indices of the screen pixels, row by row, borrowed so drawing a frame does
+    /// not copy the screen.
+    pub fn get_display(&self) -> &[u8; SCREEN_WIDTH * SCREEN_HEIGHT] {
+        self.chip8.display.pixels()
+    }
+
+    /// Renders the screen as text, one line per row with `█` for lit pixels of any plane and
+    /// `.` for dark ones.
+    pub fn display_to_string(&self) -> String {
+        let mut text = String::with_capacity((SCREEN_WIDTH * 3 + 1) * SCREEN_HEIGHT);
+        for row in self.chip8.display.pixels().chunks(SCREEN_WIDTH) {
+            text.extend(row.iter().map(|&pixel| if pixel == 0 { '.' } else { '█' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// XXH3 hash of the framebuffer, the same for the same screen on every platform.
+    pub fn display_hash(&self) -> u64 {
+        xxh3_64(self.chip8.display.pixels())
+    }
+
+    /// XXH3 hash of everything that decides how the machine runs from here: RAM, registers,
+    /// the used part of the stack, timers, keypad, screen, quirks, frame count and RNG
+    /// position. Two emulators with the same hash behave the same given the same input.
+    pub fn state_hash(&self) -> u64 {
+        let chip8 = &self.chip8;
+        let mut hasher = Xxh3::new();
+        hasher.update(&chip8.ram);
+        hasher.update(&chip8.v_reg);
+        for address in &chip8.stack[..chip8.sp as usize] {
+            hasher.update(&address.to_le_bytes());
+        }
+        hasher.update(&chip8.i_reg.to_le_bytes());
+        hasher.update(&chip8.pc.to_le_bytes());
+        hasher.update(&[chip8.sp, chip8.dt, chip8.st, chip8.plane_mask]);
+        hasher.update(&chip8.keys.map(|pressed| pressed as u8));
+        hasher.update(chip8.display.pixels());
+        hasher.update(&[self.quirks.to_bits(), self.one_shot_frames]);
+        hasher.update(&self.frame.to_le_bytes());
+        hasher.update(&self.seed.to_le_bytes());
+        hasher.update(&self.random_draws.to_le_bytes());
+        hasher.digest()
+    }
+
+    /// The machine as it is now, to continue from later with `load_state`.
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            chip8: self.chip8.clone(),
+            rom: self.rom.clone(),
+            quirks: self.quirks,
+            one_shot_frames: self.one_shot_frames,
+            seed: self.seed,
+            random_draws: self.random_draws,
+            frame: self.frame,
+        }
+    }
+
+    /// Continues from `state`, replacing the machine and the ROM. The RNG is seeded again and
+    /// advanced past the draws made before the state was saved.
+    pub fn load_state(&mut self, state: SaveState) {
+        info!("Loading a save state at frame {}", state.frame);
+        self.chip8 = state.chip8;
+        self.decoded.clear();
+        self.rom = state.rom;
+        self.quirks = state.quirks;
+        self.one_shot_frames = state.one_shot_frames;
+        self.seed = state.seed;
+        self.rng = StdRng::seed_from_u64(state.seed);
+        for _ in 0..state.random_draws {
+            self.rng.gen::<u8>();
+        }
+        self.random_draws = state.random_draws;
+        self.frame = state.frame;
+        self.turbo = [None; 16];
+        self.history.clear();
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
+        }
+        self.dirty = [u64::MAX; SCREEN_HEIGHT];
+        self.delta = [u64::MAX; SCREEN_HEIGHT];
+        self.status_changed = true;
+    }
+
+    /// Saves a capture of the screen without any window, e.g. from headless runs or tests.
+    #[cfg(feature = "image")]
+    pub fn export_display(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        format: ExportFormat,
+        scale: u32,
+        palette: &Palette,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        info!("Exporting display to: {}", path.display());
+        export::write_display(self.chip8.display.pixels(), path, format, scale, palette)
+    }
+
+    pub fn get_pixel(&self, index: usize) -> Result<u8, Error> {
+        if index >= SCREEN_WIDTH * SCREEN_HEIGHT {
+            return Err(anyhow!("Index out of range for this display!"));
+        }
+        Ok(self.chip8.display.pixels()[index])
     }
 
-    pub fn set_pixel(&mut self, index: usize, value: bool) -> Result<(), Error> {
+    pub fn set_pixel(&mut self, index: usize, value: u8) -> Result<(), Error> {
         if index >= SCREEN_WIDTH * SCREEN_HEIGHT {
             return Err(anyhow!("Index out of range for this display!"));
         }
 
-        self.chip8.display[index] = value;
+        if self.chip8.display.set(index, value) {
+            self.mark_dirty(index);
+        }
 
         Ok(())
     }
 
+    /// Flips the pixels set in the packed row `bits` of row `y` in `planes`, as a sprite row
+    /// drawn over them does, and returns whether one of them was lit, i.e. whether the sprite
+    /// collided there.
+    pub fn xor_row(&mut self, y: usize, bits: u64, planes: u8) -> Result<bool, Error> {
+        if y >= SCREEN_HEIGHT {
+            return Err(anyhow!("Row out of range for this display!"));
+        }
+        let collision = self.chip8.display.xor_row(y, bits, planes);
+        if planes != 0 {
+            self.mark_changed(y, bits);
+        }
+        Ok(collision)
+    }
+
+    /// Clears the selected planes only, the others keep their pixels.
     pub fn clear_screen(&mut self) {
-        self.chip8.display = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let mask = self.chip8.plane_mask;
+        self.mark_lit_dirty(mask);
+        self.chip8.display.clear(mask);
+    }
+
+    /// Marks the pixels lit in `planes` as changed, before they are cleared.
+    fn mark_lit_dirty(&mut self, planes: u8) {
+        for y in 0..SCREEN_HEIGHT {
+            self.mark_changed(y, self.chip8.display.row(y, planes));
+        }
+    }
+
+    pub fn get_plane_mask(&self) -> u8 {
+        self.chip8.plane_mask
+    }
+
+    pub fn set_plane_mask(&mut self, mask: u8) -> Result<(), Error> {
+        if mask > 0b11 {
+            error!("Invalid plane mask!");
+            return Err(anyhow!("Invalid plane mask: only planes 1 and 2 exist!"));
+        }
+        self.chip8.plane_mask = mask;
+        Ok(())
+    }
+
+    /// Returns the display indices changed since the previous call and resets the tracking.
+    pub fn take_dirty(&mut self) -> Vec<usize> {
+        let mut dirty = Vec::new();
+        for (y, &row) in self.dirty.iter().enumerate() {
+            for_each_bit(row, |x| dirty.push(y * SCREEN_WIDTH + x));
+        }
+        self.dirty = [0; SCREEN_HEIGHT];
+        dirty
+    }
+
+    /// Returns the `(x, y, color index)` pixels changed since the previous call, e.g. for
+    /// streaming the screen over the network. Tracked apart from `take_dirty`, which the
+    /// frontends take every frame.
+    pub fn take_display_delta(&mut self) -> Vec<(usize, usize, u8)> {
+        let pixels = self.chip8.display.pixels();
+        let mut delta = Vec::new();
+        for (y, &row) in self.delta.iter().enumerate() {
+            for_each_bit(row, |x| delta.push((x, y, pixels[y * SCREEN_WIDTH + x])));
+        }
+        self.delta = [0; SCREEN_HEIGHT];
+        delta
+    }
+
+    fn mark_dirty(&mut self, index: usize) {
+        self.mark_changed(index / SCREEN_WIDTH, 1 << 63 >> (index % SCREEN_WIDTH));
+    }
+
+    /// Marks the pixels set in the packed row `bits` of row `y` as changed for both
+    /// `take_dirty` and `take_display_delta`.
+    fn mark_changed(&mut self, y: usize, bits: u64) {
+        self.dirty[y] |= bits;
+        self.delta[y] |= bits;
     }
 
     pub fn key_press(&mut self, idx: u8) -> Result<(), Error> {
@@ -227,7 +1540,7 @@ impl Emulator {
             error!("Invalid key press!");
             return Err(anyhow!("Invalid key press!"));
         }
-        self.chip8.keys[idx as usize] = true;
+        self.set_key(idx, true);
         Ok(())
     }
 
@@ -236,10 +1549,58 @@ impl Emulator {
             error!("Invalid key release!");
             return Err(anyhow!("Invalid key release!"));
         }
-        self.chip8.keys[idx as usize] = false;
+        self.set_key(idx, false);
+        Ok(())
+    }
+
+    fn set_key(&mut self, idx: u8, pressed: bool) {
+        // The keypad is set from both players' keys before every frame
+        if let Some(netplay) = &mut self.netplay {
+            netplay.set_key(idx, pressed);
+            return;
+        }
+        if self.chip8.keys[idx as usize] == pressed {
+            return;
+        }
+        self.chip8.keys[idx as usize] = pressed;
+        if let Some(recording) = &mut self.recording {
+            recording.push(self.frame, idx, pressed);
+        }
+    }
+
+    /// Holds an autofire key, pressed and released `rate` times per second of emulated time
+    /// until `turbo_release`. A press and a release take a frame each, so 30 is the fastest.
+    pub fn turbo_press(&mut self, idx: u8, rate: u32) -> Result<(), Error> {
+        if idx > 0xF || rate == 0 {
+            error!("Invalid turbo key press!");
+            return Err(anyhow!("Invalid turbo key press!"));
+        }
+        let period = (REFRESH_RATE / (2 * rate)).max(1) as u64;
+        self.turbo[idx as usize] = Some((self.frame, period));
         Ok(())
     }
 
+    pub fn turbo_release(&mut self, idx: u8) -> Result<(), Error> {
+        if idx > 0xF {
+            error!("Invalid turbo key release!");
+            return Err(anyhow!("Invalid turbo key release!"));
+        }
+        if self.turbo[idx as usize].take().is_some() {
+            self.set_key(idx, false);
+        }
+        Ok(())
+    }
+
+    /// Toggles the held autofire keys for the next frame.
+    pub fn apply_turbo(&mut self) {
+        for idx in 0..16u8 {
+            if let Some((start, period)) = self.turbo[idx as usize] {
+                let pressed = ((self.frame - start) / period).is_multiple_of(2);
+                self.set_key(idx, pressed);
+            }
+        }
+    }
+
     pub fn is_key_pressed(&self, idx: u8) -> Result<bool, Error> {
         if idx > 0xF {
             error!("Invalid key index!");
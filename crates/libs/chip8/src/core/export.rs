@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use image::{ImageFormat, RgbaImage};
+use shared::config::config::Palette;
+
+use super::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::sprites::{Sprite, MAX_ROWS};
+
+/// File formats a screen capture can be written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// RGBA image colored with the palette.
+    Png,
+    /// Binary (P4) bitmap, every lit pixel is black whatever its plane.
+    Pbm,
+}
+
+impl ExportFormat {
+    /// Picks the format from the file extension, e.g. `shot.png`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ExportFormat::Png),
+            "pbm" => Some(ExportFormat::Pbm),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `display` to `path`, every CHIP-8 pixel becoming a `scale` x `scale` square.
+pub fn write_display(
+    display: &[u8],
+    path: &Path,
+    format: ExportFormat,
+    scale: u32,
+    palette: &Palette,
+) -> Result<(), Error> {
+    if scale == 0 {
+        return Err(anyhow!("Export scale must be at least 1!"));
+    }
+    let width = SCREEN_WIDTH as u32 * scale;
+    let height = SCREEN_HEIGHT as u32 * scale;
+    let pixel =
+        |x: u32, y: u32| display[(y / scale) as usize * SCREEN_WIDTH + (x / scale) as usize];
+
+    match format {
+        ExportFormat::Png => colored(display, scale, palette)
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(|e| anyhow!("Failed to write PNG capture: {}", e)),
+        ExportFormat::Pbm => {
+            let file =
+                File::create(path).map_err(|e| anyhow!("Failed to create PBM capture: {}", e))?;
+            let mut writer = BufWriter::new(file);
+            let mut data = format!("P4\n{} {}\n", width, height).into_bytes();
+            // Rows are packed 8 pixels per byte, most significant bit first, 1 is black
+            let row_bytes = width.div_ceil(8) as usize;
+            for y in 0..height {
+                let mut row = vec![0u8; row_bytes];
+                for x in 0..width {
+                    if pixel(x, y) != 0 {
+                        row[x as usize / 8] |= 0b1000_0000 >> (x % 8);
+                    }
+                }
+                data.extend_from_slice(&row);
+            }
+            writer
+                .write_all(&data)
+                .and_then(|_| writer.flush())
+                .map_err(|e| anyhow!("Failed to write PBM capture: {}", e))
+        }
+    }
+}
+
+/// Encodes `display` as a PNG in memory, e.g. to serve it over HTTP, scaled as
+/// `write_display` does.
+pub fn encode_png(display: &[u8], scale: u32, palette: &Palette) -> Result<Vec<u8>, Error> {
+    if scale == 0 {
+        return Err(anyhow!("Export scale must be at least 1!"));
+    }
+    let mut png = Vec::new();
+    colored(display, scale, palette)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to encode PNG capture: {}", e))?;
+    Ok(png)
+}
+
+/// `display` colored with the palette, every CHIP-8 pixel a `scale` x `scale` square.
+fn colored(display: &[u8], scale: u32, palette: &Palette) -> RgbaImage {
+    let colors = palette.colors();
+    RgbaImage::from_fn(
+        SCREEN_WIDTH as u32 * scale,
+        SCREEN_HEIGHT as u32 * scale,
+        |x, y| {
+            let color = colors
+                [display[(y / scale) as usize * SCREEN_WIDTH + (x / scale) as usize] as usize];
+            image::Rgba([color.r, color.g, color.b, color.a])
+        },
+    )
+}
+
+/// Sprites per row of a sprite sheet.
+const SHEET_COLUMNS: usize = 16;
+
+/// Writes `sprites` to `path` as a PNG sheet, 16 to a row in order, each in an 8 x 15 cell
+/// colored with the palette and kept apart by a transparent line.
+pub fn write_sprite_sheet(
+    sprites: &[Sprite],
+    path: &Path,
+    scale: u32,
+    palette: &Palette,
+) -> Result<(), Error> {
+    if scale == 0 {
+        return Err(anyhow!("Export scale must be at least 1!"));
+    }
+    if sprites.is_empty() {
+        return Err(anyhow!("No sprites to write to the sheet!"));
+    }
+    let (cell_width, cell_height) = (9, MAX_ROWS as u32 + 1);
+    let columns = sprites.len().min(SHEET_COLUMNS) as u32;
+    let lines = sprites.len().div_ceil(SHEET_COLUMNS) as u32;
+    let colors = palette.colors();
+    let image = RgbaImage::from_fn(
+        columns * cell_width * scale,
+        lines * cell_height * scale,
+        |x, y| {
+            let (x, y) = (x / scale, y / scale);
+            let index = (y / cell_height * columns + x / cell_width) as usize;
+            let (column, row) = (x % cell_width, y % cell_height);
+            match sprites
+                .get(index)
+                .and_then(|sprite| sprite.rows.get(row as usize))
+            {
+                Some(bits) if column < 8 => {
+                    let color = colors[(bits >> (7 - column) & 1) as usize];
+                    image::Rgba([color.r, color.g, color.b, color.a])
+                }
+                _ => image::Rgba([0, 0, 0, 0]),
+            }
+        },
+    );
+    image
+        .save_with_format(path, ImageFormat::Png)
+        .map_err(|e| anyhow!("Failed to write sprite sheet: {}", e))
+}
@@ -0,0 +1,43 @@
+/// A ROM offered on the first run, see `GALLERY`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GalleryRom {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub bytes: &'static [u8],
+}
+
+/// Public-domain classics from the repository's `roms` folder and two demos of
+/// `crates/libs/chip8/roms`, compiled in so the emulator has something to run when it is
+/// started without a ROM and no ROM folder is set up.
+pub const GALLERY: &[GalleryRom] = &[
+    GalleryRom {
+        name: "Brix",
+        description: "Breakout: move the paddle with 4 and 6 to clear the bricks",
+        bytes: include_bytes!("../../../../../roms/BRIX"),
+    },
+    GalleryRom {
+        name: "Maze",
+        description: "Draws a random maze across the screen, a 34 byte classic",
+        bytes: include_bytes!("../../../../../roms/MAZE"),
+    },
+    GalleryRom {
+        name: "Kaleidoscope",
+        description: "Paint with 2, 4, 6 and 8, press 0 to replay the pattern mirrored",
+        bytes: include_bytes!("../../../../../roms/KALEID"),
+    },
+    GalleryRom {
+        name: "15 Puzzle",
+        description: "Slide the tiles back in order, the keypad keys move the tile at that spot",
+        bytes: include_bytes!("../../../../../roms/15PUZZLE"),
+    },
+    GalleryRom {
+        name: "Logo",
+        description: "Draws CHIP-8 in the middle of the screen",
+        bytes: include_bytes!("../../roms/logo.ch8"),
+    },
+    GalleryRom {
+        name: "Keypad",
+        description: "Shows the last pressed key with a short beep, to try the key mapping",
+        bytes: include_bytes!("../../roms/keypad.ch8"),
+    },
+];
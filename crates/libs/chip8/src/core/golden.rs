@@ -0,0 +1,142 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use serde_derive::Deserialize;
+
+use super::chip8::{CHIP8, SCREEN_WIDTH};
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::replay::Quirks;
+
+/// Manifest listing the ROMs of a golden-test suite folder.
+pub const MANIFEST: &str = "suite.yaml";
+
+/// A ROM of the suite and how long to run it. Its known-good screen is the capture
+/// `<name>.txt` next to the manifest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Golden {
+    pub name: String,
+    /// Path of the ROM, relative to the suite folder
+    pub rom: PathBuf,
+    #[serde(default = "default_frames")]
+    pub frames: u64,
+    #[serde(default = "default_cycles_per_frame")]
+    pub cycles_per_frame: u32,
+    #[serde(default)]
+    pub quirks: Quirks,
+}
+
+fn default_frames() -> u64 {
+    120
+}
+
+fn default_cycles_per_frame() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    /// The screen differs from the capture, with a description of the differences
+    Fail(String),
+    /// The ROM is not in the folder, e.g. a test suite that was not downloaded
+    MissingRom,
+    /// No capture was saved yet, see `check_suite`
+    MissingCapture,
+    /// The capture was (re)written from this run
+    Blessed,
+}
+
+/// Runs `rom` without a frontend from power-on for `frames` emulated frames, with a fixed
+/// RNG seed so the screen is the same on every run.
+pub fn run_headless(
+    rom: &[u8],
+    frames: u64,
+    cycles_per_frame: u32,
+    quirks: Quirks,
+) -> Result<Emulator, Error> {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(rom)?;
+    emulator.set_seed(0);
+    emulator.set_quirks(quirks);
+    let cpu = CpuController;
+    for _ in 0..frames {
+        cpu.step_frame(&mut emulator, cycles_per_frame)?;
+    }
+    Ok(emulator)
+}
+
+/// Renders a framebuffer as text, one line per row: `.` for color 0 and `#`, `+` and `*`
+/// for colors 1 to 3.
+pub fn capture(display: &[u8]) -> String {
+    let mut text = String::with_capacity(display.len() + display.len() / SCREEN_WIDTH);
+    for row in display.chunks(SCREEN_WIDTH) {
+        text.extend(row.iter().map(|&pixel| match pixel {
+            0 => '.',
+            1 => '#',
+            2 => '+',
+            _ => '*',
+        }));
+        text.push('\n');
+    }
+    text
+}
+
+/// Lists the rows where two captures differ.
+fn diff(expected: &str, actual: &str) -> String {
+    let mut out = String::new();
+    let rows = expected.lines().zip(actual.lines()).enumerate();
+    for (row, (expected, actual)) in rows.filter(|(_, (e, a))| e != a) {
+        let _ = writeln!(out, "row {:2} expected {}", row, expected);
+        let _ = writeln!(out, "       actual   {}", actual);
+    }
+    if expected.lines().count() != actual.lines().count() {
+        let _ = writeln!(out, "the captures have a different number of rows");
+    }
+    out
+}
+
+pub fn load_manifest(folder: &Path) -> Result<Vec<Golden>, Error> {
+    let path = folder.join(MANIFEST);
+    let text = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    serde_yaml::from_str(&text).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+}
+
+/// Runs one ROM of the suite in `folder` and compares its screen to the capture, or writes
+/// the capture when `bless` is set.
+pub fn check(folder: &Path, golden: &Golden, bless: bool) -> Result<Outcome, Error> {
+    let Ok(rom) = fs::read(folder.join(&golden.rom)) else {
+        return Ok(Outcome::MissingRom);
+    };
+    let emulator = run_headless(&rom, golden.frames, golden.cycles_per_frame, golden.quirks)
+        .map_err(|e| anyhow!("{} stopped: {}", golden.name, e))?;
+    let actual = capture(emulator.get_display());
+    let path = folder.join(format!("{}.txt", golden.name));
+    if bless {
+        fs::write(&path, &actual)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        return Ok(Outcome::Blessed);
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        return Ok(Outcome::MissingCapture);
+    };
+    Ok(if expected == actual {
+        Outcome::Pass
+    } else {
+        Outcome::Fail(diff(&expected, &actual))
+    })
+}
+
+/// Checks every ROM listed in the manifest of `folder`, see `check`.
+pub fn check_suite(folder: &Path, bless: bool) -> Result<Vec<(Golden, Outcome)>, Error> {
+    load_manifest(folder)?
+        .into_iter()
+        .map(|golden| {
+            let outcome = check(folder, &golden, bless)?;
+            Ok((golden, outcome))
+        })
+        .collect()
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeSet;
+
+use shared::config::profile::rom_hash;
+
+use super::analysis::{successors, word_at, START_ADDR};
+use super::instruction::Instruction;
+
+/// CHIP-8 extension a ROM can be written for, in the order they added to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::SuperChip => "SUPER-CHIP",
+            Platform::XoChip => "XO-CHIP",
+        }
+    }
+}
+
+/// A reachable instruction only some platforms have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub address: u16,
+    pub opcode: u16,
+    pub platform: Platform,
+    pub description: &'static str,
+}
+
+/// What identifies a ROM when reporting an issue: its size and checksums, and the platform its
+/// instructions point to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub size: usize,
+    /// Lowercase hex, as the ROM profiles and database use it
+    pub sha1: String,
+    pub crc32: u32,
+    /// The first use of each extended instruction, by address
+    pub extensions: Vec<Extension>,
+}
+
+impl Identity {
+    pub fn new(rom: &[u8]) -> Self {
+        Self {
+            size: rom.len(),
+            sha1: rom_hash(rom),
+            crc32: crc32fast::hash(rom),
+            extensions: scan(rom),
+        }
+    }
+
+    /// The newest platform whose instructions the ROM uses, plain CHIP-8 when it uses none.
+    pub fn platform(&self) -> Platform {
+        self.extensions
+            .iter()
+            .map(|extension| extension.platform)
+            .max()
+            .unwrap_or(Platform::Chip8)
+    }
+}
+
+/// The platform and meaning of `opcode` when it is not a plain CHIP-8 instruction.
+fn extension(opcode: u16) -> Option<(Platform, &'static str)> {
+    Some(match opcode {
+        0x00C1..=0x00CF => (Platform::SuperChip, "scroll down"),
+        0x00FB => (Platform::SuperChip, "scroll right"),
+        0x00FC => (Platform::SuperChip, "scroll left"),
+        0x00FD => (Platform::SuperChip, "exit"),
+        0x00FE => (Platform::SuperChip, "low resolution"),
+        0x00FF => (Platform::SuperChip, "high resolution"),
+        0x00D1..=0x00DF => (Platform::XoChip, "scroll up"),
+        0xF000 => (Platform::XoChip, "long I load"),
+        0xF002 => (Platform::XoChip, "load audio pattern"),
+        _ => match (opcode >> 12, opcode & 0x00FF, opcode & 0x000F) {
+            (0xD, _, 0) => (Platform::SuperChip, "16x16 sprite"),
+            (0xF, 0x30, _) => (Platform::SuperChip, "large font character"),
+            (0xF, 0x75, _) => (Platform::SuperChip, "save flags"),
+            (0xF, 0x85, _) => (Platform::SuperChip, "load flags"),
+            (0x5, _, 2) => (Platform::XoChip, "save register range"),
+            (0x5, _, 3) => (Platform::XoChip, "load register range"),
+            (0xF, 0x01, _) => (Platform::XoChip, "select planes"),
+            (0xF, 0x3A, _) => (Platform::XoChip, "set pitch"),
+            _ => return None,
+        },
+    })
+}
+
+/// Follows the code from 0x200 as `CodeMap` does, but also through the extended instructions
+/// this interpreter does not run, collecting the first use of each.
+fn scan(rom: &[u8]) -> Vec<Extension> {
+    let mut extensions: Vec<Extension> = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![START_ADDR];
+    while let Some(address) = pending.pop() {
+        if !visited.insert(address) {
+            continue;
+        }
+        let Some(opcode) = word_at(rom, address) else {
+            continue;
+        };
+        if let Some((platform, description)) = extension(opcode) {
+            extensions.push(Extension {
+                address,
+                opcode,
+                platform,
+                description,
+            });
+            // F000 is followed by the 16 bit address it loads, and 00FD stops the program
+            match opcode {
+                0x00FD => {}
+                0xF000 => pending.push(address.wrapping_add(4)),
+                _ => pending.push(address.wrapping_add(2)),
+            }
+            continue;
+        }
+        if let Some(instruction) = Instruction::decode(opcode) {
+            pending.extend(successors(rom, address, &instruction));
+        }
+    }
+    extensions.sort_by_key(|extension| extension.address);
+    let mut seen = BTreeSet::new();
+    extensions.retain(|extension| seen.insert(extension.description));
+    extensions
+}
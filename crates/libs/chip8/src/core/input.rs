@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Error};
+use shared::config::config::{ChipSettings, KeyboardLayout, Player, Rotation, Turbo};
+
+use super::controller::{Hotkey, InputController, InputEvent};
+
+/// Host keys forming the 4x4 keypad grid, row by row (1234 / QWER / ASDF / ZXCV).
+pub const HOST_LAYOUT: [char; 16] = [
+    '1', '2', '3', '4', 'Q', 'W', 'E', 'R', 'A', 'S', 'D', 'F', 'Z', 'X', 'C', 'V',
+];
+
+/// CHIP-8 keys at the same grid positions as `HOST_LAYOUT` (123C / 456D / 789E / A0BF).
+pub const KEYPAD_LAYOUT: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Maps a host key, given as its upper-case character, to the CHIP-8 key at the same position.
+pub fn map_key(key: char) -> Option<u8> {
+    HOST_LAYOUT
+        .iter()
+        .position(|host| *host == key.to_ascii_uppercase())
+        .map(|position| KEYPAD_LAYOUT[position])
+}
+
+/// Maps a host key to the frontend hotkey bound to it.
+pub fn map_hotkey(key: char) -> Option<Hotkey> {
+    match key.to_ascii_uppercase() {
+        'P' => Some(Hotkey::CyclePalette),
+        'T' => Some(Hotkey::ToggleCrt),
+        'B' => Some(Hotkey::ToggleGrid),
+        'H' => Some(Hotkey::TogglePause),
+        'G' => Some(Hotkey::CycleSpeed),
+        'N' => Some(Hotkey::CycleWaveform),
+        '=' | '+' => Some(Hotkey::VolumeUp),
+        '-' => Some(Hotkey::VolumeDown),
+        'M' => Some(Hotkey::ToggleMute),
+        'O' => Some(Hotkey::ToggleScope),
+        'K' => Some(Hotkey::ToggleRecording),
+        'J' => Some(Hotkey::ToggleCheats),
+        'U' => Some(Hotkey::Rewind),
+        '`' => Some(Hotkey::ToggleLogConsole),
+        _ => None,
+    }
+}
+
+/// Maps a key pressed while looking at a screen rotated by `rotation` to the key the ROM
+/// expects, by turning the 1-9 block (with the 2/4/6/8 directions) back around key 5.
+pub fn rotate_key(key: u8, rotation: Rotation) -> u8 {
+    if !(1..=9).contains(&key) {
+        return key;
+    }
+    let (mut dx, mut dy) = ((key as i8 - 1) % 3 - 1, (key as i8 - 1) / 3 - 1);
+    for _ in 0..rotation.quarter_turns() {
+        (dx, dy) = (dy, -dx);
+    }
+    ((dy + 1) * 3 + dx + 1 + 1) as u8
+}
+
+/// Input events collected from callback-driven frontends until the next frame polls them.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: Vec<InputEvent>,
+    key_rotation: Rotation,
+    // Only set for frontends reporting typed characters
+    keyboard_layout: Option<KeyboardLayout>,
+    // Autofire bindings as host key, CHIP-8 key and presses per second
+    turbo: Vec<(char, u8, u32)>,
+    // Host keys of the grid when they differ from `HOST_LAYOUT`
+    host_layout: Option<[char; 16]>,
+    // Digits pressed with shift to pick a save slot, their releases are not keypad releases
+    slot_digits: u16,
+}
+
+impl EventQueue {
+    /// Remaps the keypad with `rotate_key` for the given screen rotation.
+    pub fn set_key_rotation(&mut self, rotation: Rotation) {
+        self.key_rotation = rotation;
+    }
+
+    /// Translates typed characters back to US QWERTY positions, for frontends reporting
+    /// characters rather than physical keys.
+    pub fn set_keyboard_layout(&mut self, layout: KeyboardLayout) {
+        self.keyboard_layout = Some(layout);
+    }
+
+    /// Moves the keypad to the keys of `player`, e.g. the right half of the keyboard for a
+    /// second player.
+    pub fn set_player(&mut self, player: &Player) -> Result<(), Error> {
+        let keys: Vec<char> = player
+            .keys
+            .chars()
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let layout = <[char; 16]>::try_from(keys)
+            .map_err(|_| anyhow!("A player needs exactly 16 keys, got '{}'", player.keys))?;
+        self.host_layout = Some(layout);
+        Ok(())
+    }
+
+    /// Maps a host key through the player's grid, see `map_key`.
+    pub fn map_key(&self, key: char) -> Option<u8> {
+        let Some(layout) = &self.host_layout else {
+            return map_key(key);
+        };
+        layout
+            .iter()
+            .position(|host| *host == key.to_ascii_uppercase())
+            .map(|position| KEYPAD_LAYOUT[position])
+    }
+
+    /// Binds autofire keys, they take precedence over the keypad and hotkey mappings.
+    pub fn set_turbo(&mut self, bindings: &[Turbo]) -> Result<(), Error> {
+        self.turbo = bindings
+            .iter()
+            .map(|binding| {
+                let key = binding
+                    .chip8_key
+                    .to_digit(16)
+                    .ok_or_else(|| anyhow!("Invalid turbo key '{}'", binding.chip8_key))?;
+                if binding.rate == 0 {
+                    return Err(anyhow!(
+                        "The turbo rate of '{}' must be at least 1",
+                        binding.host_key
+                    ));
+                }
+                Ok((
+                    binding.host_key.to_ascii_uppercase(),
+                    key as u8,
+                    binding.rate,
+                ))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, event: InputEvent) {
+        self.events.push(event);
+    }
+
+    /// Translates a host key transition through the keypad and hotkey mappings.
+    pub fn push_key(&mut self, key: char, pressed: bool, repeat: bool) {
+        self.push_key_with_shift(key, false, pressed, repeat);
+    }
+
+    /// As `push_key`, but a digit pressed with shift picks the save slot of that number. Its
+    /// repeats and release stay off the keypad, also when shift is let go first.
+    pub fn push_key_with_shift(&mut self, key: char, shift: bool, pressed: bool, repeat: bool) {
+        let key = self
+            .keyboard_layout
+            .map_or(key, |layout| layout.to_qwerty(key));
+        if let Some(digit) = key.to_digit(10) {
+            let bit = 1 << digit;
+            if shift && pressed && !repeat {
+                self.events
+                    .push(InputEvent::Hotkey(Hotkey::SelectSlot(digit as u8)));
+                self.slot_digits |= bit;
+                return;
+            }
+            if self.slot_digits & bit != 0 {
+                if !pressed {
+                    self.slot_digits &= !bit;
+                }
+                return;
+            }
+        }
+        let turbo = self
+            .turbo
+            .iter()
+            .find(|(host, _, _)| *host == key.to_ascii_uppercase());
+        if let Some(&(_, chip8_key, rate)) = turbo {
+            if !repeat {
+                self.events.push(if pressed {
+                    InputEvent::TurboDown(chip8_key, rate)
+                } else {
+                    InputEvent::TurboUp(chip8_key)
+                });
+            }
+        } else if let Some(chip8_key) = self.map_key(key).map(|k| rotate_key(k, self.key_rotation))
+        {
+            self.events.push(if pressed {
+                InputEvent::KeyDown(chip8_key)
+            } else {
+                InputEvent::KeyUp(chip8_key)
+            });
+        } else if let Some(hotkey) = map_hotkey(key).filter(|_| !repeat) {
+            if hotkey == Hotkey::Rewind {
+                self.events.push(InputEvent::Rewind(pressed));
+            } else if pressed {
+                self.events.push(InputEvent::Hotkey(hotkey));
+            }
+        }
+    }
+}
+
+impl InputController for EventQueue {
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn set_keymap(&mut self, settings: &ChipSettings, rom: Option<&str>) -> Result<(), Error> {
+        if self.keyboard_layout.is_some() {
+            self.keyboard_layout = Some(settings.keyboard_layout);
+        }
+        self.set_turbo(settings.turbo_for(rom))?;
+        match settings.active_player() {
+            Some(player) => self.set_player(player),
+            None => {
+                self.host_layout = None;
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
+use std::fmt;
+
 use anyhow::Error;
-use rand::Rng;
 use tracing::error;
 
 use super::{
@@ -24,9 +25,9 @@ pub enum Instruction {
     Op8XY3(u8, u8),
     Op8XY4(u8, u8),
     Op8XY5(u8, u8),
-    Op8XY6(u8),
+    Op8XY6(u8, u8),
     Op8XY7(u8, u8),
-    Op8XYE(u8),
+    Op8XYE(u8, u8),
     Op9XY0(u8, u8),
     OpANNN(u16),
     OpBNNN(u16),
@@ -43,222 +44,467 @@ pub enum Instruction {
     OpFX33(u8),
     OpFX55(u8),
     OpFX65(u8),
+    OpFN01(u8),
 }
 
 impl Instruction {
+    /// Decodes an opcode, `None` for the ones this interpreter does not support.
+    pub fn decode(word: u16) -> Option<Self> {
+        let x = ((word >> 8) & 0x0F) as u8;
+        let y = ((word >> 4) & 0x0F) as u8;
+        let nibble = (word & 0x0F) as u8;
+        let addr = word & 0x0FFF;
+        let byte = word as u8;
+        Some(match (word >> 12, nibble, byte) {
+            (0x0, _, _) => match word {
+                0x0000 => Instruction::Op0000,
+                0x00E0 => Instruction::Op00E0,
+                0x00EE => Instruction::Op00EE,
+                _ => return None,
+            },
+            (0x1, _, _) => Instruction::Op1NNN(addr),
+            (0x2, _, _) => Instruction::Op2NNN(addr),
+            (0x3, _, _) => Instruction::Op3XNN(x, byte),
+            (0x4, _, _) => Instruction::Op4XNN(x, byte),
+            (0x5, _, _) => Instruction::Op5XY0(x, y),
+            (0x6, _, _) => Instruction::Op6XNN(x, byte),
+            (0x7, _, _) => Instruction::Op7XNN(x, byte),
+            (0x8, 0x0, _) => Instruction::Op8XY0(x, y),
+            (0x8, 0x1, _) => Instruction::Op8XY1(x, y),
+            (0x8, 0x2, _) => Instruction::Op8XY2(x, y),
+            (0x8, 0x3, _) => Instruction::Op8XY3(x, y),
+            (0x8, 0x4, _) => Instruction::Op8XY4(x, y),
+            (0x8, 0x5, _) => Instruction::Op8XY5(x, y),
+            (0x8, 0x6, _) => Instruction::Op8XY6(x, y),
+            (0x8, 0x7, _) => Instruction::Op8XY7(x, y),
+            (0x8, 0xE, _) => Instruction::Op8XYE(x, y),
+            (0x9, _, _) => Instruction::Op9XY0(x, y),
+            (0xA, _, _) => Instruction::OpANNN(addr),
+            (0xB, _, _) => Instruction::OpBNNN(addr),
+            (0xC, _, _) => Instruction::OpCXNN(x, byte),
+            (0xD, _, _) => Instruction::OpDXYN(x, y, nibble),
+            (0xE, _, 0x9E) => Instruction::OpEX9E(x),
+            (0xE, _, 0xA1) => Instruction::OpEXA1(x),
+            (0xF, _, 0x01) => Instruction::OpFN01(x),
+            (0xF, _, 0x07) => Instruction::OpFX07(x),
+            (0xF, _, 0x0A) => Instruction::OpFX0A(x),
+            (0xF, _, 0x15) => Instruction::OpFX15(x),
+            (0xF, _, 0x18) => Instruction::OpFX18(x),
+            (0xF, _, 0x1E) => Instruction::OpFX1E(x),
+            (0xF, _, 0x29) => Instruction::OpFX29(x),
+            (0xF, _, 0x33) => Instruction::OpFX33(x),
+            (0xF, _, 0x55) => Instruction::OpFX55(x),
+            (0xF, _, 0x65) => Instruction::OpFX65(x),
+            _ => return None,
+        })
+    }
+
     pub fn call(&self, emu: &mut Emulator) -> Result<(), Error> {
+        match *self {
+            Instruction::Op0000 => op_0000(emu),
+            Instruction::Op00E0 => op_00e0(emu),
+            Instruction::Op00EE => op_00ee(emu),
+            Instruction::Op1NNN(addr) => op_1nnn(emu, addr),
+            Instruction::Op2NNN(addr) => op_2nnn(emu, addr),
+            Instruction::Op3XNN(x, byte) => op_3xnn(emu, x, byte),
+            Instruction::Op4XNN(x, byte) => op_4xnn(emu, x, byte),
+            Instruction::Op5XY0(x, y) => op_5xy0(emu, x, y),
+            Instruction::Op6XNN(x, byte) => op_6xnn(emu, x, byte),
+            Instruction::Op7XNN(x, byte) => op_7xnn(emu, x, byte),
+            Instruction::Op8XY0(x, y) => op_8xy0(emu, x, y),
+            Instruction::Op8XY1(x, y) => op_8xy1(emu, x, y),
+            Instruction::Op8XY2(x, y) => op_8xy2(emu, x, y),
+            Instruction::Op8XY3(x, y) => op_8xy3(emu, x, y),
+            Instruction::Op8XY4(x, y) => op_8xy4(emu, x, y),
+            Instruction::Op8XY5(x, y) => op_8xy5(emu, x, y),
+            Instruction::Op8XY6(x, y) => op_8xy6(emu, x, y),
+            Instruction::Op8XY7(x, y) => op_8xy7(emu, x, y),
+            Instruction::Op8XYE(x, y) => op_8xye(emu, x, y),
+            Instruction::Op9XY0(x, y) => op_9xy0(emu, x, y),
+            Instruction::OpANNN(addr) => op_annn(emu, addr),
+            Instruction::OpBNNN(addr) => op_bnnn(emu, addr),
+            Instruction::OpCXNN(x, byte) => op_cxnn(emu, x, byte),
+            Instruction::OpDXYN(x, y, nibble) => op_dxyn(emu, x, y, nibble),
+            Instruction::OpEX9E(x) => op_ex9e(emu, x),
+            Instruction::OpEXA1(x) => op_exa1(emu, x),
+            Instruction::OpFX07(x) => op_fx07(emu, x),
+            Instruction::OpFX0A(x) => op_fx0a(emu, x),
+            Instruction::OpFX15(x) => op_fx15(emu, x),
+            Instruction::OpFX18(x) => op_fx18(emu, x),
+            Instruction::OpFX1E(x) => op_fx1e(emu, x),
+            Instruction::OpFX29(x) => op_fx29(emu, x),
+            Instruction::OpFX33(x) => op_fx33(emu, x),
+            Instruction::OpFX55(x) => op_fx55(emu, x),
+            Instruction::OpFX65(x) => op_fx65(emu, x),
+            Instruction::OpFN01(mask) => op_fn01(emu, mask),
+        }
+    }
+}
+
+// The instructions, shared by `Instruction::call` and the dispatch table of `CpuController`
+
+pub(super) fn op_0000(_emu: &mut Emulator) -> Result<(), Error> {
+    Ok(())
+}
+
+pub(super) fn op_00e0(emu: &mut Emulator) -> Result<(), Error> {
+    emu.clear_screen();
+    Ok(())
+}
+
+pub(super) fn op_00ee(emu: &mut Emulator) -> Result<(), Error> {
+    emu.stack_pop().map_err(|err| {
+        error!("Failed to return from subroutine: {:?}", err);
+        err
+    })?;
+    Ok(())
+}
+
+pub(super) fn op_1nnn(emu: &mut Emulator, addr: u16) -> Result<(), Error> {
+    emu.set_pc(addr);
+    Ok(())
+}
+
+pub(super) fn op_2nnn(emu: &mut Emulator, addr: u16) -> Result<(), Error> {
+    emu.stack_push(emu.get_pc())?;
+    emu.set_pc(addr);
+    Ok(())
+}
+
+pub(super) fn op_3xnn(emu: &mut Emulator, x: u8, byte: u8) -> Result<(), Error> {
+    let v = emu.get_v(x)?;
+    if v == byte {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_4xnn(emu: &mut Emulator, x: u8, byte: u8) -> Result<(), Error> {
+    let v = emu.get_v(x)?;
+    if v != byte {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_5xy0(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    if vx == vy {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_6xnn(emu: &mut Emulator, x: u8, byte: u8) -> Result<(), Error> {
+    emu.set_v(x, byte)?;
+    Ok(())
+}
+
+pub(super) fn op_7xnn(emu: &mut Emulator, x: u8, byte: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let result = vx.wrapping_add(byte);
+    emu.set_v(x, result)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy0(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vy = emu.get_v(y)?;
+    emu.set_v(x, vy)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy1(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    emu.set_v(x, vx | vy)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy2(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    emu.set_v(x, vx & vy)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy3(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    emu.set_v(x, vx ^ vy)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy4(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    let (result, overflow) = vx.overflowing_add(vy);
+    // The flag is written last so it wins when X is F
+    emu.set_v(x, result)?;
+    emu.set_v(0xF, if overflow { 1 } else { 0 })?;
+    Ok(())
+}
+
+pub(super) fn op_8xy5(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    let (result, overflow) = vx.overflowing_sub(vy);
+    emu.set_v(x, result)?;
+    emu.set_v(0xF, if overflow { 0 } else { 1 })?;
+    Ok(())
+}
+
+// 0b0000_0001 & 0b0000_0001 -> 0b0000_0001
+pub(super) fn op_8xy6(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let source = if emu.quirks().bit_shift_instructions_use_vy {
+        y
+    } else {
+        x
+    };
+    let value = emu.get_v(source)?;
+    let lsb = value & 0b0000_0001;
+    emu.set_v(x, value >> 1)?;
+    emu.set_v(0xF, lsb)?;
+    Ok(())
+}
+
+pub(super) fn op_8xy7(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    let (result, overflow) = vy.overflowing_sub(vx);
+    emu.set_v(x, result)?;
+    emu.set_v(0xF, if overflow { 0 } else { 1 })?;
+    Ok(())
+}
+
+pub(super) fn op_8xye(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let source = if emu.quirks().bit_shift_instructions_use_vy {
+        y
+    } else {
+        x
+    };
+    let value = emu.get_v(source)?;
+    let msb = (value & 0b10000000) >> 7;
+    emu.set_v(x, value << 1)?;
+    emu.set_v(0xF, msb)?;
+    Ok(())
+}
+
+pub(super) fn op_9xy0(emu: &mut Emulator, x: u8, y: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    if vx != vy {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_annn(emu: &mut Emulator, addr: u16) -> Result<(), Error> {
+    emu.set_i(addr);
+    Ok(())
+}
+
+pub(super) fn op_bnnn(emu: &mut Emulator, addr: u16) -> Result<(), Error> {
+    let v0 = emu.get_v(0)?;
+    emu.set_pc((addr) + (v0 as u16));
+    Ok(())
+}
+
+pub(super) fn op_cxnn(emu: &mut Emulator, x: u8, byte: u8) -> Result<(), Error> {
+    let rnd = emu.random_byte();
+    emu.set_v(x, rnd & byte)?;
+    Ok(())
+}
+
+pub(super) fn op_dxyn(emu: &mut Emulator, x: u8, y: u8, nibble: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let vy = emu.get_v(y)?;
+    let rows = nibble;
+    let mask = emu.get_plane_mask();
+    let mut addr = emu.get_i();
+    let mut collision = false;
+    // With both planes selected the sprite for plane 2 follows the one for plane 1
+    for plane in [0b01, 0b10] {
+        if mask & plane == 0 {
+            continue;
+        }
+        for ordinate in 0..rows {
+            let pixel_row = emu.get_from_ram(addr as usize + ordinate as usize)?;
+            if pixel_row == 0 {
+                continue;
+            }
+            // The sprite row moved to the top bits, then rotated so it wraps around the edge
+            let bits = ((pixel_row as u64) << 56).rotate_right(vx as u32 % SCREEN_WIDTH as u32);
+            let y = (vy as usize + ordinate as usize) % SCREEN_HEIGHT;
+            collision |= emu.xor_row(y, bits, plane)?;
+        }
+        addr = addr.wrapping_add(rows as u16);
+    }
+
+    if collision {
+        emu.set_v(0xF, 1)?;
+    } else {
+        emu.set_v(0xF, 0)?;
+    }
+    Ok(())
+}
+
+pub(super) fn op_ex9e(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let is_pressed = emu.is_key_pressed(vx);
+    if is_pressed? {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_exa1(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let is_pressed = emu.is_key_pressed(vx);
+    if !is_pressed? {
+        emu.inc_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_fx07(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let dt = emu.get_dt();
+    emu.set_v(x, dt)?;
+    Ok(())
+}
+
+pub(super) fn op_fx0a(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    if let Some(key) = emu.check_key_press() {
+        emu.set_v(x, key)?;
+    } else {
+        emu.dec_pc_by(2);
+    }
+    Ok(())
+}
+
+pub(super) fn op_fx15(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    emu.set_dt(vx);
+    Ok(())
+}
+
+pub(super) fn op_fx18(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    emu.set_st(vx);
+    Ok(())
+}
+
+pub(super) fn op_fx1e(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let i = emu.get_i();
+    emu.set_i(i.wrapping_add(vx as u16));
+    Ok(())
+}
+
+pub(super) fn op_fx29(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let f = 5 * vx as u16;
+    emu.set_i(f);
+    Ok(())
+}
+
+pub(super) fn op_fx33(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let vx = emu.get_v(x)?;
+    let hundreds = vx / 100;
+    let tens = (vx / 10) % 10;
+    let ones = vx % 10;
+
+    emu.set_to_ram(emu.get_i() as usize, hundreds)?;
+    emu.set_to_ram(emu.get_i() as usize + 1, tens)?;
+    emu.set_to_ram(emu.get_i() as usize + 2, ones)?;
+    Ok(())
+}
+
+pub(super) fn op_fx55(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let i = emu.get_i();
+    for index in 0..=x {
+        let vx = emu.get_v(index)?;
+        emu.set_to_ram(i as usize + index as usize, vx)?;
+    }
+    if emu.quirks().store_read_instructions_change_i {
+        emu.set_i(i.wrapping_add(x as u16 + 1));
+    }
+    Ok(())
+}
+
+pub(super) fn op_fx65(emu: &mut Emulator, x: u8) -> Result<(), Error> {
+    let i = emu.get_i();
+    for idx in 0..=x {
+        let value = emu.get_from_ram(i as usize + idx as usize)?;
+        emu.set_v(idx, value)?;
+    }
+    if emu.quirks().store_read_instructions_change_i {
+        emu.set_i(i.wrapping_add(x as u16 + 1));
+    }
+    Ok(())
+}
+
+pub(super) fn op_fn01(emu: &mut Emulator, mask: u8) -> Result<(), Error> {
+    emu.set_plane_mask(mask)?;
+    Ok(())
+}
+
+/// Mnemonics in the common Cowgod notation, e.g. `LD VA, 0x02` or `DRW V0, V1, 5`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Instruction::Op0000 => {} // NOP
-            Instruction::Op00E0 => {
-                emu.clear_screen();
-            }
-            Instruction::Op00EE => {
-                emu.stack_pop().map_err(|err| {
-                    error!("Failed to return from subroutine: {:?}", err);
-                    err
-                })?;
-            }
-            Instruction::Op1NNN(addr) => {
-                emu.set_pc(*addr);
-            }
-            Instruction::Op2NNN(addr) => {
-                emu.stack_push(emu.get_pc())?;
-                emu.set_pc(*addr);
-            }
-            Instruction::Op3XNN(x, byte) => {
-                let v = emu.get_v(*x)?;
-                if v == *byte {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::Op4XNN(x, byte) => {
-                let v = emu.get_v(*x)?;
-                if v != *byte {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::Op5XY0(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                if vx == vy {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::Op6XNN(x, byte) => {
-                emu.set_v(*x, *byte)?;
-            }
-            Instruction::Op7XNN(x, byte) => {
-                let vx = emu.get_v(*x)?;
-                let result = vx.wrapping_add(*byte as u8);
-                emu.set_v(*x, result)?;
-            }
-            Instruction::Op8XY0(x, y) => {
-                let vy = emu.get_v(*y)?;
-                emu.set_v(*x, vy)?;
-            }
-            Instruction::Op8XY1(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                emu.set_v(*x, vx | vy)?;
-            }
-            Instruction::Op8XY2(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                emu.set_v(*x, vx & vy)?;
-            }
-            Instruction::Op8XY3(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                emu.set_v(*x, vx ^ vy)?;
-            }
-            Instruction::Op8XY4(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                let (result, overflow) = vx.overflowing_add(vy);
-                emu.set_v(0xF, if overflow { 1 } else { 0 })?;
-                emu.set_v(*x, result)?;
-            }
-            Instruction::Op8XY5(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                let (result, overflow) = vx.overflowing_sub(vy);
-                emu.set_v(0xF, if overflow { 0 } else { 1 })?;
-                emu.set_v(*x, result)?;
-            }
-            // 0b0000_0001 & 0b0000_0001 -> 0b0000_0001
-            Instruction::Op8XY6(x) => {
-                let vx = emu.get_v(*x)?;
-                let lsb = vx & 0b0000_0001;
-                emu.set_v(0xF, lsb)?;
-                let result = vx >> 1;
-                emu.set_v(*x, result)?;
-            }
-            Instruction::Op8XY7(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                let (result, overflow) = vy.overflowing_sub(vx);
-                emu.set_v(0xF, if overflow { 0 } else { 1 })?;
-                emu.set_v(*x, result)?;
-            }
-            Instruction::Op8XYE(x) => {
-                let vx = emu.get_v(*x)?;
-                let msb = (vx & 0b10000000) >> 7;
-                emu.set_v(0xF, msb)?;
-                let result = vx << 1;
-                emu.set_v(*x, result)?;
-            }
-            Instruction::Op9XY0(x, y) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                if vx != vy {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::OpANNN(addr) => {
-                emu.set_i(*addr);
-            }
-            Instruction::OpBNNN(addr) => {
-                let v0 = emu.get_v(0)?;
-                emu.set_pc((*addr) + (v0 as u16));
-            }
-            Instruction::OpCXNN(x, byte) => {
-                let rnd = rand::thread_rng().gen_range(0..=255);
-                emu.set_v(*x, rnd & *byte)?;
-            }
-            Instruction::OpDXYN(x, y, nibble) => {
-                let vx = emu.get_v(*x)?;
-                let vy = emu.get_v(*y)?;
-                let rows = *nibble;
-                let mut collision = false;
-                for ordinate in 0..rows {
-                    let addr = emu.get_i() + ordinate as u16;
-                    let pixel_row = emu.get_ram()[addr as usize];
-                    for abscissa in 0..8 {
-                        if (pixel_row & (0b1000_0000 >> abscissa)) != 0 {
-                            let x = (vx as usize + abscissa) % SCREEN_WIDTH;
-                            let y = (vy as usize + ordinate as usize) % SCREEN_HEIGHT;
-                            let index = x + y * SCREEN_WIDTH;
-                            let pixel = emu.get_display()[index];
-                            collision |= pixel;
-                            emu.set_pixel(index, pixel ^ true)?;
-                        }
-                    }
-                }
-
-                if collision {
-                    emu.set_v(0xF, 1)?;
-                } else {
-                    emu.set_v(0xF, 0)?;
-                }
-            }
-            Instruction::OpEX9E(x) => {
-                let vx = emu.get_v(*x)?;
-                let is_pressed = emu.is_key_pressed(vx);
-                if is_pressed? {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::OpEXA1(x) => {
-                let vx = emu.get_v(*x)?;
-                let is_pressed = emu.is_key_pressed(vx);
-                if !is_pressed? {
-                    emu.inc_pc_by(2);
-                }
-            }
-            Instruction::OpFX07(x) => {
-                let dt = emu.get_dt();
-                emu.set_v(*x, dt)?;
-            }
-            Instruction::OpFX0A(x) => {
-                if let Some(key) = emu.check_key_press() {
-                    emu.set_v(*x, key)?;
-                } else {
-                    emu.dec_pc_by(2);
-                }
-            }
-            Instruction::OpFX15(x) => {
-                let vx = emu.get_v(*x)?;
-                emu.set_dt(vx);
-            }
-            Instruction::OpFX18(x) => {
-                let vx = emu.get_v(*x)?;
-                emu.set_st(vx);
-            }
-            Instruction::OpFX1E(x) => {
-                let vx = emu.get_v(*x)?;
-                let i = emu.get_i();
-                emu.set_i(i.wrapping_add(vx as u16));
-            }
-            Instruction::OpFX29(x) => {
-                let vx = emu.get_v(*x)?;
-                let f = 5 * vx as u16;
-                emu.set_i(f);
-            }
-            Instruction::OpFX33(x) => {
-                let vx = emu.get_v(*x)?;
-                let hundreds = vx / 100;
-                let tens = vx / 10 % 10;
-                let ones = vx % 10;
-
-                emu.set_to_ram(emu.get_i() as usize, hundreds)?;
-                emu.set_to_ram(emu.get_i() as usize + 1, tens)?;
-                emu.set_to_ram(emu.get_i() as usize + 2, ones)?;
-            }
-            Instruction::OpFX55(x) => {
-                let i = emu.get_i();
-                for index in 0..=*x {
-                    let vx = emu.get_v(index)?;
-                    emu.set_to_ram(i as usize + index as usize, vx)?;
-                }
-            }
-            Instruction::OpFX65(x) => {
-                let i = emu.get_i();
-                for idx in 0..=*x {
-                    let value = emu.get_ram()[i as usize + idx as usize];
-                    emu.set_v(idx, value)?;
-                }
-            }
+            Instruction::Op0000 => write!(f, "NOP"),
+            Instruction::Op00E0 => write!(f, "CLS"),
+            Instruction::Op00EE => write!(f, "RET"),
+            Instruction::Op1NNN(addr) => write!(f, "JP 0x{:03X}", addr),
+            Instruction::Op2NNN(addr) => write!(f, "CALL 0x{:03X}", addr),
+            Instruction::Op3XNN(x, byte) => write!(f, "SE V{:X}, 0x{:02X}", x, byte),
+            Instruction::Op4XNN(x, byte) => write!(f, "SNE V{:X}, 0x{:02X}", x, byte),
+            Instruction::Op5XY0(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::Op6XNN(x, byte) => write!(f, "LD V{:X}, 0x{:02X}", x, byte),
+            Instruction::Op7XNN(x, byte) => write!(f, "ADD V{:X}, 0x{:02X}", x, byte),
+            Instruction::Op8XY0(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Op8XY1(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::Op8XY2(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Op8XY3(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Op8XY4(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Op8XY5(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Op8XY6(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::Op8XY7(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Op8XYE(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::Op9XY0(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::OpANNN(addr) => write!(f, "LD I, 0x{:03X}", addr),
+            Instruction::OpBNNN(addr) => write!(f, "JP V0, 0x{:03X}", addr),
+            Instruction::OpCXNN(x, byte) => write!(f, "RND V{:X}, 0x{:02X}", x, byte),
+            Instruction::OpDXYN(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::OpEX9E(x) => write!(f, "SKP V{:X}", x),
+            Instruction::OpEXA1(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::OpFX07(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::OpFX0A(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::OpFX15(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::OpFX18(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::OpFX1E(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::OpFX29(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::OpFX33(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::OpFX55(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::OpFX65(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::OpFN01(mask) => write!(f, "PLANE {}", mask),
         }
-        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_mnemonics() {
+        let mnemonic = |word| Instruction::decode(word).map(|i| i.to_string());
+        assert_eq!(mnemonic(0x00E0).as_deref(), Some("CLS"));
+        assert_eq!(mnemonic(0x6A02).as_deref(), Some("LD VA, 0x02"));
+        assert_eq!(mnemonic(0xD015).as_deref(), Some("DRW V0, V1, 5"));
+        assert_eq!(mnemonic(0xF365).as_deref(), Some("LD V3, [I]"));
+        assert_eq!(mnemonic(0x0123), None);
+        assert_eq!(mnemonic(0xFFFF), None);
     }
 }
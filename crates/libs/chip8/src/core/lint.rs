@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::analysis::{instruction_at, successors, word_at, CodeMap, START_ADDR};
+use super::chip8::{RAM_SIZE, STACK_SIZE};
+use super::instruction::Instruction;
+
+/// A suspicious pattern found by `lint`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Finding {
+    /// Address of the instruction the finding is about
+    pub address: u16,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:03X}: {}", self.address, self.message)
+    }
+}
+
+/// Checks the reachable code of `rom`, as loaded at 0x200, for patterns that break on some
+/// interpreters or by accident: jumps to odd addresses or out of the ROM, paths running into
+/// bytes that are no instruction, reads and writes past the end of RAM, writes over the font
+/// and interpreter area or the code itself, subroutines nesting deeper than the stack, and
+/// instructions whose result depends on the shift or load/store quirks.
+///
+/// I is followed through straight-line code from the last `LD I`, so accesses are only
+/// checked where its value is known without running the ROM.
+pub fn lint(rom: &[u8]) -> Vec<Finding> {
+    let code = CodeMap::new(rom);
+    let end = START_ADDR as usize + rom.len();
+    let mut findings = BTreeSet::new();
+    let mut report = |address: u16, message: String| {
+        findings.insert(Finding { address, message });
+    };
+
+    // Addresses reached other than by falling through, where the value of I is unknown
+    let mut joins = BTreeSet::new();
+    for &address in &code.instructions {
+        let Some(instruction) = instruction_at(rom, address) else {
+            continue;
+        };
+        let next = address.wrapping_add(2);
+        let targets = successors(rom, address, &instruction);
+        if let Instruction::Op1NNN(target)
+        | Instruction::Op2NNN(target)
+        | Instruction::OpBNNN(target) = instruction
+        {
+            if target % 2 != 0 {
+                report(
+                    address,
+                    format!("jumps to the odd address 0x{:03X}", target),
+                );
+            }
+            if !(START_ADDR as usize..end).contains(&(target as usize)) {
+                report(
+                    address,
+                    format!("jumps to 0x{:03X}, outside the ROM", target),
+                );
+            }
+            if matches!(instruction, Instruction::OpBNNN(_)) && target as usize + 0xFF >= RAM_SIZE {
+                report(
+                    address,
+                    format!("JP V0, 0x{:03X} can jump past the end of RAM", target),
+                );
+            }
+        }
+        for &target in &targets {
+            let inside = (START_ADDR as usize..=end).contains(&(target as usize));
+            if inside && instruction_at(rom, target).is_none() {
+                let opcode = word_at(rom, target);
+                report(
+                    address,
+                    match opcode {
+                        Some(opcode) => format!(
+                            "execution can continue to 0x{:03X}, which holds no instruction ({:04X})",
+                            target, opcode
+                        ),
+                        None => format!("execution can run off the end of the ROM at 0x{:03X}", target),
+                    },
+                );
+            }
+        }
+        let plain = !matches!(
+            instruction,
+            Instruction::Op1NNN(_)
+                | Instruction::Op2NNN(_)
+                | Instruction::OpBNNN(_)
+                | Instruction::Op00EE
+        );
+        joins.extend(
+            targets
+                .into_iter()
+                .filter(|&target| !(plain && target == next)),
+        );
+        // The subroutine may change I before returning here
+        if let Instruction::Op2NNN(_) = instruction {
+            joins.insert(next);
+        }
+    }
+
+    // Straight-line pass tracking I and whether it was last moved by a load or store
+    let (mut i, mut stored): (Option<u16>, Option<u16>) = (None, None);
+    let mut previous = None;
+    for &address in &code.instructions {
+        let Some(instruction) = instruction_at(rom, address) else {
+            continue;
+        };
+        if joins.contains(&address) || previous.map(|previous: u16| previous + 2) != Some(address) {
+            (i, stored) = (None, None);
+        }
+        previous = Some(address);
+
+        // Bytes read or written from I, and whether they are written
+        let access = match instruction {
+            // DXY0 draws a 16x16 SUPER-CHIP sprite
+            Instruction::OpDXYN(_, _, 0) => Some((32, false)),
+            Instruction::OpDXYN(_, _, n) => Some((n as u16, false)),
+            Instruction::OpFX65(x) => Some((x as u16 + 1, false)),
+            Instruction::OpFX55(x) => Some((x as u16 + 1, true)),
+            Instruction::OpFX33(_) => Some((3, true)),
+            _ => None,
+        };
+        if let Some((length, writes)) = access {
+            if let Some(from) = stored {
+                report(
+                    address,
+                    format!(
+                        "uses I after the load/store at 0x{:03X}, which moves I on the COSMAC VIP \
+                         but not on SUPER-CHIP; set I again first",
+                        from
+                    ),
+                );
+            }
+            if let Some(i) = i {
+                let last = i as usize + length as usize - 1;
+                if last >= RAM_SIZE {
+                    let verb = if writes { "writes" } else { "reads" };
+                    report(
+                        address,
+                        format!("{} 0x{:03X}-0x{:03X}, past the end of RAM", verb, i, last),
+                    );
+                } else if writes && i < START_ADDR {
+                    report(
+                        address,
+                        format!(
+                            "writes 0x{:03X}-0x{:03X}, over the interpreter area and font",
+                            i, last
+                        ),
+                    );
+                } else if writes {
+                    if let Some(&target) = code
+                        .instructions
+                        .range(i.saturating_sub(1)..=last as u16)
+                        .next()
+                    {
+                        report(
+                            address,
+                            format!(
+                                "writes over the instruction at 0x{:03X}, self-modifying code",
+                                target
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+        match instruction {
+            Instruction::OpANNN(addr) => (i, stored) = (Some(addr), None),
+            Instruction::OpFX1E(_) | Instruction::OpFX29(_) => (i, stored) = (None, None),
+            Instruction::OpFX55(_) | Instruction::OpFX65(_) => (i, stored) = (None, Some(address)),
+            Instruction::Op8XY6(x, y) | Instruction::Op8XYE(x, y) if x != y => report(
+                address,
+                format!(
+                    "shifts V{:X} into V{:X} on the COSMAC VIP but V{:X} in place on SUPER-CHIP; \
+                     copy V{:X} into V{:X} first and shift V{:X} by itself",
+                    y, x, x, y, x, x
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    for finding in stack_depth(rom, &code) {
+        findings.insert(finding);
+    }
+    findings.into_iter().collect()
+}
+
+/// The calls made from the code reachable from `start` without entering them, as call site
+/// and target.
+fn calls_from(rom: &[u8], start: u16) -> Vec<(u16, u16)> {
+    let mut calls = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![start];
+    while let Some(address) = pending.pop() {
+        if !visited.insert(address) {
+            continue;
+        }
+        let Some(instruction) = instruction_at(rom, address) else {
+            continue;
+        };
+        match instruction {
+            Instruction::Op2NNN(target) => {
+                calls.push((address, target));
+                pending.push(address.wrapping_add(2));
+            }
+            _ => pending.extend(successors(rom, address, &instruction)),
+        }
+    }
+    calls.sort_unstable();
+    calls
+}
+
+/// Reports recursive calls and call chains from 0x200 nesting deeper than the stack.
+fn stack_depth(rom: &[u8], code: &CodeMap) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // Deepest nesting below each subroutine, with the chain of targets reaching it
+    let mut depths: BTreeMap<u16, (usize, Vec<u16>)> = BTreeMap::new();
+    let mut visiting = BTreeSet::new();
+
+    fn depth(
+        rom: &[u8],
+        start: u16,
+        depths: &mut BTreeMap<u16, (usize, Vec<u16>)>,
+        visiting: &mut BTreeSet<u16>,
+        findings: &mut Vec<Finding>,
+    ) -> (usize, Vec<u16>) {
+        if let Some(known) = depths.get(&start) {
+            return known.clone();
+        }
+        visiting.insert(start);
+        let mut deepest = (0, Vec::new());
+        for (site, target) in calls_from(rom, start) {
+            if visiting.contains(&target) {
+                findings.push(Finding {
+                    address: site,
+                    message: format!(
+                        "calls 0x{:03X} recursively, the stack overflows unless a condition ends it",
+                        target
+                    ),
+                });
+                continue;
+            }
+            let (nested, chain) = depth(rom, target, depths, visiting, findings);
+            if nested + 1 > deepest.0 {
+                deepest = (nested + 1, std::iter::once(target).chain(chain).collect());
+            }
+        }
+        visiting.remove(&start);
+        depths.insert(start, deepest.clone());
+        deepest
+    }
+
+    if !code.is_instruction(START_ADDR) {
+        return findings;
+    }
+    let (nested, chain) = depth(rom, START_ADDR, &mut depths, &mut visiting, &mut findings);
+    if nested > STACK_SIZE {
+        let chain: Vec<String> = chain
+            .iter()
+            .map(|target| format!("0x{:03X}", target))
+            .collect();
+        findings.push(Finding {
+            address: START_ADDR,
+            message: format!(
+                "subroutine calls nest {} deep ({}), more than the {} entries of the stack",
+                nested,
+                chain.join(" -> "),
+                STACK_SIZE
+            ),
+        });
+    }
+    findings
+}
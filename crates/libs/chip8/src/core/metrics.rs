@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Host frames the metrics are computed over, about two seconds at 60Hz.
+const WINDOW: usize = 120;
+
+/// Performance of the last couple of seconds, see `Emulator::metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    /// Frames presented per second by the frontend
+    pub fps: f64,
+    /// Emulated 60Hz frames per second, above 60 when sped up and 0 while paused
+    pub emulated_fps: f64,
+    /// CHIP-8 instructions executed per second
+    pub ips: f64,
+    /// Time between presented frames, the median and the slowest 5% and 1%
+    pub frame_time_p50: Duration,
+    pub frame_time_p95: Duration,
+    pub frame_time_p99: Duration,
+    /// Frames presented and instructions executed since start
+    pub frames: u64,
+    pub instructions: u64,
+}
+
+struct Sample {
+    frame_time: Duration,
+    emulated_frames: u32,
+    instructions: u64,
+}
+
+/// Rolling window of the frames presented through `run_frames`.
+pub struct MetricsRecorder {
+    last: Option<Instant>,
+    samples: VecDeque<Sample>,
+    recorded: u64,
+    instructions: u64,
+}
+
+impl Default for MetricsRecorder {
+    fn default() -> Self {
+        Self {
+            last: None,
+            samples: VecDeque::with_capacity(WINDOW),
+            recorded: 0,
+            instructions: 0,
+        }
+    }
+}
+
+impl MetricsRecorder {
+    /// Records a presented frame, timed from the previous one.
+    pub fn record(&mut self, now: Instant, emulated_frames: u32, instructions: u64) {
+        if let Some(last) = self.last.replace(now) {
+            self.push(now - last, emulated_frames, instructions);
+        }
+    }
+
+    fn push(&mut self, frame_time: Duration, emulated_frames: u32, instructions: u64) {
+        if self.samples.len() == WINDOW {
+            self.samples.pop_front();
+        }
+        self.recorded += 1;
+        self.instructions += instructions;
+        self.samples.push_back(Sample {
+            frame_time,
+            emulated_frames,
+            instructions,
+        });
+    }
+
+    /// Whether the last frame completed a fresh window, for logging once per window.
+    pub fn window_completed(&self) -> bool {
+        self.recorded > 0 && self.recorded.is_multiple_of(WINDOW as u64)
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        let elapsed: Duration = self.samples.iter().map(|s| s.frame_time).sum();
+        if elapsed.is_zero() {
+            return Metrics {
+                frames: self.recorded,
+                instructions: self.instructions,
+                ..Metrics::default()
+            };
+        }
+        let per_second = |count: f64| count / elapsed.as_secs_f64();
+
+        let mut frame_times: Vec<Duration> = self.samples.iter().map(|s| s.frame_time).collect();
+        frame_times.sort_unstable();
+        // Nearest-rank percentile
+        let percentile =
+            |p: usize| frame_times[(frame_times.len() * p).div_ceil(100).saturating_sub(1)];
+        Metrics {
+            fps: per_second(self.samples.len() as f64),
+            emulated_fps: per_second(self.samples.iter().map(|s| s.emulated_frames as f64).sum()),
+            ips: per_second(self.samples.iter().map(|s| s.instructions as f64).sum()),
+            frame_time_p50: percentile(50),
+            frame_time_p95: percentile(95),
+            frame_time_p99: percentile(99),
+            frames: self.recorded,
+            instructions: self.instructions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let mut recorder = MetricsRecorder::default();
+        for i in 1..=100 {
+            recorder.push(Duration::from_millis(i), 1, 10);
+        }
+        let metrics = recorder.metrics();
+
+        assert_eq!(metrics.frame_time_p50, Duration::from_millis(50));
+        assert_eq!(metrics.frame_time_p95, Duration::from_millis(95));
+        assert_eq!(metrics.frame_time_p99, Duration::from_millis(99));
+        // 100 frames over 5.05 seconds
+        assert!((metrics.fps - 100.0 / 5.05).abs() < 1e-9);
+        assert!((metrics.ips - 1000.0 / 5.05).abs() < 1e-9);
+        assert_eq!((metrics.frames, metrics.instructions), (100, 1000));
+    }
+}
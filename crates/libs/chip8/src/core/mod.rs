@@ -1,7 +1,44 @@
-pub mod auido;
+pub mod analysis;
+pub mod audio;
+pub mod bench;
+pub mod blend;
+pub mod cheats;
 pub mod chip8;
+pub mod compat;
 pub mod controller;
 pub mod cpu;
+pub mod crash;
+pub mod disasm;
+#[cfg(feature = "dynarec")]
+pub mod dynarec;
 pub mod emulator;
+#[cfg(feature = "image")]
+pub mod export;
+pub mod gallery;
+pub mod golden;
+pub mod identify;
 pub mod input;
 pub mod instruction;
+pub mod lint;
+pub mod metrics;
+pub mod netplay;
+pub mod octo;
+pub mod pacer;
+pub mod plugin;
+pub mod replay;
+pub mod rewind;
+#[cfg(feature = "rhai")]
+pub mod rhai;
+pub mod romdb;
+#[cfg(feature = "test-roms")]
+pub mod roms;
+pub mod script;
+pub mod search;
+pub mod service;
+pub mod sprites;
+pub mod state;
+pub mod testing;
+pub mod threaded;
+pub mod touch;
+pub mod trace;
+pub mod wav;
@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Error};
+use tracing::warn;
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::emulator::Emulator;
+use super::replay::Quirks;
+
+/// How long a frame waits for the peer's input before the session is given up, e.g. when
+/// the other player paused or closed the window.
+const PEER_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What both instances must agree on to run the same frames: the host's values win, the ROM
+/// must be the same on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    /// XXH3 hash of the ROM
+    pub rom: u64,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub cycles_per_frame: u32,
+    /// Frames between a key press and the frame it is applied on, hiding the network latency
+    pub input_delay: u8,
+}
+
+impl Session {
+    /// The session `emulator` would host or join with, running its ROM, seed and quirks.
+    pub fn new(emulator: &Emulator, cycles_per_frame: u32, input_delay: u8) -> Self {
+        Self {
+            rom: xxh3_64(emulator.get_rom()),
+            seed: emulator.get_seed(),
+            quirks: emulator.quirks(),
+            cycles_per_frame,
+            input_delay,
+        }
+    }
+}
+
+/// An instance's keypad for a frame and its state hash before running it, as exchanged with
+/// the peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerFrame {
+    pub frame: u64,
+    pub keys: u16,
+    pub hash: u64,
+}
+
+/// Lockstep netplay with one peer. Before every frame both instances send their keypad and
+/// state hash and wait for the other's, then run the frame with the keys of both pressed, so
+/// the machines stay identical; differing hashes are reported as a desync. Key presses are
+/// applied `input_delay` frames after they are sent.
+///
+/// The frames travel over channels, `remote::netplay` connects them to the peer over TCP.
+pub struct Lockstep {
+    outgoing: Sender<PeerFrame>,
+    incoming: Receiver<Result<PeerFrame, Error>>,
+    session: Session,
+    // Keys held on this instance, sent with the next frame
+    local: u16,
+    // Keys sent and received but not applied yet, `input_delay` frames of them
+    local_queue: VecDeque<u16>,
+    remote_queue: VecDeque<u16>,
+    // Frame whose keys went out and when, while waiting for the peer's
+    sent: Option<(u64, Instant)>,
+}
+
+impl Lockstep {
+    /// Runs `session` with the peer, whose frames come out of `incoming`, an error once the
+    /// connection is lost, and ours go into `outgoing`.
+    pub fn new(
+        session: Session,
+        outgoing: Sender<PeerFrame>,
+        incoming: Receiver<Result<PeerFrame, Error>>,
+    ) -> Self {
+        let delay = session.input_delay as usize;
+        Self {
+            outgoing,
+            incoming,
+            session,
+            local: 0,
+            local_queue: VecDeque::from(vec![0; delay]),
+            remote_queue: VecDeque::from(vec![0; delay]),
+            sent: None,
+        }
+    }
+
+    /// The settings both instances run with.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Presses or releases a key of this instance, sent with the next frame.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        if pressed {
+            self.local |= 1 << key;
+        } else {
+            self.local &= !(1 << key);
+        }
+    }
+
+    /// Sends this instance's keys for `frame`, whose state hashes to `hash`, once, then
+    /// returns the keypad to run it with when the peer's keys for it arrived. `None` while
+    /// they have not, the frame has to wait.
+    pub fn exchange(&mut self, frame: u64, hash: u64) -> Result<Option<u16>, Error> {
+        let sent_at = match self.sent {
+            Some((sent, at)) if sent == frame => at,
+            _ => {
+                let local = PeerFrame {
+                    frame,
+                    keys: self.local,
+                    hash,
+                };
+                self.outgoing
+                    .send(local)
+                    .map_err(|_| anyhow!("The netplay peer disconnected"))?;
+                self.local_queue.push_back(self.local);
+                let now = Instant::now();
+                self.sent = Some((frame, now));
+                now
+            }
+        };
+        let peer = match self.incoming.try_recv() {
+            Ok(peer) => peer?,
+            Err(TryRecvError::Empty) if sent_at.elapsed() > PEER_TIMEOUT => {
+                return Err(anyhow!(
+                    "The netplay peer sent nothing for {} seconds",
+                    PEER_TIMEOUT.as_secs()
+                ));
+            }
+            Err(TryRecvError::Empty) => return Ok(None),
+            Err(TryRecvError::Disconnected) => {
+                return Err(anyhow!("The netplay peer disconnected"))
+            }
+        };
+        if peer.frame != frame {
+            return Err(anyhow!(
+                "Netplay out of step: frame {} here, {} on the peer",
+                frame,
+                peer.frame
+            ));
+        }
+        if peer.hash != hash {
+            warn!("Netplay desync at frame {}", frame);
+            return Err(anyhow!(
+                "Netplay desync at frame {}: the machines differ, e.g. from cheats or a \
+                 different interpreter version",
+                frame
+            ));
+        }
+        self.remote_queue.push_back(peer.keys);
+        let local = self.local_queue.pop_front().unwrap_or_default();
+        let remote = self.remote_queue.pop_front().unwrap_or_default();
+        Ok(Some(local | remote))
+    }
+}
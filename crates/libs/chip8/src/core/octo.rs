@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use super::analysis::START_ADDR;
+use super::disasm::{Item, Listing};
+use super::instruction::Instruction;
+use super::sprites::find_sprites;
+
+/// Data bytes per line outside sprites.
+const BYTES_PER_LINE: usize = 8;
+
+/// Octo source of `rom`, as loaded at 0x200, which assembles back to the same bytes. The
+/// reachable code becomes Octo statements and every address named by a `JP`, `CALL` or
+/// `LD I` a label, 0x200 being `main`; sprites referenced by the code are written one binary
+/// row per line and the other bytes as hex. Labels falling inside an instruction, where code
+/// jumps into the middle of another one, are declared as constants instead.
+pub fn decompile(rom: &[u8]) -> String {
+    let listing = Listing::new(rom);
+    let name = |address: u16| {
+        if address == START_ADDR {
+            Some("main".to_string())
+        } else {
+            listing.label(address)
+        }
+    };
+    let sprite_rows: BTreeSet<u16> = find_sprites(rom, 8)
+        .into_iter()
+        .filter(|sprite| sprite.referenced)
+        .flat_map(|sprite| sprite.address..sprite.address + sprite.rows.len() as u16)
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# Decompiled from a {} byte ROM, code is what is reachable from main",
+        rom.len()
+    );
+    let inside: Vec<u16> = listing
+        .items
+        .iter()
+        .filter_map(|item| match *item {
+            Item::Code { address, .. } => Some(address + 1),
+            Item::Data { .. } => None,
+        })
+        .filter(|address| listing.labels.contains_key(address))
+        .collect();
+    for &address in &inside {
+        let _ = writeln!(
+            out,
+            ":const {} 0x{:03X}",
+            name(address).unwrap_or_default(),
+            address
+        );
+    }
+    out.push('\n');
+
+    // Hex bytes waiting to be written on one line
+    let mut pending: Vec<u8> = Vec::new();
+    let flush = |out: &mut String, pending: &mut Vec<u8>| {
+        if !pending.is_empty() {
+            let bytes: Vec<String> = pending
+                .iter()
+                .map(|byte| format!("0x{:02X}", byte))
+                .collect();
+            let _ = writeln!(out, "\t{}", bytes.join(" "));
+            pending.clear();
+        }
+    };
+    for item in &listing.items {
+        let address = match *item {
+            Item::Code { address, .. } | Item::Data { address, .. } => address,
+        };
+        if let Some(label) = name(address) {
+            flush(&mut out, &mut pending);
+            if !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            let _ = writeln!(out, ": {}", label);
+        }
+        match *item {
+            Item::Code { opcode, .. } => {
+                flush(&mut out, &mut pending);
+                let _ = writeln!(out, "\t{}", statement(opcode, &name));
+            }
+            Item::Data { byte, .. } if sprite_rows.contains(&address) => {
+                flush(&mut out, &mut pending);
+                let _ = writeln!(out, "\t0b{:08b}", byte);
+            }
+            Item::Data { byte, .. } => {
+                pending.push(byte);
+                if pending.len() == BYTES_PER_LINE {
+                    flush(&mut out, &mut pending);
+                }
+            }
+        }
+    }
+    flush(&mut out, &mut pending);
+    out
+}
+
+/// The Octo statement assembling to `opcode`, naming labelled addresses with `name`.
+fn statement(opcode: u16, name: &dyn Fn(u16) -> Option<String>) -> String {
+    let target = |addr: u16| name(addr).unwrap_or_else(|| format!("0x{:03X}", addr));
+    let raw = || format!("0x{:02X} 0x{:02X}", opcode >> 8, opcode & 0xFF);
+    let Some(instruction) = Instruction::decode(opcode) else {
+        return raw();
+    };
+    match instruction {
+        // No Octo statement for these, and 5XYN and 9XYN decode whatever N is
+        Instruction::Op0000 => raw(),
+        Instruction::Op5XY0(..) | Instruction::Op9XY0(..) if opcode & 0x0F != 0 => raw(),
+        Instruction::OpFN01(mask) if mask > 3 => raw(),
+        Instruction::Op00E0 => "clear".to_string(),
+        Instruction::Op00EE => "return".to_string(),
+        Instruction::Op1NNN(addr) => format!("jump {}", target(addr)),
+        Instruction::Op2NNN(addr) => match name(addr) {
+            Some(label) => label,
+            None => format!(":call 0x{:03X}", addr),
+        },
+        // Octo's `if` skips the next statement when the condition does not hold
+        Instruction::Op3XNN(x, byte) => format!("if v{:x} != 0x{:02X} then", x, byte),
+        Instruction::Op4XNN(x, byte) => format!("if v{:x} == 0x{:02X} then", x, byte),
+        Instruction::Op5XY0(x, y) => format!("if v{:x} != v{:x} then", x, y),
+        Instruction::Op6XNN(x, byte) => format!("v{:x} := 0x{:02X}", x, byte),
+        Instruction::Op7XNN(x, byte) => format!("v{:x} += 0x{:02X}", x, byte),
+        Instruction::Op8XY0(x, y) => format!("v{:x} := v{:x}", x, y),
+        Instruction::Op8XY1(x, y) => format!("v{:x} |= v{:x}", x, y),
+        Instruction::Op8XY2(x, y) => format!("v{:x} &= v{:x}", x, y),
+        Instruction::Op8XY3(x, y) => format!("v{:x} ^= v{:x}", x, y),
+        Instruction::Op8XY4(x, y) => format!("v{:x} += v{:x}", x, y),
+        Instruction::Op8XY5(x, y) => format!("v{:x} -= v{:x}", x, y),
+        Instruction::Op8XY6(x, y) => format!("v{:x} >>= v{:x}", x, y),
+        Instruction::Op8XY7(x, y) => format!("v{:x} =- v{:x}", x, y),
+        Instruction::Op8XYE(x, y) => format!("v{:x} <<= v{:x}", x, y),
+        Instruction::Op9XY0(x, y) => format!("if v{:x} == v{:x} then", x, y),
+        Instruction::OpANNN(addr) => format!("i := {}", target(addr)),
+        Instruction::OpBNNN(addr) => format!("jump0 {}", target(addr)),
+        Instruction::OpCXNN(x, byte) => format!("v{:x} := random 0x{:02X}", x, byte),
+        Instruction::OpDXYN(x, y, n) => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Instruction::OpEX9E(x) => format!("if v{:x} -key then", x),
+        Instruction::OpEXA1(x) => format!("if v{:x} key then", x),
+        Instruction::OpFX07(x) => format!("v{:x} := delay", x),
+        Instruction::OpFX0A(x) => format!("v{:x} := key", x),
+        Instruction::OpFX15(x) => format!("delay := v{:x}", x),
+        Instruction::OpFX18(x) => format!("buzzer := v{:x}", x),
+        Instruction::OpFX1E(x) => format!("i += v{:x}", x),
+        Instruction::OpFX29(x) => format!("i := hex v{:x}", x),
+        Instruction::OpFX33(x) => format!("bcd v{:x}", x),
+        Instruction::OpFX55(x) => format!("save v{:x}", x),
+        Instruction::OpFX65(x) => format!("load v{:x}", x),
+        Instruction::OpFN01(mask) => format!("plane {}", mask),
+    }
+}
@@ -0,0 +1,66 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Frames emulated at most per update, the rest of a larger backlog (e.g. after the window
+/// was dragged or the process was suspended) is dropped instead of fast-forwarding.
+const MAX_CATCH_UP_FRAMES: u32 = 5;
+
+/// Remaining time spent spinning instead of sleeping, covers the OS scheduler granularity.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Fixed-timestep frame pacer. Wall-clock time is accumulated and converted into whole
+/// emulation frames, so the CPU and the 60Hz timers keep their speed whatever the render
+/// rate, vsync or loop overhead is.
+#[derive(Debug)]
+pub struct FramePacer {
+    step: Duration,
+    last: Instant,
+    accumulator: Duration,
+}
+
+impl FramePacer {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / rate,
+            last: Instant::now(),
+            // Start with one frame due so the first update emulates and presents right away.
+            accumulator: Duration::from_secs(1) / rate,
+        }
+    }
+
+    /// Adds the time elapsed since the previous call and returns the number of frames due.
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        self.accumulator += now - self.last;
+        self.last = now;
+
+        let due = (self.accumulator.as_nanos() / self.step.as_nanos()) as u32;
+        if due > MAX_CATCH_UP_FRAMES {
+            self.accumulator = Duration::ZERO;
+            return MAX_CATCH_UP_FRAMES;
+        }
+        self.accumulator -= self.step * due;
+        due
+    }
+
+    /// Instant at which the next frame becomes due.
+    pub fn next_deadline(&self) -> Instant {
+        self.last + self.step.saturating_sub(self.accumulator)
+    }
+
+    /// Sleeps until the next frame is due, spinning for the last stretch to hit it precisely.
+    pub fn wait(&self) {
+        let deadline = self.next_deadline();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return;
+            }
+            if remaining > SPIN_THRESHOLD {
+                thread::sleep(remaining - SPIN_THRESHOLD);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::Error;
+
+/// The machine as plugins see it, read only.
+#[derive(Debug, Clone, Copy)]
+pub struct MachineView<'a> {
+    pub frame: u64,
+    pub ram: &'a [u8],
+    pub v: &'a [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub dt: u8,
+    pub st: u8,
+    /// Palette indices of the screen pixels, row by row
+    pub display: &'a [u8],
+}
+
+/// Lines a plugin shows over the screen, e.g. a memory watch or a call graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Panel {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+/// A tool loaded next to the emulator, e.g. from a WASM module, which watches the machine
+/// without changing it: it is told about every frame and, when it asks for them, every
+/// instruction, and may show a panel over the screen.
+pub trait Plugin: Send {
+    fn name(&self) -> &str;
+
+    /// Whether `on_instruction` is called, leaving it out keeps the instructions fast.
+    fn wants_instructions(&self) -> bool;
+
+    /// Called after the instruction `opcode` at `pc` ran.
+    fn on_instruction(&mut self, machine: &MachineView, pc: u16, opcode: u16) -> Result<(), Error>;
+
+    /// Called after every emulated frame.
+    fn on_frame(&mut self, machine: &MachineView) -> Result<(), Error>;
+
+    /// The panel to show, `None` for plugins that only watch or export.
+    fn panel(&self) -> Option<&Panel>;
+}
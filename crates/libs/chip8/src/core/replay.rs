@@ -0,0 +1,257 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use serde_derive::Deserialize;
+use shared::config::config::ChipSettings;
+
+/// File signature of `.c8r` input recordings.
+const MAGIC: &[u8; 3] = b"C8R";
+const VERSION: u8 = 1;
+
+/// Interpreter behaviours that differ between CHIP-8 implementations. They change how a ROM
+/// runs, so recordings store them next to the RNG seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    pub st_equals_buzzer: bool,
+    pub bit_shift_instructions_use_vy: bool,
+    pub store_read_instructions_change_i: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            st_equals_buzzer: true,
+            bit_shift_instructions_use_vy: false,
+            store_read_instructions_change_i: true,
+        }
+    }
+}
+
+impl From<&ChipSettings> for Quirks {
+    fn from(settings: &ChipSettings) -> Self {
+        Self {
+            st_equals_buzzer: settings.st_equals_buzzer,
+            bit_shift_instructions_use_vy: settings.bit_shift_instructions_use_vy,
+            store_read_instructions_change_i: settings.store_read_instructions_change_i,
+        }
+    }
+}
+
+impl Quirks {
+    /// Quirk sets of well-known interpreters by name, `default` being `Quirks::default()`.
+    pub const PRESETS: [(&'static str, Quirks); 3] = [
+        (
+            "default",
+            Quirks {
+                st_equals_buzzer: true,
+                bit_shift_instructions_use_vy: false,
+                store_read_instructions_change_i: true,
+            },
+        ),
+        // The original COSMAC VIP interpreter
+        (
+            "vip",
+            Quirks {
+                st_equals_buzzer: true,
+                bit_shift_instructions_use_vy: true,
+                store_read_instructions_change_i: true,
+            },
+        ),
+        // SUPER-CHIP on the HP 48
+        (
+            "schip",
+            Quirks {
+                st_equals_buzzer: true,
+                bit_shift_instructions_use_vy: false,
+                store_read_instructions_change_i: false,
+            },
+        ),
+    ];
+
+    pub fn preset(name: &str) -> Result<Self, Error> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, quirks)| *quirks)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::PRESETS.iter().map(|(preset, _)| *preset).collect();
+                anyhow!(
+                    "Unknown quirk preset '{}', expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    /// The quirks as one byte, as recordings, save states and the netplay handshake store them.
+    pub fn to_bits(self) -> u8 {
+        self.st_equals_buzzer as u8
+            | (self.bit_shift_instructions_use_vy as u8) << 1
+            | (self.store_read_instructions_change_i as u8) << 2
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            st_equals_buzzer: bits & 1 != 0,
+            bit_shift_instructions_use_vy: bits & 2 != 0,
+            store_read_instructions_change_i: bits & 4 != 0,
+        }
+    }
+}
+
+/// A keypad transition, applied before the emulated frame `frame` runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Keypad input of a run from power-on, with everything else needed to reproduce it.
+///
+/// The `.c8r` format is the magic `C8R`, a version byte, the RNG seed as a little endian
+/// u64 and the quirk flags byte, followed by one entry per event: the frames since the
+/// previous event as a LEB128 varint and a byte holding the key in the low nibble and the
+/// pressed state in the top bit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub events: Vec<KeyEvent>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, quirks: Quirks) -> Self {
+        Self {
+            seed,
+            quirks,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, frame: u64, key: u8, pressed: bool) {
+        self.events.push(KeyEvent {
+            frame,
+            key,
+            pressed,
+        });
+    }
+
+    /// Forgets the events after `frame`, e.g. those of the frames undone by rewinding.
+    pub fn truncate(&mut self, frame: u64) {
+        let kept = self.events.partition_point(|event| event.frame <= frame);
+        self.events.truncate(kept);
+    }
+
+    /// Frames covered by the recording, up to the last event.
+    pub fn frames(&self) -> u64 {
+        self.events.last().map_or(0, |event| event.frame)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(13 + self.events.len() * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.quirks.to_bits());
+
+        let mut previous = 0;
+        for event in &self.events {
+            let mut delta = event.frame - previous;
+            previous = event.frame;
+            while delta >= 0x80 {
+                bytes.push(delta as u8 | 0x80);
+                delta >>= 7;
+            }
+            bytes.push(delta as u8);
+            bytes.push(event.key & 0xF | (event.pressed as u8) << 7);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 13 || &bytes[..3] != MAGIC {
+            return Err(anyhow!("Not a CHIP-8 input recording"));
+        }
+        if bytes[3] != VERSION {
+            return Err(anyhow!("Unsupported input recording version {}", bytes[3]));
+        }
+        let seed = u64::from_le_bytes(bytes[4..12].try_into().unwrap());
+        let mut recording = Self::new(seed, Quirks::from_bits(bytes[12]));
+
+        let mut rest = bytes[13..].iter();
+        let mut frame = 0u64;
+        while let Some(&first) = rest.next() {
+            let (mut delta, mut shift, mut byte) = (0u64, 0, first);
+            loop {
+                if shift > 63 {
+                    return Err(anyhow!("Corrupt input recording: frame delta overflows"));
+                }
+                delta |= ((byte & 0x7F) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+                byte = *rest
+                    .next()
+                    .ok_or_else(|| anyhow!("Corrupt input recording: truncated event"))?;
+            }
+            let key = *rest
+                .next()
+                .ok_or_else(|| anyhow!("Corrupt input recording: truncated event"))?;
+            frame += delta;
+            recording.push(frame, key & 0xF, key & 0x80 != 0);
+        }
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| anyhow!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let file = File::create(path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(&self.to_bytes())
+            .and_then(|_| out.flush())
+            .map_err(|e| anyhow!("Failed to write input recording: {}", e))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let bytes =
+            fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Plays the events of a recording back frame by frame.
+pub struct Playback {
+    events: Vec<KeyEvent>,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            events: recording.events,
+            next: 0,
+        }
+    }
+
+    /// Returns the events to apply before running `frame`.
+    pub fn due(&mut self, frame: u64) -> &[KeyEvent] {
+        let start = self.next;
+        while self.next < self.events.len() && self.events[self.next].frame <= frame {
+            self.next += 1;
+        }
+        &self.events[start..self.next]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.events.len()
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Error};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use super::state::MAX_STATE_LEN;
+
+/// Frames between two full keyframes, the ones in between are stored as deltas.
+const KEYFRAME_INTERVAL: usize = 60;
+/// Delta bytes reserved per frame, a keyframe whose deltas outgrow them ends early.
+const DELTA_BUDGET: usize = 128;
+/// Most bytes a delta run header takes, two LEB128 varints of a `usize`.
+const RUN_HEADER_LEN: usize = 20;
+
+/// A full state and the deltas of the frames after it, reused once dropped from the buffer.
+struct Keyframe {
+    state: Vec<u8>,
+    // The random number generator when the keyframe was taken and the draws made by then,
+    // the states after it draw again from a clone
+    rng: StdRng,
+    random_draws: u64,
+    // The deltas one after the other, each ending at the offset in `ends`
+    deltas: Vec<u8>,
+    ends: Vec<usize>,
+}
+
+impl Keyframe {
+    fn new() -> Self {
+        Self {
+            state: Vec::with_capacity(MAX_STATE_LEN),
+            rng: StdRng::seed_from_u64(0),
+            random_draws: 0,
+            deltas: Vec::with_capacity(KEYFRAME_INTERVAL * DELTA_BUDGET),
+            ends: Vec::with_capacity(KEYFRAME_INTERVAL),
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.ends.len() + 1
+    }
+}
+
+/// A frame taken back out of a `RewindBuffer`.
+pub struct Rewound<'a> {
+    /// The save state bytes of the machine
+    pub state: &'a [u8],
+    /// The random number generator and the draws made as of the frame's keyframe, to be
+    /// advanced to the draws of the state
+    pub rng: StdRng,
+    pub random_draws: u64,
+}
+
+/// The machine before each of the last seconds of emulated frames, newest last, to step back
+/// through one by one.
+///
+/// Every `KEYFRAME_INTERVAL` frames the whole state is kept, the frames in between as the
+/// XOR of their state with the keyframe's, run-length encoded: each run is the number of
+/// unchanged bytes and the number of changed bytes as LEB128 varints followed by the changed
+/// bytes XORed. Most frames touch a few registers, the timers and a sprite, so a frame takes
+/// tens of bytes instead of the 6 KB of a save state.
+///
+/// The keyframes and their delta space are allocated up front and reused in turn, so storing
+/// and restoring frames never allocates. Frames changing more than `DELTA_BUDGET` bytes on
+/// average start keyframes early, which shortens the time covered.
+pub struct RewindBuffer {
+    keyframes: VecDeque<Keyframe>,
+    spare: Vec<Keyframe>,
+    // The state being stored or the one restored last
+    scratch: Vec<u8>,
+    len: usize,
+}
+
+impl RewindBuffer {
+    /// A buffer holding `seconds` of 60 Hz frames, up to one second more.
+    pub fn new(seconds: u32) -> Self {
+        let count = seconds as usize + 1;
+        Self {
+            keyframes: VecDeque::with_capacity(count),
+            spare: (0..count).map(|_| Keyframe::new()).collect(),
+            scratch: Vec::with_capacity(MAX_STATE_LEN),
+            len: 0,
+        }
+    }
+
+    /// Frames stored.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes taken by the stored states and deltas.
+    pub fn byte_len(&self) -> usize {
+        self.keyframes
+            .iter()
+            .map(|keyframe| keyframe.state.len() + keyframe.deltas.len())
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.spare.extend(self.keyframes.drain(..));
+        self.len = 0;
+    }
+
+    /// Stores the machine before a frame runs: `write` appends its save state bytes to the
+    /// buffer given, `rng` and `random_draws` are the random number generator state.
+    pub fn push(&mut self, rng: &StdRng, random_draws: u64, write: impl FnOnce(&mut Vec<u8>)) {
+        self.scratch.clear();
+        write(&mut self.scratch);
+        let state = &self.scratch;
+        let stored = self.keyframes.back_mut().is_some_and(|keyframe| {
+            // A state of another length belongs to another ROM, it starts a keyframe
+            keyframe.ends.len() + 1 < KEYFRAME_INTERVAL
+                && keyframe.state.len() == state.len()
+                && encode(&keyframe.state, state, &mut keyframe.deltas)
+        });
+        if stored {
+            let keyframe = self.keyframes.back_mut().unwrap();
+            keyframe.ends.push(keyframe.deltas.len());
+        } else {
+            let mut keyframe = match self.spare.pop() {
+                Some(keyframe) => keyframe,
+                None => {
+                    let oldest = self.keyframes.pop_front().unwrap();
+                    self.len -= oldest.frames();
+                    oldest
+                }
+            };
+            keyframe.state.clear();
+            keyframe.state.extend_from_slice(state);
+            keyframe.rng = rng.clone();
+            keyframe.random_draws = random_draws;
+            keyframe.deltas.clear();
+            keyframe.ends.clear();
+            self.keyframes.push_back(keyframe);
+        }
+        self.len += 1;
+    }
+
+    /// Removes the newest frame and returns it.
+    pub fn pop(&mut self) -> Result<Option<Rewound<'_>>, Error> {
+        let Some(keyframe) = self.keyframes.back_mut() else {
+            return Ok(None);
+        };
+        self.len -= 1;
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&keyframe.state);
+        let (rng, random_draws) = (keyframe.rng.clone(), keyframe.random_draws);
+        match keyframe.ends.pop() {
+            Some(end) => {
+                let start = keyframe.ends.last().copied().unwrap_or(0);
+                decode(&mut self.scratch, &keyframe.deltas[start..end])?;
+                keyframe.deltas.truncate(start);
+            }
+            None => {
+                let keyframe = self.keyframes.pop_back().unwrap();
+                self.spare.push(keyframe);
+            }
+        }
+        Ok(Some(Rewound {
+            state: &self.scratch,
+            rng,
+            random_draws,
+        }))
+    }
+}
+
+/// Appends the runs of bytes in which `state` differs from `base` to `delta`, see
+/// `RewindBuffer`. Returns false, leaving `delta` as it was, when they don't fit in its
+/// capacity.
+fn encode(base: &[u8], state: &[u8], delta: &mut Vec<u8>) -> bool {
+    let len = delta.len();
+    let mut position = 0;
+    while position < state.len() {
+        let unchanged = base[position..]
+            .iter()
+            .zip(&state[position..])
+            .take_while(|(base, state)| base == state)
+            .count();
+        if position + unchanged == state.len() {
+            break;
+        }
+        let start = position + unchanged;
+        let changed = base[start..]
+            .iter()
+            .zip(&state[start..])
+            .take_while(|(base, state)| base != state)
+            .count();
+        if delta.len() + RUN_HEADER_LEN + changed > delta.capacity() {
+            delta.truncate(len);
+            return false;
+        }
+        push_varint(delta, unchanged);
+        push_varint(delta, changed);
+        delta.extend(
+            base[start..start + changed]
+                .iter()
+                .zip(&state[start..start + changed])
+                .map(|(base, state)| base ^ state),
+        );
+        position = start + changed;
+    }
+    true
+}
+
+/// Applies the runs of `delta` to `state`, a copy of the keyframe.
+fn decode(state: &mut [u8], delta: &[u8]) -> Result<(), Error> {
+    let mut rest = delta;
+    let mut position = 0;
+    while !rest.is_empty() {
+        position += take_varint(&mut rest)?;
+        let changed = take_varint(&mut rest)?;
+        if changed > rest.len() || position + changed > state.len() {
+            return Err(anyhow!("Corrupt rewind delta at byte {}", position));
+        }
+        for (byte, xor) in state[position..position + changed].iter_mut().zip(rest) {
+            *byte ^= xor;
+        }
+        rest = &rest[changed..];
+        position += changed;
+    }
+    Ok(())
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn take_varint(bytes: &mut &[u8]) -> Result<usize, Error> {
+    let mut value = 0;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| anyhow!("Corrupt rewind delta: truncated"))?;
+        *bytes = rest;
+        value |= (byte as usize & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(anyhow!("Corrupt rewind delta: varint too long"))
+}
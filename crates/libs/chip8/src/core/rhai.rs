@@ -0,0 +1,148 @@
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Error};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use tracing::info;
+
+use super::script::{ScriptContext, ScriptHost};
+
+/// Operations a script may run per call before it is stopped, so an endless loop in a script
+/// ends with an error instead of freezing the emulator.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+type Shared = Arc<Mutex<ScriptContext>>;
+
+/// Runs the script with the context shared with the registered functions, as `rhai` errors.
+fn with<T>(
+    shared: &Shared,
+    call: impl FnOnce(&mut ScriptContext) -> Result<T, Error>,
+) -> Result<T, Box<EvalAltResult>> {
+    let mut context = shared
+        .lock()
+        .map_err(|_| "The script context is poisoned")?;
+    call(&mut context).map_err(|e| e.to_string().into())
+}
+
+/// `ScriptHost` for Rhai, the pure Rust scripting language. Besides the shared API `on_frame`
+/// gets `this`, a map kept from frame to frame for the script's own state, since Rhai
+/// functions cannot see the variables of the top level.
+pub struct RhaiHost {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    this: Dynamic,
+    context: Shared,
+    on_frame: bool,
+}
+
+impl RhaiHost {
+    /// Compiles `source` and runs its top level.
+    pub fn new(source: &str) -> Result<Self, Error> {
+        let context = Shared::default();
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.on_print(|text| info!("Script: {}", text));
+        engine.on_debug(|text, _, position| info!("Script {}: {}", position, text));
+
+        let shared = context.clone();
+        engine.register_fn("frame", move || with(&shared, |c| Ok(c.frame() as i64)));
+        let shared = context.clone();
+        engine.register_fn("peek", move |address: i64| {
+            with(&shared, |c| c.peek(address).map(i64::from))
+        });
+        let shared = context.clone();
+        engine.register_fn("poke", move |address: i64, value: i64| {
+            with(&shared, |c| c.poke(address, value))
+        });
+        let shared = context.clone();
+        engine.register_fn("reg", move |n: i64| {
+            with(&shared, |c| c.reg(n).map(i64::from))
+        });
+        let shared = context.clone();
+        engine.register_fn("set_reg", move |n: i64, value: i64| {
+            with(&shared, |c| c.set_reg(n, value))
+        });
+        let shared = context.clone();
+        engine.register_fn("reg_i", move || with(&shared, |c| Ok(c.reg_i() as i64)));
+        let shared = context.clone();
+        engine.register_fn("pc", move || with(&shared, |c| Ok(c.pc() as i64)));
+        let shared = context.clone();
+        engine.register_fn("dt", move || with(&shared, |c| Ok(c.dt() as i64)));
+        let shared = context.clone();
+        engine.register_fn("st", move || with(&shared, |c| Ok(c.st() as i64)));
+        let shared = context.clone();
+        engine.register_fn("is_pressed", move |key: i64| {
+            with(&shared, |c| c.is_pressed(key))
+        });
+        let shared = context.clone();
+        engine.register_fn("press", move |key: i64| {
+            with(&shared, |c| c.set_key(key, true))
+        });
+        let shared = context.clone();
+        engine.register_fn("release", move |key: i64| {
+            with(&shared, |c| c.set_key(key, false))
+        });
+        let shared = context.clone();
+        engine.register_fn("pause", move || {
+            with(&shared, |c| {
+                c.pause();
+                Ok(())
+            })
+        });
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("Failed to compile the script: {}", e))?;
+        let on_frame = ast
+            .iter_functions()
+            .any(|function| function.name == "on_frame" && function.params.is_empty());
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("The script failed: {}", e))?;
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            this: Dynamic::from_map(Map::new()),
+            context,
+            on_frame,
+        })
+    }
+}
+
+impl ScriptHost for RhaiHost {
+    fn language(&self) -> &'static str {
+        "Rhai"
+    }
+
+    fn on_frame(&mut self, context: &mut ScriptContext) -> Result<(), Error> {
+        if !self.on_frame {
+            return Ok(());
+        }
+        *self
+            .context
+            .lock()
+            .map_err(|_| anyhow!("The script context is poisoned"))? = mem::take(context);
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            "on_frame",
+            (),
+        );
+        *context = mem::take(
+            &mut *self
+                .context
+                .lock()
+                .map_err(|_| anyhow!("The script context is poisoned"))?,
+        );
+        result
+            .map(|_| ())
+            .map_err(|e| anyhow!("The script failed in on_frame: {}", e))
+    }
+}
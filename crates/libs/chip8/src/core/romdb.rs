@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use serde_derive::Deserialize;
+use serde_yaml::{Mapping, Value};
+use shared::config::profile::rom_hash;
+
+use super::replay::Quirks;
+
+/// The database compiled into the emulator.
+const BUILTIN: &str = include_str!("../../romdb.yaml");
+
+/// What is known about one ROM.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RomInfo {
+    /// Lowercase hex SHA-1 of the ROM, as `shared::config::profile::rom_hash` gives it
+    pub sha1: String,
+    pub title: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Name of the `Quirks::PRESETS` entry the ROM was written for
+    #[serde(default = "default_platform")]
+    pub platform: String,
+    #[serde(default)]
+    pub cycles_per_frame: Option<u32>,
+    /// Quirks replacing the ones of the platform, missing fields being the default ones
+    #[serde(default)]
+    pub quirks: Option<Quirks>,
+}
+
+fn default_platform() -> String {
+    "default".to_string()
+}
+
+impl RomInfo {
+    /// The recommended settings as a ROM profile, for `Config::with_profile`.
+    pub fn profile(&self) -> Result<Value, Error> {
+        let quirks = match self.quirks {
+            Some(quirks) => quirks,
+            None => Quirks::preset(&self.platform)?,
+        };
+        let mut profile = Mapping::new();
+        let mut set = |field: &str, value: Value| profile.insert(field.into(), value);
+        set("st_equals_buzzer", quirks.st_equals_buzzer.into());
+        set(
+            "bit_shift_instructions_use_vy",
+            quirks.bit_shift_instructions_use_vy.into(),
+        );
+        set(
+            "store_read_instructions_change_i",
+            quirks.store_read_instructions_change_i.into(),
+        );
+        if let Some(cycles_per_frame) = self.cycles_per_frame {
+            set("cycles_per_frame", cycles_per_frame.into());
+        }
+        Ok(Value::Mapping(profile))
+    }
+
+    /// The recommended settings with the fields of the ROM profile `overrides` put over them.
+    pub fn profile_with(&self, overrides: Option<Value>) -> Result<Value, Error> {
+        let mut profile = self.profile()?;
+        if let (Value::Mapping(profile), Some(Value::Mapping(overrides))) =
+            (&mut profile, overrides)
+        {
+            profile.extend(overrides);
+        }
+        Ok(profile)
+    }
+}
+
+/// A `programs.json` entry of the CHIP-8 Archive.
+#[derive(Debug, Deserialize)]
+struct ArchiveProgram {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    /// `chip8`, `schip` or `xochip`
+    #[serde(default)]
+    platform: String,
+    #[serde(default)]
+    options: ArchiveOptions,
+}
+
+/// The Octo settings the program was released with, the ones this emulator supports.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveOptions {
+    /// Instructions per frame
+    tickrate: Option<u32>,
+    /// Shifts change VX in place instead of shifting VY into it
+    shift_quirks: Option<bool>,
+    /// `LD [I], VX` and `LD VX, [I]` leave I unchanged
+    load_store_quirks: Option<bool>,
+}
+
+impl ArchiveProgram {
+    fn into_info(self, sha1: String) -> RomInfo {
+        // Octo runs CHIP-8 and XO-CHIP programs with the VIP behaviours unless the options
+        // turn the quirks on
+        let platform = match self.platform.as_str() {
+            "schip" => "schip",
+            _ => "vip",
+        };
+        let mut quirks = Quirks::preset(platform).expect("The archive platforms are presets");
+        if let Some(shift_quirks) = self.options.shift_quirks {
+            quirks.bit_shift_instructions_use_vy = !shift_quirks;
+        }
+        if let Some(load_store_quirks) = self.options.load_store_quirks {
+            quirks.store_read_instructions_change_i = !load_store_quirks;
+        }
+        RomInfo {
+            sha1,
+            title: self.title,
+            author: (!self.authors.is_empty()).then(|| self.authors.join(", ")),
+            platform: platform.to_string(),
+            cycles_per_frame: self.options.tickrate,
+            quirks: Some(quirks),
+        }
+    }
+}
+
+/// Titles and recommended settings of known ROMs by hash.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomDatabase {
+    entries: Vec<RomInfo>,
+}
+
+impl RomDatabase {
+    /// Reads a database, a YAML list of entries.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let entries: Vec<RomInfo> = serde_yaml::from_str::<Option<_>>(text)
+            .map_err(|e| anyhow!("Invalid ROM database: {}", e))?
+            .unwrap_or_default();
+        for entry in &entries {
+            Quirks::preset(&entry.platform).map_err(|e| anyhow!("{}: {}", entry.title, e))?;
+        }
+        Ok(Self { entries })
+    }
+
+    /// The database shipped with the emulator.
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("The builtin ROM database is invalid!")
+    }
+
+    /// The builtin database updated with the programs of the CHIP-8 Archive checkout in
+    /// `archive`, then with the entries of `path`, each when it exists.
+    pub fn with_updates(archive: &Path, path: &Path) -> Result<Self, Error> {
+        let mut database = Self::builtin();
+        if archive.join("programs.json").exists() {
+            database.update(Self::from_archive(archive)?);
+        }
+        if path.exists() {
+            let text = fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            let updates = Self::parse(&text).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            database.update(updates);
+        }
+        Ok(database)
+    }
+
+    /// Reads a checkout of the [CHIP-8 Archive](https://github.com/JohnEarnest/chip8Archive):
+    /// `programs.json` holds the metadata by program name and `roms/<name>.ch8` the ROMs,
+    /// which are hashed to match them. Programs without their ROM are left out.
+    pub fn from_archive(folder: &Path) -> Result<Self, Error> {
+        let path = folder.join("programs.json");
+        let text = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let programs: BTreeMap<String, ArchiveProgram> = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?;
+        let mut entries = Vec::new();
+        for (name, program) in programs {
+            let rom = folder.join("roms").join(format!("{name}.ch8"));
+            if let Ok(rom) = fs::read(&rom) {
+                entries.push(program.into_info(rom_hash(&rom)));
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// Adds the entries of `updates`, replacing the ones with the same hash.
+    pub fn update(&mut self, updates: RomDatabase) {
+        for entry in updates.entries {
+            let sha1 = entry.sha1.to_ascii_lowercase();
+            self.entries.retain(|known| known.sha1 != sha1);
+            self.entries.push(RomInfo { sha1, ..entry });
+        }
+    }
+
+    pub fn find(&self, sha1: &str) -> Option<&RomInfo> {
+        self.entries
+            .iter()
+            .find(|entry| entry.sha1.eq_ignore_ascii_case(sha1))
+    }
+
+    pub fn entries(&self) -> &[RomInfo] {
+        &self.entries
+    }
+}
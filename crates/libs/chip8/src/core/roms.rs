@@ -0,0 +1,48 @@
+use anyhow::{anyhow, Error};
+
+/// A ROM compiled into the crate, see `BUILTIN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinRom {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub bytes: &'static [u8],
+}
+
+/// Test and demo ROMs written for this repository and dedicated to the public domain, so
+/// tests and demos run without downloading anything. Their listings are in
+/// `crates/libs/chip8/roms/README.md`.
+pub const BUILTIN: &[BuiltinRom] = &[
+    BuiltinRom {
+        name: "logo",
+        description: "Draws CHIP-8 in the middle of the screen",
+        bytes: include_bytes!("../../roms/logo.ch8"),
+    },
+    BuiltinRom {
+        name: "self-test",
+        description: "Checks arithmetic, BCD, load/store, calls and compares, shows OK or E and \
+                      the number of the failed check",
+        bytes: include_bytes!("../../roms/self-test.ch8"),
+    },
+    BuiltinRom {
+        name: "keypad",
+        description: "Shows the last pressed key with a short beep",
+        bytes: include_bytes!("../../roms/keypad.ch8"),
+    },
+    BuiltinRom {
+        name: "timer",
+        description: "Counts from 0 to F once a second with the delay timer",
+        bytes: include_bytes!("../../roms/timer.ch8"),
+    },
+];
+
+/// Looks up a builtin ROM by name.
+pub fn builtin(name: &str) -> Result<&'static BuiltinRom, Error> {
+    BUILTIN.iter().find(|rom| rom.name == name).ok_or_else(|| {
+        let names: Vec<&str> = BUILTIN.iter().map(|rom| rom.name).collect();
+        anyhow!(
+            "Unknown builtin ROM '{}', expected one of {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use tracing::info;
+
+use super::chip8::RAM_SIZE;
+use super::emulator::Emulator;
+use super::service::Service;
+
+/// A scripting language running a script before every emulated frame. Hosts only translate
+/// their language's calls to `ScriptContext`, so every script sees the same API whatever it
+/// is written in:
+///
+/// | Function | |
+/// |---|---|
+/// | `frame()` | Frames emulated since power-on |
+/// | `peek(address)`, `poke(address, value)` | Reads or writes a byte of RAM |
+/// | `reg(n)`, `set_reg(n, value)` | Reads or writes V0 to VF |
+/// | `reg_i()`, `pc()`, `dt()`, `st()` | The index register, program counter and timers |
+/// | `is_pressed(key)`, `press(key)`, `release(key)` | The keypad, keys 0 to 15 |
+/// | `pause()` | Pauses the emulation after the frame |
+/// | `print(text)` | Writes to the log |
+///
+/// The script's top level runs once when it is loaded, its `on_frame` function before every
+/// frame.
+pub trait ScriptHost: Send {
+    /// Name of the language, for the logs.
+    fn language(&self) -> &'static str;
+
+    /// Calls the script's `on_frame`, a no-op when the script has none.
+    fn on_frame(&mut self, context: &mut ScriptContext) -> Result<(), Error>;
+}
+
+/// A script runs as a service: its `on_frame` before every frame, with what it changed applied
+/// once it returns. A failing script is stopped, the game goes on without it.
+impl Service for Box<dyn ScriptHost> {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn apply(&mut self, emulator: &mut Emulator) -> Result<(), Error> {
+        let mut context = ScriptContext::new(emulator);
+        self.on_frame(&mut context)?;
+        context.apply(emulator)
+    }
+}
+
+/// Loads the script at `path` with the host for its extension.
+pub fn load(path: &Path) -> Result<Box<dyn ScriptHost>, Error> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read the script {}: {}", path.display(), e))?;
+    let host = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("rhai") => rhai_host(&source)?,
+        _ => {
+            return Err(anyhow!(
+                "Unknown script language of {}, expected a .rhai file",
+                path.display()
+            ))
+        }
+    };
+    info!("Loaded the {} script {}", host.language(), path.display());
+    Ok(host)
+}
+
+#[cfg(feature = "rhai")]
+fn rhai_host(source: &str) -> Result<Box<dyn ScriptHost>, Error> {
+    Ok(Box::new(super::rhai::RhaiHost::new(source)?))
+}
+
+#[cfg(not(feature = "rhai"))]
+fn rhai_host(_source: &str) -> Result<Box<dyn ScriptHost>, Error> {
+    Err(anyhow!(
+        "Rhai scripts are not compiled into this build, enable the rhai cargo feature"
+    ))
+}
+
+/// The machine as a script sees it before a frame, and what the script changed, applied to
+/// the emulator once the script returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptContext {
+    frame: u64,
+    ram: Vec<u8>,
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    dt: u8,
+    st: u8,
+    keys: [bool; 16],
+    // Writes in the order the script made them
+    pokes: Vec<(u16, u8)>,
+    registers: Vec<(u8, u8)>,
+    key_changes: Vec<(u8, bool)>,
+    pause: bool,
+}
+
+impl ScriptContext {
+    /// The state of `emulator` before its next frame.
+    pub fn new(emulator: &Emulator) -> Self {
+        let mut v = [0; 16];
+        let mut keys = [false; 16];
+        for n in 0..16 {
+            v[n as usize] = emulator.get_v(n).unwrap_or_default();
+            keys[n as usize] = emulator.is_key_pressed(n).unwrap_or_default();
+        }
+        Self {
+            frame: emulator.frame(),
+            ram: emulator.get_ram().to_vec(),
+            v,
+            i: emulator.get_i(),
+            pc: emulator.get_pc(),
+            dt: emulator.get_dt(),
+            st: emulator.get_st(),
+            keys,
+            pokes: Vec::new(),
+            registers: Vec::new(),
+            key_changes: Vec::new(),
+            pause: false,
+        }
+    }
+
+    /// Applies what the script changed to `emulator`.
+    pub fn apply(self, emulator: &mut Emulator) -> Result<(), Error> {
+        for (address, value) in self.pokes {
+            emulator.set_to_ram(address as usize, value)?;
+        }
+        for (n, value) in self.registers {
+            emulator.set_v(n, value)?;
+        }
+        for (key, pressed) in self.key_changes {
+            if pressed {
+                emulator.key_press(key)?;
+            } else {
+                emulator.key_release(key)?;
+            }
+        }
+        if self.pause {
+            emulator.set_paused(true);
+        }
+        Ok(())
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn peek(&self, address: i64) -> Result<u8, Error> {
+        Ok(self.ram[Self::address(address)?])
+    }
+
+    pub fn poke(&mut self, address: i64, value: i64) -> Result<(), Error> {
+        let address = Self::address(address)?;
+        let value = Self::byte(value)?;
+        self.ram[address] = value;
+        self.pokes.push((address as u16, value));
+        Ok(())
+    }
+
+    pub fn reg(&self, n: i64) -> Result<u8, Error> {
+        Ok(self.v[Self::nibble(n, "V register")? as usize])
+    }
+
+    pub fn set_reg(&mut self, n: i64, value: i64) -> Result<(), Error> {
+        let n = Self::nibble(n, "V register")?;
+        let value = Self::byte(value)?;
+        self.v[n as usize] = value;
+        self.registers.push((n, value));
+        Ok(())
+    }
+
+    pub fn reg_i(&self) -> u16 {
+        self.i
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    pub fn is_pressed(&self, key: i64) -> Result<bool, Error> {
+        Ok(self.keys[Self::nibble(key, "key")? as usize])
+    }
+
+    /// Presses or releases `key`, which stays so until the script or the player changes it.
+    pub fn set_key(&mut self, key: i64, pressed: bool) -> Result<(), Error> {
+        let key = Self::nibble(key, "key")?;
+        self.keys[key as usize] = pressed;
+        self.key_changes.push((key, pressed));
+        Ok(())
+    }
+
+    pub fn pause(&mut self) {
+        self.pause = true;
+    }
+
+    fn address(address: i64) -> Result<usize, Error> {
+        usize::try_from(address)
+            .ok()
+            .filter(|&address| address < RAM_SIZE)
+            .ok_or_else(|| anyhow!("Address {} is outside the RAM", address))
+    }
+
+    fn byte(value: i64) -> Result<u8, Error> {
+        u8::try_from(value).map_err(|_| anyhow!("{} does not fit in a byte", value))
+    }
+
+    fn nibble(n: i64, what: &str) -> Result<u8, Error> {
+        u8::try_from(n)
+            .ok()
+            .filter(|&n| n < 16)
+            .ok_or_else(|| anyhow!("There is no {} {}", what, n))
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, Error};
+
+/// Candidates listed with their values after a scan, `list` shows more.
+pub const LIST_LIMIT: usize = 16;
+
+/// How a scan narrows down the candidates, comparing RAM to the previous scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Equals(u8),
+    Changed,
+    Unchanged,
+    Decreased,
+    Increased,
+}
+
+impl Filter {
+    fn keeps(self, previous: u8, current: u8) -> bool {
+        match self {
+            Filter::Equals(value) => current == value,
+            Filter::Changed => current != previous,
+            Filter::Unchanged => current == previous,
+            Filter::Decreased => current < previous,
+            Filter::Increased => current > previous,
+        }
+    }
+}
+
+/// A line typed into the search console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchCommand {
+    /// Starts over with every RAM address as a candidate
+    New,
+    Filter(Filter),
+    List,
+    /// Adds a cheat freezing a found address
+    Freeze {
+        address: u16,
+        value: u8,
+    },
+    Help,
+}
+
+pub const HELP: &str = "Commands: new, = <value>, changed, unchanged, decreased, increased, \
+                        list, freeze <address> <value>, help. Numbers are decimal or 0x hex.";
+
+fn number(text: Option<&str>) -> Result<u16, Error> {
+    let text = text.ok_or_else(|| anyhow!("Missing number, {}", HELP))?;
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse(),
+    }
+    .map_err(|e| anyhow!("Invalid number '{}': {}", text, e))
+}
+
+fn byte(text: Option<&str>) -> Result<u8, Error> {
+    let value = number(text)?;
+    u8::try_from(value).map_err(|_| anyhow!("{} does not fit in a byte", value))
+}
+
+impl SearchCommand {
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut words = line.split_whitespace();
+        let command = match words.next().unwrap_or("help") {
+            "new" => SearchCommand::New,
+            "=" | "eq" => SearchCommand::Filter(Filter::Equals(byte(words.next())?)),
+            "changed" => SearchCommand::Filter(Filter::Changed),
+            "unchanged" => SearchCommand::Filter(Filter::Unchanged),
+            "decreased" => SearchCommand::Filter(Filter::Decreased),
+            "increased" => SearchCommand::Filter(Filter::Increased),
+            "list" => SearchCommand::List,
+            "freeze" => SearchCommand::Freeze {
+                address: number(words.next())?,
+                value: byte(words.next())?,
+            },
+            "help" => SearchCommand::Help,
+            other => return Err(anyhow!("Unknown command '{}'. {}", other, HELP)),
+        };
+        if words.next().is_some() {
+            return Err(anyhow!("Too many arguments in '{}'. {}", line.trim(), HELP));
+        }
+        Ok(command)
+    }
+}
+
+/// Cheat-engine style search for the RAM address of a value such as the lives or the score:
+/// scan for its value, then keep narrowing the candidates down by how it changed since.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemorySearch {
+    // RAM at the previous scan
+    previous: Vec<u8>,
+    // None until the first scan, meaning every address
+    candidates: Option<Vec<u16>>,
+}
+
+impl MemorySearch {
+    /// Makes every address of `ram` a candidate again.
+    pub fn start(&mut self, ram: &[u8]) {
+        self.previous = ram.to_vec();
+        self.candidates = Some((0..ram.len() as u16).collect());
+    }
+
+    /// Keeps the candidates `filter` accepts. The first scan has nothing to compare to, so it
+    /// can only look for a value.
+    pub fn refine(&mut self, ram: &[u8], filter: Filter) -> Result<(), Error> {
+        if self.candidates.is_none() {
+            if !matches!(filter, Filter::Equals(_)) {
+                return Err(anyhow!(
+                    "Nothing to compare to yet, scan for a value with = first"
+                ));
+            }
+            self.start(ram);
+        }
+        let previous = &self.previous;
+        if let Some(candidates) = &mut self.candidates {
+            candidates.retain(|&address| {
+                let address = address as usize;
+                filter.keeps(previous[address], ram[address])
+            });
+        }
+        self.previous = ram.to_vec();
+        Ok(())
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        self.candidates.as_deref().unwrap_or_default()
+    }
+
+    /// Counts the candidates left and lists the first `limit` with their values.
+    pub fn summary(&self, limit: usize) -> String {
+        let candidates = self.candidates();
+        let mut out = format!("{} candidates", candidates.len());
+        for &address in candidates.iter().take(limit) {
+            let value = self.previous[address as usize];
+            let _ = write!(out, "\n0x{:03X} = {} (0x{:02X})", address, value, value);
+        }
+        if limit > 0 && candidates.len() > limit {
+            let _ = write!(out, "\n... and {} more", candidates.len() - limit);
+        }
+        out
+    }
+}
@@ -0,0 +1,29 @@
+use anyhow::Error;
+
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+
+/// Something driving the emulator from outside the frontend, e.g. a remote control endpoint,
+/// a debugger, the chat vote or a script. The protocol it speaks and the clients it serves
+/// stay with it, the emulator only calls it at the points of the frame loop below, and drops
+/// it when a call fails.
+pub trait Service: Send {
+    /// What the service is, for the logs, e.g. `script`.
+    fn name(&self) -> &str;
+
+    /// Called between frames before the input is applied, e.g. to serve the requests
+    /// received since. `cpu` runs the instructions of step commands.
+    fn poll(&mut self, _emulator: &mut Emulator, _cpu: &CpuController) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called before every emulated frame runs, with the keys of the frame applied.
+    fn apply(&mut self, _emulator: &mut Emulator) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once the emulated frames are presented, e.g. to stream the screen.
+    fn publish(&mut self, _emulator: &mut Emulator) -> Result<(), Error> {
+        Ok(())
+    }
+}
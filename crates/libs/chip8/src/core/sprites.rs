@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+
+use super::analysis::{CodeMap, START_ADDR};
+use super::instruction::Instruction;
+
+/// Tallest sprite a `DXYN` can draw.
+pub const MAX_ROWS: u8 = 15;
+
+/// A stretch of a ROM that looks like sprite data, one byte per 8 pixel wide row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    pub address: u16,
+    pub rows: Vec<u8>,
+    /// Pointed to by a reachable `LD I`, rather than only guessed from unreachable bytes
+    pub referenced: bool,
+}
+
+impl Sprite {
+    /// Draws the sprite one row per line, `█` for set bits and `.` for clear ones.
+    pub fn to_art(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| {
+                (0..8)
+                    .map(|bit| if row & (0x80 >> bit) != 0 { '█' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Row count of the first `DXYN` at most 8 reachable instructions after `from`, the usual
+/// distance between setting I and drawing.
+fn drawn_rows(rom: &[u8], code: &CodeMap, from: u16) -> Option<u8> {
+    code.instructions
+        .range(from..)
+        .skip(1)
+        .take(8)
+        .find_map(|&address| {
+            let offset = (address - START_ADDR) as usize;
+            let opcode = u16::from_be_bytes([rom[offset], *rom.get(offset + 1)?]);
+            match Instruction::decode(opcode)? {
+                Instruction::OpDXYN(_, _, n) if n > 0 => Some(n),
+                _ => None,
+            }
+        })
+}
+
+/// Finds the plausible sprites of `rom`, as loaded at 0x200. Every address a reachable
+/// `LD I` names inside the ROM is a sprite, as tall as the `DXYN` drawing it or `rows` when
+/// none follows closely. The remaining bytes outside code are cut into `rows` tall
+/// candidates, skipping the ones that are all zero.
+pub fn find_sprites(rom: &[u8], rows: u8) -> Vec<Sprite> {
+    let rows = rows.clamp(1, MAX_ROWS) as usize;
+    let code = CodeMap::new(rom);
+    let end = START_ADDR as usize + rom.len();
+    let slice = |address: u16, rows: usize| {
+        let start = (address - START_ADDR) as usize;
+        rom[start..(start + rows).min(rom.len())].to_vec()
+    };
+
+    let mut sprites = Vec::new();
+    let mut covered = BTreeSet::new();
+    for &address in &code.instructions {
+        let offset = (address - START_ADDR) as usize;
+        let Some(&[high, low]) = rom.get(offset..offset + 2) else {
+            continue;
+        };
+        let Some(Instruction::OpANNN(target)) =
+            Instruction::decode(u16::from_be_bytes([high, low]))
+        else {
+            continue;
+        };
+        if !(START_ADDR as usize..end).contains(&(target as usize))
+            || sprites
+                .iter()
+                .any(|sprite: &Sprite| sprite.address == target)
+        {
+            continue;
+        }
+        let height = drawn_rows(rom, &code, address).map_or(rows, usize::from);
+        let sprite = Sprite {
+            address: target,
+            rows: slice(target, height),
+            referenced: true,
+        };
+        covered.extend(target..target + sprite.rows.len() as u16);
+        sprites.push(sprite);
+    }
+
+    let mut address = START_ADDR;
+    while (address as usize) < end {
+        if code.is_code(address) || covered.contains(&address) {
+            address += 1;
+            continue;
+        }
+        // A candidate stops before code or a referenced sprite
+        let length = (address..address + rows as u16)
+            .take_while(|&next| {
+                (next as usize) < end && !code.is_code(next) && !covered.contains(&next)
+            })
+            .count();
+        let candidate = slice(address, length);
+        if candidate.iter().any(|&row| row != 0) {
+            sprites.push(Sprite {
+                address,
+                rows: candidate,
+                referenced: false,
+            });
+        }
+        address += length as u16;
+    }
+    sprites.sort_by_key(|sprite| sprite.address);
+    sprites
+}
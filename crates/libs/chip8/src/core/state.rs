@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Error};
+
+use super::chip8::{Framebuffer, CHIP8, RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, STACK_SIZE};
+use super::replay::Quirks;
+
+/// File signature of save states.
+const MAGIC: &[u8; 3] = b"C8S";
+const VERSION: u8 = 1;
+/// Bytes of the machine: RAM, stack, V0 to VF, I, SP, PC, DT, ST, keypad, screen and plane
+/// mask.
+const MACHINE_LEN: usize = RAM_SIZE + STACK_SIZE * 2 + 16 + 9 + SCREEN_WIDTH * SCREEN_HEIGHT + 1;
+/// Bytes before the ROM: magic and version, the machine, quirks, one-shot beep frames, seed,
+/// random draws, frame and ROM length.
+const HEADER_LEN: usize = 4 + MACHINE_LEN + 2 + 24 + 2;
+/// Largest ROM that fits in RAM after the reserved 512 bytes.
+const MAX_ROM_LEN: usize = RAM_SIZE - 0x200;
+/// Bytes of the largest save state, the one of a ROM filling the RAM.
+pub(crate) const MAX_STATE_LEN: usize = HEADER_LEN + MAX_ROM_LEN;
+
+/// Everything `Emulator::state_hash` covers plus the loaded ROM, so a run continues exactly
+/// where it was saved, also in a fresh emulator.
+///
+/// The format is the magic `C8S` and a version byte followed by the fields in declaration
+/// order, multi-byte values little endian, the keypad as a u16 bitmask and the ROM last,
+/// prefixed with its length as a u16.
+#[derive(Clone)]
+pub struct SaveState {
+    pub chip8: CHIP8,
+    pub rom: Vec<u8>,
+    pub quirks: Quirks,
+    pub one_shot_frames: u8,
+    pub seed: u64,
+    pub random_draws: u64,
+    pub frame: u64,
+}
+
+impl SaveState {
+    /// Size of `to_bytes` for a ROM of `rom_len` bytes, to size buffers up front.
+    pub fn len_for(rom_len: usize) -> usize {
+        HEADER_LEN + rom_len
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::len_for(self.rom.len()));
+        self.as_ref().write(&mut bytes);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut chip8 = CHIP8::default();
+        let state = StateRef::read(bytes, &mut chip8)?;
+        Ok(Self {
+            rom: state.rom.to_vec(),
+            quirks: state.quirks,
+            one_shot_frames: state.one_shot_frames,
+            seed: state.seed,
+            random_draws: state.random_draws,
+            frame: state.frame,
+            chip8,
+        })
+    }
+
+    fn as_ref(&self) -> StateRef<'_> {
+        StateRef {
+            chip8: &self.chip8,
+            rom: &self.rom,
+            quirks: self.quirks,
+            one_shot_frames: self.one_shot_frames,
+            seed: self.seed,
+            random_draws: self.random_draws,
+            frame: self.frame,
+        }
+    }
+}
+
+/// A `SaveState` borrowing the machine and the ROM, to write and read states in buffers kept
+/// from frame to frame without allocating, e.g. for rewinding.
+pub(crate) struct StateRef<'a> {
+    pub(crate) chip8: &'a CHIP8,
+    pub(crate) rom: &'a [u8],
+    pub(crate) quirks: Quirks,
+    pub(crate) one_shot_frames: u8,
+    pub(crate) seed: u64,
+    pub(crate) random_draws: u64,
+    pub(crate) frame: u64,
+}
+
+impl<'a> StateRef<'a> {
+    /// Appends the state to `bytes` in the `SaveState` format.
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        let chip8 = self.chip8;
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&chip8.ram);
+        for address in chip8.stack {
+            bytes.extend_from_slice(&address.to_le_bytes());
+        }
+        bytes.extend_from_slice(&chip8.v_reg);
+        bytes.extend_from_slice(&chip8.i_reg.to_le_bytes());
+        bytes.push(chip8.sp);
+        bytes.extend_from_slice(&chip8.pc.to_le_bytes());
+        bytes.extend_from_slice(&[chip8.dt, chip8.st]);
+        let keys = (0..16)
+            .filter(|&key| chip8.keys[key])
+            .fold(0u16, |keys, key| keys | 1 << key);
+        bytes.extend_from_slice(&keys.to_le_bytes());
+        bytes.extend_from_slice(chip8.display.pixels());
+        bytes.extend_from_slice(&[
+            chip8.plane_mask,
+            self.quirks.to_bits(),
+            self.one_shot_frames,
+        ]);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.random_draws.to_le_bytes());
+        bytes.extend_from_slice(&self.frame.to_le_bytes());
+        bytes.extend_from_slice(&(self.rom.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self.rom);
+    }
+
+    /// Reads the `SaveState` bytes `bytes` into `chip8`, borrowing the ROM from them.
+    pub(crate) fn read(bytes: &'a [u8], chip8: &'a mut CHIP8) -> Result<Self, Error> {
+        if bytes.len() < 4 || &bytes[..3] != MAGIC {
+            return Err(anyhow!("Not a CHIP-8 save state"));
+        }
+        if bytes[3] != VERSION {
+            return Err(anyhow!("Unsupported save state version {}", bytes[3]));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(anyhow!("Corrupt save state: truncated"));
+        }
+        let mut rest = &bytes[4..];
+        let mut take = |len: usize| {
+            let (taken, left) = rest.split_at(len);
+            rest = left;
+            taken
+        };
+        chip8.ram.copy_from_slice(take(RAM_SIZE));
+        for address in chip8.stack.iter_mut() {
+            *address = u16::from_le_bytes(take(2).try_into().unwrap());
+        }
+        chip8.v_reg.copy_from_slice(take(16));
+        chip8.i_reg = u16::from_le_bytes(take(2).try_into().unwrap());
+        chip8.sp = take(1)[0];
+        chip8.pc = u16::from_le_bytes(take(2).try_into().unwrap());
+        chip8.dt = take(1)[0];
+        chip8.st = take(1)[0];
+        let keys = u16::from_le_bytes(take(2).try_into().unwrap());
+        for (key, pressed) in chip8.keys.iter_mut().enumerate() {
+            *pressed = keys & 1 << key != 0;
+        }
+        let display: &[u8; SCREEN_WIDTH * SCREEN_HEIGHT] =
+            take(SCREEN_WIDTH * SCREEN_HEIGHT).try_into().unwrap();
+        let [plane_mask, quirks, one_shot_frames] = take(3).try_into().unwrap();
+        chip8.plane_mask = plane_mask;
+        let seed = u64::from_le_bytes(take(8).try_into().unwrap());
+        let random_draws = u64::from_le_bytes(take(8).try_into().unwrap());
+        let frame = u64::from_le_bytes(take(8).try_into().unwrap());
+        let rom_len = u16::from_le_bytes(take(2).try_into().unwrap()) as usize;
+
+        if chip8.sp as usize > STACK_SIZE {
+            return Err(anyhow!("Corrupt save state: stack pointer {}", chip8.sp));
+        }
+        if display.iter().any(|&pixel| pixel > 3) {
+            return Err(anyhow!("Corrupt save state: invalid pixel color"));
+        }
+        chip8.display = Framebuffer::from_pixels(display);
+        if rom_len > MAX_ROM_LEN || rest.len() != rom_len {
+            return Err(anyhow!("Corrupt save state: ROM length {}", rom_len));
+        }
+        Ok(Self {
+            chip8,
+            rom: rest,
+            quirks: Quirks::from_bits(quirks),
+            one_shot_frames,
+            seed,
+            random_draws,
+            frame,
+        })
+    }
+}
@@ -0,0 +1,469 @@
+use anyhow::Error;
+use shared::config::config::Palette;
+
+use super::audio::AudioSink;
+use super::chip8::{CHIP8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::controller::{self, DisplayController, Hotkey, InputController, InputEvent, Status};
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::replay::Quirks;
+use super::threaded;
+
+/// Chained set-up, run and assertions on a headless emulator for instruction tests, e.g.
+/// `EmulatorTest::new().load(&[0x60, 0x05, 0x70, 0x01]).run(2).assert_v(0, 6)`. The RNG
+/// seed is fixed and every failure panics with the instruction count run so far.
+pub struct EmulatorTest {
+    emulator: Emulator,
+    cpu: CpuController,
+    executed: usize,
+}
+
+impl Default for EmulatorTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmulatorTest {
+    pub fn new() -> Self {
+        let mut emulator = Emulator::new(CHIP8::default());
+        emulator.set_seed(0);
+        Self {
+            emulator,
+            cpu: CpuController,
+            executed: 0,
+        }
+    }
+
+    /// Loads a program at 0x200 next to the font.
+    pub fn load(mut self, program: &[u8]) -> Self {
+        self.emulator
+            .init_ram_from_bytes(program)
+            .expect("Failed to load the test program");
+        self
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.emulator.set_quirks(quirks);
+        self
+    }
+
+    pub fn set_v(mut self, x: u8, value: u8) -> Self {
+        self.emulator.set_v(x, value).unwrap();
+        self
+    }
+
+    pub fn set_i(mut self, value: u16) -> Self {
+        self.emulator.set_i(value);
+        self
+    }
+
+    pub fn set_dt(mut self, value: u8) -> Self {
+        self.emulator.set_dt(value);
+        self
+    }
+
+    pub fn press(mut self, key: u8) -> Self {
+        self.emulator.key_press(key).unwrap();
+        self
+    }
+
+    pub fn release(mut self, key: u8) -> Self {
+        self.emulator.key_release(key).unwrap();
+        self
+    }
+
+    /// Executes `instructions` instructions, panicking when one fails.
+    #[track_caller]
+    pub fn run(mut self, instructions: usize) -> Self {
+        for _ in 0..instructions {
+            if let Err(e) = self.cpu.tick(&mut self.emulator) {
+                panic!("instruction {} failed: {}", self.executed + 1, e);
+            }
+            self.executed += 1;
+        }
+        self
+    }
+
+    /// Executes one instruction that is expected to fail.
+    #[track_caller]
+    pub fn run_err(mut self) -> Self {
+        let result = self.cpu.tick(&mut self.emulator);
+        self.executed += 1;
+        assert!(
+            result.is_err(),
+            "instruction {} succeeded, expected an error",
+            self.executed
+        );
+        self
+    }
+
+    /// Runs whole frames of `cycles_per_frame` instructions, decrementing the timers.
+    #[track_caller]
+    pub fn run_frames(mut self, frames: usize, cycles_per_frame: u32) -> Self {
+        for _ in 0..frames {
+            if let Err(e) = self.cpu.step_frame(&mut self.emulator, cycles_per_frame) {
+                panic!("frame {} failed: {}", self.emulator.frame() + 1, e);
+            }
+            self.executed += cycles_per_frame as usize;
+        }
+        self
+    }
+
+    /// Runs `frames` frames through the frontend loop, as the apps do, with mock frontends
+    /// such as `NullDisplay`, `ScriptedInput` and `CapturingAudio`. Stops early when the
+    /// input quits.
+    #[track_caller]
+    pub fn run_session<D: DisplayController, I: InputController, A: AudioSink>(
+        mut self,
+        frames: usize,
+        cycles_per_frame: u32,
+        display: &mut D,
+        input: &mut I,
+        audio: &mut A,
+    ) -> Self {
+        for _ in 0..frames {
+            let before = self.emulator.frame();
+            let running = controller::run_frame(
+                &mut self.emulator,
+                &self.cpu,
+                cycles_per_frame,
+                display,
+                input,
+                audio,
+            );
+            self.executed += (self.emulator.frame() - before) as usize * cycles_per_frame as usize;
+            match running {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => panic!("frame {} failed: {}", self.emulator.frame() + 1, e),
+            }
+        }
+        self
+    }
+
+    /// Runs the frontend loop with the emulation on its own thread, as the desktop app does,
+    /// in real time until the input quits.
+    #[track_caller]
+    pub fn run_threaded<D: DisplayController, I: InputController, A: AudioSink>(
+        mut self,
+        cycles_per_frame: u32,
+        display: &mut D,
+        input: &mut I,
+        audio: &mut A,
+    ) -> Self {
+        let before = self.emulator.frame();
+        if let Err(e) = threaded::run(
+            &mut self.emulator,
+            &self.cpu,
+            cycles_per_frame,
+            display,
+            input,
+            audio,
+        ) {
+            panic!("frame {} failed: {}", self.emulator.frame() + 1, e);
+        }
+        self.executed += (self.emulator.frame() - before) as usize * cycles_per_frame as usize;
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_v(self, x: u8, expected: u8) -> Self {
+        let actual = self.emulator.get_v(x).unwrap();
+        assert_eq!(
+            actual, expected,
+            "V{:X} after {} instructions",
+            x, self.executed
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_i(self, expected: u16) -> Self {
+        assert_eq!(
+            self.emulator.get_i(),
+            expected,
+            "I after {} instructions",
+            self.executed
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_pc(self, expected: u16) -> Self {
+        assert_eq!(
+            self.emulator.get_pc(),
+            expected,
+            "PC after {} instructions",
+            self.executed
+        );
+        self
+    }
+
+    /// Checks the return addresses on the stack, the innermost call last.
+    #[track_caller]
+    pub fn assert_stack(self, expected: &[u16]) -> Self {
+        assert_eq!(
+            self.emulator.get_stack(),
+            expected,
+            "stack after {} instructions",
+            self.executed
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_dt(self, expected: u8) -> Self {
+        assert_eq!(
+            self.emulator.get_dt(),
+            expected,
+            "DT after {} instructions",
+            self.executed
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_st(self, expected: u8) -> Self {
+        assert_eq!(
+            self.emulator.get_st(),
+            expected,
+            "ST after {} instructions",
+            self.executed
+        );
+        self
+    }
+
+    /// Checks the RAM from `address` on.
+    #[track_caller]
+    pub fn assert_ram(self, address: usize, expected: &[u8]) -> Self {
+        assert_eq!(
+            &self.emulator.get_ram()[address..address + expected.len()],
+            expected,
+            "RAM at 0x{:03X} after {} instructions",
+            address,
+            self.executed
+        );
+        self
+    }
+
+    #[track_caller]
+    pub fn assert_pixel(self, x: usize, y: usize, expected: u8) -> Self {
+        let actual = self.emulator.get_pixel(x + y * SCREEN_WIDTH).unwrap();
+        assert_eq!(
+            actual,
+            expected,
+            "pixel ({}, {}) after {} instructions, screen:\n{}",
+            x,
+            y,
+            self.executed,
+            self.emulator.display_to_string()
+        );
+        self
+    }
+
+    /// Checks the number of lit pixels on the screen.
+    #[track_caller]
+    pub fn assert_lit(self, expected: usize) -> Self {
+        let lit = self
+            .emulator
+            .get_display()
+            .iter()
+            .filter(|&&p| p != 0)
+            .count();
+        assert_eq!(
+            lit,
+            expected,
+            "lit pixels after {} instructions, screen:\n{}",
+            self.executed,
+            self.emulator.display_to_string()
+        );
+        self
+    }
+
+    /// Checks the screen against a known `Emulator::display_hash`.
+    #[track_caller]
+    pub fn assert_display_hash(self, expected: u64) -> Self {
+        assert_eq!(
+            self.emulator.display_hash(),
+            expected,
+            "display hash after {} instructions, screen:\n{}",
+            self.executed,
+            self.emulator.display_to_string()
+        );
+        self
+    }
+
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    pub fn emulator_mut(&mut self) -> &mut Emulator {
+        &mut self.emulator
+    }
+}
+
+/// Display keeping what the frontend loop showed instead of drawing it.
+#[derive(Debug, Clone)]
+pub struct NullDisplay {
+    /// The last presented framebuffer
+    pub screen: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    /// Calls to `present` so far
+    pub presents: u64,
+    pub status: Option<Status>,
+    pub sound: bool,
+    /// Renderer hotkeys received, oldest first
+    pub hotkeys: Vec<Hotkey>,
+}
+
+impl Default for NullDisplay {
+    fn default() -> Self {
+        Self {
+            screen: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            presents: 0,
+            status: None,
+            sound: false,
+            hotkeys: Vec::new(),
+        }
+    }
+}
+
+impl DisplayController for NullDisplay {
+    fn present(&mut self, display: &[u8], _dirty: &[usize]) -> Result<(), Error> {
+        self.screen.copy_from_slice(display);
+        self.presents += 1;
+        Ok(())
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        self.hotkeys.push(hotkey);
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        self.status = Some(status.clone());
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        self.sound = on;
+        Ok(())
+    }
+
+    fn set_palette(&mut self, _palette: &Palette) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Input playing a script of events, each given on the poll it comes out of. The frontend
+/// loop polls once per `run_frame`, so poll `n` is the start of the frame `n` from 0, e.g.
+/// `ScriptedInput::new().tap(10, 0x5, 3).quit(60)`.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInput {
+    // Events by poll, kept sorted by poll
+    events: Vec<(u64, InputEvent)>,
+    polls: u64,
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn at(mut self, poll: u64, event: InputEvent) -> Self {
+        let index = self.events.partition_point(|&(at, _)| at <= poll);
+        self.events.insert(index, (poll, event));
+        self
+    }
+
+    pub fn press(self, poll: u64, key: u8) -> Self {
+        self.at(poll, InputEvent::KeyDown(key))
+    }
+
+    pub fn release(self, poll: u64, key: u8) -> Self {
+        self.at(poll, InputEvent::KeyUp(key))
+    }
+
+    /// Presses `key` on `poll` and releases it `frames` polls later.
+    pub fn tap(self, poll: u64, key: u8, frames: u64) -> Self {
+        self.press(poll, key).release(poll + frames, key)
+    }
+
+    pub fn hotkey(self, poll: u64, hotkey: Hotkey) -> Self {
+        self.at(poll, InputEvent::Hotkey(hotkey))
+    }
+
+    pub fn quit(self, poll: u64) -> Self {
+        self.at(poll, InputEvent::Quit)
+    }
+
+    /// Polls so far.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Whether every scripted event came out.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl InputController for ScriptedInput {
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        let due = self.events.partition_point(|&(at, _)| at <= self.polls);
+        self.polls += 1;
+        Ok(self.events.drain(..due).map(|(_, event)| event).collect())
+    }
+}
+
+/// Audio sink capturing the buzzer state of every emulated frame and the pushed samples.
+#[derive(Debug, Clone, Default)]
+pub struct CapturingAudio {
+    beeping: bool,
+    /// The buzzer state at the end of every emulated frame, oldest first
+    pub frames: Vec<bool>,
+    pub samples: Vec<f32>,
+    pub volume: u8,
+    pub muted: bool,
+}
+
+impl CapturingAudio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emulated frames the buzzer sounded in.
+    pub fn beep_frames(&self) -> usize {
+        self.frames.iter().filter(|&&on| on).count()
+    }
+}
+
+impl AudioSink for CapturingAudio {
+    fn set_beep(&mut self, on: bool) {
+        self.beeping = on;
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+    }
+
+    fn end_frame(&mut self) {
+        self.frames.push(self.beeping);
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+}
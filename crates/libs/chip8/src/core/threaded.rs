@@ -0,0 +1,220 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use shared::config::config::ConfigUpdate;
+use tracing::{info, trace_span};
+
+use super::audio::AudioSink;
+use super::chip8::{REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::controller::{self, DisplayController, InputController, InputEvent, Status};
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::pacer::FramePacer;
+use super::plugin::Panel;
+
+/// What the emulation thread hands the frontend: the screen after the latest frame and what
+/// changed since the frontend took the previous snapshot.
+pub struct Snapshot {
+    pub display: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    /// Pixel indices changed since the previous snapshot
+    pub dirty: Vec<usize>,
+    /// Whether the buzzer sounded in each frame emulated since the previous snapshot
+    pub sound: Vec<bool>,
+    pub buzzing: bool,
+    pub status: Option<Status>,
+    pub panels: Option<Vec<Panel>>,
+    /// A reloaded config, with the name of the ROM it applies to
+    pub config: Option<(ConfigUpdate, Option<String>)>,
+    /// Frontend events received from remote input
+    pub events: Vec<InputEvent>,
+}
+
+impl Snapshot {
+    /// Folds a newer snapshot into this one, for when the frontend fell behind.
+    fn merge(&mut self, newer: Snapshot) {
+        self.display = newer.display;
+        self.dirty.extend(newer.dirty);
+        self.dirty.sort_unstable();
+        self.dirty.dedup();
+        self.sound.extend(newer.sound);
+        self.buzzing = newer.buzzing;
+        self.status = newer.status.or(self.status.take());
+        self.panels = newer.panels.or(self.panels.take());
+        self.config = match (self.config.take(), newer.config) {
+            (Some((older, _)), Some((newer, rom))) => Some((
+                ConfigUpdate {
+                    previous: older.previous,
+                    current: newer.current,
+                },
+                rom,
+            )),
+            (older, newer) => newer.or(older),
+        };
+        self.events.extend(newer.events);
+    }
+}
+
+/// Drives the emulator at a fixed `REFRESH_RATE` on its own thread until the frontend quits,
+/// while the calling thread polls input, plays the sound and presents the snapshots it
+/// receives. Slow rendering, screenshots or recordings never hold the emulation back: frames
+/// the frontend has not taken yet are merged into one snapshot.
+pub fn run<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<(), Error> {
+    let (events, event_receiver) = mpsc::channel();
+    let (snapshot_sender, snapshots) = mpsc::sync_channel(1);
+    info!(
+        "Entering the emulation thread with {} cycles per frame",
+        cycles_per_frame
+    );
+
+    thread::scope(|scope| {
+        let emulation = thread::Builder::new()
+            .name("emulation".to_string())
+            .spawn_scoped(scope, move || {
+                emulate(
+                    emulator,
+                    cpu,
+                    cycles_per_frame,
+                    event_receiver,
+                    snapshot_sender,
+                )
+            })
+            .map_err(|e| anyhow!("Failed to start the emulation thread: {}", e))?;
+        // Returning drops the snapshot receiver, which stops the emulation thread
+        let presented = present(display, input, audio, events, snapshots);
+        let emulated = emulation
+            .join()
+            .map_err(|_| anyhow!("The emulation thread panicked"))?;
+        presented.and(emulated)
+    })
+}
+
+/// The emulation thread: applies the input events received and emulates the frames due,
+/// until the frontend stops listening.
+fn emulate(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    cycles_per_frame: u32,
+    events: Receiver<InputEvent>,
+    snapshots: SyncSender<Snapshot>,
+) -> Result<(), Error> {
+    let mut pacer = FramePacer::new(REFRESH_RATE);
+    let mut pending: Option<Snapshot> = None;
+    loop {
+        let frames = pacer.advance();
+        let _span = trace_span!("emulate", frames).entered();
+        let config = emulator.poll_config_update().map(|update| {
+            controller::apply_emulator_config(emulator, &update);
+            (update, emulator.get_rom_name().map(str::to_string))
+        });
+        let cycles_per_frame = controller::poll_services(emulator, cpu, cycles_per_frame);
+
+        let mut frontend_events = Vec::new();
+        let received = events.try_iter().collect::<Vec<_>>();
+        for event in received.into_iter().chain(emulator.poll_remote_input()) {
+            if controller::is_frontend_event(event) {
+                frontend_events.push(event);
+            } else if !controller::apply_event(emulator, event)? {
+                return Ok(());
+            }
+        }
+        let status = emulator.take_status_changed().then(|| {
+            let status = emulator.status();
+            emulator.publish_status(&status);
+            status
+        });
+
+        let mut sound = Vec::new();
+        let emulated = controller::emulate(emulator, cpu, cycles_per_frame, frames, |buzzing| {
+            sound.push(buzzing)
+        })?;
+        let mut snapshot = Snapshot {
+            display: *emulator.get_display(),
+            dirty: emulator.take_dirty(),
+            sound,
+            buzzing: !emulator.is_paused() && emulator.is_buzzing(),
+            status,
+            panels: emulator.take_panels().map(<[Panel]>::to_vec),
+            config,
+            events: frontend_events,
+        };
+        emulator.publish_services();
+        emulator.record_frame(emulated, emulated as u64 * cycles_per_frame as u64);
+
+        if let Some(mut older) = pending.take() {
+            older.merge(snapshot);
+            snapshot = older;
+        }
+        match snapshots.try_send(snapshot) {
+            Ok(()) => {}
+            Err(TrySendError::Full(snapshot)) => pending = Some(snapshot),
+            Err(TrySendError::Disconnected(_)) => return Ok(()),
+        }
+        pacer.wait();
+    }
+}
+
+/// The frontend side: forwards input to the emulation thread and shows its snapshots, until
+/// the frontend asks to quit or the emulation thread stops.
+fn present<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+    events: mpsc::Sender<InputEvent>,
+    snapshots: Receiver<Snapshot>,
+) -> Result<(), Error> {
+    let frame = Duration::from_secs(1) / REFRESH_RATE;
+    loop {
+        for event in trace_span!("input").in_scope(|| input.poll())? {
+            if controller::is_frontend_event(event) {
+                controller::apply_frontend_event(event, display, audio)?;
+            } else if event == InputEvent::Quit || events.send(event).is_err() {
+                return Ok(());
+            }
+        }
+        // Input is still polled while the emulation thread waits, e.g. for a netplay peer
+        let snapshot = match snapshots.recv_timeout(frame) {
+            Ok(snapshot) => snapshot,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        show(snapshot, display, input, audio)?;
+    }
+}
+
+fn show<D: DisplayController, I: InputController, A: AudioSink + ?Sized>(
+    snapshot: Snapshot,
+    display: &mut D,
+    input: &mut I,
+    audio: &mut A,
+) -> Result<(), Error> {
+    if let Some((update, rom)) = &snapshot.config {
+        controller::apply_frontend_config(update, rom.as_deref(), display, input, audio)?;
+    }
+    for event in snapshot.events {
+        controller::apply_frontend_event(event, display, audio)?;
+    }
+    if let Some(status) = &snapshot.status {
+        display.show_status(status)?;
+    }
+    trace_span!("audio").in_scope(|| {
+        for buzzing in snapshot.sound {
+            audio.set_beep(buzzing);
+            audio.end_frame();
+        }
+    });
+    audio.set_beep(snapshot.buzzing);
+    display.show_sound(snapshot.buzzing)?;
+    if let Some(panels) = &snapshot.panels {
+        display.show_panels(panels)?;
+    }
+    trace_span!("render").in_scope(|| display.present(&snapshot.display, &snapshot.dirty))
+}
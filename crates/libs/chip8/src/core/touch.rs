@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Error};
+use shared::config::config::Touch;
+
+use super::controller::InputEvent;
+use super::input::EventQueue;
+
+/// Pointer id frontends use for the mouse, next to the ids of the touch points.
+pub const MOUSE_POINTER: i64 = -1;
+
+/// Turns touches and mouse clicks into keypad presses through a grid of keys laid over the
+/// window. Every pointer holds at most one key, so several fingers press chorded keys and a
+/// key stays down until the last pointer on it lifts.
+pub struct TouchPad {
+    rows: Vec<Vec<Option<u8>>>,
+    // Pressed pointers and the key under each, `None` over cells without a key
+    pointers: HashMap<i64, Option<u8>>,
+    holders: [u8; 16],
+}
+
+impl TouchPad {
+    pub fn new(settings: &Touch) -> Result<Self, Error> {
+        let rows = settings
+            .layout
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| match cell {
+                        '.' => Ok(None),
+                        _ => cell
+                            .to_digit(16)
+                            .map(|key| Some(key as u8))
+                            .ok_or_else(|| anyhow!("Invalid touch layout key '{}'", cell)),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if rows.is_empty() || rows.iter().any(|row| row.is_empty()) {
+            return Err(anyhow!("The touch layout needs at least one key per row"));
+        }
+        Ok(Self {
+            rows,
+            pointers: HashMap::new(),
+            holders: [0; 16],
+        })
+    }
+
+    /// The keys of the grid row by row, `None` for empty cells.
+    pub fn rows(&self) -> &[Vec<Option<u8>>] {
+        &self.rows
+    }
+
+    /// Returns the key under a point given in window fractions from 0.0 to 1.0.
+    pub fn key_at(&self, x: f32, y: f32) -> Option<u8> {
+        let cell = |position: f32, count: usize| {
+            ((position.clamp(0.0, 1.0) * count as f32) as usize).min(count - 1)
+        };
+        let row = &self.rows[cell(y, self.rows.len())];
+        row[cell(x, row.len())]
+    }
+
+    pub fn press(&mut self, pointer: i64, x: f32, y: f32, queue: &mut EventQueue) {
+        self.release(pointer, queue);
+        let key = self.key_at(x, y);
+        self.pointers.insert(pointer, key);
+        if let Some(key) = key {
+            self.holders[key as usize] += 1;
+            if self.holders[key as usize] == 1 {
+                queue.push(InputEvent::KeyDown(key));
+            }
+        }
+    }
+
+    /// Follows a pointer sliding over the grid. Moves of pointers not pressed are ignored, so
+    /// hovering the mouse does nothing.
+    pub fn motion(&mut self, pointer: i64, x: f32, y: f32, queue: &mut EventQueue) {
+        if let Some(&key) = self.pointers.get(&pointer) {
+            if self.key_at(x, y) != key {
+                self.press(pointer, x, y, queue);
+            }
+        }
+    }
+
+    /// Lifts every pointer, e.g. when the window loses focus or the app is suspended.
+    pub fn release_all(&mut self, queue: &mut EventQueue) {
+        let pointers: Vec<i64> = self.pointers.keys().copied().collect();
+        for pointer in pointers {
+            self.release(pointer, queue);
+        }
+    }
+
+    pub fn release(&mut self, pointer: i64, queue: &mut EventQueue) {
+        if let Some(Some(key)) = self.pointers.remove(&pointer) {
+            self.holders[key as usize] -= 1;
+            if self.holders[key as usize] == 0 {
+                queue.push(InputEvent::KeyUp(key));
+            }
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+
+use super::chip8::CHIP8;
+use super::cpu::CpuController;
+use super::emulator::Emulator;
+use super::instruction::Instruction;
+use super::replay::Quirks;
+
+/// Matching steps shown before a divergence.
+const CONTEXT: usize = 8;
+
+/// An executed instruction and the machine state right after it.
+///
+/// In a trace file it is one line: the address and opcode in hex, then any of
+/// `v=<32 hex digits>`, `i=<hex>`, `sp=<hex>`, `dt=<hex>`, `st=<hex>` and `display=<hex>`,
+/// the latter being `Emulator::display_hash`. Fields left out are not compared, so traces of
+/// emulators that log less still work.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: u16,
+    pub opcode: u16,
+    pub v: Option<[u8; 16]>,
+    pub i: Option<u16>,
+    pub sp: Option<u8>,
+    pub dt: Option<u8>,
+    pub st: Option<u8>,
+    pub display: Option<u64>,
+}
+
+impl TraceStep {
+    /// The step with every field taken from `emulator`.
+    pub fn capture(pc: u16, opcode: u16, emulator: &Emulator) -> Result<Self, Error> {
+        let mut v = [0; 16];
+        for (x, value) in v.iter_mut().enumerate() {
+            *value = emulator.get_v(x as u8)?;
+        }
+        Ok(Self {
+            pc,
+            opcode,
+            v: Some(v),
+            i: Some(emulator.get_i()),
+            sp: Some(emulator.get_stack().len() as u8),
+            dt: Some(emulator.get_dt()),
+            st: Some(emulator.get_st()),
+            display: Some(emulator.display_hash()),
+        })
+    }
+
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut fields = line.split_whitespace();
+        let mut hex = |name: &str| -> Result<u16, Error> {
+            let field = fields.next().ok_or_else(|| anyhow!("Missing {}", name))?;
+            u16::from_str_radix(field, 16)
+                .map_err(|e| anyhow!("Invalid {} '{}': {}", name, field, e))
+        };
+        let mut step = Self {
+            pc: hex("address")?,
+            opcode: hex("opcode")?,
+            ..Self::default()
+        };
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key=value, got '{}'", field))?;
+            let invalid = |e| anyhow!("Invalid {} '{}': {}", key, value, e);
+            match key {
+                "v" => {
+                    if value.len() != 32 {
+                        return Err(anyhow!("Expected 32 hex digits for v, got '{}'", value));
+                    }
+                    let mut v = [0; 16];
+                    for (x, register) in v.iter_mut().enumerate() {
+                        *register =
+                            u8::from_str_radix(&value[x * 2..x * 2 + 2], 16).map_err(invalid)?;
+                    }
+                    step.v = Some(v);
+                }
+                "i" => step.i = Some(u16::from_str_radix(value, 16).map_err(invalid)?),
+                "sp" => step.sp = Some(u8::from_str_radix(value, 16).map_err(invalid)?),
+                "dt" => step.dt = Some(u8::from_str_radix(value, 16).map_err(invalid)?),
+                "st" => step.st = Some(u8::from_str_radix(value, 16).map_err(invalid)?),
+                "display" => step.display = Some(u64::from_str_radix(value, 16).map_err(invalid)?),
+                _ => return Err(anyhow!("Unknown trace field '{}'", key)),
+            }
+        }
+        Ok(step)
+    }
+
+    /// Describes how `actual` differs from this step, one line per field, over the fields
+    /// this step has.
+    pub fn diff(&self, actual: &TraceStep) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut field = |name: String, expected: String, actual: String| {
+            if expected != actual {
+                out.push(format!(
+                    "{:<7} expected {:<18} actual {}",
+                    name, expected, actual
+                ));
+            }
+        };
+        field(
+            "pc".into(),
+            format!("{:#06X}", self.pc),
+            format!("{:#06X}", actual.pc),
+        );
+        field(
+            "opcode".into(),
+            format!("{:04X}", self.opcode),
+            format!("{:04X}", actual.opcode),
+        );
+        if let (Some(expected), Some(actual)) = (self.v, actual.v) {
+            for x in 0..16 {
+                field(
+                    format!("V{:X}", x),
+                    format!("{:#04X}", expected[x]),
+                    format!("{:#04X}", actual[x]),
+                );
+            }
+        }
+        let mut optional = |name: &str, expected: Option<u64>, actual: Option<u64>| {
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                field(
+                    name.into(),
+                    format!("{:#X}", expected),
+                    format!("{:#X}", actual),
+                );
+            }
+        };
+        optional("I", self.i.map(u64::from), actual.i.map(u64::from));
+        optional("SP", self.sp.map(u64::from), actual.sp.map(u64::from));
+        optional("DT", self.dt.map(u64::from), actual.dt.map(u64::from));
+        optional("ST", self.st.map(u64::from), actual.st.map(u64::from));
+        optional("display", self.display, actual.display);
+        out
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X} {:04X}", self.pc, self.opcode)?;
+        if let Some(v) = self.v {
+            f.write_str(" v=")?;
+            for value in v {
+                write!(f, "{:02X}", value)?;
+            }
+        }
+        if let Some(i) = self.i {
+            write!(f, " i={:04X}", i)?;
+        }
+        if let Some(sp) = self.sp {
+            write!(f, " sp={:X}", sp)?;
+        }
+        if let Some(dt) = self.dt {
+            write!(f, " dt={:02X}", dt)?;
+        }
+        if let Some(st) = self.st {
+            write!(f, " st={:02X}", st)?;
+        }
+        if let Some(display) = self.display {
+            write!(f, " display={:016X}", display)?;
+        }
+        Ok(())
+    }
+}
+
+/// A reference run of a ROM, instruction by instruction.
+///
+/// Trace files start with `# key: value` header lines for the settings the run used:
+/// `cycles_per_frame` (default 10), `seed` (default 0) and the three quirks as `true` or
+/// `false`. Other lines starting with `#` are comments, every other line is a `TraceStep`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub cycles_per_frame: u32,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub steps: Vec<TraceStep>,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self {
+            cycles_per_frame: 10,
+            seed: 0,
+            quirks: Quirks::default(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut trace = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some((key, value)) = comment.split_once(':') {
+                    trace
+                        .header(key.trim(), value.trim())
+                        .map_err(|e| anyhow!("Line {}: {}", number + 1, e))?;
+                }
+                continue;
+            }
+            let step = TraceStep::parse(line).map_err(|e| anyhow!("Line {}: {}", number + 1, e))?;
+            trace.steps.push(step);
+        }
+        Ok(trace)
+    }
+
+    fn header(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let flag = || {
+            value
+                .parse::<bool>()
+                .map_err(|_| anyhow!("Expected true or false for {}, got '{}'", key, value))
+        };
+        match key {
+            "cycles_per_frame" => {
+                self.cycles_per_frame = value
+                    .parse()
+                    .map_err(|e| anyhow!("Invalid cycles_per_frame '{}': {}", value, e))?
+            }
+            "seed" => {
+                self.seed = value
+                    .parse()
+                    .map_err(|e| anyhow!("Invalid seed '{}': {}", value, e))?
+            }
+            "st_equals_buzzer" => self.quirks.st_equals_buzzer = flag()?,
+            "bit_shift_instructions_use_vy" => self.quirks.bit_shift_instructions_use_vy = flag()?,
+            "store_read_instructions_change_i" => {
+                self.quirks.store_read_instructions_change_i = flag()?
+            }
+            // Free-form comments that happen to contain a colon
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| anyhow!("Invalid trace {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.to_string())
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# cycles_per_frame: {}", self.cycles_per_frame)?;
+        writeln!(f, "# seed: {}", self.seed)?;
+        writeln!(f, "# st_equals_buzzer: {}", self.quirks.st_equals_buzzer)?;
+        writeln!(
+            f,
+            "# bit_shift_instructions_use_vy: {}",
+            self.quirks.bit_shift_instructions_use_vy
+        )?;
+        writeln!(
+            f,
+            "# store_read_instructions_change_i: {}",
+            self.quirks.store_read_instructions_change_i
+        )?;
+        for step in &self.steps {
+            writeln!(f, "{}", step)?;
+        }
+        Ok(())
+    }
+}
+
+/// The first step where a run left its reference trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the step in the trace, from 0
+    pub step: usize,
+    pub frame: u64,
+    pub expected: TraceStep,
+    pub actual: TraceStep,
+    /// The matching steps right before, oldest first
+    pub context: Vec<TraceStep>,
+    /// `Emulator::display_to_string` right after the step
+    pub screen: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let disassemble = |opcode| {
+            Instruction::decode(opcode).map_or_else(|| "???".to_string(), |i| i.to_string())
+        };
+        writeln!(
+            f,
+            "Diverged at step {} (frame {}) running {:#06X} {:04X} {}",
+            self.step,
+            self.frame,
+            self.actual.pc,
+            self.actual.opcode,
+            disassemble(self.actual.opcode)
+        )?;
+        for line in self.expected.diff(&self.actual) {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "Steps before:")?;
+        for step in &self.context {
+            writeln!(
+                f,
+                "  {:#06X}  {:04X}  {}",
+                step.pc,
+                step.opcode,
+                disassemble(step.opcode)
+            )?;
+        }
+        if self.expected.display.is_some() && self.expected.display != self.actual.display {
+            write!(f, "Screen:\n{}", self.screen)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `rom` from power-on, one `TraceStep` per instruction, with the timers counted down
+/// every `cycles_per_frame` instructions as in `CpuController::step_frame`. `on_step` gets
+/// each step and the machine right after it, and stops the run by returning false.
+fn run(
+    rom: &[u8],
+    cycles_per_frame: u32,
+    seed: u64,
+    quirks: Quirks,
+    mut on_step: impl FnMut(TraceStep, &Emulator) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(rom)?;
+    emulator.set_seed(seed);
+    emulator.set_quirks(quirks);
+    let cpu = CpuController;
+    loop {
+        for _ in 0..cycles_per_frame {
+            let pc = emulator.get_pc();
+            let opcode = u16::from_be_bytes([
+                emulator.get_from_ram(pc as usize)?,
+                emulator.get_from_ram(pc as usize + 1)?,
+            ]);
+            cpu.tick(&mut emulator)
+                .map_err(|e| anyhow!("{:#06X} {:04X} failed: {}", pc, opcode, e))?;
+            if !on_step(TraceStep::capture(pc, opcode, &emulator)?, &emulator)? {
+                return Ok(());
+            }
+        }
+        emulator.dec_all_timers();
+        emulator.advance_frame();
+    }
+}
+
+/// Runs `rom` against `trace` and returns the first step that differs from it, or `None`
+/// when every step of the trace matched.
+pub fn conform(rom: &[u8], trace: &Trace) -> Result<Option<Divergence>, Error> {
+    if trace.steps.is_empty() {
+        return Ok(None);
+    }
+    let mut divergence = None;
+    let mut index = 0;
+    run(
+        rom,
+        trace.cycles_per_frame,
+        trace.seed,
+        trace.quirks,
+        |actual, emulator| {
+            let expected = &trace.steps[index];
+            if !expected.diff(&actual).is_empty() {
+                divergence = Some(Divergence {
+                    step: index,
+                    frame: emulator.frame(),
+                    expected: expected.clone(),
+                    actual,
+                    context: trace.steps[index.saturating_sub(CONTEXT)..index].to_vec(),
+                    screen: emulator.display_to_string(),
+                });
+                return Ok(false);
+            }
+            index += 1;
+            Ok(index < trace.steps.len())
+        },
+    )
+    .map_err(|e| anyhow!("Step {}: {}", index, e))?;
+    Ok(divergence)
+}
+
+/// Records the first `steps` instructions of `rom` as a trace with every field, e.g. to
+/// keep the behaviour of a known-good build as a reference.
+pub fn record(
+    rom: &[u8],
+    steps: usize,
+    cycles_per_frame: u32,
+    seed: u64,
+    quirks: Quirks,
+) -> Result<Trace, Error> {
+    let mut trace = Trace {
+        cycles_per_frame,
+        seed,
+        quirks,
+        steps: Vec::with_capacity(steps),
+    };
+    if steps > 0 {
+        run(rom, cycles_per_frame, seed, quirks, |step, _| {
+            trace.steps.push(step);
+            Ok(trace.steps.len() < steps)
+        })?;
+    }
+    Ok(trace)
+}
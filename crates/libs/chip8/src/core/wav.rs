@@ -0,0 +1,159 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use shared::config::config::Audio;
+
+use super::audio::{AudioSink, Scope, Synth};
+use super::chip8::REFRESH_RATE;
+use super::controller::Hotkey;
+
+/// Sample rate of the recordings, a whole number of samples per 60Hz frame.
+pub const RECORD_SAMPLE_RATE: u32 = 44_100;
+
+/// Size of the RIFF and format headers written before the samples.
+const HEADER_SIZE: u32 = 44;
+
+/// Writes mono 16-bit PCM WAV files. The header sizes are patched in by `finish`.
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    samples: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, Error> {
+        let file = File::create(path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+        Self::new(BufWriter::new(file), sample_rate)
+            .map_err(|e| anyhow!("Failed to write WAV header: {}", e))
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        // PCM, mono, 16 bits per sample
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * 2).to_le_bytes())?;
+        out.write_all(&2u16.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { out, samples: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&value.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    /// Fills in the chunk sizes and flushes the file.
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_size = self.samples * 2;
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out
+            .write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&data_size.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Records the buzzer into a WAV file while forwarding everything to `inner`. Samples are
+/// rendered per emulated frame instead of in the realtime callback, so runs with the same
+/// input give identical files. The recording uses the configured volume, runtime volume
+/// changes and muting only affect `inner`.
+pub struct WavRecorder<'a, S: AudioSink + ?Sized> {
+    inner: &'a mut S,
+    synth: Synth,
+    writer: WavWriter<BufWriter<File>>,
+    frame: Vec<f32>,
+    error: Option<io::Error>,
+}
+
+impl<'a, S: AudioSink + ?Sized> WavRecorder<'a, S> {
+    pub fn create(path: &Path, settings: &Audio, inner: &'a mut S) -> Result<Self, Error> {
+        Ok(Self {
+            inner,
+            synth: Synth::new(settings, RECORD_SAMPLE_RATE),
+            writer: WavWriter::create(path, RECORD_SAMPLE_RATE)?,
+            frame: vec![0.0; (RECORD_SAMPLE_RATE / REFRESH_RATE) as usize],
+            error: None,
+        })
+    }
+
+    /// Completes the file, reporting the first write error of the recording if any.
+    pub fn finish(self) -> Result<(), Error> {
+        if let Some(e) = self.error {
+            return Err(anyhow!("Failed to write audio recording: {}", e));
+        }
+        self.writer
+            .finish()
+            .map(|_| ())
+            .map_err(|e| anyhow!("Failed to finish audio recording: {}", e))
+    }
+}
+
+impl<S: AudioSink + ?Sized> AudioSink for WavRecorder<'_, S> {
+    fn set_beep(&mut self, on: bool) {
+        self.synth.set_beep(on);
+        self.inner.set_beep(on);
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.synth.push_samples(samples);
+        self.inner.push_samples(samples);
+    }
+
+    fn end_frame(&mut self) {
+        self.synth.fill(&mut self.frame);
+        if self.error.is_none() {
+            self.error = self.writer.write_samples(&self.frame).err();
+        }
+        self.inner.end_frame();
+    }
+
+    fn volume(&self) -> u8 {
+        self.inner.volume()
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.inner.set_volume(volume);
+    }
+
+    fn is_muted(&self) -> bool {
+        self.inner.is_muted()
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.inner.set_muted(muted);
+    }
+
+    fn cycle_waveform(&mut self) {
+        self.synth.cycle_waveform();
+        self.inner.cycle_waveform();
+    }
+
+    fn scope(&self) -> Option<Scope> {
+        self.inner.scope()
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) {
+        if hotkey == Hotkey::CycleWaveform {
+            self.synth.cycle_waveform();
+        }
+        self.inner.handle_hotkey(hotkey);
+    }
+}
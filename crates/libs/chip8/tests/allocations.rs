@@ -0,0 +1,84 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+
+const BRIX: &[u8] = include_bytes!("../../../../roms/BRIX");
+
+/// Counts the allocations made by the threads that turned counting on.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+fn count() {
+    if COUNTING.with(Cell::get) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    COUNTING.with(|counting| counting.set(true));
+    f();
+    COUNTING.with(|counting| counting.set(false));
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn play(emulator: &mut Emulator, frames: u64) {
+    for frame in 0..frames {
+        // Moves the paddle so the recording gets events
+        if frame % 30 == 0 {
+            emulator.key_press(0x4).unwrap();
+        } else if frame % 30 == 15 {
+            emulator.key_release(0x4).unwrap();
+        }
+        emulator.record_rewind();
+        CpuController.step_frame(emulator, 15).unwrap();
+    }
+}
+
+#[test]
+fn test_frames_do_not_allocate_while_rewinding_and_recording() {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(BRIX).unwrap();
+    emulator.set_seed(0);
+    emulator.set_rewind(2);
+    emulator.start_recording().unwrap();
+    // Long enough for the rewind buffer to reuse its oldest keyframes
+    play(&mut emulator, 300);
+
+    assert_eq!(allocations(|| play(&mut emulator, 300)), 0);
+    let rewinding = allocations(|| {
+        for _ in 0..100 {
+            assert!(emulator.rewind_frame().unwrap());
+        }
+    });
+    assert_eq!(rewinding, 0);
+    assert_eq!(allocations(|| play(&mut emulator, 100)), 0);
+}
@@ -0,0 +1,33 @@
+use chip8::core::bench::{self, BenchSettings};
+use chip8::core::gallery::GALLERY;
+
+#[test]
+fn test_bench_compares_every_configuration() {
+    let settings = BenchSettings {
+        frames: 30,
+        cycles_per_frame: vec![5, 50],
+    };
+    let measurements = bench::run(GALLERY[0].bytes, &settings).unwrap();
+    let names: Vec<_> = measurements.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "interpreter, 5 cycles per frame",
+            "cached decode, 5 cycles per frame",
+            "block compiler, 5 cycles per frame",
+            "interpreter, 50 cycles per frame",
+            "cached decode, 50 cycles per frame",
+            "block compiler, 50 cycles per frame",
+            "sprites, a pixel at a time",
+            "sprites, packed rows",
+        ]
+    );
+    assert_eq!(measurements[3].count, 30 * 50);
+    // Each group is measured against its first configuration
+    assert_eq!(measurements[4].baseline, measurements[3].elapsed);
+    assert_eq!(measurements[6].speedup(), 1.0);
+
+    let table = bench::table(&measurements);
+    assert_eq!(table.lines().count(), measurements.len() + 1);
+    assert!(table.contains("sprites, packed rows"));
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "test-roms")]
+
+use chip8::core::gallery::GALLERY;
+use chip8::core::golden::capture;
+use chip8::core::roms::{builtin, BUILTIN};
+use chip8::core::testing::{CapturingAudio, EmulatorTest, NullDisplay, ScriptedInput};
+
+fn load(name: &str) -> EmulatorTest {
+    EmulatorTest::new().load(builtin(name).unwrap().bytes)
+}
+
+fn screen(test: &EmulatorTest) -> String {
+    capture(test.emulator().get_display())
+}
+
+#[test]
+fn test_builtin_roms_run() {
+    for rom in BUILTIN {
+        EmulatorTest::new().load(rom.bytes).run_frames(120, 10);
+    }
+    assert!(builtin("missing").is_err());
+}
+
+#[test]
+fn test_gallery_roms_run() {
+    for rom in GALLERY {
+        EmulatorTest::new().load(rom.bytes).run_frames(120, 10);
+    }
+}
+
+#[test]
+fn test_logo() {
+    insta::assert_snapshot!(screen(&load("logo").run_frames(10, 10)));
+}
+
+#[test]
+fn test_self_test_passes() {
+    insta::assert_snapshot!(screen(&load("self-test").run_frames(30, 10)));
+}
+
+#[test]
+fn test_keypad_shows_the_last_key() {
+    let (mut display, mut audio) = (NullDisplay::default(), CapturingAudio::new());
+    let mut input = ScriptedInput::new().tap(10, 0xA, 5).tap(30, 0x3, 5);
+    load("keypad")
+        .run_session(20, 10, &mut display, &mut input, &mut audio)
+        .assert_v(2, 0xA)
+        .run_session(40, 10, &mut display, &mut input, &mut audio)
+        .assert_v(2, 0x3)
+        .assert_lit(14);
+    assert_eq!(audio.beep_frames(), 6);
+}
+
+#[test]
+fn test_timer_counts_seconds() {
+    load("timer")
+        .run_frames(50, 10)
+        .assert_v(2, 0)
+        .run_frames(60, 10)
+        .assert_v(2, 1)
+        .run_frames(60 * 15, 10)
+        .assert_v(2, 0);
+}
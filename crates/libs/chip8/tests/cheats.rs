@@ -0,0 +1,82 @@
+use std::fs;
+
+use chip8::core::cheats::{Cheats, Condition, Effect};
+use chip8::core::testing::EmulatorTest;
+
+const CHEATS: &str = "
+- name: Infinite lives
+  freeze: { address: 0x300, value: 3 }
+- name: Start on level 5
+  patch: { address: 0x301, value: 5 }
+- name: Refill
+  patch_if: { address: 0x302, value: 9, when: { address: 0x302, equals: 0 } }
+  enabled: false
+";
+
+fn load() -> Cheats {
+    let path = std::env::temp_dir().join(format!("chip8-cheats-{}.yaml", std::process::id()));
+    fs::write(&path, CHEATS).unwrap();
+    let cheats = Cheats::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    cheats
+}
+
+#[test]
+fn test_load() {
+    let cheats = load();
+    assert_eq!(cheats.list().len(), 3);
+    assert_eq!(
+        cheats.list()[2].effect,
+        Effect::PatchIf {
+            address: 0x302,
+            value: 9,
+            when: Condition {
+                address: 0x302,
+                equals: 0
+            }
+        }
+    );
+    assert!(!cheats.list()[2].enabled);
+}
+
+#[test]
+fn test_apply() {
+    let mut cheats = load();
+    let mut ram = [0u8; 4096];
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x300..0x303], [3, 5, 0]);
+
+    // A patch is written once, a freeze every time
+    ram[0x300] = 1;
+    ram[0x301] = 1;
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x300..0x302], [3, 1]);
+
+    cheats.set_cheat_enabled(2, true).unwrap();
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x302], 9);
+    ram[0x302] = 4;
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x302], 4);
+
+    // Turning the cheats back on writes the patches again
+    cheats.set_enabled(false);
+    ram[0x300] = 0;
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x300], 0);
+    cheats.set_enabled(true);
+    cheats.apply(&mut ram);
+    assert_eq!(ram[0x300..0x302], [3, 5]);
+}
+
+#[test]
+fn test_applied_between_ticks() {
+    // Loads the lives at 0x300, takes one off, stores them and loads them again
+    let program = [
+        0xA3, 0x00, 0xF0, 0x65, 0x70, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0xA3, 0x00, 0xF0, 0x65,
+    ];
+    EmulatorTest::new().load(&program).run(7).assert_v(0, 0xFF);
+    let mut test = EmulatorTest::new().load(&program);
+    test.emulator_mut().set_cheats(load());
+    test.run(7).assert_v(0, 3);
+}
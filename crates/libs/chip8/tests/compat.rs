@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use chip8::core::compat::{check_rom, list_roms, markdown, CompatSettings, Verdict};
+use chip8::core::replay::Quirks;
+
+fn verdict(program: &[u8]) -> Verdict {
+    let settings = CompatSettings {
+        seconds: 1,
+        ..CompatSettings::default()
+    };
+    check_rom("test", program, Quirks::default(), &settings).verdict
+}
+
+#[test]
+fn test_verdicts() {
+    assert_eq!(verdict(&[0xA0, 0x00, 0xD0, 0x05, 0x12, 0x04]), Verdict::Ok);
+    assert_eq!(verdict(&[0x12, 0x00]), Verdict::Blank);
+    assert_eq!(
+        verdict(&[0x60, 0x01, 0x01, 0x23]),
+        Verdict::UnknownOpcode {
+            pc: 0x202,
+            opcode: 0x0123
+        }
+    );
+    assert!(matches!(verdict(&[0x00, 0xEE]), Verdict::Crashed(_)));
+    assert!(matches!(verdict(&[0; 4000]), Verdict::Crashed(_)));
+}
+
+#[test]
+fn test_markdown_report() {
+    let folder = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../roms");
+    let roms = list_roms(&folder).unwrap();
+    assert!(roms.iter().any(|path| path.ends_with("BRIX")));
+
+    let settings = CompatSettings::default();
+    let reports = vec![
+        check_rom("blank|rom", &[0x12, 0x00], Quirks::default(), &settings),
+        check_rom("empty stack", &[0x00, 0xEE], Quirks::default(), &settings),
+    ];
+    let report = markdown(&reports, &settings);
+    assert!(report.contains("0 ok, 1 blank, 0 unknown opcode, 1 crashed"));
+    assert!(report.contains("| blank\\|rom | blank | 600 |  |"));
+    assert!(report.contains("| empty stack | crashed | 0 | 00EE at 0x200: "));
+}
@@ -0,0 +1,63 @@
+use chip8::core::analysis::CodeMap;
+use chip8::core::disasm::{Item, LabelKind, Listing};
+
+// 0x200: CALL 0x208, LD I 0x20C, JP 0x204 (skipped by nothing, so 0x206 is data),
+// 0x208: SE V0 0, RET, then the sprite at 0x20C
+const PROGRAM: [u8; 14] = [
+    0x22, 0x08, 0xA2, 0x0C, 0x12, 0x04, 0xAB, 0xCD, 0x30, 0x00, 0x00, 0xEE, 0xF0, 0x90,
+];
+
+#[test]
+fn test_labels_and_data() {
+    let listing = Listing::new(&PROGRAM);
+    assert_eq!(listing.labels.get(&0x208), Some(&LabelKind::Sub));
+    assert_eq!(listing.labels.get(&0x204), Some(&LabelKind::Jump));
+    assert_eq!(listing.labels.get(&0x20C), Some(&LabelKind::Data));
+    assert_eq!(
+        listing.items[3],
+        Item::Data {
+            address: 0x206,
+            byte: 0xAB
+        }
+    );
+    assert_eq!(
+        listing.items[5],
+        Item::Code {
+            address: 0x208,
+            opcode: 0x3000
+        }
+    );
+    assert_eq!(
+        listing.items.last(),
+        Some(&Item::Data {
+            address: 0x20D,
+            byte: 0x90
+        })
+    );
+}
+
+#[test]
+fn test_render() {
+    let text = Listing::new(&PROGRAM).render(true);
+    assert!(text.contains("    0x200  2208  CALL sub_208\n"));
+    assert!(text.contains("label_204:\n    0x204  1204  JP label_204\n"));
+    assert!(text.contains("data_20C:\n    0x20C  F0    DB 0xF0  ; ████....\n"));
+    assert!(text.contains("; sub_208    <- 0x200\n"));
+}
+
+#[test]
+fn test_code_map() {
+    // 0x200: JP V0 0x206, data, table of JP 0x20C and JP 0x20E, then SKP V0 / RET / RET
+    let program = [
+        0xB2, 0x06, 0xFF, 0xFF, 0x12, 0x34, 0x12, 0x0C, 0x12, 0x0E, 0x00, 0x00, 0xE0, 0x9E, 0x00,
+        0xEE, 0x00, 0xEE,
+    ];
+    let code = CodeMap::new(&program);
+    assert_eq!(
+        code.instructions.iter().copied().collect::<Vec<_>>(),
+        [0x200, 0x206, 0x208, 0x20C, 0x20E, 0x210]
+    );
+    assert!(code.is_code(0x20D));
+    assert!(!code.is_code(0x202));
+    assert!(!code.is_code(0x204));
+}
@@ -0,0 +1,49 @@
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::dynarec::DynarecMode;
+use chip8::core::emulator::Emulator;
+use chip8::core::testing::EmulatorTest;
+
+const BRIX: &[u8] = include_bytes!("../../../../roms/BRIX");
+
+fn run_brix(mode: DynarecMode) -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(BRIX).unwrap();
+    emulator.set_seed(0);
+    emulator.set_dynarec(mode);
+    for frame in 0..600 {
+        // Moves the paddle left for a while so the run does not only wait
+        match frame {
+            100 => emulator.key_press(0x4).unwrap(),
+            160 => emulator.key_release(0x4).unwrap(),
+            _ => {}
+        }
+        CpuController.step_frame(&mut emulator, 15).unwrap();
+    }
+    emulator
+}
+
+#[test]
+fn test_compiled_blocks_match_the_interpreter() {
+    let interpreted = run_brix(DynarecMode::Off);
+    // Verify fails on the first block whose result differs from the interpreter's
+    let verified = run_brix(DynarecMode::Verify);
+    let compiled = run_brix(DynarecMode::On);
+    assert_eq!(verified.state_hash(), interpreted.state_hash());
+    assert_eq!(compiled.state_hash(), interpreted.state_hash());
+    assert_eq!(compiled.display_hash(), interpreted.display_hash());
+}
+
+#[test]
+fn test_compiled_blocks_follow_writes_to_ram() {
+    // Calls LD V2, 1 at 0x20C, rewrites its low byte to 5 with FX55 and calls it again
+    let mut test = EmulatorTest::new().load(&[
+        0x22, 0x0C, 0xA2, 0x0D, 0x60, 0x05, 0xF0, 0x55, 0x22, 0x0C, 0x12, 0x0A, 0x62, 0x01, 0x00,
+        0xEE,
+    ]);
+    test.emulator_mut().set_dynarec(DynarecMode::On);
+    let mut test = test.run_frames(1, 20).assert_v(2, 5);
+    test.emulator_mut().set_to_ram(0x20D, 7).unwrap();
+    test.emulator_mut().set_pc(0x20C);
+    test.run_frames(1, 1).assert_v(2, 7);
+}
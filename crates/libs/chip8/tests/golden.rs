@@ -0,0 +1,18 @@
+use std::path::Path;
+
+use chip8::core::golden::{check_suite, Outcome};
+
+#[test]
+fn test_golden_suite() {
+    let folder = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../tests/golden");
+    let failures: Vec<String> = check_suite(&folder, false)
+        .unwrap()
+        .into_iter()
+        .filter_map(|(golden, outcome)| match outcome {
+            Outcome::Fail(diff) => Some(format!("{}:\n{}", golden.name, diff)),
+            Outcome::MissingCapture => Some(format!("{}: no capture", golden.name)),
+            _ => None,
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,58 @@
+use chip8::core::testing::EmulatorTest;
+
+// Draws the glyph 0, then keeps drawing random bytes
+const PROGRAM: &[u8] = &[0xA0, 0x00, 0xD0, 0x05, 0xC1, 0xFF, 0x12, 0x04];
+
+#[test]
+fn test_display_hash_is_stable() {
+    // Pinned so a change of hash function or of the framebuffer layout shows up
+    EmulatorTest::new()
+        .load(PROGRAM)
+        .run(2)
+        .assert_display_hash(0xD7A6_C5D1_C579_3075);
+}
+
+#[test]
+fn test_same_run_same_state_hash() {
+    let first = EmulatorTest::new().load(PROGRAM).run(20);
+    let second = EmulatorTest::new().load(PROGRAM).run(20);
+    assert_eq!(
+        first.emulator().state_hash(),
+        second.emulator().state_hash()
+    );
+}
+
+#[test]
+fn test_state_hash_follows_the_rng() {
+    let first = EmulatorTest::new().load(PROGRAM).run(3);
+    let mut second = EmulatorTest::new().load(PROGRAM).run(5);
+    // Same registers and screen, but the second run drew one more random byte
+    second
+        .emulator_mut()
+        .set_v(1, first.emulator().get_v(1).unwrap())
+        .unwrap();
+    second.emulator_mut().set_pc(first.emulator().get_pc());
+    assert_eq!(
+        first.emulator().display_hash(),
+        second.emulator().display_hash()
+    );
+    assert_ne!(
+        first.emulator().state_hash(),
+        second.emulator().state_hash()
+    );
+}
+
+#[test]
+fn test_state_hash_ignores_the_unused_stack() {
+    // With V0 = 0 it calls and returns, leaving the return address above the stack pointer,
+    // with V0 = 1 it skips the call
+    let program = [0x30, 0x01, 0x22, 0x08, 0x12, 0x04, 0x00, 0x00, 0x00, 0xEE];
+    let called = EmulatorTest::new().load(&program).run(4);
+    let mut jumped = EmulatorTest::new().load(&program).set_v(0, 1).run(2);
+    jumped.emulator_mut().set_v(0, 0).unwrap();
+    assert_eq!(called.emulator().get_pc(), jumped.emulator().get_pc());
+    assert_eq!(
+        called.emulator().state_hash(),
+        jumped.emulator().state_hash()
+    );
+}
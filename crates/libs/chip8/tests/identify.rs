@@ -0,0 +1,50 @@
+use chip8::core::identify::{Identity, Platform};
+
+#[test]
+fn test_identity() {
+    // 0x200: HIGH, SE V0 0 going to JP 0x208 or F000 0x0210 at 0x206, then LD V1 0x00 and
+    // EXIT. The address word of F000 at 0x208 is no instruction, and the second HIGH and the
+    // scroll after EXIT are never reached
+    let program = [
+        0x00, 0xFF, 0x30, 0x00, 0x12, 0x08, 0xF0, 0x00, 0x02, 0x10, 0x61, 0x00, 0x00, 0xFD, 0x00,
+        0xFF, 0x00, 0xC4,
+    ];
+    let identity = Identity::new(&program);
+    assert_eq!(identity.size, 18);
+    assert_eq!(identity.sha1.len(), 40);
+    assert_eq!(identity.crc32, crc32(&program));
+    let found: Vec<_> = identity
+        .extensions
+        .iter()
+        .map(|extension| (extension.address, extension.description))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (0x200, "high resolution"),
+            (0x206, "long I load"),
+            (0x20C, "exit")
+        ]
+    );
+    assert_eq!(identity.platform(), Platform::XoChip);
+
+    let plain = Identity::new(&[0x60, 0x01, 0x12, 0x02]);
+    assert!(plain.extensions.is_empty());
+    assert_eq!(plain.platform(), Platform::Chip8);
+}
+
+// Bitwise CRC-32, to check against an implementation other than the one used
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
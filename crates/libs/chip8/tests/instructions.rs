@@ -0,0 +1,426 @@
+use chip8::core::chip8::{Framebuffer, CHIP8, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::instruction::Instruction;
+use chip8::core::testing::EmulatorTest;
+
+#[test]
+fn test_00e0_clears_the_screen() {
+    EmulatorTest::new()
+        .load(&[0xA0, 0x00, 0xD0, 0x05, 0x00, 0xE0])
+        .run(2)
+        .assert_lit(14)
+        .run(1)
+        .assert_lit(0);
+}
+
+#[test]
+fn test_2nnn_00ee_call_and_return() {
+    EmulatorTest::new()
+        .load(&[0x22, 0x04, 0x00, 0x00, 0x00, 0xEE])
+        .run(1)
+        .assert_pc(0x204)
+        .assert_stack(&[0x202])
+        .run(1)
+        .assert_pc(0x202)
+        .assert_stack(&[]);
+}
+
+#[test]
+fn test_00ee_fails_on_an_empty_stack() {
+    EmulatorTest::new().load(&[0x00, 0xEE]).run_err();
+}
+
+#[test]
+fn test_2nnn_fails_past_16_calls() {
+    // Calls itself until the stack is full
+    EmulatorTest::new().load(&[0x22, 0x00]).run(16).run_err();
+}
+
+#[test]
+fn test_1nnn_jumps() {
+    EmulatorTest::new()
+        .load(&[0x13, 0x45])
+        .run(1)
+        .assert_pc(0x345);
+}
+
+#[test]
+fn test_3xnn_4xnn_skip_on_byte() {
+    EmulatorTest::new()
+        .load(&[0x30, 0x07])
+        .set_v(0, 7)
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0x30, 0x07])
+        .run(1)
+        .assert_pc(0x202);
+    EmulatorTest::new()
+        .load(&[0x40, 0x07])
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0x40, 0x07])
+        .set_v(0, 7)
+        .run(1)
+        .assert_pc(0x202);
+}
+
+#[test]
+fn test_5xy0_9xy0_skip_on_register() {
+    EmulatorTest::new()
+        .load(&[0x50, 0x10])
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0x50, 0x10])
+        .set_v(1, 3)
+        .run(1)
+        .assert_pc(0x202);
+    EmulatorTest::new()
+        .load(&[0x90, 0x10])
+        .set_v(1, 3)
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0x90, 0x10])
+        .run(1)
+        .assert_pc(0x202);
+}
+
+#[test]
+fn test_6xnn_7xnn_load_and_add() {
+    EmulatorTest::new()
+        .load(&[0x60, 0x05, 0x70, 0x01])
+        .run(2)
+        .assert_v(0, 6)
+        .assert_pc(0x204);
+    // 7XNN wraps and leaves VF alone
+    EmulatorTest::new()
+        .load(&[0x60, 0xFF, 0x70, 0x02])
+        .run(2)
+        .assert_v(0, 1)
+        .assert_v(0xF, 0);
+}
+
+#[test]
+fn test_8xy0_to_8xy3_logic() {
+    let run = |n: u8, expected| {
+        EmulatorTest::new()
+            .load(&[0x80, 0x10 | n])
+            .set_v(0, 0b1100)
+            .set_v(1, 0b1010)
+            .run(1)
+            .assert_v(0, expected);
+    };
+    run(0, 0b1010);
+    run(1, 0b1110);
+    run(2, 0b1000);
+    run(3, 0b0110);
+}
+
+#[test]
+fn test_8xy4_adds_with_carry() {
+    EmulatorTest::new()
+        .load(&[0x80, 0x14])
+        .set_v(0, 200)
+        .set_v(1, 100)
+        .run(1)
+        .assert_v(0, 44)
+        .assert_v(0xF, 1);
+    EmulatorTest::new()
+        .load(&[0x80, 0x14])
+        .set_v(0, 20)
+        .set_v(1, 10)
+        .run(1)
+        .assert_v(0, 30)
+        .assert_v(0xF, 0);
+}
+
+#[test]
+fn test_8xy5_8xy7_subtract_with_borrow() {
+    EmulatorTest::new()
+        .load(&[0x80, 0x15])
+        .set_v(0, 10)
+        .set_v(1, 3)
+        .run(1)
+        .assert_v(0, 7)
+        .assert_v(0xF, 1);
+    EmulatorTest::new()
+        .load(&[0x80, 0x15])
+        .set_v(0, 3)
+        .set_v(1, 10)
+        .run(1)
+        .assert_v(0, 249)
+        .assert_v(0xF, 0);
+    EmulatorTest::new()
+        .load(&[0x80, 0x17])
+        .set_v(0, 3)
+        .set_v(1, 10)
+        .run(1)
+        .assert_v(0, 7)
+        .assert_v(0xF, 1);
+    EmulatorTest::new()
+        .load(&[0x80, 0x17])
+        .set_v(0, 10)
+        .set_v(1, 3)
+        .run(1)
+        .assert_v(0, 249)
+        .assert_v(0xF, 0);
+}
+
+#[test]
+fn test_8xy6_8xye_shift() {
+    EmulatorTest::new()
+        .load(&[0x80, 0x06])
+        .set_v(0, 0b0000_0101)
+        .run(1)
+        .assert_v(0, 0b0000_0010)
+        .assert_v(0xF, 1);
+    EmulatorTest::new()
+        .load(&[0x80, 0x0E])
+        .set_v(0, 0b1000_0001)
+        .run(1)
+        .assert_v(0, 0b0000_0010)
+        .assert_v(0xF, 1);
+}
+
+#[test]
+fn test_annn_bnnn_address() {
+    EmulatorTest::new()
+        .load(&[0xA1, 0x23])
+        .run(1)
+        .assert_i(0x123);
+    EmulatorTest::new()
+        .load(&[0xB3, 0x00])
+        .set_v(0, 0x10)
+        .run(1)
+        .assert_pc(0x310);
+}
+
+#[test]
+fn test_cxnn_masks_the_random_byte() {
+    let test = EmulatorTest::new().load(&[0xC0, 0x0F]).run(1);
+    assert_eq!(test.emulator().get_v(0).unwrap() & 0xF0, 0);
+}
+
+#[test]
+fn test_dxyn_draws_wraps_and_collides() {
+    // Digit 0 of the font at (62, 30) wraps to the left and top edges
+    EmulatorTest::new()
+        .load(&[0xD0, 0x15, 0xD0, 0x15])
+        .set_v(0, 62)
+        .set_v(1, 30)
+        .run(1)
+        .assert_pixel(62, 30, 1)
+        .assert_pixel(1, 30, 1)
+        .assert_pixel(62, 0, 1)
+        .assert_v(0xF, 0)
+        .run(1)
+        .assert_lit(0)
+        .assert_v(0xF, 1);
+}
+
+#[test]
+fn test_ex9e_exa1_skip_on_key() {
+    EmulatorTest::new()
+        .load(&[0xE0, 0x9E])
+        .set_v(0, 5)
+        .press(5)
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0xE0, 0x9E])
+        .set_v(0, 5)
+        .run(1)
+        .assert_pc(0x202);
+    EmulatorTest::new()
+        .load(&[0xE0, 0xA1])
+        .set_v(0, 5)
+        .run(1)
+        .assert_pc(0x204);
+    EmulatorTest::new()
+        .load(&[0xE0, 0xA1])
+        .set_v(0, 5)
+        .press(5)
+        .run(1)
+        .assert_pc(0x202);
+}
+
+#[test]
+fn test_fx07_fx15_fx18_timers() {
+    EmulatorTest::new()
+        .load(&[0x60, 0x09, 0xF0, 0x15, 0xF0, 0x18, 0xF1, 0x07])
+        .run(4)
+        .assert_dt(9)
+        .assert_st(9)
+        .assert_v(1, 9);
+    EmulatorTest::new()
+        .load(&[0x00, 0x00])
+        .set_dt(3)
+        .run_frames(2, 1)
+        .assert_dt(1);
+}
+
+#[test]
+fn test_fx0a_waits_for_a_key() {
+    EmulatorTest::new()
+        .load(&[0xF0, 0x0A])
+        .run(3)
+        .assert_pc(0x200)
+        .press(0xB)
+        .run(1)
+        .assert_pc(0x202)
+        .assert_v(0, 0xB);
+}
+
+#[test]
+fn test_fx1e_fx29_index() {
+    EmulatorTest::new()
+        .load(&[0xF0, 0x1E])
+        .set_i(0x100)
+        .set_v(0, 0x20)
+        .run(1)
+        .assert_i(0x120);
+    EmulatorTest::new()
+        .load(&[0xF0, 0x29])
+        .set_v(0, 0xA)
+        .run(1)
+        .assert_i(50);
+}
+
+#[test]
+fn test_fx33_stores_bcd() {
+    EmulatorTest::new()
+        .load(&[0xF0, 0x33])
+        .set_v(0, 254)
+        .set_i(0x300)
+        .run(1)
+        .assert_ram(0x300, &[2, 5, 4]);
+}
+
+#[test]
+fn test_fx55_fx65_store_and_load() {
+    EmulatorTest::new()
+        .load(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xA3, 0x00, 0xF2, 0x55])
+        .run(5)
+        .assert_ram(0x300, &[1, 2, 3]);
+    EmulatorTest::new()
+        .load(&[0xA2, 0x00, 0xF1, 0x65])
+        .run(2)
+        .assert_v(0, 0xA2)
+        .assert_v(1, 0x00)
+        .assert_v(2, 0);
+}
+
+#[test]
+fn test_fn01_selects_planes() {
+    let test = EmulatorTest::new().load(&[0xF2, 0x01]).run(1);
+    assert_eq!(test.emulator().get_plane_mask(), 2);
+}
+
+#[test]
+fn test_framebuffer_rows_match_the_pixels() {
+    let mut framebuffer = Framebuffer::default();
+    // A byte wrapped around the right edge on plane 1, then over its left half on plane 2
+    let bits = (0xFFu64 << 56).rotate_right(60);
+    assert!(!framebuffer.xor_row(3, bits, 0b01));
+    assert!(!framebuffer.xor_row(3, 0xFu64 << 60, 0b10));
+    assert!(framebuffer.xor_row(3, 0x1u64 << 60, 0b01));
+    framebuffer.set(SCREEN_WIDTH * 31 + 63, 3);
+    framebuffer.clear(0b01);
+    for y in 0..SCREEN_HEIGHT {
+        for x in 0..SCREEN_WIDTH {
+            let pixel = framebuffer.pixels()[y * SCREEN_WIDTH + x];
+            let bit = 1 << 63 >> x;
+            let expected = (framebuffer.row(y, 0b01) & bit != 0) as u8
+                | ((framebuffer.row(y, 0b10) & bit != 0) as u8) << 1;
+            assert_eq!(pixel, expected, "pixel {},{}", x, y);
+        }
+    }
+    assert_eq!(framebuffer.row(3, 0b11), 0xFu64 << 60);
+    assert_eq!(framebuffer.row(31, 0b10), 1);
+    let copy = Framebuffer::from_pixels(framebuffer.pixels());
+    assert_eq!(copy.row(3, 0b10), framebuffer.row(3, 0b10));
+}
+
+#[test]
+fn test_display_delta_is_tracked_apart_from_dirty() {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.xor_row(2, 0xC0u64 << 56, 0b01).unwrap();
+    // A display takes the changes every frame, a streamer in the same frame still gets them
+    assert_eq!(
+        emulator.take_dirty(),
+        vec![2 * SCREEN_WIDTH, 2 * SCREEN_WIDTH + 1]
+    );
+    assert_eq!(emulator.take_display_delta(), vec![(0, 2, 1), (1, 2, 1)]);
+    assert!(emulator.take_display_delta().is_empty());
+
+    // Changes pile up until the streamer takes them, however often the display does
+    emulator.xor_row(2, 0x80u64 << 56, 0b01).unwrap();
+    assert_eq!(emulator.take_dirty(), vec![2 * SCREEN_WIDTH]);
+    emulator.clear_screen();
+    assert_eq!(emulator.take_dirty(), vec![2 * SCREEN_WIDTH + 1]);
+    assert!(emulator.take_dirty().is_empty());
+    assert_eq!(emulator.take_display_delta(), vec![(0, 2, 0), (1, 2, 0)]);
+}
+
+#[test]
+fn test_unsupported_opcodes_fail() {
+    for opcode in [[0x01, 0x23], [0x80, 0x1F], [0xE0, 0x00], [0xF0, 0xFF]] {
+        EmulatorTest::new().load(&opcode).run_err();
+    }
+}
+
+#[test]
+fn test_dispatch_table_matches_decode() {
+    let machine = |word: u16| {
+        let mut emulator = Emulator::new(CHIP8::default());
+        emulator.init_ram_from_bytes(&word.to_be_bytes()).unwrap();
+        emulator.set_seed(0);
+        emulator.set_i(0x300);
+        for n in 0..16 {
+            emulator.set_v(n, n * 17).unwrap();
+        }
+        emulator
+    };
+    // Every first nibble and low byte, the parts the table dispatches on
+    let words = (0..16u16).flat_map(|group| {
+        [0x0, 0x5]
+            .into_iter()
+            .flat_map(move |x| (0..=0xFF).map(move |low| group << 12 | x << 8 | low))
+    });
+    for word in words {
+        let mut dispatched = machine(word);
+        let dispatched_ok = CpuController.tick(&mut dispatched).is_ok();
+        let mut decoded = machine(word);
+        decoded.inc_pc_by(2);
+        let decoded_ok = Instruction::decode(word).is_some_and(|i| i.call(&mut decoded).is_ok());
+        assert_eq!(dispatched_ok, decoded_ok, "{:04X}", word);
+        assert_eq!(
+            dispatched.state_hash(),
+            decoded.state_hash(),
+            "{:04X}",
+            word
+        );
+    }
+}
+
+#[test]
+fn test_self_modifying_code_runs_the_new_instruction() {
+    // Calls LD V2, 1 at 0x20C, rewrites its low byte to 5 with FX55 and calls it again
+    let mut test = EmulatorTest::new()
+        .load(&[
+            0x22, 0x0C, 0xA2, 0x0D, 0x60, 0x05, 0xF0, 0x55, 0x22, 0x0C, 0x12, 0x0A, 0x62, 0x01,
+            0x00, 0xEE,
+        ])
+        .run(3)
+        .assert_v(2, 1)
+        .run(5)
+        .assert_v(2, 5);
+    // Writes from outside the program count too, here turning it into LD V2, 7
+    test.emulator_mut().set_to_ram(0x20D, 7).unwrap();
+    test.emulator_mut().set_pc(0x20C);
+    test.run(1).assert_v(2, 7);
+}
@@ -0,0 +1,67 @@
+use chip8::core::lint::lint;
+
+fn messages(program: &[u8]) -> Vec<String> {
+    lint(program)
+        .iter()
+        .map(|finding| finding.to_string())
+        .collect()
+}
+
+#[test]
+fn test_clean() {
+    // LD I 0x206, DRW V0 V1 2, JP 0x204, then the sprite
+    assert!(messages(&[0xA2, 0x06, 0xD0, 0x12, 0x12, 0x04, 0xF0, 0xF0]).is_empty());
+}
+
+#[test]
+fn test_findings() {
+    let program = [
+        0x81, 0x06, // 0x200: SHR V1, V0
+        0xA0, 0x10, // 0x202: LD I 0x010
+        0xF1, 0x55, // 0x204: LD [I], V1, over the font
+        0xD0, 0x15, // 0x206: DRW with the I the store left
+        0xAF, 0xFE, // 0x208: LD I 0xFFE
+        0xF3, 0x65, // 0x20A: LD V3, [I], past the end of RAM
+        0x12, 0x0F, // 0x20C: JP 0x20F
+    ];
+    assert_eq!(
+        messages(&program),
+        [
+            "0x200: shifts V0 into V1 on the COSMAC VIP but V1 in place on SUPER-CHIP; copy V0 \
+             into V1 first and shift V1 by itself",
+            "0x204: writes 0x010-0x011, over the interpreter area and font",
+            "0x206: uses I after the load/store at 0x204, which moves I on the COSMAC VIP but \
+             not on SUPER-CHIP; set I again first",
+            "0x20A: reads 0xFFE-0x1001, past the end of RAM",
+            "0x20C: jumps to 0x20F, outside the ROM",
+            "0x20C: jumps to the odd address 0x20F",
+        ]
+    );
+}
+
+#[test]
+fn test_stack_depth() {
+    // 0x200 calls a chain of 17 subroutines, each calling the next
+    let mut program = vec![0x22, 0x02];
+    for sub in 1..=17u16 {
+        let next = 0x200 + (sub + 1) * 2;
+        if sub < 17 {
+            program.extend([0x20 | (next >> 8) as u8, next as u8]);
+        } else {
+            program.extend([0x00, 0xEE]);
+        }
+    }
+    let found = messages(&program);
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].starts_with("0x200: subroutine calls nest 17 deep"));
+
+    // A subroutine calling itself
+    assert_eq!(
+        messages(&[0x22, 0x04, 0x12, 0x02, 0x22, 0x04, 0x00, 0xEE]),
+        ["0x204: calls 0x204 recursively, the stack overflows unless a condition ends it"]
+    );
+    assert_eq!(
+        messages(&[0x22, 0x04, 0x12, 0x02, 0x00, 0xE0]),
+        ["0x204: execution can run off the end of the ROM at 0x206"]
+    );
+}
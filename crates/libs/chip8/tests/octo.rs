@@ -0,0 +1,41 @@
+use chip8::core::octo::decompile;
+
+#[test]
+fn test_decompile() {
+    // 0x200: CALL 0x208, LD I 0x20C, JP 0x204, two unreachable bytes,
+    // 0x208: SE V0 0, RET, then the sprite at 0x20C drawn by nothing
+    let program = [
+        0x22, 0x08, 0xA2, 0x0C, 0x12, 0x04, 0xAB, 0xCD, 0x30, 0x00, 0x00, 0xEE, 0xF0, 0x90,
+    ];
+    assert_eq!(
+        decompile(&program),
+        "# Decompiled from a 14 byte ROM, code is what is reachable from main\n\
+         \n\
+         : main\n\
+         \tsub_208\n\
+         \ti := data_20C\n\
+         \n\
+         : label_204\n\
+         \tjump label_204\n\
+         \t0xAB 0xCD\n\
+         \n\
+         : sub_208\n\
+         \tif v0 != 0x00 then\n\
+         \treturn\n\
+         \n\
+         : data_20C\n\
+         \t0b11110000\n\
+         \t0b10010000\n"
+    );
+}
+
+#[test]
+fn test_jump_into_instruction() {
+    // 0x200: LD V1 0x12, JP 0x201, where 0x201 reads as JP 0x212, then RET at 0x212
+    let mut program = [0; 0x14];
+    program[..4].copy_from_slice(&[0x61, 0x12, 0x12, 0x01]);
+    program[0x12..].copy_from_slice(&[0x00, 0xEE]);
+    let source = decompile(&program);
+    assert!(source.contains(":const label_201 0x201\n"));
+    assert!(source.contains("\tv1 := 0x12\n\tjump label_201\n"));
+}
@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::plugin::{MachineView, Panel, Plugin};
+
+// ADD V0 1, JP 0x200
+const PROGRAM: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+/// Counts the instructions and shows V0, failing on `fail_at`.
+struct Counter {
+    instructions: bool,
+    count: u64,
+    fail_at: Option<u64>,
+    panel: Panel,
+}
+
+impl Counter {
+    fn new(instructions: bool, fail_at: Option<u64>) -> Self {
+        Self {
+            instructions,
+            count: 0,
+            fail_at,
+            panel: Panel::default(),
+        }
+    }
+}
+
+impl Plugin for Counter {
+    fn name(&self) -> &str {
+        "counter"
+    }
+
+    fn wants_instructions(&self) -> bool {
+        self.instructions
+    }
+
+    fn on_instruction(
+        &mut self,
+        _machine: &MachineView,
+        pc: u16,
+        _opcode: u16,
+    ) -> Result<(), Error> {
+        assert!(pc == 0x200 || pc == 0x202);
+        self.count += 1;
+        Ok(())
+    }
+
+    fn on_frame(&mut self, machine: &MachineView) -> Result<(), Error> {
+        if Some(machine.frame) == self.fail_at {
+            return Err(anyhow!("failed"));
+        }
+        self.panel = Panel {
+            title: "V0".to_string(),
+            lines: vec![format!("{} after {}", machine.v[0], self.count)],
+        };
+        Ok(())
+    }
+
+    fn panel(&self) -> Option<&Panel> {
+        Some(&self.panel)
+    }
+}
+
+fn emulator() -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(&PROGRAM).unwrap();
+    emulator
+}
+
+#[test]
+fn test_plugins_see_frames_and_instructions() {
+    let mut emulator = emulator();
+    emulator.add_plugin(Box::new(Counter::new(true, None)));
+    emulator.add_plugin(Box::new(Counter::new(false, None)));
+    CpuController.step_frame(&mut emulator, 10).unwrap();
+    let panels = emulator.take_panels().unwrap().to_vec();
+    assert_eq!(panels[0].lines, ["5 after 10"]);
+    assert_eq!(panels[1].lines, ["5 after 0"]);
+    assert!(emulator.take_panels().is_none());
+}
+
+#[test]
+fn test_failing_plugin_is_dropped() {
+    let mut emulator = emulator();
+    emulator.add_plugin(Box::new(Counter::new(false, Some(2))));
+    CpuController.step_frame(&mut emulator, 2).unwrap();
+    assert_eq!(emulator.take_panels().unwrap().len(), 1);
+    CpuController.step_frame(&mut emulator, 2).unwrap();
+    assert!(emulator.take_panels().unwrap().is_empty());
+}
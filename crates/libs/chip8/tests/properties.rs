@@ -0,0 +1,167 @@
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::instruction::Instruction;
+use chip8::core::replay::Quirks;
+use chip8::core::testing::EmulatorTest;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Both settings of the quirks that change the arithmetic and memory instructions.
+fn quirks() -> impl Strategy<Value = Quirks> {
+    (any::<bool>(), any::<bool>()).prop_map(|(use_vy, change_i)| Quirks {
+        bit_shift_instructions_use_vy: use_vy,
+        store_read_instructions_change_i: change_i,
+        ..Quirks::default()
+    })
+}
+
+/// Two different registers below VF.
+fn registers() -> impl Strategy<Value = (u8, u8)> {
+    (0u8..0xF, 0u8..0xF).prop_filter("distinct registers", |(x, y)| x != y)
+}
+
+/// Runs `8XYn` with VX = `a` and VY = `b`.
+fn run_8xyn(quirks: Quirks, x: u8, y: u8, n: u8, a: u8, b: u8) -> EmulatorTest {
+    EmulatorTest::new()
+        .quirks(quirks)
+        .load(&[0x80 | x, y << 4 | n])
+        .set_v(x, a)
+        .set_v(y, b)
+        .run(1)
+}
+
+proptest! {
+    /// The fuzz targets in `fuzz/` in short: random instructions on random machine states and
+    /// random programs fail with errors, never with panics.
+    #[test]
+    fn test_random_instructions_never_panic(
+        quirks in quirks(),
+        word: u16,
+        registers: [u8; 16],
+        i: u16,
+        pc: u16,
+        calls in 0usize..20,
+    ) {
+        let mut emulator = Emulator::new(CHIP8::default());
+        emulator.set_quirks(quirks);
+        for (x, &value) in registers.iter().enumerate() {
+            emulator.set_v(x as u8, value).unwrap();
+        }
+        emulator.set_i(i);
+        emulator.set_pc(pc);
+        for _ in 0..calls {
+            let _ = emulator.stack_push(pc);
+        }
+        if let Some(instruction) = Instruction::decode(word) {
+            let _ = instruction.call(&mut emulator);
+        }
+    }
+
+    #[test]
+    fn test_random_programs_never_panic(quirks in quirks(), rom in vec(any::<u8>(), 0..256), keys: u16) {
+        let mut emulator = Emulator::new(CHIP8::default());
+        emulator.init_ram_from_bytes(&rom).unwrap();
+        emulator.set_quirks(quirks);
+        for key in (0..16).filter(|key| keys & 1 << key != 0) {
+            emulator.key_press(key).unwrap();
+        }
+        for _ in 0..4 {
+            if CpuController.step_frame(&mut emulator, 32).is_err() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_8xy4_carry(quirks in quirks(), (x, y) in registers(), a: u8, b: u8) {
+        let sum = a as u16 + b as u16;
+        run_8xyn(quirks, x, y, 0x4, a, b)
+            .assert_v(x, sum as u8)
+            .assert_v(0xF, (sum > 0xFF) as u8)
+            .assert_v(y, b);
+    }
+
+    #[test]
+    fn test_8xy5_borrow(quirks in quirks(), (x, y) in registers(), a: u8, b: u8) {
+        run_8xyn(quirks, x, y, 0x5, a, b)
+            .assert_v(x, a.wrapping_sub(b))
+            .assert_v(0xF, (a >= b) as u8);
+    }
+
+    #[test]
+    fn test_8xy7_borrow(quirks in quirks(), (x, y) in registers(), a: u8, b: u8) {
+        run_8xyn(quirks, x, y, 0x7, a, b)
+            .assert_v(x, b.wrapping_sub(a))
+            .assert_v(0xF, (b >= a) as u8);
+    }
+
+    #[test]
+    fn test_8xy6_shift_right(quirks in quirks(), (x, y) in registers(), a: u8, b: u8) {
+        let source = if quirks.bit_shift_instructions_use_vy { b } else { a };
+        run_8xyn(quirks, x, y, 0x6, a, b)
+            .assert_v(x, source >> 1)
+            .assert_v(0xF, source & 1)
+            .assert_v(y, b);
+    }
+
+    #[test]
+    fn test_8xye_shift_left(quirks in quirks(), (x, y) in registers(), a: u8, b: u8) {
+        let source = if quirks.bit_shift_instructions_use_vy { b } else { a };
+        run_8xyn(quirks, x, y, 0xE, a, b)
+            .assert_v(x, source << 1)
+            .assert_v(0xF, source >> 7)
+            .assert_v(y, b);
+    }
+
+    /// With VF as the destination the flag overwrites the result.
+    #[test]
+    fn test_flag_wins_over_vf_result(quirks in quirks(), y in 0u8..0xF, a: u8, b: u8) {
+        let expected = [
+            (0x4, (a as u16 + b as u16 > 0xFF) as u8),
+            (0x5, (a >= b) as u8),
+            (0x7, (b >= a) as u8),
+        ];
+        for (n, flag) in expected {
+            run_8xyn(quirks, 0xF, y, n, a, b).assert_v(0xF, flag);
+        }
+    }
+
+    #[test]
+    fn test_fx33_digits_recombine(quirks in quirks(), x in 0u8..=0xF, value: u8) {
+        let test = EmulatorTest::new()
+            .quirks(quirks)
+            .load(&[0xF0 | x, 0x33])
+            .set_v(x, value)
+            .set_i(0x300)
+            .run(1)
+            .assert_i(0x300);
+        let digits = &test.emulator().get_ram()[0x300..0x303];
+        prop_assert!(digits.iter().all(|&digit| digit < 10));
+        let recombined = digits[0] as u16 * 100 + digits[1] as u16 * 10 + digits[2] as u16;
+        prop_assert_eq!(recombined, value as u16);
+    }
+
+    #[test]
+    fn test_fx55_fx65_round_trip(quirks in quirks(), x in 0u8..=0xF, values: [u8; 16]) {
+        let mut test = EmulatorTest::new()
+            .quirks(quirks)
+            .load(&[0xF0 | x, 0x55, 0xA3, 0x00, 0xF0 | x, 0x65])
+            .set_i(0x300);
+        for (register, &value) in values.iter().enumerate() {
+            test = test.set_v(register as u8, value);
+        }
+        let moved = if quirks.store_read_instructions_change_i { x as u16 + 1 } else { 0 };
+        test = test
+            .run(1)
+            .assert_ram(0x300, &values[..=x as usize])
+            .assert_i(0x300 + moved);
+        for register in 0..16 {
+            test = test.set_v(register, 0);
+        }
+        test = test.run(2).assert_i(0x300 + moved);
+        for register in 0..=x {
+            test = test.assert_v(register, values[register as usize]);
+        }
+    }
+}
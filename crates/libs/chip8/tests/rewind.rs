@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::controller::{self, InputEvent};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::state::SaveState;
+use chip8::core::testing::{CapturingAudio, NullDisplay, ScriptedInput};
+
+const BRIX: &[u8] = include_bytes!("../../../../roms/BRIX");
+
+fn brix(rewind_seconds: u32) -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(BRIX).unwrap();
+    emulator.set_seed(0);
+    emulator.set_rewind(rewind_seconds);
+    emulator
+}
+
+#[test]
+fn test_rewinding_steps_back_through_the_frames() {
+    let mut emulator = brix(10);
+    let mut input = ScriptedInput::new()
+        .tap(100, 0x4, 60)
+        .at(300, InputEvent::Rewind(true))
+        .at(400, InputEvent::Rewind(false));
+    let (mut display, mut audio) = (NullDisplay::default(), CapturingAudio::new());
+    let mut run_frame = |emulator: &mut Emulator| {
+        controller::run_frame(
+            emulator,
+            &CpuController,
+            15,
+            &mut display,
+            &mut input,
+            &mut audio,
+        )
+        .unwrap()
+    };
+    let mut hashes = HashMap::new();
+    for _ in 0..300 {
+        run_frame(&mut emulator);
+        hashes.insert(emulator.frame(), emulator.state_hash());
+    }
+
+    // Every held frame steps back one, running no instructions
+    let instructions = emulator.metrics().instructions;
+    for _ in 0..100 {
+        run_frame(&mut emulator);
+    }
+    assert_eq!(emulator.frame(), 200);
+    assert_eq!(emulator.metrics().instructions, instructions);
+    assert_eq!(emulator.state_hash(), hashes[&200]);
+
+    // Released, the run goes on as it did, random draws included
+    for _ in 0..100 {
+        run_frame(&mut emulator);
+    }
+    assert_eq!(emulator.frame(), 300);
+    assert_eq!(emulator.state_hash(), hashes[&300]);
+}
+
+#[test]
+fn test_rewind_buffer_keeps_deltas_within_its_length() {
+    let mut emulator = brix(5);
+    for _ in 0..600 {
+        emulator.record_rewind();
+        CpuController.step_frame(&mut emulator, 15).unwrap();
+    }
+    let rewind = emulator.rewind_buffer().unwrap();
+    // The oldest second goes once five are stored
+    assert!(
+        (300..=360).contains(&rewind.len()),
+        "{} frames",
+        rewind.len()
+    );
+    // Keyframes every second and small deltas in between, far below full states
+    let full = rewind.len() * SaveState::len_for(BRIX.len());
+    assert!(rewind.byte_len() * 10 < full, "{} bytes", rewind.byte_len());
+
+    let frames = rewind.len();
+    for _ in 0..frames {
+        assert!(emulator.rewind_frame().unwrap());
+    }
+    assert!(!emulator.rewind_frame().unwrap());
+    assert_eq!(emulator.frame(), 600 - frames as u64);
+}
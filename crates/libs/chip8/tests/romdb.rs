@@ -0,0 +1,76 @@
+use chip8::core::romdb::RomDatabase;
+use serde_yaml::Value;
+
+const BRIX: &str = "f13766c14aeb02ad8d4d103cb5eadd282d20cddc";
+
+#[test]
+fn test_builtin() {
+    let database = RomDatabase::builtin();
+    let brix = database.find(&BRIX.to_uppercase()).unwrap();
+    assert_eq!(brix.title, "Brix");
+    assert_eq!(brix.platform, "default");
+    assert!(database.find("0000").is_none());
+}
+
+#[test]
+fn test_update() {
+    let mut database = RomDatabase::builtin();
+    let count = database.entries().len();
+    let updates = RomDatabase::parse(&format!(
+        "- sha1: {}\n  title: Brix (fast)\n  platform: vip\n  cycles_per_frame: 20\n\
+         - sha1: 1234\n  title: New game",
+        BRIX.to_uppercase()
+    ))
+    .unwrap();
+    database.update(updates);
+    assert_eq!(database.entries().len(), count + 1);
+    let brix = database.find(BRIX).unwrap();
+    assert_eq!(brix.title, "Brix (fast)");
+
+    let profile: Value = serde_yaml::from_str(
+        "bit_shift_instructions_use_vy: true\nstore_read_instructions_change_i: true\n\
+         cycles_per_frame: 20\nst_equals_buzzer: false",
+    )
+    .unwrap();
+    let overrides: Value = serde_yaml::from_str("st_equals_buzzer: false").unwrap();
+    assert_eq!(brix.profile_with(Some(overrides)).unwrap(), profile);
+
+    assert!(RomDatabase::parse("- sha1: 12\n  title: Odd\n  platform: xochip").is_err());
+}
+
+#[test]
+fn test_archive() {
+    let folder = std::env::temp_dir().join(format!("chip8-archive-{}", std::process::id()));
+    std::fs::create_dir_all(folder.join("roms")).unwrap();
+    std::fs::write(
+        folder.join("programs.json"),
+        r##"{
+            "jam": {
+                "title": "Jam Game",
+                "authors": ["Ann", "Bob"],
+                "platform": "chip8",
+                "images": ["jam.gif"],
+                "options": {"tickrate": 15, "shiftQuirks": true, "fillColor": "#FFCC00"}
+            },
+            "missing": {"title": "Missing ROM", "platform": "xochip"}
+        }"##,
+    )
+    .unwrap();
+    std::fs::write(folder.join("roms/jam.ch8"), [0x12, 0x00]).unwrap();
+    let database = RomDatabase::from_archive(&folder).unwrap();
+    std::fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(database.entries().len(), 1);
+    let jam = database
+        .find("92a5652d382a18e89c4881ec57041fc7d885ca80")
+        .unwrap();
+    assert_eq!(jam.title, "Jam Game");
+    assert_eq!(jam.author.as_deref(), Some("Ann, Bob"));
+    assert_eq!(jam.platform, "vip");
+    let profile: Value = serde_yaml::from_str(
+        "st_equals_buzzer: true\nbit_shift_instructions_use_vy: false\n\
+         store_read_instructions_change_i: true\ncycles_per_frame: 15",
+    )
+    .unwrap();
+    assert_eq!(jam.profile().unwrap(), profile);
+}
@@ -0,0 +1,57 @@
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::rhai::RhaiHost;
+use chip8::core::script::ScriptHost;
+
+// JP 0x200
+const PROGRAM: [u8; 2] = [0x12, 0x00];
+
+fn run(source: &str, frames: u64) -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(&PROGRAM).unwrap();
+    let host: Box<dyn ScriptHost> = Box::new(RhaiHost::new(source).unwrap());
+    emulator.add_service(Box::new(host));
+    for _ in 0..frames {
+        emulator.apply_services();
+        CpuController.step_frame(&mut emulator, 10).unwrap();
+    }
+    emulator
+}
+
+#[test]
+fn test_rhai_script_drives_the_machine() {
+    let emulator = run(
+        r#"
+        fn on_frame() {
+            if this.count == () { this.count = 0; }
+            this.count += 1;
+            poke(0x300, this.count);
+            set_reg(3, peek(0x300) * 2);
+            if frame() == 2 { press(5); }
+            if frame() == 4 { release(5); press(0xA); }
+        }
+        "#,
+        6,
+    );
+    assert_eq!(emulator.get_from_ram(0x300).unwrap(), 6);
+    assert_eq!(emulator.get_v(3).unwrap(), 12);
+    assert!(!emulator.is_key_pressed(5).unwrap());
+    assert!(emulator.is_key_pressed(0xA).unwrap());
+}
+
+#[test]
+fn test_failing_script_is_stopped() {
+    let emulator = run(
+        r#"
+        fn on_frame() {
+            poke(0x300, frame());
+            if frame() == 2 { poke(0x1000, 1); }
+        }
+        "#,
+        5,
+    );
+    // The write out of the RAM stopped the script before its third frame was applied
+    assert_eq!(emulator.get_from_ram(0x300).unwrap(), 1);
+    assert!(RhaiHost::new("fn on_frame( {").is_err());
+}
@@ -0,0 +1,56 @@
+use chip8::core::search::{Filter, MemorySearch, SearchCommand};
+use chip8::core::testing::EmulatorTest;
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        SearchCommand::parse("= 0x10").unwrap(),
+        SearchCommand::Filter(Filter::Equals(16))
+    );
+    assert_eq!(
+        SearchCommand::parse("freeze 0x3F2 3").unwrap(),
+        SearchCommand::Freeze {
+            address: 0x3F2,
+            value: 3
+        }
+    );
+    assert!(SearchCommand::parse("= 300").is_err());
+    assert!(SearchCommand::parse("changed 1").is_err());
+    assert!(SearchCommand::parse("jump").is_err());
+}
+
+#[test]
+fn test_refine() {
+    let mut search = MemorySearch::default();
+    let mut ram = [5u8, 5, 5, 9];
+    assert!(search.refine(&ram, Filter::Decreased).is_err());
+    search.refine(&ram, Filter::Equals(5)).unwrap();
+    assert_eq!(search.candidates(), [0, 1, 2]);
+    ram[0] = 4;
+    ram[1] = 6;
+    search.refine(&ram, Filter::Changed).unwrap();
+    assert_eq!(search.candidates(), [0, 1]);
+    ram[0] = 3;
+    search.refine(&ram, Filter::Decreased).unwrap();
+    assert_eq!(search.candidates(), [0]);
+    search.start(&ram);
+    search.refine(&ram, Filter::Unchanged).unwrap();
+    assert_eq!(search.candidates().len(), 4);
+}
+
+#[test]
+fn test_find_and_freeze_lives() {
+    // Stores 3 lives at 0x300, then takes one off each time through the loop at 0x206
+    let program = [
+        0x60, 0x03, 0xA3, 0x00, 0xF0, 0x55, 0x70, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06,
+    ];
+    let mut test = EmulatorTest::new().load(&program).run(3);
+    let emulator = test.emulator_mut();
+    emulator.search_command("= 3").unwrap();
+    let mut test = test.run(4);
+    let emulator = test.emulator_mut();
+    let reply = emulator.search_command("decreased").unwrap();
+    assert!(reply.starts_with("1 candidates\n0x300 = 2"), "{}", reply);
+    emulator.search_command("freeze 0x300 9").unwrap();
+    assert_eq!(test.run(8).emulator().get_from_ram(0x300).unwrap(), 9);
+}
@@ -0,0 +1,63 @@
+use chip8::core::controller::Hotkey;
+use chip8::core::testing::{CapturingAudio, EmulatorTest, NullDisplay, ScriptedInput};
+
+// Waits for a key, draws its digit and beeps for as many frames as the key's value
+const PROGRAM: &[u8] = &[0xF0, 0x0A, 0xF0, 0x29, 0xD1, 0x15, 0xF0, 0x18, 0x12, 0x08];
+
+#[test]
+fn test_session_waits_for_a_key() {
+    let (mut display, mut audio) = (NullDisplay::default(), CapturingAudio::new());
+    let mut input = ScriptedInput::new().tap(5, 0x7, 3);
+    let test =
+        EmulatorTest::new()
+            .load(PROGRAM)
+            .run_session(5, 10, &mut display, &mut input, &mut audio);
+    // Still waiting on FX0A
+    assert_eq!(display.presents, 5);
+    assert!(display.screen.iter().all(|&pixel| pixel == 0));
+    test.assert_pc(0x200)
+        .run_session(20, 10, &mut display, &mut input, &mut audio)
+        .assert_v(0, 0x7)
+        .assert_lit(8);
+    assert!(input.is_finished());
+    assert_eq!(audio.frames.len(), 25);
+    // ST is set to 7 mid-frame and counted down once before that frame ends
+    assert_eq!(audio.beep_frames(), 6);
+    assert!(audio.frames[..5].iter().all(|&on| !on));
+}
+
+#[test]
+fn test_session_quits_and_forwards_hotkeys() {
+    let (mut display, mut audio) = (NullDisplay::default(), CapturingAudio::new());
+    let mut input = ScriptedInput::new()
+        .hotkey(2, Hotkey::CyclePalette)
+        .hotkey(3, Hotkey::ToggleMute)
+        .quit(10);
+    EmulatorTest::new()
+        .load(PROGRAM)
+        .run_session(60, 10, &mut display, &mut input, &mut audio);
+    assert_eq!(input.polls(), 11);
+    assert_eq!(display.presents, 10);
+    assert_eq!(display.hotkeys, [Hotkey::CyclePalette]);
+    assert!(audio.muted);
+    assert_eq!(display.status.unwrap().rom, None);
+}
+
+#[test]
+fn test_threaded_session_matches_the_emulator() {
+    let (mut display, mut audio) = (NullDisplay::default(), CapturingAudio::new());
+    let mut input = ScriptedInput::new()
+        .hotkey(2, Hotkey::CyclePalette)
+        .tap(5, 0x7, 10)
+        .quit(40);
+    let test = EmulatorTest::new()
+        .load(PROGRAM)
+        .run_threaded(10, &mut display, &mut input, &mut audio)
+        .assert_v(0, 0x7)
+        .assert_lit(8);
+    assert!(input.is_finished());
+    assert_eq!(display.hotkeys, [Hotkey::CyclePalette]);
+    assert_eq!(&display.screen, test.emulator().get_display());
+    // Every emulated frame reaches the audio, even those merged into one snapshot
+    assert_eq!(audio.beep_frames(), 6);
+}
@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+use chip8::core::golden::{capture, run_headless};
+use chip8::core::replay::Quirks;
+use chip8::core::testing::EmulatorTest;
+
+fn screen(test: &EmulatorTest) -> String {
+    capture(test.emulator().get_display())
+}
+
+fn rom_screen(name: &str, frames: u64) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../roms")
+        .join(name);
+    let rom = fs::read(&path).unwrap();
+    let emulator = run_headless(&rom, frames, 10, Quirks::default()).unwrap();
+    capture(emulator.get_display())
+}
+
+#[test]
+fn test_font_digits() {
+    // Draws the glyphs 0 to 7 side by side along the top row
+    let mut program = vec![0x60, 0x00, 0x61, 0x00, 0x62, 0x00];
+    for _ in 0..8 {
+        program.extend_from_slice(&[0xF2, 0x29, 0xD0, 0x15, 0x70, 0x05, 0x72, 0x01]);
+    }
+    let test = EmulatorTest::new().load(&program).run(3 + 8 * 4);
+    insta::assert_snapshot!(screen(&test));
+}
+
+#[test]
+fn test_sprite_wraps_around_the_edges() {
+    // The glyph 8 drawn at (60, 29) spills over both the right and the bottom edge
+    let test = EmulatorTest::new()
+        .load(&[0x60, 0x3C, 0x61, 0x1D, 0x62, 0x08, 0xF2, 0x29, 0xD0, 0x15])
+        .run(5);
+    insta::assert_snapshot!(screen(&test));
+}
+
+#[test]
+fn test_sprite_xor_erases_the_overlap() {
+    // The glyphs 0 and 1 drawn on top of each other, two pixels apart
+    let test = EmulatorTest::new()
+        .load(&[
+            0x60, 0x00, 0x61, 0x00, 0xF0, 0x29, 0xD0, 0x15, 0x62, 0x01, 0xF2, 0x29, 0x70, 0x02,
+            0xD0, 0x15,
+        ])
+        .run(8)
+        .assert_v(0xF, 1);
+    insta::assert_snapshot!(screen(&test));
+}
+
+#[test]
+fn test_clear_screen_after_drawing() {
+    let test = EmulatorTest::new()
+        .load(&[0xA0, 0x00, 0xD0, 0x05, 0x00, 0xE0])
+        .run(3);
+    insta::assert_snapshot!(screen(&test));
+}
+
+#[test]
+fn test_both_planes() {
+    // The glyph 0 on plane 1, then 1 on plane 2 drawn over it
+    let test = EmulatorTest::new()
+        .load(&[
+            0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0xF2, 0x01, 0x60, 0x01, 0xF0, 0x29, 0x60, 0x02,
+            0xD0, 0x05,
+        ])
+        .run(8);
+    insta::assert_snapshot!(screen(&test));
+}
+
+#[test]
+fn test_maze() {
+    insta::assert_snapshot!(rom_screen("MAZE", 60));
+}
+
+#[test]
+fn test_kaleid() {
+    insta::assert_snapshot!(rom_screen("KALEID", 120));
+}
+
+#[test]
+fn test_display_to_string() {
+    let test = EmulatorTest::new().load(&[0xA0, 0x00, 0xD0, 0x05]).run(2);
+    let text = test.emulator().display_to_string();
+    let rows: Vec<&str> = text.lines().collect();
+    assert_eq!(rows.len(), 32);
+    assert_eq!(rows[0], format!("████{}", ".".repeat(60)));
+    assert_eq!(rows[1], format!("█..█{}", ".".repeat(60)));
+    assert_eq!(text, screen(&test).replace('#', "█"));
+}
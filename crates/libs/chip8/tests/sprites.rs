@@ -0,0 +1,23 @@
+use chip8::core::sprites::find_sprites;
+
+#[test]
+fn test_find_sprites() {
+    // LD I 0x208, DRW V0 V1 3, JP 0x204, then a 3 row sprite, zero padding and 2 stray bytes
+    let program = [
+        0xA2, 0x08, 0xD0, 0x13, 0x12, 0x04, 0x00, 0x00, 0x3C, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x18, 0x18,
+    ];
+    let sprites = find_sprites(&program, 4);
+    let found: Vec<_> = sprites
+        .iter()
+        .map(|sprite| (sprite.address, sprite.rows.clone(), sprite.referenced))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (0x208, vec![0x3C, 0x42, 0x3C], true),
+            (0x20F, vec![0x00, 0x18, 0x18], false)
+        ]
+    );
+    assert_eq!(sprites[0].to_art(), "..████..\n.█....█.\n..████..");
+}
@@ -0,0 +1,106 @@
+use chip8::core::chip8::CHIP8;
+use chip8::core::controller::{Hotkey, InputController, InputEvent};
+use chip8::core::emulator::Emulator;
+use chip8::core::input::EventQueue;
+use chip8::core::state::SaveState;
+use chip8::core::testing::EmulatorTest;
+use shared::config::config::KeyboardLayout;
+
+// Draws the glyph 0, then keeps drawing random bytes
+const PROGRAM: &[u8] = &[0xA0, 0x00, 0xD0, 0x05, 0xC1, 0xFF, 0x12, 0x04];
+
+#[test]
+fn test_save_state_continues_the_run() {
+    let mut test = EmulatorTest::new().load(PROGRAM).press(7).run(20);
+    let bytes = test.emulator().save_state().to_bytes();
+    assert_eq!(bytes.len(), SaveState::len_for(PROGRAM.len()));
+
+    // A fresh emulator picks up the machine, the ROM and the RNG where they were
+    let mut restored = Emulator::new(CHIP8::default());
+    restored.load_state(SaveState::from_bytes(&bytes).unwrap());
+    assert_eq!(restored.state_hash(), test.emulator().state_hash());
+    assert_eq!(restored.get_rom(), PROGRAM);
+    assert!(restored.is_key_pressed(7).unwrap());
+
+    test = test.run(20);
+    let mut resumed = EmulatorTest::new();
+    resumed
+        .emulator_mut()
+        .load_state(SaveState::from_bytes(&bytes).unwrap());
+    assert_eq!(
+        resumed.run(20).emulator().state_hash(),
+        test.emulator().state_hash()
+    );
+}
+
+#[test]
+fn test_corrupt_save_states_are_rejected() {
+    let bytes = EmulatorTest::new()
+        .load(PROGRAM)
+        .emulator()
+        .save_state()
+        .to_bytes();
+    assert!(SaveState::from_bytes(PROGRAM).is_err());
+    assert!(SaveState::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut future = bytes.clone();
+    future[3] = 2;
+    assert!(SaveState::from_bytes(&future)
+        .err()
+        .unwrap()
+        .to_string()
+        .contains("version 2"));
+}
+
+#[test]
+fn test_shift_and_a_digit_picks_a_save_slot() {
+    let mut queue = EventQueue::default();
+    queue.push_key_with_shift('2', true, true, false);
+    queue.push_key_with_shift('2', true, true, true);
+    // Shift goes up first, the digit still isn't released on the keypad
+    queue.push_key_with_shift('2', false, false, false);
+    queue.push_key('2', true, false);
+    assert_eq!(
+        queue.poll().unwrap(),
+        vec![
+            InputEvent::Hotkey(Hotkey::SelectSlot(2)),
+            InputEvent::KeyDown(0x2)
+        ]
+    );
+
+    // The digits are found by position, on AZERTY shift types them
+    queue.set_keyboard_layout(KeyboardLayout::Azerty);
+    queue.push_key_with_shift('\u{e0}', true, true, false);
+    queue.push_key_with_shift('\u{e0}', true, false, false);
+    assert_eq!(
+        queue.poll().unwrap(),
+        vec![InputEvent::Hotkey(Hotkey::SelectSlot(0))]
+    );
+}
+
+#[test]
+fn test_save_slots_are_kept_per_rom_and_slot() {
+    let folder = std::env::temp_dir().join(format!("chip8-slots-{}", std::process::id()));
+    let mut test = EmulatorTest::new().load(PROGRAM).run(20);
+    let emulator = test.emulator_mut();
+    emulator.set_save_slot_folder(Some(folder.clone()));
+    emulator.select_save_slot(2);
+    emulator.quick_save();
+    let saved = emulator.state_hash();
+    assert!(emulator.save_slot_path(2).unwrap().exists());
+    assert!(!emulator.load_from_slot(3).unwrap());
+
+    // Loading the slot goes back to the frame it was saved on
+    test = test.run(20);
+    let emulator = test.emulator_mut();
+    assert_ne!(emulator.state_hash(), saved);
+    emulator.quick_load();
+    assert_eq!(emulator.state_hash(), saved);
+
+    // Another ROM has slots of its own
+    let mut other = EmulatorTest::new().load(&[0x12, 0x00]);
+    other
+        .emulator_mut()
+        .set_save_slot_folder(Some(folder.clone()));
+    assert!(!other.emulator_mut().load_from_slot(2).unwrap());
+    std::fs::remove_dir_all(&folder).unwrap();
+}
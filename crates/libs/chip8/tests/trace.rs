@@ -0,0 +1,42 @@
+use chip8::core::replay::Quirks;
+use chip8::core::trace::{conform, record, Trace, TraceStep};
+
+// Counts V0 up and draws its digit, forever
+const PROGRAM: &[u8] = &[0x70, 0x01, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x00];
+
+#[test]
+fn test_recorded_trace_conforms() {
+    let trace = record(PROGRAM, 100, 10, 0, Quirks::default()).unwrap();
+    assert_eq!(trace.steps.len(), 100);
+    let reloaded = Trace::parse(&trace.to_string()).unwrap();
+    assert_eq!(reloaded, trace);
+    assert_eq!(conform(PROGRAM, &reloaded).unwrap(), None);
+}
+
+#[test]
+fn test_first_divergence_is_reported() {
+    let mut trace = record(PROGRAM, 100, 10, 0, Quirks::default()).unwrap();
+    trace.steps[41].i = Some(0x123);
+    trace.steps[60].pc = 0x300;
+    let divergence = conform(PROGRAM, &trace).unwrap().unwrap();
+    assert_eq!(divergence.step, 41);
+    assert_eq!(divergence.frame, 4);
+    assert_eq!(divergence.context.len(), 8);
+    assert_eq!(divergence.expected.diff(&divergence.actual).len(), 1);
+}
+
+#[test]
+fn test_missing_fields_are_not_compared() {
+    let text = "# cycles_per_frame: 4\n0200 7001 v=01000000000000000000000000000000\n0202 F029\n";
+    let trace = Trace::parse(text).unwrap();
+    assert_eq!(trace.cycles_per_frame, 4);
+    assert_eq!(
+        trace.steps[1],
+        TraceStep {
+            pc: 0x202,
+            opcode: 0xF029,
+            ..TraceStep::default()
+        }
+    );
+    assert_eq!(conform(PROGRAM, &trace).unwrap(), None);
+}
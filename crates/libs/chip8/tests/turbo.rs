@@ -0,0 +1,27 @@
+use chip8::core::chip8::CHIP8;
+use chip8::core::emulator::Emulator;
+
+/// Frames of the first emulated second on which the autofire key goes down.
+fn presses(rate: u32) -> usize {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.turbo_press(5, rate).unwrap();
+    let mut was_pressed = false;
+    let mut presses = 0;
+    for _ in 0..60 {
+        emulator.apply_turbo();
+        let pressed = emulator.is_key_pressed(5).unwrap();
+        presses += (pressed && !was_pressed) as usize;
+        was_pressed = pressed;
+        emulator.advance_frame();
+    }
+    presses
+}
+
+#[test]
+fn test_turbo_presses_rate_times_per_second() {
+    assert_eq!(presses(1), 1);
+    assert_eq!(presses(10), 10);
+    assert_eq!(presses(30), 30);
+    // A frame down and a frame up is as fast as it goes
+    assert_eq!(presses(60), 30);
+}
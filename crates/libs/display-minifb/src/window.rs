@@ -0,0 +1,292 @@
+use anyhow::{anyhow, Error};
+use chip8::core::audio::AudioSink;
+use chip8::core::blend::{self, FrameBlend};
+use chip8::core::chip8::{REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{self, DisplayController, Hotkey, InputEvent, Status};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::input::EventQueue;
+use chip8::core::pacer::FramePacer;
+use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
+use shared::config::config::{ChipSettings, Color, Grid, Palette, Rotation, Scaling};
+use tracing::info;
+
+/// Color of the visual bell border.
+const BORDER_COLOR: Color = Color::rgb(0xFF, 0x30, 0x30);
+
+/// Converts a key to the character printed on it, e.g. `Key::Q` -> 'Q', `Key::Key1` -> '1'.
+pub fn key_char(key: Key) -> Option<char> {
+    let name = format!("{:?}", key);
+    let name = name.strip_prefix("Key").unwrap_or(&name);
+    let mut chars = name.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// minifb display controller: the 64x32 screen is kept rotated as `0RGB` colors and scaled
+/// into a buffer the size of the window, so the scaling mode is honored on every resize. minifb
+/// also pumps the window events inside `update_with_buffer`.
+pub struct MinifbDisplay {
+    window: Window,
+    title: String,
+    screen: Vec<u32>,
+    screen_size: (usize, usize),
+    rotation: Rotation,
+    buffer: Vec<u32>,
+    buffer_size: (usize, usize),
+    palette: Palette,
+    scaling: Scaling,
+    grid: Grid,
+    letterbox: u32,
+    blend: Option<FrameBlend>,
+    visual_bell: bool,
+    buzzing: bool,
+    needs_repaint: bool,
+}
+
+impl MinifbDisplay {
+    pub fn new(settings: &ChipSettings) -> Result<Self, Error> {
+        let scale = settings.scale as usize;
+        let screen_size = settings.rotation.size((SCREEN_WIDTH, SCREEN_HEIGHT));
+        let mut window = Window::new(
+            &settings.title,
+            screen_size.0 * scale,
+            screen_size.1 * scale,
+            WindowOptions {
+                resize: true,
+                scale_mode: ScaleMode::UpperLeft,
+                ..WindowOptions::default()
+            },
+        )
+        .map_err(|e| anyhow!("minifb: {}", e))?;
+        // Pacing is done by the frame pacer, don't let minifb sleep as well.
+        window.set_target_fps(0);
+
+        Ok(Self {
+            window,
+            title: settings.title.clone(),
+            screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            screen_size,
+            rotation: settings.rotation,
+            buffer: Vec::new(),
+            buffer_size: (0, 0),
+            palette: settings.palette.clone(),
+            scaling: settings.scaling,
+            grid: settings.grid.clone(),
+            letterbox: Self::pack(settings.letterbox_color),
+            blend: settings.motion_blur.then(FrameBlend::default),
+            visual_bell: settings.visual_bell,
+            buzzing: false,
+            needs_repaint: true,
+        })
+    }
+
+    /// Collects the key transitions seen by the last window update.
+    pub fn poll_input(&self, queue: &mut EventQueue) {
+        if !self.window.is_open() {
+            queue.push(InputEvent::Quit);
+            return;
+        }
+        let shift =
+            self.window.is_key_down(Key::LeftShift) || self.window.is_key_down(Key::RightShift);
+        for key in self.window.get_keys_pressed(KeyRepeat::No) {
+            match key {
+                Key::Escape => queue.push(InputEvent::Quit),
+                Key::F5 => queue.push(InputEvent::Hotkey(Hotkey::SaveToSlot)),
+                Key::F9 => queue.push(InputEvent::Hotkey(Hotkey::LoadFromSlot)),
+                _ => {
+                    if let Some(key) = key_char(key) {
+                        queue.push_key_with_shift(key, shift, true, false);
+                    }
+                }
+            }
+        }
+        for key in self.window.get_keys_released() {
+            if let Some(key) = key_char(key) {
+                queue.push_key_with_shift(key, shift, false, false);
+            }
+        }
+    }
+
+    /// Scales the screen into the window sized buffer with nearest neighbour sampling, the
+    /// optional grid replaces the first row and column of every scaled pixel but the first.
+    fn blit(&mut self) {
+        let (width, height) = self.buffer_size;
+        self.buffer.fill(self.letterbox);
+        let (screen_w, screen_h) = self.screen_size;
+        let viewport = self.scaling.viewport(
+            (screen_w as u32, screen_h as u32),
+            (width as u32, height as u32),
+        );
+        let (x0, y0) = (viewport.x as usize, viewport.y as usize);
+        let (view_w, view_h) = (viewport.width as usize, viewport.height as usize);
+        let min_scale = self.grid.min_scale.max(1) as usize;
+        let grid =
+            self.grid.enable && view_w / screen_w >= min_scale && view_h / screen_h >= min_scale;
+        let grid_color = Self::pack(self.grid.color);
+        let starts_cell = |d: usize, cells: usize, size: usize| {
+            d > 0 && d * cells / size != (d - 1) * cells / size
+        };
+        for dy in 0..view_h {
+            let row = &self.screen[dy * screen_h / view_h * screen_w..][..screen_w];
+            let line = &mut self.buffer[(y0 + dy) * width + x0..][..view_w];
+            if grid && starts_cell(dy, screen_h, view_h) {
+                line.fill(grid_color);
+                continue;
+            }
+            for (dx, pixel) in line.iter_mut().enumerate() {
+                *pixel = if grid && starts_cell(dx, screen_w, view_w) {
+                    grid_color
+                } else {
+                    row[dx * screen_w / view_w]
+                };
+            }
+        }
+        if self.visual_bell && self.buzzing {
+            self.draw_border(view_h / screen_h);
+        }
+    }
+
+    /// Outlines the whole buffer with a border `size` pixels thick.
+    fn draw_border(&mut self, size: usize) {
+        let (width, height) = self.buffer_size;
+        let size = size.max(2).min(width / 2).min(height / 2);
+        let border = Self::pack(BORDER_COLOR);
+        for (y, line) in self.buffer.chunks_exact_mut(width).enumerate() {
+            if y < size || y >= height - size {
+                line.fill(border);
+            } else {
+                line[..size].fill(border);
+                line[width - size..].fill(border);
+            }
+        }
+    }
+
+    fn pack(color: Color) -> u32 {
+        (color.r as u32) << 16 | (color.g as u32) << 8 | color.b as u32
+    }
+}
+
+impl DisplayController for MinifbDisplay {
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let dirty = blend::repaint_list(self.blend.as_mut(), display, dirty);
+        let colors = self.palette.colors();
+        let color = |index: usize| {
+            Self::pack(match &self.blend {
+                Some(blend) => blend.color(&colors, index),
+                None => colors[display[index] as usize],
+            })
+        };
+        let mut changed = self.needs_repaint || !dirty.is_empty();
+        if self.needs_repaint {
+            for index in 0..display.len() {
+                self.screen[self
+                    .rotation
+                    .rotate_index(index, (SCREEN_WIDTH, SCREEN_HEIGHT))] = color(index);
+            }
+            self.needs_repaint = false;
+        } else {
+            for &index in dirty.iter() {
+                self.screen[self
+                    .rotation
+                    .rotate_index(index, (SCREEN_WIDTH, SCREEN_HEIGHT))] = color(index);
+            }
+        }
+
+        let (width, height) = self.window.get_size();
+        let size = (width.max(1), height.max(1));
+        if size != self.buffer_size {
+            self.buffer_size = size;
+            self.buffer = vec![self.letterbox; size.0 * size.1];
+            changed = true;
+        }
+        if !changed {
+            // Still update, minifb only processes window events here
+            self.window.update();
+            return Ok(());
+        }
+        self.blit();
+        self.window
+            .update_with_buffer(&self.buffer, size.0, size.1)
+            .map_err(|e| anyhow!("Failed to update window: {}", e))
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        match hotkey {
+            Hotkey::CyclePalette => {
+                self.palette.cycle_preset();
+                info!("Switched palette to {:?}", self.palette.preset);
+                self.needs_repaint = true;
+            }
+            Hotkey::ToggleGrid => {
+                self.grid.enable = !self.grid.enable;
+                info!("Pixel grid enabled: {}", self.grid.enable);
+                self.needs_repaint = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        self.window.set_title(&status.caption(&self.title));
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        if self.visual_bell && on != self.buzzing {
+            self.buzzing = on;
+            self.needs_repaint = true;
+        }
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.palette = palette.clone();
+        self.needs_repaint = true;
+        Ok(())
+    }
+
+    fn request_repaint(&mut self) {
+        self.needs_repaint = true;
+    }
+}
+
+/// Opens a minifb window and drives the emulator until it is closed.
+pub fn run(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+) -> Result<(), Error> {
+    let mut display = MinifbDisplay::new(settings)?;
+    let mut input = EventQueue::default();
+    if settings.rotate_keys {
+        input.set_key_rotation(settings.rotation);
+    }
+    // minifb reports keys by the character they type
+    input.set_keyboard_layout(settings.keyboard_layout);
+    input.set_turbo(settings.turbo_for(emulator.get_rom_name()))?;
+    if let Some(player) = settings.active_player() {
+        input.set_player(player)?;
+    }
+    let mut pacer = FramePacer::new(REFRESH_RATE);
+    info!("minifb frontend initialized");
+
+    loop {
+        display.poll_input(&mut input);
+        let frames = pacer.advance();
+        let running = controller::run_frames(
+            emulator,
+            cpu,
+            settings.cycles_per_frame,
+            frames,
+            &mut display,
+            &mut input,
+            audio,
+        )?;
+        if !running {
+            return Ok(());
+        }
+        pacer.wait();
+    }
+}
@@ -0,0 +1,307 @@
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, Status};
+use chip8::core::emulator::HEX_DIGITS;
+use display_winit::title::WindowTitle;
+use shared::config::config::{ChipSettings, Color, Palette, Rotation, Scaling};
+use softbuffer::{Context, Surface};
+use tracing::{info, warn};
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+/// Share of the shorter window side left between the keypad cells.
+const KEY_GAP: u32 = 96;
+
+/// A rectangle of the window in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && y >= self.y as f64
+            && x < (self.x + self.width) as f64
+            && y < (self.y + self.height) as f64
+    }
+
+    /// Where a window position lies in the rectangle, as fractions of its width and height.
+    pub fn fraction(&self, x: f64, y: f64) -> (f32, f32) {
+        (
+            ((x - self.x as f64) / self.width.max(1) as f64) as f32,
+            ((y - self.y as f64) / self.height.max(1) as f64) as f32,
+        )
+    }
+}
+
+/// Where the screen and the touch keypad go: the keypad below the screen in portrait, to its
+/// right in landscape, each as large as fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub screen: Rect,
+    pub keypad: Rect,
+}
+
+impl Layout {
+    pub fn new(width: u32, height: u32, scaling: Scaling) -> Self {
+        let (area, keypad) = if height >= width {
+            let side = width.min(height * 3 / 5);
+            let keypad = Rect {
+                x: (width - side) / 2,
+                y: height - side,
+                width: side,
+                height: side,
+            };
+            let area = (0, 0, width, height - side);
+            (area, keypad)
+        } else {
+            let side = height.min(width * 2 / 5);
+            let keypad = Rect {
+                x: width - side,
+                y: (height - side) / 2,
+                width: side,
+                height: side,
+            };
+            let area = (0, 0, width - side, height);
+            (area, keypad)
+        };
+        let (x, y, area_width, area_height) = area;
+        let viewport = scaling.viewport(
+            (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32),
+            (area_width, area_height),
+        );
+        let screen = Rect {
+            x: x + viewport.x,
+            y: y + viewport.y,
+            width: viewport.width,
+            height: viewport.height,
+        };
+        Self { screen, keypad }
+    }
+}
+
+/// Software renderer for phones and tablets: the CHIP-8 screen and a touch keypad drawn into
+/// a `softbuffer` surface, with the held keys lit. The surface lives as long as the window,
+/// which mobile platforms take away while the app is suspended.
+pub struct MobileRenderer {
+    window: Arc<Window>,
+    surface: Surface<Arc<Window>, Arc<Window>>,
+    scene: Scene,
+    title: WindowTitle,
+    scaling: Scaling,
+    needs_repaint: bool,
+}
+
+/// Everything drawn into the window.
+struct Scene {
+    size: (u32, u32),
+    layout: Layout,
+    screen: Vec<u8>,
+    keys: Vec<Vec<Option<u8>>>,
+    held: [bool; 16],
+    palette: Palette,
+    letterbox: Color,
+}
+
+impl MobileRenderer {
+    /// Draws into `window` with the keypad grid `keys`, see `TouchPad::rows`.
+    pub fn new(
+        window: Arc<Window>,
+        settings: &ChipSettings,
+        keys: Vec<Vec<Option<u8>>>,
+    ) -> Result<Self, Error> {
+        if settings.rotation != Rotation::Deg0 || settings.grid.enable {
+            warn!("The mobile backend cannot rotate the screen or draw a pixel grid, ignoring it");
+        }
+        let context = Context::new(window.clone())
+            .map_err(|e| anyhow!("Failed to create a softbuffer context: {}", e))?;
+        let surface = Surface::new(&context, window.clone())
+            .map_err(|e| anyhow!("Failed to create a softbuffer surface: {}", e))?;
+        let mut renderer = Self {
+            title: WindowTitle::new(window.clone(), &settings.title, settings.visual_bell),
+            window,
+            surface,
+            scene: Scene {
+                size: (0, 0),
+                layout: Layout::new(0, 0, settings.scaling),
+                screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+                keys,
+                held: [false; 16],
+                palette: settings.palette.clone(),
+                letterbox: settings.letterbox_color,
+            },
+            scaling: settings.scaling,
+            needs_repaint: true,
+        };
+        renderer.resize(renderer.window.inner_size())?;
+        Ok(renderer)
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.scene.layout
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), Error> {
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return Ok(());
+        };
+        self.surface
+            .resize(width, height)
+            .map_err(|e| anyhow!("Failed to resize the softbuffer surface: {}", e))?;
+        self.scene.size = (size.width, size.height);
+        self.scene.layout = Layout::new(size.width, size.height, self.scaling);
+        self.needs_repaint = true;
+        Ok(())
+    }
+
+    /// Lights the keys of the keypad held in `held`, redrawn with the next frame.
+    pub fn set_held(&mut self, held: [bool; 16]) {
+        if held != self.scene.held {
+            self.scene.held = held;
+            self.needs_repaint = true;
+        }
+    }
+}
+
+impl Scene {
+    fn pack(color: Color) -> u32 {
+        (color.r as u32) << 16 | (color.g as u32) << 8 | color.b as u32
+    }
+
+    /// Draws the whole window into `buffer`, `width` pixels per row.
+    fn draw(&self, buffer: &mut [u32], width: u32) {
+        let colors = self.palette.colors();
+        buffer.fill(Self::pack(self.letterbox));
+
+        let screen = self.layout.screen;
+        for dy in 0..screen.height {
+            let row = &self.screen
+                [(dy * SCREEN_HEIGHT as u32 / screen.height) as usize * SCREEN_WIDTH..]
+                [..SCREEN_WIDTH];
+            let start = ((screen.y + dy) * width + screen.x) as usize;
+            for (dx, pixel) in buffer[start..start + screen.width as usize]
+                .iter_mut()
+                .enumerate()
+            {
+                let index = row[dx * SCREEN_WIDTH / screen.width as usize];
+                *pixel = Self::pack(colors[index as usize]);
+            }
+        }
+
+        let keypad = self.layout.keypad;
+        let gap = (self.size.0.min(self.size.1) / KEY_GAP).max(1);
+        let (off, on) = (colors[0], colors[1]);
+        let rows = self.keys.len() as u32;
+        for (r, keys) in self.keys.iter().enumerate() {
+            let columns = keys.len() as u32;
+            for (c, key) in keys.iter().enumerate() {
+                let Some(key) = *key else {
+                    continue;
+                };
+                let cell = Rect {
+                    x: keypad.x + keypad.width * c as u32 / columns + gap,
+                    y: keypad.y + keypad.height * r as u32 / rows + gap,
+                    width: (keypad.width / columns).saturating_sub(gap * 2),
+                    height: (keypad.height / rows).saturating_sub(gap * 2),
+                };
+                let (fill, label) = if self.held[key as usize] {
+                    (on, off)
+                } else {
+                    (off.mix(on).mix(off), on)
+                };
+                Self::fill(buffer, width, cell, Self::pack(fill));
+                Self::draw_digit(buffer, width, cell, key, Self::pack(label));
+            }
+        }
+    }
+
+    fn fill(buffer: &mut [u32], width: u32, rect: Rect, color: u32) {
+        for y in rect.y..rect.y + rect.height {
+            let start = (y * width + rect.x) as usize;
+            buffer[start..start + rect.width as usize].fill(color);
+        }
+    }
+
+    /// Draws the hex digit `key` from the CHIP-8 font centered in `cell`, half as tall.
+    fn draw_digit(buffer: &mut [u32], width: u32, cell: Rect, key: u8, color: u32) {
+        let size = (cell.height / 2 / 5).min(cell.width / 2 / 4);
+        if size == 0 {
+            return;
+        }
+        let (x0, y0) = (
+            cell.x + (cell.width - 4 * size) / 2,
+            cell.y + (cell.height - 5 * size) / 2,
+        );
+        let sprite = &HEX_DIGITS[key as usize * 5..][..5];
+        for (row, bits) in sprite.iter().enumerate() {
+            for column in 0..4 {
+                if bits & 0x80 >> column != 0 {
+                    let dot = Rect {
+                        x: x0 + column * size,
+                        y: y0 + row as u32 * size,
+                        width: size,
+                        height: size,
+                    };
+                    Self::fill(buffer, width, dot, color);
+                }
+            }
+        }
+    }
+}
+
+impl DisplayController for MobileRenderer {
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        if dirty.is_empty() && !self.needs_repaint {
+            return Ok(());
+        }
+        self.scene.screen.copy_from_slice(display);
+        let (width, height) = self.scene.size;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let mut buffer = self
+            .surface
+            .buffer_mut()
+            .map_err(|e| anyhow!("Failed to get the softbuffer buffer: {}", e))?;
+        self.scene.draw(&mut buffer, width);
+        buffer
+            .present()
+            .map_err(|e| anyhow!("Failed to present the softbuffer buffer: {}", e))?;
+        self.needs_repaint = false;
+        Ok(())
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        if hotkey == Hotkey::CyclePalette {
+            self.scene.palette.cycle_preset();
+            info!("Switched palette to {:?}", self.scene.palette.preset);
+            self.needs_repaint = true;
+        }
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        self.title.show_status(status);
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        self.title.show_sound(on);
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.scene.palette = palette.clone();
+        self.needs_repaint = true;
+        Ok(())
+    }
+}
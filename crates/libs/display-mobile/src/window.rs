@@ -0,0 +1,274 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use chip8::core::audio::AudioSink;
+use chip8::core::chip8::REFRESH_RATE;
+use chip8::core::controller::{self, InputEvent};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::input::EventQueue;
+use chip8::core::pacer::FramePacer;
+use chip8::core::state::SaveState;
+use chip8::core::touch::{TouchPad, MOUSE_POINTER};
+use display_winit::input::Keyboard;
+use shared::config::config::ChipSettings;
+use tracing::{error, info, warn};
+use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton, Touch, TouchPhase, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Window, WindowId};
+
+use crate::renderer::MobileRenderer;
+
+struct App<'a> {
+    emulator: &'a mut Emulator,
+    cpu: &'a CpuController,
+    settings: &'a ChipSettings,
+    audio: &'a mut dyn AudioSink,
+    autosave: Option<PathBuf>,
+    renderer: Option<MobileRenderer>,
+    touch: TouchPad,
+    cursor: PhysicalPosition<f64>,
+    input: EventQueue,
+    keyboard: Keyboard,
+    pacer: FramePacer,
+    // The autosave is loaded on the first resume only, later ones continue where they are
+    started: bool,
+    error: Option<Error>,
+}
+
+impl App<'_> {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, err: Error) {
+        error!("mobile frontend stopped: {:?}", err);
+        self.error = Some(err);
+        event_loop.exit();
+    }
+
+    /// Continues from the autosave when it is of the running ROM.
+    fn load_autosave(&mut self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        let Ok(bytes) = fs::read(path) else {
+            return;
+        };
+        match SaveState::from_bytes(&bytes) {
+            Ok(state) if state.rom == self.emulator.get_rom() => {
+                info!("Continuing from the autosave {}", path.display());
+                self.emulator.load_state(state);
+            }
+            Ok(_) => info!("The autosave {} is of another ROM", path.display()),
+            Err(e) => warn!("Ignoring the autosave {}: {}", path.display(), e),
+        }
+    }
+
+    /// Saves the machine, the app may be killed any time once it is in the background.
+    fn save_autosave(&self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, self.emulator.save_state().to_bytes()));
+        match result {
+            Ok(()) => info!("Saved the autosave {}", path.display()),
+            Err(e) => warn!("Failed to write the autosave {}: {}", path.display(), e),
+        }
+    }
+
+    /// Sends a pointer going down, moving or lifting to the keypad. Pointers only press keys
+    /// going down on it, but may slide off and back while held.
+    fn pointer(&mut self, pointer: i64, phase: TouchPhase, position: PhysicalPosition<f64>) {
+        let Some(renderer) = &self.renderer else {
+            return;
+        };
+        let keypad = renderer.layout().keypad;
+        let (x, y) = keypad.fraction(position.x, position.y);
+        match phase {
+            TouchPhase::Started if keypad.contains(position.x, position.y) => {
+                self.touch.press(pointer, x, y, &mut self.input)
+            }
+            TouchPhase::Started => {}
+            TouchPhase::Moved => self.touch.motion(pointer, x, y, &mut self.input),
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touch.release(pointer, &mut self.input)
+            }
+        }
+    }
+}
+
+impl ApplicationHandler for App<'_> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            return;
+        }
+
+        let attributes = Window::default_attributes().with_title(&self.settings.title);
+        let result = event_loop
+            .create_window(attributes)
+            .map_err(|e| anyhow!("Failed to create window: {}", e))
+            .and_then(|window| {
+                MobileRenderer::new(Arc::new(window), self.settings, self.touch.rows().to_vec())
+            });
+        match result {
+            Ok(renderer) => {
+                info!("mobile frontend initialized");
+                self.renderer = Some(renderer);
+                self.pacer = FramePacer::new(REFRESH_RATE);
+                if !self.started {
+                    self.started = true;
+                    self.load_autosave();
+                }
+            }
+            Err(err) => self.fail(event_loop, err),
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop) {
+        info!("mobile frontend suspended");
+        // The surface goes with the window, it is created again on resume
+        self.renderer = None;
+        self.touch.release_all(&mut self.input);
+        self.save_autosave();
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => self.input.push(InputEvent::Quit),
+            WindowEvent::Resized(size) => {
+                if let Some(Err(err)) = self.renderer.as_mut().map(|r| r.resize(size)) {
+                    self.fail(event_loop, err);
+                }
+            }
+            WindowEvent::Focused(false) => self.touch.release_all(&mut self.input),
+            WindowEvent::ModifiersChanged(modifiers) => self.keyboard.set_modifiers(&modifiers),
+            // Keyboards attached to tablets and Chromebooks work as on the desktop, the back
+            // button arrives as Escape
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.keyboard.push_key(&mut self.input, &event)
+            }
+            WindowEvent::Touch(Touch {
+                id,
+                phase,
+                location,
+                ..
+            }) => self.pointer(id as i64, phase, location),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = position;
+                self.pointer(MOUSE_POINTER, TouchPhase::Moved, position);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let phase = match state {
+                    ElementState::Pressed => TouchPhase::Started,
+                    ElementState::Released => TouchPhase::Ended,
+                };
+                self.pointer(MOUSE_POINTER, phase, self.cursor);
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+
+        let frames = self.pacer.advance();
+        if frames > 0 {
+            let result = controller::run_frames(
+                self.emulator,
+                self.cpu,
+                self.settings.cycles_per_frame,
+                frames,
+                renderer,
+                &mut self.input,
+                self.audio,
+            );
+            let mut held = [false; 16];
+            for (key, held) in held.iter_mut().enumerate() {
+                *held = self.emulator.is_key_pressed(key as u8).unwrap_or(false);
+            }
+            renderer.set_held(held);
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    event_loop.exit();
+                    return;
+                }
+                Err(err) => {
+                    self.fail(event_loop, err);
+                    return;
+                }
+            }
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(self.pacer.next_deadline()));
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            self.save_autosave();
+        }
+    }
+}
+
+/// Opens a winit window and drives the emulator with the touch keypad until it is closed,
+/// see `run_with`.
+pub fn run(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+    autosave: Option<PathBuf>,
+) -> Result<(), Error> {
+    let event_loop = EventLoop::new().map_err(|e| anyhow!("Failed to create event loop: {}", e))?;
+    run_with(event_loop, emulator, cpu, settings, audio, autosave)
+}
+
+/// Drives the emulator on `event_loop`, built by the caller on platforms that need more than
+/// `EventLoop::new`, e.g. with the `AndroidApp` on Android. The machine is saved to
+/// `autosave` whenever the app is suspended or exits and continued from it on start.
+pub fn run_with(
+    event_loop: EventLoop<()>,
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+    autosave: Option<PathBuf>,
+) -> Result<(), Error> {
+    let mut app = App {
+        emulator,
+        cpu,
+        settings,
+        audio,
+        autosave,
+        renderer: None,
+        touch: TouchPad::new(&settings.touch)?,
+        cursor: PhysicalPosition::default(),
+        input: EventQueue::default(),
+        keyboard: Keyboard::default(),
+        pacer: FramePacer::new(REFRESH_RATE),
+        started: false,
+        error: None,
+    };
+    if settings.rotate_keys {
+        app.input.set_key_rotation(settings.rotation);
+    }
+    app.input
+        .set_turbo(settings.turbo_for(app.emulator.get_rom_name()))?;
+    if let Some(player) = settings.active_player() {
+        app.input.set_player(player)?;
+    }
+    event_loop
+        .run_app(&mut app)
+        .map_err(|e| anyhow!("Event loop failed: {}", e))?;
+
+    app.error.map_or(Ok(()), Err)
+}
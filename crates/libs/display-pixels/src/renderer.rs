@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use chip8::core::blend::{self, FrameBlend};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, Status};
+use display_winit::title::WindowTitle;
+use pixels::{wgpu, Pixels, PixelsBuilder, ScalingMode, SurfaceTexture};
+use shared::config::config::{ChipSettings, Color, Palette, Rotation, Scaling};
+use tracing::{info, warn};
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Minimal display controller: one RGBA buffer the size of the CHIP-8 screen, blitted by
+/// `pixels` with integer scaling on every frame.
+pub struct PixelsRenderer {
+    title: WindowTitle,
+    pixels: Pixels<'static>,
+    palette: Palette,
+    rotation: Rotation,
+    blend: Option<FrameBlend>,
+    needs_repaint: bool,
+}
+
+impl PixelsRenderer {
+    pub fn new(window: Arc<Window>, settings: &ChipSettings) -> Result<Self, Error> {
+        let size = window.inner_size();
+        let surface_texture =
+            SurfaceTexture::new(size.width.max(1), size.height.max(1), window.clone());
+        if settings.grid.enable {
+            warn!("The pixels backend cannot draw a pixel grid, ignoring it");
+        }
+        let letterbox = settings.letterbox_color;
+        // The buffer holds the screen already rotated
+        let (width, height) = settings.rotation.size((SCREEN_WIDTH, SCREEN_HEIGHT));
+        let pixels = PixelsBuilder::new(width as u32, height as u32, surface_texture)
+            .clear_color(wgpu::Color {
+                r: letterbox.r as f64 / 255.0,
+                g: letterbox.g as f64 / 255.0,
+                b: letterbox.b as f64 / 255.0,
+                a: letterbox.a as f64 / 255.0,
+            })
+            .build()
+            .map_err(|e| anyhow!("Failed to create pixel buffer: {}", e))?;
+        let mut renderer = Self {
+            title: WindowTitle::new(window, &settings.title, settings.visual_bell),
+            pixels,
+            palette: settings.palette.clone(),
+            rotation: settings.rotation,
+            blend: settings.motion_blur.then(FrameBlend::default),
+            needs_repaint: true,
+        };
+        renderer
+            .pixels
+            .set_scaling_mode(Self::scaling_mode(settings.scaling));
+        Ok(renderer)
+    }
+
+    /// pixels only scales by whole multiples or to fit, it cannot ignore the aspect ratio.
+    fn scaling_mode(scaling: Scaling) -> ScalingMode {
+        match scaling {
+            Scaling::Integer => ScalingMode::PixelPerfect,
+            Scaling::Fit => ScalingMode::Fill,
+            Scaling::Stretch => {
+                warn!("The pixels backend cannot stretch the screen, using fit scaling");
+                ScalingMode::Fill
+            }
+        }
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) -> Result<(), Error> {
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        self.needs_repaint = true;
+        self.pixels
+            .resize_surface(size.width, size.height)
+            .map_err(|e| anyhow!("Failed to resize pixel surface: {}", e))
+    }
+
+    fn paint_pixel(frame: &mut [u8], rotation: Rotation, index: usize, color: Color) {
+        let offset = rotation.rotate_index(index, (SCREEN_WIDTH, SCREEN_HEIGHT)) * BYTES_PER_PIXEL;
+        frame[offset..offset + BYTES_PER_PIXEL]
+            .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+impl DisplayController for PixelsRenderer {
+    /// Paints the changed pixels and renders, skipped when nothing changed.
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let dirty = blend::repaint_list(self.blend.as_mut(), display, dirty);
+        if !self.needs_repaint && dirty.is_empty() {
+            return Ok(());
+        }
+        let colors = self.palette.colors();
+        let color = |index: usize| match &self.blend {
+            Some(blend) => blend.color(&colors, index),
+            None => colors[display[index] as usize],
+        };
+        let frame = self.pixels.frame_mut();
+        if self.needs_repaint {
+            for index in 0..display.len() {
+                Self::paint_pixel(frame, self.rotation, index, color(index));
+            }
+            self.needs_repaint = false;
+        } else {
+            for &index in dirty.iter() {
+                Self::paint_pixel(frame, self.rotation, index, color(index));
+            }
+        }
+
+        self.pixels
+            .render()
+            .map_err(|e| anyhow!("Failed to render pixel buffer: {}", e))
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        if hotkey == Hotkey::CyclePalette {
+            self.palette.cycle_preset();
+            info!("Switched palette to {:?}", self.palette.preset);
+            self.needs_repaint = true;
+        }
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        self.title.show_status(status);
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        self.title.show_sound(on);
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.palette = palette.clone();
+        self.needs_repaint = true;
+        Ok(())
+    }
+
+    fn request_repaint(&mut self) {
+        self.needs_repaint = true;
+    }
+}
@@ -0,0 +1,62 @@
+use chip8::core::audio::AudioSink;
+use embedded_hal::digital::OutputPin;
+use tracing::warn;
+
+/// An active buzzer on an output pin, driven high while the sound timer runs. It has no
+/// volume, muting keeps it off.
+pub struct Buzzer<P: OutputPin> {
+    pin: P,
+    on: bool,
+    muted: bool,
+}
+
+impl<P: OutputPin> Buzzer<P> {
+    pub fn new(pin: P) -> Self {
+        Self {
+            pin,
+            on: false,
+            muted: false,
+        }
+    }
+
+    fn update(&mut self) {
+        let result = if self.on && !self.muted {
+            self.pin.set_high()
+        } else {
+            self.pin.set_low()
+        };
+        if let Err(e) = result {
+            warn!("Failed to drive the buzzer: {:?}", e);
+        }
+    }
+}
+
+impl<P: OutputPin> AudioSink for Buzzer<P> {
+    fn set_beep(&mut self, on: bool) {
+        if on != self.on {
+            self.on = on;
+            self.update();
+        }
+    }
+
+    fn volume(&self) -> u8 {
+        if self.muted {
+            0
+        } else {
+            100
+        }
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.set_muted(volume == 0);
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.update();
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Error};
+use chip8::core::audio::AudioSink;
+use chip8::core::chip8::{REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{self, DisplayController, InputController};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::pacer::FramePacer;
+use embedded_graphics::pixelcolor::{BinaryColor, Rgb888};
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Rectangle;
+use shared::config::config::{ChipSettings, Palette};
+use tracing::info;
+
+/// Called after every drawn frame, e.g. to send the buffer of an SSD1306 in buffered graphics
+/// mode to the panel.
+type Flush<D> = Box<dyn FnMut(&mut D) -> Result<(), Error>>;
+
+/// embedded-graphics display controller: draws the 64x32 screen on any `DrawTarget`, such as
+/// an SSD1306 OLED or an ST7789 TFT, at the largest whole scale that fits it, centered. Only
+/// the changed pixels are drawn, which keeps SPI and I2C displays at full speed.
+pub struct PanelDisplay<D: DrawTarget> {
+    target: D,
+    colors: [D::Color; 4],
+    scale: u32,
+    origin: Point,
+    flush: Option<Flush<D>>,
+    needs_repaint: bool,
+}
+
+impl<D: DrawTarget<Color = BinaryColor>> PanelDisplay<D>
+where
+    D::Error: Debug,
+{
+    /// Draws on a monochrome panel, every lit pixel on whichever XO-CHIP plane it is.
+    pub fn monochrome(target: D) -> Self {
+        let on = BinaryColor::On;
+        Self::new(target, [BinaryColor::Off, on, on, on])
+    }
+}
+
+impl<D: DrawTarget> PanelDisplay<D>
+where
+    D::Color: From<Rgb888>,
+    D::Error: Debug,
+{
+    /// Draws on a color panel with the colors of `palette`, e.g. an ST7789 in RGB565.
+    pub fn with_palette(target: D, palette: &Palette) -> Self {
+        let colors = palette
+            .colors()
+            .map(|color| Rgb888::new(color.r, color.g, color.b).into());
+        Self::new(target, colors)
+    }
+}
+
+impl<D: DrawTarget> PanelDisplay<D>
+where
+    D::Error: Debug,
+{
+    /// Draws palette index `i` of the screen in `colors[i]`.
+    pub fn new(target: D, colors: [D::Color; 4]) -> Self {
+        let size = target.bounding_box().size;
+        let scale = (size.width / SCREEN_WIDTH as u32)
+            .min(size.height / SCREEN_HEIGHT as u32)
+            .max(1);
+        let origin = target.bounding_box().top_left
+            + Point::new(
+                (size.width as i32 - (SCREEN_WIDTH as u32 * scale) as i32).max(0) / 2,
+                (size.height as i32 - (SCREEN_HEIGHT as u32 * scale) as i32).max(0) / 2,
+            );
+        Self {
+            target,
+            colors,
+            scale,
+            origin,
+            flush: None,
+            needs_repaint: true,
+        }
+    }
+
+    /// Runs `flush` after every frame that drew something, for buffered displays.
+    pub fn with_flush(mut self, flush: impl FnMut(&mut D) -> Result<(), Error> + 'static) -> Self {
+        self.flush = Some(Box::new(flush));
+        self
+    }
+
+    pub fn target(&self) -> &D {
+        &self.target
+    }
+
+    pub fn target_mut(&mut self) -> &mut D {
+        &mut self.target
+    }
+
+    /// Fills the scaled square of the pixel at `index` with the color of `pixel`.
+    fn draw_pixel(&mut self, index: usize, pixel: u8) -> Result<(), Error> {
+        let (x, y) = ((index % SCREEN_WIDTH) as i32, (index / SCREEN_WIDTH) as i32);
+        let scale = self.scale as i32;
+        let area = Rectangle::new(
+            self.origin + Point::new(x * scale, y * scale),
+            Size::new_equal(self.scale),
+        );
+        self.target
+            .fill_solid(&area, self.colors[pixel as usize])
+            .map_err(|e| anyhow!("Failed to draw on the display: {:?}", e))
+    }
+}
+
+impl<D: DrawTarget> DisplayController for PanelDisplay<D>
+where
+    D::Error: Debug,
+{
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let drawn = if self.needs_repaint {
+            self.target
+                .clear(self.colors[0])
+                .map_err(|e| anyhow!("Failed to clear the display: {:?}", e))?;
+            for (index, &pixel) in display.iter().enumerate() {
+                self.draw_pixel(index, pixel)?;
+            }
+            self.needs_repaint = false;
+            true
+        } else {
+            for &index in dirty {
+                self.draw_pixel(index, display[index])?;
+            }
+            !dirty.is_empty()
+        };
+        match &mut self.flush {
+            Some(flush) if drawn => flush(&mut self.target),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Drives the emulator on `display` with the keypad `input` at 60 frames per second until the
+/// input asks to quit.
+pub fn run<D: DrawTarget, I: InputController>(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    display: &mut PanelDisplay<D>,
+    input: &mut I,
+    audio: &mut dyn AudioSink,
+) -> Result<(), Error>
+where
+    D::Error: Debug,
+{
+    let mut pacer = FramePacer::new(REFRESH_RATE);
+    info!("embedded-graphics frontend initialized");
+
+    loop {
+        let frames = pacer.advance();
+        let running = controller::run_frames(
+            emulator,
+            cpu,
+            settings.cycles_per_frame,
+            frames,
+            display,
+            input,
+            audio,
+        )?;
+        if !running {
+            return Ok(());
+        }
+        pacer.wait();
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{anyhow, Error};
+use chip8::core::controller::{InputController, InputEvent};
+use embedded_hal::digital::{InputPin, OutputPin};
+
+/// CHIP-8 keys of a 4x4 membrane keypad labelled 123A/456B/789C/*0#D, row by row: the keys
+/// sit where they were on the COSMAC VIP.
+pub const MATRIX_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// The key events between the keys held before, `held`, and now, as bitmasks.
+fn changes(held: u16, now: u16) -> Vec<InputEvent> {
+    (0..16u8)
+        .filter(|&key| (held ^ now) & 1 << key != 0)
+        .map(|key| {
+            if now & 1 << key != 0 {
+                InputEvent::KeyDown(key)
+            } else {
+                InputEvent::KeyUp(key)
+            }
+        })
+        .collect()
+}
+
+/// A 4x4 matrix keypad scanned through embedded-hal pins: every poll drives one row low at
+/// a time and reads the columns, pulled up, low where a key of that row is held.
+pub struct MatrixKeypad<R: OutputPin, C: InputPin> {
+    rows: [R; 4],
+    columns: [C; 4],
+    layout: [[u8; 4]; 4],
+    held: u16,
+}
+
+impl<R: OutputPin, C: InputPin> MatrixKeypad<R, C> {
+    /// A keypad with `MATRIX_LAYOUT`, the rows idle high.
+    pub fn new(rows: [R; 4], columns: [C; 4]) -> Result<Self, Error> {
+        Self::with_layout(rows, columns, MATRIX_LAYOUT)
+    }
+
+    /// A keypad whose key at row `r` and column `c` is the CHIP-8 key `layout[r][c]`.
+    pub fn with_layout(
+        mut rows: [R; 4],
+        columns: [C; 4],
+        layout: [[u8; 4]; 4],
+    ) -> Result<Self, Error> {
+        if let Some(key) = layout.iter().flatten().find(|&&key| key > 0xF) {
+            return Err(anyhow!("Invalid keypad key {}", key));
+        }
+        for row in rows.iter_mut() {
+            row.set_high()
+                .map_err(|e| anyhow!("Failed to set a keypad row: {:?}", e))?;
+        }
+        Ok(Self {
+            rows,
+            columns,
+            layout,
+            held: 0,
+        })
+    }
+
+    /// The keys held now, as a bitmask.
+    fn scan(&mut self) -> Result<u16, Error> {
+        let mut held = 0;
+        for (row, keys) in self.rows.iter_mut().zip(self.layout) {
+            row.set_low()
+                .map_err(|e| anyhow!("Failed to set a keypad row: {:?}", e))?;
+            for (column, key) in self.columns.iter_mut().zip(keys) {
+                let pressed = column
+                    .is_low()
+                    .map_err(|e| anyhow!("Failed to read a keypad column: {:?}", e))?;
+                if pressed {
+                    held |= 1 << key;
+                }
+            }
+            row.set_high()
+                .map_err(|e| anyhow!("Failed to set a keypad row: {:?}", e))?;
+        }
+        Ok(held)
+    }
+}
+
+impl<R: OutputPin, C: InputPin> InputController for MatrixKeypad<R, C> {
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        let held = self.scan()?;
+        let events = changes(self.held, held);
+        self.held = held;
+        Ok(events)
+    }
+}
+
+/// Buttons wired to a pin each, pressed when the pin reads low, e.g. the D-pad and buttons
+/// of a handheld with pull-ups.
+pub struct GpioKeypad<P: InputPin> {
+    buttons: Vec<(P, u8)>,
+    held: u16,
+}
+
+impl<P: InputPin> GpioKeypad<P> {
+    /// `buttons` pairs every pin with the CHIP-8 key it presses.
+    pub fn new(buttons: Vec<(P, u8)>) -> Result<Self, Error> {
+        if let Some((_, key)) = buttons.iter().find(|(_, key)| *key > 0xF) {
+            return Err(anyhow!("Invalid keypad key {}", key));
+        }
+        Ok(Self { buttons, held: 0 })
+    }
+}
+
+impl<P: InputPin> InputController for GpioKeypad<P> {
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        let mut held = 0;
+        for (pin, key) in self.buttons.iter_mut() {
+            let pressed = pin
+                .is_low()
+                .map_err(|e| anyhow!("Failed to read a button: {:?}", e))?;
+            if pressed {
+                held |= 1 << *key;
+            }
+        }
+        let events = changes(self.held, held);
+        self.held = held;
+        Ok(events)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod buzzer;
+pub mod display;
+pub mod keypad;
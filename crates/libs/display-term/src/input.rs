@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use chip8::core::controller::{Hotkey, InputController, InputEvent};
+use chip8::core::input::EventQueue;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use shared::config::config::{ChipSettings, KeyboardLayout, Player};
+
+/// Frames a key stays down after its last press when the terminal does not report releases.
+/// Long enough to bridge the delay before the terminal starts auto-repeating a held key.
+const HOLD_FRAMES: u32 = 30;
+
+/// Reads keys from the terminal. Without key release reporting, releases are synthesized
+/// once a key has not been pressed or repeated for `HOLD_FRAMES` polls.
+pub struct TermInput {
+    key_releases: bool,
+    keyboard_layout: KeyboardLayout,
+    held: [u32; 16],
+    queue: EventQueue,
+}
+
+impl TermInput {
+    pub fn new(key_releases: bool) -> Self {
+        Self {
+            key_releases,
+            keyboard_layout: KeyboardLayout::default(),
+            held: [0; 16],
+            queue: EventQueue::default(),
+        }
+    }
+
+    /// Terminals only report typed characters, see `KeyboardLayout::to_qwerty`.
+    pub fn set_keyboard_layout(&mut self, layout: KeyboardLayout) {
+        self.keyboard_layout = layout;
+    }
+
+    /// Moves the keypad to a player's keys, see `EventQueue::set_player`.
+    pub fn set_player(&mut self, player: &Player) -> Result<(), Error> {
+        self.queue.set_player(player)
+    }
+
+    fn push_key(&mut self, key: KeyEvent) {
+        let queue = &mut self.queue;
+        let quit = key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+        if quit {
+            queue.push(InputEvent::Quit);
+            return;
+        }
+        let hotkey = match key.code {
+            KeyCode::F(5) => Some(Hotkey::SaveToSlot),
+            KeyCode::F(9) => Some(Hotkey::LoadFromSlot),
+            _ => None,
+        };
+        if let Some(hotkey) = hotkey {
+            if key.kind == KeyEventKind::Press {
+                queue.push(InputEvent::Hotkey(hotkey));
+            }
+            return;
+        }
+        let KeyCode::Char(c) = key.code else {
+            return;
+        };
+        let c = self.keyboard_layout.to_qwerty(c);
+
+        let pressed = key.kind != KeyEventKind::Release;
+        let repeat = key.kind == KeyEventKind::Repeat;
+        // Shift is only reported with the kitty keyboard protocol, which sends the key without
+        // shift, so shift and a digit picks a save slot on any layout
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let slot = shift && c.is_ascii_digit();
+        if !self.key_releases && !slot {
+            if let Some(chip8_key) = queue.map_key(c) {
+                let held = &mut self.held[chip8_key as usize];
+                let was_held = *held > 0;
+                *held = HOLD_FRAMES;
+                if was_held {
+                    return;
+                }
+            }
+        }
+        queue.push_key_with_shift(c, shift, pressed, repeat);
+    }
+
+    fn release_expired(&mut self) {
+        let queue = &mut self.queue;
+        for (key, held) in self.held.iter_mut().enumerate() {
+            if *held > 0 {
+                *held -= 1;
+                if *held == 0 {
+                    queue.push(InputEvent::KeyUp(key as u8));
+                }
+            }
+        }
+    }
+}
+
+impl InputController for TermInput {
+    fn set_keymap(&mut self, settings: &ChipSettings, rom: Option<&str>) -> Result<(), Error> {
+        self.keyboard_layout = settings.keyboard_layout;
+        self.queue.set_keymap(settings, rom)
+    }
+
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        if !self.key_releases {
+            self.release_expired();
+        }
+        while event::poll(Duration::ZERO).map_err(|e| anyhow!("Failed to poll terminal: {}", e))? {
+            let event =
+                event::read().map_err(|e| anyhow!("Failed to read terminal event: {}", e))?;
+            if let Event::Key(key) = event {
+                self.push_key(key);
+            }
+        }
+        self.queue.poll()
+    }
+}
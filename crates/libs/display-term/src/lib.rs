@@ -0,0 +1,3 @@
+pub mod input;
+pub mod renderer;
+pub mod terminal;
@@ -0,0 +1,185 @@
+use std::io::{stdout, Stdout, Write};
+
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, SOUND_ICON};
+use crossterm::style::{
+    Color as TermColor, Print, ResetColor, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::{cursor, queue};
+use shared::config::config::{Color, Palette, TerminalMode};
+use tracing::info;
+
+/// Upper half block: the foreground color paints the top pixel, the background the bottom one.
+const HALF_BLOCK: char = '\u{2580}';
+
+/// Empty Braille pattern, dots are added by setting the bits of `BRAILLE_DOTS`.
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// Braille dot bits indexed by `[y][x]` inside a 2x4 cell.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Terminal display controller. In half-block mode every character cell covers two vertically
+/// stacked pixels (64x16 cells), in Braille mode a 2x4 block of pixels (32x8 cells).
+pub struct TermRenderer {
+    out: Stdout,
+    palette: Palette,
+    mode: TerminalMode,
+    truecolor: bool,
+    visual_bell: bool,
+    buzzing: bool,
+    needs_repaint: bool,
+}
+
+impl TermRenderer {
+    pub fn new(palette: Palette, mode: TerminalMode) -> Self {
+        // Terminals advertise 24-bit color support through COLORTERM, fall back to the
+        // 256-color ANSI palette otherwise.
+        let truecolor = std::env::var("COLORTERM")
+            .map(|value| value == "truecolor" || value == "24bit")
+            .unwrap_or(false);
+        Self {
+            out: stdout(),
+            palette,
+            mode,
+            truecolor,
+            visual_bell: false,
+            buzzing: false,
+            needs_repaint: true,
+        }
+    }
+
+    /// Shows a speaker below the screen while the buzzer sounds.
+    pub fn set_visual_bell(&mut self, enable: bool) {
+        self.visual_bell = enable;
+    }
+
+    /// Pixel size of one character cell in the current mode.
+    fn cell_size(&self) -> (usize, usize) {
+        match self.mode {
+            TerminalMode::HalfBlock => (1, 2),
+            TerminalMode::Braille => (2, 4),
+        }
+    }
+
+    fn draw_cell(&mut self, display: &[u8], column: usize, row: usize) -> Result<(), Error> {
+        let colors = self.palette.colors().map(|color| self.term_color(color));
+        let (cell_w, cell_h) = self.cell_size();
+        let pixel = |dx: usize, dy: usize| {
+            display[(row * cell_h + dy) * SCREEN_WIDTH + column * cell_w + dx]
+        };
+
+        let (fg, bg, symbol) = match self.mode {
+            TerminalMode::HalfBlock => (
+                colors[pixel(0, 0) as usize],
+                colors[pixel(0, 1) as usize],
+                HALF_BLOCK,
+            ),
+            TerminalMode::Braille => {
+                // A cell has a single foreground color, the highest color index in it wins.
+                let mut code = BRAILLE_BLANK;
+                let mut value = 0;
+                for (dy, dots) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, dot) in dots.iter().enumerate() {
+                        if pixel(dx, dy) != 0 {
+                            code |= dot;
+                            value = value.max(pixel(dx, dy));
+                        }
+                    }
+                }
+                let symbol = char::from_u32(code).unwrap_or(' ');
+                (colors[value.max(1) as usize], colors[0], symbol)
+            }
+        };
+        queue!(
+            self.out,
+            cursor::MoveTo(column as u16, row as u16),
+            SetForegroundColor(fg),
+            SetBackgroundColor(bg),
+            Print(symbol)
+        )
+        .map_err(|e| anyhow!("Failed to draw terminal cell: {}", e))
+    }
+
+    fn term_color(&self, color: Color) -> TermColor {
+        if self.truecolor {
+            return TermColor::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            };
+        }
+        // Nearest entry of the 6x6x6 color cube starting at index 16.
+        let level = |c: u8| (c as u16 * 5 + 127) / 255;
+        TermColor::AnsiValue((16 + 36 * level(color.r) + 6 * level(color.g) + level(color.b)) as u8)
+    }
+}
+
+impl DisplayController for TermRenderer {
+    /// Redraws only the character cells containing a changed pixel.
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let (cell_w, cell_h) = self.cell_size();
+        if self.needs_repaint {
+            for row in 0..SCREEN_HEIGHT / cell_h {
+                for column in 0..SCREEN_WIDTH / cell_w {
+                    self.draw_cell(display, column, row)?;
+                }
+            }
+            self.needs_repaint = false;
+        } else {
+            let mut cells: Vec<(usize, usize)> = dirty
+                .iter()
+                .map(|index| (index % SCREEN_WIDTH / cell_w, index / SCREEN_WIDTH / cell_h))
+                .collect();
+            cells.sort_unstable_by_key(|&(column, row)| (row, column));
+            cells.dedup();
+            for (column, row) in cells {
+                self.draw_cell(display, column, row)?;
+            }
+        }
+        self.out
+            .flush()
+            .map_err(|e| anyhow!("Failed to flush terminal output: {}", e))
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        match hotkey {
+            Hotkey::CyclePalette => {
+                self.palette.cycle_preset();
+                info!("Switched palette to {:?}", self.palette.preset);
+                self.needs_repaint = true;
+            }
+            // The CRT effect has no meaning on character cells.
+            Hotkey::ToggleCrt => {}
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        if !self.visual_bell || on == self.buzzing {
+            return Ok(());
+        }
+        self.buzzing = on;
+        let (_, cell_h) = self.cell_size();
+        // The speaker is two cells wide, blank both when it goes away
+        let icon = if on {
+            SOUND_ICON.to_string()
+        } else {
+            "  ".to_string()
+        };
+        queue!(
+            self.out,
+            cursor::MoveTo(0, (SCREEN_HEIGHT / cell_h) as u16),
+            ResetColor,
+            Print(icon)
+        )
+        .map_err(|e| anyhow!("Failed to draw sound indicator: {}", e))
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.palette = palette.clone();
+        self.needs_repaint = true;
+        Ok(())
+    }
+}
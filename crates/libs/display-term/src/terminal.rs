@@ -0,0 +1,68 @@
+use std::io::{stdout, Write};
+
+use anyhow::{anyhow, Error};
+use crossterm::event::{
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, terminal};
+use tracing::{info, warn};
+
+/// Puts the terminal into raw mode on the alternate screen and restores it when dropped,
+/// including when the emulator stops with an error.
+pub struct TerminalGuard {
+    key_releases: bool,
+}
+
+impl TerminalGuard {
+    pub fn enter() -> Result<Self, Error> {
+        terminal::enable_raw_mode().map_err(|e| anyhow!("Failed to enable raw mode: {}", e))?;
+        let mut guard = Self {
+            key_releases: false,
+        };
+        execute!(
+            stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(terminal::ClearType::All)
+        )
+        .map_err(|e| anyhow!("Failed to prepare the terminal: {}", e))?;
+
+        // Most terminals only report key presses, the kitty keyboard protocol adds releases.
+        // Reporting every key as an escape code adds the modifiers of typed characters too.
+        if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(
+                    KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                        | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+                )
+            )
+            .map_err(|e| anyhow!("Failed to enable key release events: {}", e))?;
+            guard.key_releases = true;
+        }
+        info!(
+            "Terminal frontend initialized, key releases reported: {}",
+            guard.key_releases
+        );
+        Ok(guard)
+    }
+
+    /// Whether the terminal reports key releases, otherwise the input has to synthesize them.
+    pub fn key_releases(&self) -> bool {
+        self.key_releases
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = stdout();
+        if self.key_releases {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = out.flush();
+        if let Err(e) = terminal::disable_raw_mode() {
+            warn!("Failed to disable raw mode: {}", e);
+        }
+    }
+}
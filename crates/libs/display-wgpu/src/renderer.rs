@@ -0,0 +1,426 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use bytemuck::{Pod, Zeroable};
+use chip8::core::blend::{self, FrameBlend};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, Status};
+use display_winit::title::WindowTitle;
+use shared::config::config::{ChipSettings, Color, Crt, Grid, Palette, Rotation, Scaling};
+use tracing::{info, warn};
+use wgpu::util::DeviceExt;
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+/// Bytes per texel of the screen texture, the current and the previous color index.
+const TEXEL_SIZE: usize = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct Uniforms {
+    // Palette indexed by pixel value
+    colors: [[f32; 4]; 4],
+    grid_color: [f32; 4],
+    crt_enabled: f32,
+    scanline_intensity: f32,
+    vignette_intensity: f32,
+    barrel_distortion: f32,
+    rotation: f32,
+    grid_enabled: f32,
+    grid_min_scale: f32,
+    _padding: f32,
+}
+
+/// wgpu implementation of the display controller. The framebuffer is uploaded as a 64x32
+/// two-channel texture holding the current and previous color index scaled to 0..=255 (the
+/// same index twice without motion blur), the palette and CRT effect are applied in the
+/// fragment shader.
+pub struct WgpuRenderer {
+    window: Arc<Window>,
+    title: WindowTitle,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    screen: wgpu::Texture,
+    uniform_buffer: wgpu::Buffer,
+    palette: Palette,
+    crt: Crt,
+    grid: Grid,
+    scaling: Scaling,
+    rotation: Rotation,
+    letterbox: wgpu::Color,
+    pixels: Vec<u8>,
+    blend: Option<FrameBlend>,
+    needs_upload: bool,
+    // Set until a frame is presented after something shown changed
+    needs_present: bool,
+}
+
+impl WgpuRenderer {
+    pub fn new(window: Arc<Window>, settings: &ChipSettings) -> Result<Self, Error> {
+        pollster::block_on(Self::init(window, settings))
+    }
+
+    async fn init(window: Arc<Window>, settings: &ChipSettings) -> Result<Self, Error> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(|e| anyhow!("Failed to create wgpu surface: {}", e))?;
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow!("No suitable GPU adapter found: {}", e))?;
+        info!("Using GPU adapter: {:?}", adapter.get_info().name);
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default())
+            .await
+            .map_err(|e| anyhow!("Failed to open GPU device: {}", e))?;
+
+        // Palette colors are authored as sRGB bytes, prefer a surface format that
+        // passes them through untouched.
+        let size = window.inner_size();
+        let mut config = surface
+            .get_default_config(&adapter, size.width.max(1), size.height.max(1))
+            .ok_or_else(|| anyhow!("Surface is not supported by the GPU adapter"))?;
+        let capabilities = surface.get_capabilities(&adapter);
+        if let Some(format) = capabilities.formats.iter().find(|f| !f.is_srgb()) {
+            config.format = *format;
+        }
+        surface.configure(&device, &config);
+
+        let screen = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("chip8 screen"),
+            size: Self::screen_extent(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rg8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("chip8 screen sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("chip8 uniforms"),
+            contents: bytemuck::bytes_of(&Self::uniforms(
+                &settings.palette,
+                &settings.crt,
+                &settings.grid,
+                settings.rotation,
+            )),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("chip8 bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let screen_view = screen.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("chip8 bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&screen_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("chip8 pipeline layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            ..Default::default()
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("chip8 pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self {
+            title: WindowTitle::new(window.clone(), &settings.title, settings.visual_bell),
+            window,
+            surface,
+            device,
+            queue,
+            config,
+            pipeline,
+            bind_group,
+            screen,
+            uniform_buffer,
+            palette: settings.palette.clone(),
+            crt: settings.crt.clone(),
+            grid: settings.grid.clone(),
+            scaling: settings.scaling,
+            rotation: settings.rotation,
+            letterbox: Self::clear_color(settings.letterbox_color),
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * TEXEL_SIZE],
+            blend: settings.motion_blur.then(FrameBlend::default),
+            needs_upload: true,
+            needs_present: true,
+        })
+    }
+
+    /// Reconfigures the surface for the new physical size, keeping HiDPI output crisp.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(&self.device, &self.config);
+        self.needs_present = true;
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn write_uniforms(&self) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&Self::uniforms(
+                &self.palette,
+                &self.crt,
+                &self.grid,
+                self.rotation,
+            )),
+        );
+    }
+
+    fn uniforms(palette: &Palette, crt: &Crt, grid: &Grid, rotation: Rotation) -> Uniforms {
+        Uniforms {
+            colors: palette.colors().map(Self::normalize),
+            grid_color: Self::normalize(grid.color),
+            crt_enabled: if crt.enable { 1.0 } else { 0.0 },
+            scanline_intensity: crt.scanline_intensity,
+            vignette_intensity: crt.vignette_intensity,
+            barrel_distortion: crt.barrel_distortion,
+            rotation: rotation.quarter_turns() as f32,
+            grid_enabled: if grid.enable { 1.0 } else { 0.0 },
+            grid_min_scale: grid.min_scale.max(1) as f32,
+            _padding: 0.0,
+        }
+    }
+
+    fn normalize(color: Color) -> [f32; 4] {
+        [color.r, color.g, color.b, color.a].map(|c| c as f32 / 255.0)
+    }
+
+    fn clear_color(color: Color) -> wgpu::Color {
+        let [r, g, b, a] = Self::normalize(color).map(f64::from);
+        wgpu::Color { r, g, b, a }
+    }
+
+    fn screen_extent() -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: SCREEN_WIDTH as u32,
+            height: SCREEN_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+impl DisplayController for WgpuRenderer {
+    /// Uploads the changed pixels and draws a frame, skipped when nothing changed.
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let dirty = blend::repaint_list(self.blend.as_mut(), display, dirty);
+        if !self.needs_upload && !self.needs_present && dirty.is_empty() {
+            return Ok(());
+        }
+        for &index in dirty.iter() {
+            let (previous, current) = match &self.blend {
+                Some(blend) => blend.get(index),
+                None => (display[index], display[index]),
+            };
+            self.pixels[index * TEXEL_SIZE..][..TEXEL_SIZE]
+                .copy_from_slice(&[current * 85, previous * 85]);
+        }
+        if self.needs_upload || !dirty.is_empty() {
+            self.queue.write_texture(
+                self.screen.as_image_copy(),
+                &self.pixels,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some((SCREEN_WIDTH * TEXEL_SIZE) as u32),
+                    rows_per_image: Some(SCREEN_HEIGHT as u32),
+                },
+                Self::screen_extent(),
+            );
+            self.needs_upload = false;
+        }
+
+        // Kept until the frame goes out, so a frame lost with the surface is drawn again
+        self.needs_present = true;
+        let frame = match self.surface.get_current_texture() {
+            wgpu::CurrentSurfaceTexture::Success(frame)
+            | wgpu::CurrentSurfaceTexture::Suboptimal(frame) => frame,
+            wgpu::CurrentSurfaceTexture::Outdated | wgpu::CurrentSurfaceTexture::Lost => {
+                warn!("Surface lost, reconfiguring");
+                self.surface.configure(&self.device, &self.config);
+                return Ok(());
+            }
+            wgpu::CurrentSurfaceTexture::Timeout | wgpu::CurrentSurfaceTexture::Occluded => {
+                return Ok(());
+            }
+            wgpu::CurrentSurfaceTexture::Validation => {
+                return Err(anyhow!("Failed to acquire the next surface texture"));
+            }
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("chip8 frame"),
+            });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("chip8 screen pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.letterbox),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                ..Default::default()
+            });
+            let (screen_w, screen_h) = self.rotation.size((SCREEN_WIDTH, SCREEN_HEIGHT));
+            let viewport = self.scaling.viewport(
+                (screen_w as u32, screen_h as u32),
+                (self.config.width, self.config.height),
+            );
+            pass.set_viewport(
+                viewport.x as f32,
+                viewport.y as f32,
+                viewport.width as f32,
+                viewport.height as f32,
+                0.0,
+                1.0,
+            );
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+        self.window.pre_present_notify();
+        self.queue.present(frame);
+        self.needs_present = false;
+        Ok(())
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        match hotkey {
+            Hotkey::CyclePalette => {
+                self.palette.cycle_preset();
+                info!("Switched palette to {:?}", self.palette.preset);
+            }
+            Hotkey::ToggleCrt => {
+                self.crt.enable = !self.crt.enable;
+                info!("CRT effect enabled: {}", self.crt.enable);
+            }
+            Hotkey::ToggleGrid => {
+                self.grid.enable = !self.grid.enable;
+                info!("Pixel grid enabled: {}", self.grid.enable);
+            }
+            _ => {}
+        }
+        self.write_uniforms();
+        self.needs_present = true;
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        self.title.show_status(status);
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        self.title.show_sound(on);
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.palette = palette.clone();
+        self.needs_present = true;
+        Ok(())
+    }
+
+    fn request_repaint(&mut self) {
+        self.needs_present = true;
+    }
+}
@@ -0,0 +1,87 @@
+struct Uniforms {
+    // Palette indexed by pixel value, the texture stores the current (r) and previous (g)
+    // index divided by 3
+    colors: array<vec4<f32>, 4>,
+    grid_color: vec4<f32>,
+    crt_enabled: f32,
+    scanline_intensity: f32,
+    vignette_intensity: f32,
+    barrel_distortion: f32,
+    // Clockwise screen rotation in quarter turns
+    rotation: f32,
+    grid_enabled: f32,
+    // Smallest size of a CHIP-8 pixel, in physical pixels, for the grid to be drawn
+    grid_min_scale: f32,
+};
+
+@group(0) @binding(0) var screen: texture_2d<f32>;
+@group(0) @binding(1) var screen_sampler: sampler;
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Single triangle covering the whole viewport
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Maps a position on the rotated viewport back to the unrotated screen texture
+fn unrotate(uv: vec2<f32>) -> vec2<f32> {
+    let turns = u32(uniforms.rotation + 0.5);
+    if turns == 1u {
+        return vec2<f32>(uv.y, 1.0 - uv.x);
+    } else if turns == 2u {
+        return vec2<f32>(1.0 - uv.x, 1.0 - uv.y);
+    } else if turns == 3u {
+        return vec2<f32>(1.0 - uv.y, uv.x);
+    }
+    return uv;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Size of a physical pixel in texture coordinates, derivatives need uniform control flow
+    let pixel_uv = fwidth(unrotate(in.uv));
+    let crt = uniforms.crt_enabled > 0.5;
+    var uv = in.uv;
+    if crt {
+        let centered = uv * 2.0 - 1.0;
+        uv = centered * (1.0 + uniforms.barrel_distortion * dot(centered, centered)) * 0.5 + 0.5;
+        if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+            return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+
+    let screen_uv = unrotate(uv);
+    let value = textureSampleLevel(screen, screen_sampler, screen_uv, 0.0).rg;
+    let index = vec2<u32>(round(value * 3.0));
+    // Equal indices unless motion blur is on
+    var color = mix(uniforms.colors[index.x], uniforms.colors[index.y], 0.5);
+    if uniforms.grid_enabled > 0.5 {
+        let size = vec2<f32>(textureDimensions(screen));
+        // Physical pixels covered by one CHIP-8 pixel, the grid only shows on large ones
+        let line = pixel_uv * size;
+        let position = screen_uv * size;
+        let between = (fract(position) < line) & (position >= vec2<f32>(1.0));
+        if all(line * uniforms.grid_min_scale <= vec2<f32>(1.0)) && any(between) {
+            color = uniforms.grid_color;
+        }
+    }
+    if crt {
+        let size = vec2<f32>(textureDimensions(screen));
+        let row = fract(screen_uv.y * size.y);
+        let scanline = 1.0 - uniforms.scanline_intensity * step(0.75, row);
+        let centered = uv * 2.0 - 1.0;
+        let vignette = 1.0 - uniforms.vignette_intensity * dot(centered, centered) * 0.5;
+        color = vec4<f32>(color.rgb * scanline * vignette, color.a);
+    }
+    return color;
+}
@@ -0,0 +1,150 @@
+use anyhow::{anyhow, Error};
+use chip8::core::audio::AudioSink;
+use chip8::core::chip8::{REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{self, InputEvent};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::input::EventQueue;
+use chip8::core::pacer::FramePacer;
+use display_winit::input::Keyboard;
+use shared::config::config::ChipSettings;
+use std::sync::Arc;
+use tracing::{error, info};
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Window, WindowId};
+
+use crate::renderer::WgpuRenderer;
+
+struct App<'a> {
+    emulator: &'a mut Emulator,
+    cpu: &'a CpuController,
+    settings: &'a ChipSettings,
+    audio: &'a mut dyn AudioSink,
+    renderer: Option<WgpuRenderer>,
+    input: EventQueue,
+    keyboard: Keyboard,
+    pacer: FramePacer,
+    error: Option<Error>,
+}
+
+impl App<'_> {
+    fn fail(&mut self, event_loop: &ActiveEventLoop, err: Error) {
+        error!("wgpu frontend stopped: {:?}", err);
+        self.error = Some(err);
+        event_loop.exit();
+    }
+}
+
+impl ApplicationHandler for App<'_> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            return;
+        }
+
+        let scale = self.settings.scale as f64;
+        let (width, height) = self.settings.rotation.size((SCREEN_WIDTH, SCREEN_HEIGHT));
+        let attributes = Window::default_attributes()
+            .with_title(&self.settings.title)
+            .with_inner_size(LogicalSize::new(
+                width as f64 * scale,
+                height as f64 * scale,
+            ));
+        let result = event_loop
+            .create_window(attributes)
+            .map_err(|e| anyhow!("Failed to create window: {}", e))
+            .and_then(|window| WgpuRenderer::new(Arc::new(window), self.settings));
+        match result {
+            Ok(renderer) => {
+                info!("wgpu frontend initialized");
+                self.renderer = Some(renderer);
+                self.pacer = FramePacer::new(REFRESH_RATE);
+            }
+            Err(err) => self.fail(event_loop, err),
+        }
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => self.input.push(InputEvent::Quit),
+            WindowEvent::Resized(size) => {
+                if let Some(renderer) = self.renderer.as_mut() {
+                    renderer.resize(size);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.keyboard.set_modifiers(&modifiers),
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.keyboard.push_key(&mut self.input, &event)
+            }
+            WindowEvent::RedrawRequested => self.input.push(InputEvent::Redraw),
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+
+        let frames = self.pacer.advance();
+        if frames > 0 {
+            let result = controller::run_frames(
+                self.emulator,
+                self.cpu,
+                self.settings.cycles_per_frame,
+                frames,
+                renderer,
+                &mut self.input,
+                self.audio,
+            );
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    event_loop.exit();
+                    return;
+                }
+                Err(err) => {
+                    self.fail(event_loop, err);
+                    return;
+                }
+            }
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(self.pacer.next_deadline()));
+    }
+}
+
+/// Opens a winit window and drives the emulator with the wgpu renderer until it is closed.
+pub fn run(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+) -> Result<(), Error> {
+    let event_loop = EventLoop::new().map_err(|e| anyhow!("Failed to create event loop: {}", e))?;
+    let mut app = App {
+        emulator,
+        cpu,
+        settings,
+        audio,
+        renderer: None,
+        input: EventQueue::default(),
+        keyboard: Keyboard::default(),
+        pacer: FramePacer::new(REFRESH_RATE),
+        error: None,
+    };
+    if settings.rotate_keys {
+        app.input.set_key_rotation(settings.rotation);
+    }
+    app.input
+        .set_turbo(settings.turbo_for(app.emulator.get_rom_name()))?;
+    if let Some(player) = settings.active_player() {
+        app.input.set_player(player)?;
+    }
+    event_loop
+        .run_app(&mut app)
+        .map_err(|e| anyhow!("Event loop failed: {}", e))?;
+
+    app.error.map_or(Ok(()), Err)
+}
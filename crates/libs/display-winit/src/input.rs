@@ -0,0 +1,51 @@
+use chip8::core::controller::{Hotkey, InputEvent};
+use chip8::core::input::EventQueue;
+use winit::event::{ElementState, KeyEvent, Modifiers};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+/// Converts a physical key to the character printed on it in the US layout, e.g. `KeyQ` -> 'Q'.
+pub fn key_char(code: KeyCode) -> Option<char> {
+    let name = format!("{:?}", code);
+    let key = name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))?;
+    let mut chars = key.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// Keyboard of the winit frontends. Keys are mapped by their position, so the keypad stays
+/// on the same keys on any layout.
+#[derive(Debug, Default)]
+pub struct Keyboard {
+    // winit reports the modifiers apart from the keys
+    shift: bool,
+}
+
+impl Keyboard {
+    pub fn set_modifiers(&mut self, modifiers: &Modifiers) {
+        self.shift = modifiers.state().shift_key();
+    }
+
+    /// Sends a key through the keypad and hotkey mappings. Escape quits, F5 and F9 save to and
+    /// load from the selected slot and shift and a digit selects it.
+    pub fn push_key(&self, queue: &mut EventQueue, event: &KeyEvent) {
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return;
+        };
+        let pressed = event.state == ElementState::Pressed;
+        match code {
+            KeyCode::Escape if pressed => queue.push(InputEvent::Quit),
+            KeyCode::F5 if pressed && !event.repeat => {
+                queue.push(InputEvent::Hotkey(Hotkey::SaveToSlot))
+            }
+            KeyCode::F9 if pressed && !event.repeat => {
+                queue.push(InputEvent::Hotkey(Hotkey::LoadFromSlot))
+            }
+            _ => {
+                if let Some(key) = key_char(code) {
+                    queue.push_key_with_shift(key, self.shift, pressed, event.repeat);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use chip8::core::controller::{Status, SOUND_ICON};
+use winit::window::Window;
+
+/// The title of a winit window, showing the status caption. The window border can't be drawn,
+/// so the visual bell shows a speaker in the title instead.
+pub struct WindowTitle {
+    window: Arc<Window>,
+    title: String,
+    // Caption of the last status, shown with a speaker while the visual bell rings
+    caption: String,
+    visual_bell: bool,
+    buzzing: bool,
+}
+
+impl WindowTitle {
+    pub fn new(window: Arc<Window>, title: &str, visual_bell: bool) -> Self {
+        Self {
+            window,
+            title: title.to_string(),
+            caption: title.to_string(),
+            visual_bell,
+            buzzing: false,
+        }
+    }
+
+    pub fn show_status(&mut self, status: &Status) {
+        self.caption = status.caption(&self.title);
+        self.update();
+    }
+
+    pub fn show_sound(&mut self, on: bool) {
+        if self.visual_bell && on != self.buzzing {
+            self.buzzing = on;
+            self.update();
+        }
+    }
+
+    fn update(&self) {
+        if self.buzzing {
+            self.window
+                .set_title(&format!("{} {}", self.caption, SOUND_ICON));
+        } else {
+            self.window.set_title(&self.caption);
+        }
+    }
+}
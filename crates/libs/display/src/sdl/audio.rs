@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Error};
+use chip8::core::audio::{self, AudioSink, Scope, Synth};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+use shared::config::config::Audio;
+use tracing::info;
+
+struct BuzzerCallback {
+    synth: Synth,
+}
+
+impl AudioCallback for BuzzerCallback {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.synth.fill(out);
+    }
+}
+
+/// SDL buzzer: the device plays continuously, the synth is silent unless beeping or fed
+/// samples.
+pub struct SdlBuzzer {
+    device: AudioDevice<BuzzerCallback>,
+    scope: Scope,
+    active: bool,
+    volume: u8,
+    muted: bool,
+}
+
+impl SdlBuzzer {
+    pub fn new(sdl: &Sdl, settings: &Audio) -> Result<Self, Error> {
+        let audio = sdl
+            .audio()
+            .map_err(|e| anyhow!("Failed to initialize SDL audio: {}", e))?;
+        let desired = AudioSpecDesired {
+            freq: Some(settings.sample_rate as i32),
+            channels: Some(1),
+            samples: Some(settings.buffer_size),
+        };
+        let mut device = audio
+            .open_playback(settings.device.as_deref(), &desired, |spec| {
+                info!(
+                    "Opened audio device {} at {} Hz with {} samples per buffer",
+                    settings.device.as_deref().unwrap_or("(default)"),
+                    spec.freq,
+                    spec.samples
+                );
+                BuzzerCallback {
+                    synth: Synth::new(settings, spec.freq as u32),
+                }
+            })
+            .map_err(|e| anyhow!("Failed to open audio device: {}", e))?;
+        device.resume();
+        let scope = device.lock().synth.scope().clone();
+
+        Ok(Self {
+            device,
+            scope,
+            active: false,
+            volume: settings.volume.min(100),
+            muted: false,
+        })
+    }
+
+    /// Fades the callback to the current volume, or to silence while muted.
+    fn update_gain(&mut self) {
+        let gain = audio::gain(self.volume, self.muted);
+        self.device.lock().synth.set_gain(gain);
+    }
+}
+
+impl AudioSink for SdlBuzzer {
+    fn set_beep(&mut self, on: bool) {
+        if on != self.active {
+            self.device.lock().synth.set_beep(on);
+            self.active = on;
+        }
+    }
+
+    fn push_samples(&mut self, samples: &[f32]) {
+        self.device.lock().synth.push_samples(samples);
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        info!("Volume set to {}", self.volume);
+        self.update_gain();
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        info!("Sound muted: {}", self.muted);
+        self.update_gain();
+    }
+
+    /// Switches to the next waveform, the callback is locked while it changes.
+    fn cycle_waveform(&mut self) {
+        let waveform = self.device.lock().synth.cycle_waveform();
+        info!("Switched buzzer waveform to {:?}", waveform);
+    }
+
+    fn scope(&self) -> Option<Scope> {
+        Some(self.scope.clone())
+    }
+}
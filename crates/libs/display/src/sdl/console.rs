@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Error};
+use chip8::core::controller::Hotkey;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+use shared::logger::console::{self, LogLine};
+use tracing::Level;
+
+use super::font::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+const BACKGROUND: Color = Color::RGBA(0x00, 0x00, 0x00, 0xD0);
+const STATUS_COLOR: Color = Color::RGB(0xFF, 0xFF, 0xFF);
+
+/// Overlay listing the recent log lines over the screen, toggled with the backtick key.
+/// Paging up freezes the list so it can be read while the emulator keeps logging, paging
+/// back down to the newest line or pressing End follows the log again.
+#[derive(Default)]
+pub struct LogConsole {
+    visible: bool,
+    // Lines from the newest one shown at the bottom, while `frozen` holds the paused lines
+    scroll: usize,
+    frozen: Option<Vec<LogLine>>,
+    // Lines that fit on the window at the last draw, scrolled by a page
+    page: usize,
+}
+
+impl LogConsole {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Applies the console hotkeys, returning false for the ones it does not handle.
+    pub fn handle_hotkey(&mut self, hotkey: Hotkey) -> bool {
+        match hotkey {
+            Hotkey::ToggleLogConsole => self.visible = !self.visible,
+            Hotkey::ScrollLogUp if self.visible => {
+                let lines = self
+                    .frozen
+                    .get_or_insert_with(|| console::recent(usize::MAX, 0));
+                let last = lines.len().saturating_sub(self.page.max(1));
+                self.scroll = (self.scroll + self.page.max(1)).min(last);
+                if self.scroll == 0 {
+                    self.frozen = None;
+                }
+            }
+            Hotkey::ScrollLogDown if self.visible => {
+                self.scroll = self.scroll.saturating_sub(self.page.max(1));
+                if self.scroll == 0 {
+                    self.frozen = None;
+                }
+            }
+            Hotkey::FollowLog if self.visible => {
+                self.scroll = 0;
+                self.frozen = None;
+            }
+            // Paging keys do nothing while the console is closed
+            Hotkey::ScrollLogUp | Hotkey::ScrollLogDown | Hotkey::FollowLog => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Darkens the window and writes as many log lines as fit, colored by level, with a
+    /// status line at the bottom.
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        (width, height): (u32, u32),
+    ) -> Result<(), Error> {
+        let scale = (height / 320).max(1);
+        let (char_width, line_height) = ((GLYPH_WIDTH + 1) * scale, (GLYPH_HEIGHT + 1) * scale);
+        let columns = (width / char_width) as usize;
+        self.page = (height / line_height).saturating_sub(1) as usize;
+
+        let lines = match &self.frozen {
+            Some(frozen) => {
+                let end = frozen.len().saturating_sub(self.scroll);
+                frozen[end.saturating_sub(self.page)..end].to_vec()
+            }
+            None => console::recent(self.page, 0),
+        };
+        let status = match &self.frozen {
+            Some(_) => format!(
+                "-- paused, {} lines up: PageUp/PageDown to scroll, End to follow --",
+                self.scroll
+            ),
+            None => "-- log, PageUp to pause and scroll, ` to close --".to_string(),
+        };
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(BACKGROUND);
+        let result = canvas.fill_rect(None);
+        canvas.set_blend_mode(BlendMode::None);
+        result.map_err(|e| anyhow!("Failed to draw log console: {}", e))?;
+
+        for (row, line) in lines.iter().enumerate() {
+            let y = (row as u32 * line_height) as i32;
+            draw_text(
+                canvas,
+                &line.text,
+                y,
+                columns,
+                scale,
+                level_color(line.level),
+            )?;
+        }
+        let y = (self.page as u32 * line_height) as i32;
+        draw_text(canvas, &status, y, columns, scale, STATUS_COLOR)
+    }
+}
+
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::ERROR => Color::RGB(0xFF, 0x50, 0x50),
+        Level::WARN => Color::RGB(0xFF, 0xD0, 0x40),
+        Level::INFO => Color::RGB(0x60, 0xE0, 0x60),
+        Level::DEBUG => Color::RGB(0x60, 0xB0, 0xFF),
+        Level::TRACE => Color::RGB(0xA0, 0xA0, 0xA0),
+    }
+}
+
+/// Draws the first `columns` characters of `text` on a line starting at `y`, one rectangle
+/// per run of lit pixels in a glyph row.
+pub(super) fn draw_text(
+    canvas: &mut Canvas<Window>,
+    text: &str,
+    y: i32,
+    columns: usize,
+    scale: u32,
+    color: Color,
+) -> Result<(), Error> {
+    let mut rects = Vec::new();
+    for (column, c) in text.chars().take(columns).enumerate() {
+        let left = column as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, bits) in font::glyph(c).iter().enumerate() {
+            let top = y + (row as u32 * scale) as i32;
+            let mut x = 0;
+            while x < GLYPH_WIDTH {
+                if bits & (0x10 >> x) == 0 {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < GLYPH_WIDTH && bits & (0x10 >> x) != 0 {
+                    x += 1;
+                }
+                rects.push(Rect::new(
+                    (left + start * scale) as i32,
+                    top,
+                    (x - start) * scale,
+                    scale,
+                ));
+            }
+        }
+    }
+    canvas.set_draw_color(color);
+    canvas
+        .fill_rects(&rects)
+        .map_err(|e| anyhow!("Failed to draw text: {}", e))
+}
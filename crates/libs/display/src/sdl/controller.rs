@@ -1,17 +1,94 @@
 use anyhow::{anyhow, Error};
-use display::sdl::window::CustomWindow;
-use sdl2::{rect::Point, AudioSubsystem, EventPump};
-use shared::data::math_2d::Math2d;
+use chip8::core::audio::Scope;
+use chip8::core::blend::{self, FrameBlend};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, Status};
+use chip8::core::plugin::Panel;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Texture, TextureCreator};
+use sdl2::video::WindowContext;
+use sdl2::{AudioSubsystem, EventPump};
+use shared::config::config::{ChipSettings, Crt, Grid, Palette, Rotation, Scaling, Viewport};
+use tracing::info;
 
-use super::emulator::Emulator;
+use super::console::LogConsole;
+use super::crt;
+use super::panels;
+use super::window::CustomWindow;
+
+const BYTES_PER_PIXEL: usize = 4;
+const LED_COLOR: Color = Color::RGB(0xFF, 0x30, 0x30);
+const SCOPE_COLOR: Color = Color::RGB(0x30, 0xFF, 0x30);
 
 pub struct Controller<'a> {
     window: &'a mut CustomWindow<'a>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    texture: Texture<'a>,
+    // Overlay matching the current viewport size, rebuilt when the window is resized
+    crt_overlay: Option<(Texture<'a>, u32, u32)>,
+    // Barrel distorted copy of the screen, the viewport size as well
+    crt_barrel: Option<(Texture<'a>, crt::Barrel)>,
+    crt: Crt,
+    grid: Grid,
+    scaling: Scaling,
+    rotation: Rotation,
+    letterbox_color: Color,
+    blend: Option<FrameBlend>,
+    sound_led: bool,
+    visual_bell: bool,
+    buzzing: bool,
+    // Audio signal plotted over the screen while `show_scope` is set
+    scope: Option<Scope>,
+    scope_samples: Vec<f32>,
+    show_scope: bool,
+    log_console: LogConsole,
+    // Plugin panels drawn over the screen
+    panels: Vec<Panel>,
+    needs_repaint: bool,
+    // Set when an overlay changed, so the next frame is presented even if no pixel changed
+    needs_present: bool,
 }
 
 impl<'a> Controller<'a> {
-    pub fn new(window: &'a mut CustomWindow<'a>) -> Self {
-        Self { window }
+    pub fn new(
+        window: &'a mut CustomWindow<'a>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        settings: &ChipSettings,
+    ) -> Result<Self, Error> {
+        let texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, window.win_w, window.win_h)
+            .map_err(|e| anyhow!("Failed to create screen texture: {}", e))?;
+        let letterbox = settings.letterbox_color;
+
+        Ok(Self {
+            window,
+            texture_creator,
+            texture,
+            crt_overlay: None,
+            crt_barrel: None,
+            crt: settings.crt.clone(),
+            grid: settings.grid.clone(),
+            scaling: settings.scaling,
+            rotation: settings.rotation,
+            letterbox_color: Color::RGBA(letterbox.r, letterbox.g, letterbox.b, letterbox.a),
+            blend: settings.motion_blur.then(FrameBlend::default),
+            sound_led: settings.sound_led,
+            visual_bell: settings.visual_bell,
+            buzzing: false,
+            scope: None,
+            scope_samples: Vec::new(),
+            show_scope: false,
+            log_console: LogConsole::default(),
+            panels: Vec::new(),
+            needs_repaint: true,
+            needs_present: true,
+        })
+    }
+
+    /// Gives the overlay toggled with the scope hotkey the signal of the audio sink.
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = Some(scope);
     }
 
     pub fn display_canvas(&mut self) {
@@ -26,7 +103,7 @@ impl<'a> Controller<'a> {
         self.window.sdl.event_pump().unwrap()
     }
 
-    pub fn get_window(&self) -> &CustomWindow {
+    pub fn get_window(&self) -> &CustomWindow<'a> {
         self.window
     }
 
@@ -36,4 +113,330 @@ impl<'a> Controller<'a> {
             .set_scale(self.window.scale as f32, self.window.scale as f32)
             .unwrap()
     }
+
+    /// Recolors every pixel and uploads the whole texture, used when the palette changes.
+    fn repaint(&mut self, display: &[u8]) -> Result<(), Error> {
+        for index in 0..display.len() {
+            self.paint_pixel(display, index);
+        }
+        let pitch = self.pitch();
+        self.texture
+            .update(None, &self.window.pixel_vec, pitch)
+            .map_err(|e| anyhow!("Failed to upload screen texture: {}", e))
+    }
+
+    /// Uploads only the bounding rectangle of the pixels the core reported as changed.
+    fn update_dirty(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let (mut min_x, mut min_y) = (usize::MAX, usize::MAX);
+        let (mut max_x, mut max_y) = (0, 0);
+        for &index in dirty {
+            let (x, y) = (index % SCREEN_WIDTH, index / SCREEN_WIDTH);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+            self.paint_pixel(display, index);
+        }
+
+        let rect = Rect::new(
+            min_x as i32,
+            min_y as i32,
+            (max_x - min_x + 1) as u32,
+            (max_y - min_y + 1) as u32,
+        );
+        let start = (min_y * SCREEN_WIDTH + min_x) * BYTES_PER_PIXEL;
+        let pitch = self.pitch();
+        self.texture
+            .update(rect, &self.window.pixel_vec[start..], pitch)
+            .map_err(|e| anyhow!("Failed to update screen texture: {}", e))
+    }
+
+    fn paint_pixel(&mut self, display: &[u8], index: usize) {
+        let color = match &self.blend {
+            Some(blend) => {
+                let color = blend.color(&self.window.palette.colors(), index);
+                Color::RGBA(color.r, color.g, color.b, color.a)
+            }
+            None => self.window.colors[display[index] as usize],
+        };
+        let offset = index * BYTES_PER_PIXEL;
+        self.window.pixel_vec[offset..offset + BYTES_PER_PIXEL]
+            .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+
+    /// Draws 1px lines between the CHIP-8 pixels of the screen placed at `viewport`, skipped
+    /// while the pixels are smaller than the configured minimum scale.
+    fn draw_grid(&mut self, viewport: Viewport, (columns, rows): (u32, u32)) -> Result<(), Error> {
+        let min_scale = self.grid.min_scale.max(1);
+        if viewport.width / columns < min_scale || viewport.height / rows < min_scale {
+            return Ok(());
+        }
+        let color = self.grid.color;
+        let canvas = &mut self.window.canvas;
+        canvas.set_draw_color(Color::RGBA(color.r, color.g, color.b, color.a));
+        let (left, top) = (viewport.x as i32, viewport.y as i32);
+        let right = (viewport.x + viewport.width) as i32 - 1;
+        let bottom = (viewport.y + viewport.height) as i32 - 1;
+        for column in 1..columns {
+            let x = left + (column * viewport.width / columns) as i32;
+            canvas
+                .draw_line((x, top), (x, bottom))
+                .map_err(|e| anyhow!("Failed to draw pixel grid: {}", e))?;
+        }
+        for row in 1..rows {
+            let y = top + (row * viewport.height / rows) as i32;
+            canvas
+                .draw_line((left, y), (right, y))
+                .map_err(|e| anyhow!("Failed to draw pixel grid: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Lights the sound LED, a square of one CHIP-8 pixel inset in the top right corner.
+    fn draw_sound_led(&mut self, viewport: Viewport) -> Result<(), Error> {
+        let size = (viewport.height / SCREEN_HEIGHT as u32).max(2);
+        let x = (viewport.x + viewport.width).saturating_sub(2 * size);
+        let canvas = &mut self.window.canvas;
+        canvas.set_draw_color(LED_COLOR);
+        canvas
+            .fill_rect(Rect::new(x as i32, (viewport.y + size) as i32, size, size))
+            .map_err(|e| anyhow!("Failed to draw sound LED: {}", e))
+    }
+
+    /// Outlines the whole window with a border one CHIP-8 pixel thick.
+    fn draw_border(
+        &mut self,
+        viewport: Viewport,
+        (width, height): (u32, u32),
+    ) -> Result<(), Error> {
+        let size = (viewport.height / SCREEN_HEIGHT as u32).max(2);
+        let canvas = &mut self.window.canvas;
+        canvas.set_draw_color(LED_COLOR);
+        canvas
+            .fill_rects(&[
+                Rect::new(0, 0, width, size),
+                Rect::new(0, height.saturating_sub(size) as i32, width, size),
+                Rect::new(0, 0, size, height),
+                Rect::new(width.saturating_sub(size) as i32, 0, size, height),
+            ])
+            .map_err(|e| anyhow!("Failed to draw window border: {}", e))
+    }
+
+    /// Plots the kept audio signal across the viewport, full scale spanning its height.
+    fn draw_scope(&mut self, viewport: Viewport) -> Result<(), Error> {
+        let Some(scope) = &self.scope else {
+            return Ok(());
+        };
+        scope.snapshot(&mut self.scope_samples);
+        if self.scope_samples.is_empty() || viewport.width == 0 {
+            return Ok(());
+        }
+        let middle = (viewport.y + viewport.height / 2) as f32;
+        let points: Vec<Point> = (0..viewport.width)
+            .map(|x| {
+                let index = x as usize * self.scope_samples.len() / viewport.width as usize;
+                let sample = self.scope_samples[index].clamp(-0.5, 0.5);
+                let y = middle - sample * viewport.height as f32;
+                Point::new((viewport.x + x) as i32, y as i32)
+            })
+            .collect();
+        let canvas = &mut self.window.canvas;
+        canvas.set_draw_color(SCOPE_COLOR);
+        canvas
+            .draw_lines(points.as_slice())
+            .map_err(|e| anyhow!("Failed to draw oscilloscope: {}", e))
+    }
+
+    /// Rebuilds the CRT overlay when the viewport size changed since it was generated.
+    fn refresh_crt_overlay(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        if matches!(self.crt_overlay, Some((_, w, h)) if (w, h) == (width, height)) {
+            return Ok(());
+        }
+        let mut overlay = self
+            .texture_creator
+            .create_texture_static(PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| anyhow!("Failed to create CRT overlay texture: {}", e))?;
+        let scale = (height / SCREEN_HEIGHT as u32).max(1);
+        overlay
+            .update(
+                None,
+                &crt::overlay_pixels(width, height, scale, &self.crt),
+                width as usize * BYTES_PER_PIXEL,
+            )
+            .map_err(|e| anyhow!("Failed to upload CRT overlay texture: {}", e))?;
+        overlay.set_blend_mode(BlendMode::Blend);
+        self.crt_overlay = Some((overlay, width, height));
+        Ok(())
+    }
+
+    /// Warps the screen into the barrel texture, rebuilt with its sampling map when the
+    /// viewport size changed.
+    fn refresh_crt_barrel(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        if !matches!(&self.crt_barrel, Some((_, barrel)) if barrel.size() == (width, height)) {
+            let texture = self
+                .texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+                .map_err(|e| anyhow!("Failed to create CRT barrel texture: {}", e))?;
+            let source = (self.window.win_w, self.window.win_h);
+            let barrel = crt::Barrel::new(width, height, source, self.crt.barrel_distortion);
+            self.crt_barrel = Some((texture, barrel));
+        }
+        if let Some((texture, barrel)) = &mut self.crt_barrel {
+            texture
+                .update(
+                    None,
+                    barrel.warp(&self.window.pixel_vec),
+                    width as usize * BYTES_PER_PIXEL,
+                )
+                .map_err(|e| anyhow!("Failed to upload CRT barrel texture: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn pitch(&self) -> usize {
+        self.window.win_w as usize * BYTES_PER_PIXEL
+    }
+}
+
+impl DisplayController for Controller<'_> {
+    /// Repaints only the changed pixels, warps them when the CRT effect bends the screen and
+    /// blends the optional CRT overlay on top. Frames changing neither the pixels nor an overlay
+    /// are not drawn at all.
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        let dirty = blend::repaint_list(self.blend.as_mut(), display, dirty);
+        if self.needs_repaint {
+            self.repaint(display)?;
+            self.needs_repaint = false;
+        } else if !dirty.is_empty() {
+            self.update_dirty(display, &dirty)?;
+        } else if !(self.needs_present || self.show_scope || self.log_console.is_visible()) {
+            // The scope and the log console change on their own, the rest is as last drawn
+            return Ok(());
+        }
+        self.needs_present = false;
+
+        // The window may have been resized or made fullscreen, place the screen every frame.
+        let output = self.window.canvas.output_size().map_err(|e| anyhow!(e))?;
+        let (screen_w, screen_h) = self
+            .rotation
+            .size((self.window.win_w as usize, self.window.win_h as usize));
+        let viewport = self
+            .scaling
+            .viewport((screen_w as u32, screen_h as u32), output);
+        // copy_ex rotates around the center of the unrotated destination rectangle
+        let (rect_w, rect_h) = self
+            .rotation
+            .size((viewport.width as usize, viewport.height as usize));
+        let rect = Rect::from_center(
+            (
+                (viewport.x + viewport.width / 2) as i32,
+                (viewport.y + viewport.height / 2) as i32,
+            ),
+            rect_w as u32,
+            rect_h as u32,
+        );
+        let angle = self.rotation.degrees() as f64;
+
+        let barrel = self.crt.enable && self.crt.barrel_distortion > 0.0;
+        if barrel {
+            self.refresh_crt_barrel(rect.width(), rect.height())?;
+        }
+        let screen = match &self.crt_barrel {
+            Some((texture, _)) if barrel => texture,
+            _ => &self.texture,
+        };
+        self.window.canvas.set_draw_color(self.letterbox_color);
+        self.window.canvas.clear();
+        self.window
+            .canvas
+            .copy_ex(screen, None, rect, angle, None, false, false)
+            .map_err(|e| anyhow!("Failed to copy screen texture: {}", e))?;
+        if self.grid.enable {
+            self.draw_grid(viewport, (screen_w as u32, screen_h as u32))?;
+        }
+        if self.crt.enable {
+            self.refresh_crt_overlay(rect.width(), rect.height())?;
+            if let Some((overlay, _, _)) = &self.crt_overlay {
+                self.window
+                    .canvas
+                    .copy_ex(overlay, None, rect, angle, None, false, false)
+                    .map_err(|e| anyhow!("Failed to copy CRT overlay texture: {}", e))?;
+            }
+        }
+        if self.sound_led && self.buzzing {
+            self.draw_sound_led(viewport)?;
+        }
+        if self.show_scope {
+            self.draw_scope(viewport)?;
+        }
+        if self.visual_bell && self.buzzing {
+            self.draw_border(viewport, output)?;
+        }
+        if !self.panels.is_empty() {
+            panels::draw_panels(&mut self.window.canvas, &self.panels, output)?;
+        }
+        if self.log_console.is_visible() {
+            self.log_console.draw(&mut self.window.canvas, output)?;
+        }
+        self.display_canvas();
+        Ok(())
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        self.needs_present = true;
+        if self.log_console.handle_hotkey(hotkey) {
+            return Ok(());
+        }
+        match hotkey {
+            Hotkey::CyclePalette => {
+                self.window.cycle_palette();
+                info!("Switched palette to {:?}", self.window.palette.preset);
+                self.needs_repaint = true;
+            }
+            Hotkey::ToggleCrt => {
+                self.crt.enable = !self.crt.enable;
+                info!("CRT effect enabled: {}", self.crt.enable);
+            }
+            Hotkey::ToggleGrid => {
+                self.grid.enable = !self.grid.enable;
+                info!("Pixel grid enabled: {}", self.grid.enable);
+            }
+            Hotkey::ToggleScope => {
+                self.show_scope = !self.show_scope;
+                info!("Oscilloscope enabled: {}", self.show_scope);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        let caption = status.caption(&self.window.title);
+        self.window.set_caption(&caption);
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        if on != self.buzzing && (self.sound_led || self.visual_bell) {
+            self.needs_present = true;
+        }
+        self.buzzing = on;
+        Ok(())
+    }
+
+    fn set_palette(&mut self, palette: &Palette) -> Result<(), Error> {
+        self.window.set_palette(palette.clone());
+        self.needs_repaint = true;
+        Ok(())
+    }
+
+    fn show_panels(&mut self, panels: &[Panel]) -> Result<(), Error> {
+        self.panels = panels.to_vec();
+        self.needs_present = true;
+        Ok(())
+    }
+
+    fn request_repaint(&mut self) {
+        self.needs_repaint = true;
+    }
 }
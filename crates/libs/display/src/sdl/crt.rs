@@ -0,0 +1,85 @@
+use shared::config::config::Crt;
+
+/// Builds the RGBA32 overlay blended over the scaled screen: black scanlines on the last
+/// physical row of every emulated row plus a radial vignette.
+pub fn overlay_pixels(width: u32, height: u32, scale: u32, crt: &Crt) -> Vec<u8> {
+    let scanline = crt.scanline_intensity.clamp(0.0, 1.0);
+    let vignette = crt.vignette_intensity.clamp(0.0, 1.0);
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+    let max_distance = center_x * center_x + center_y * center_y;
+
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let on_scanline = if scale > 1 {
+            y % scale == scale - 1
+        } else {
+            y % 2 == 1
+        };
+        let line_alpha = if on_scanline { scanline } else { 0.0 };
+
+        for x in 0..width {
+            let (dx, dy) = (x as f32 - center_x, y as f32 - center_y);
+            let edge_alpha = vignette * (dx * dx + dy * dy) / max_distance;
+            let alpha = 1.0 - (1.0 - line_alpha) * (1.0 - edge_alpha);
+            pixels.extend_from_slice(&[0, 0, 0, (alpha * 255.0) as u8]);
+        }
+    }
+    pixels
+}
+
+/// Barrel distortion of the screen, done on the CPU: every pixel of the `width` x `height`
+/// destination samples the source pixel its bulged position falls on, as the wgpu shader does.
+pub struct Barrel {
+    width: u32,
+    height: u32,
+    // Index of the source pixel sampled by every destination pixel, `None` past its edges
+    map: Vec<Option<u32>>,
+    pixels: Vec<u8>,
+}
+
+impl Barrel {
+    /// Maps a destination of `width` x `height` onto a `source` of the given size.
+    pub fn new(width: u32, height: u32, (source_w, source_h): (u32, u32), distortion: f32) -> Self {
+        let mut map = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                // Centered coordinates of the pixel center, -1 to 1 across the screen
+                let cx = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let cy = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+                let bulge = 1.0 + distortion * (cx * cx + cy * cy);
+                let (u, v) = ((cx * bulge + 1.0) / 2.0, (cy * bulge + 1.0) / 2.0);
+                map.push(
+                    ((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v)).then(|| {
+                        let sx = (u * source_w as f32) as u32;
+                        let sy = (v * source_h as f32) as u32;
+                        sy.min(source_h - 1) * source_w + sx.min(source_w - 1)
+                    }),
+                );
+            }
+        }
+        Self {
+            width,
+            height,
+            map,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Warps the RGBA32 `source`, pixels past its edges are black.
+    pub fn warp(&mut self, source: &[u8]) -> &[u8] {
+        for (pixel, index) in self.pixels.chunks_exact_mut(4).zip(&self.map) {
+            match index {
+                Some(index) => {
+                    let offset = *index as usize * 4;
+                    pixel.copy_from_slice(&source[offset..offset + 4]);
+                }
+                None => pixel.copy_from_slice(&[0, 0, 0, 0xFF]),
+            }
+        }
+        &self.pixels
+    }
+}
@@ -0,0 +1,112 @@
+/// 5x8 bitmap font for printable ASCII from 0x20 to 0x7E, taken from the public domain X11
+/// "misc-fixed" 5x8 font. Each glyph is 8 rows, the top one first, with the leftmost of the 5
+/// columns in bit 4.
+pub const GLYPHS: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04, 0x00], // !
+    [0x00, 0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00], // "
+    [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A, 0x00], // #
+    [0x04, 0x0E, 0x14, 0x0E, 0x05, 0x0E, 0x04, 0x00], // $
+    [0x00, 0x08, 0x0A, 0x04, 0x0A, 0x02, 0x00, 0x00], // %
+    [0x08, 0x14, 0x14, 0x08, 0x14, 0x14, 0x0A, 0x00], // &
+    [0x00, 0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00], // '
+    [0x00, 0x04, 0x08, 0x08, 0x08, 0x08, 0x04, 0x00], // (
+    [0x00, 0x08, 0x04, 0x04, 0x04, 0x04, 0x08, 0x00], // )
+    [0x00, 0x00, 0x12, 0x0C, 0x1E, 0x0C, 0x12, 0x00], // *
+    [0x00, 0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x04, 0x08], // ,
+    [0x00, 0x00, 0x00, 0x00, 0x1E, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x0E, 0x04], // .
+    [0x00, 0x02, 0x02, 0x04, 0x08, 0x10, 0x10, 0x00], // /
+    [0x00, 0x04, 0x0A, 0x0A, 0x0A, 0x0A, 0x04, 0x00], // 0
+    [0x00, 0x04, 0x0C, 0x04, 0x04, 0x04, 0x0E, 0x00], // 1
+    [0x00, 0x0C, 0x12, 0x02, 0x0C, 0x10, 0x1E, 0x00], // 2
+    [0x00, 0x1E, 0x04, 0x0C, 0x02, 0x12, 0x0C, 0x00], // 3
+    [0x00, 0x04, 0x0C, 0x14, 0x1E, 0x04, 0x04, 0x00], // 4
+    [0x00, 0x1E, 0x10, 0x1C, 0x02, 0x12, 0x0C, 0x00], // 5
+    [0x00, 0x0C, 0x10, 0x1C, 0x12, 0x12, 0x0C, 0x00], // 6
+    [0x00, 0x1E, 0x02, 0x04, 0x04, 0x08, 0x08, 0x00], // 7
+    [0x00, 0x0C, 0x12, 0x0C, 0x12, 0x12, 0x0C, 0x00], // 8
+    [0x00, 0x0C, 0x12, 0x12, 0x0E, 0x02, 0x0C, 0x00], // 9
+    [0x00, 0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x00, 0x06, 0x06, 0x00, 0x06, 0x04, 0x08], // ;
+    [0x00, 0x02, 0x04, 0x08, 0x08, 0x04, 0x02, 0x00], // <
+    [0x00, 0x00, 0x00, 0x1E, 0x00, 0x1E, 0x00, 0x00], // =
+    [0x00, 0x08, 0x04, 0x02, 0x02, 0x04, 0x08, 0x00], // >
+    [0x00, 0x04, 0x0A, 0x02, 0x04, 0x00, 0x04, 0x00], // ?
+    [0x06, 0x09, 0x13, 0x15, 0x15, 0x12, 0x08, 0x06], // @
+    [0x00, 0x0C, 0x12, 0x12, 0x1E, 0x12, 0x12, 0x00], // A
+    [0x00, 0x1C, 0x12, 0x1C, 0x12, 0x12, 0x1C, 0x00], // B
+    [0x00, 0x0C, 0x12, 0x10, 0x10, 0x12, 0x0C, 0x00], // C
+    [0x00, 0x1C, 0x12, 0x12, 0x12, 0x12, 0x1C, 0x00], // D
+    [0x00, 0x1E, 0x10, 0x1C, 0x10, 0x10, 0x1E, 0x00], // E
+    [0x00, 0x1E, 0x10, 0x1C, 0x10, 0x10, 0x10, 0x00], // F
+    [0x00, 0x0C, 0x12, 0x10, 0x16, 0x12, 0x0C, 0x00], // G
+    [0x00, 0x12, 0x12, 0x1E, 0x12, 0x12, 0x12, 0x00], // H
+    [0x00, 0x0E, 0x04, 0x04, 0x04, 0x04, 0x0E, 0x00], // I
+    [0x00, 0x0E, 0x04, 0x04, 0x04, 0x14, 0x08, 0x00], // J
+    [0x00, 0x12, 0x14, 0x18, 0x14, 0x14, 0x12, 0x00], // K
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1E, 0x00], // L
+    [0x00, 0x12, 0x1E, 0x1E, 0x12, 0x12, 0x12, 0x00], // M
+    [0x00, 0x12, 0x1A, 0x1E, 0x16, 0x16, 0x12, 0x00], // N
+    [0x00, 0x0C, 0x12, 0x12, 0x12, 0x12, 0x0C, 0x00], // O
+    [0x00, 0x1C, 0x12, 0x12, 0x1C, 0x10, 0x10, 0x00], // P
+    [0x00, 0x0C, 0x12, 0x12, 0x1A, 0x16, 0x0C, 0x02], // Q
+    [0x00, 0x1C, 0x12, 0x12, 0x1C, 0x12, 0x12, 0x00], // R
+    [0x00, 0x0C, 0x12, 0x08, 0x04, 0x12, 0x0C, 0x00], // S
+    [0x00, 0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // T
+    [0x00, 0x12, 0x12, 0x12, 0x12, 0x12, 0x0C, 0x00], // U
+    [0x00, 0x12, 0x12, 0x12, 0x12, 0x0C, 0x0C, 0x00], // V
+    [0x00, 0x12, 0x12, 0x12, 0x1E, 0x1E, 0x12, 0x00], // W
+    [0x00, 0x12, 0x12, 0x0C, 0x0C, 0x12, 0x12, 0x00], // X
+    [0x00, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x00], // Y
+    [0x00, 0x1E, 0x02, 0x04, 0x08, 0x10, 0x1E, 0x00], // Z
+    [0x00, 0x0E, 0x08, 0x08, 0x08, 0x08, 0x0E, 0x00], // [
+    [0x00, 0x10, 0x10, 0x08, 0x04, 0x02, 0x02, 0x00], // backslash
+    [0x00, 0x0E, 0x02, 0x02, 0x02, 0x02, 0x0E, 0x00], // ]
+    [0x00, 0x04, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1E], // _
+    [0x00, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x00, 0x0E, 0x12, 0x12, 0x0E, 0x00], // a
+    [0x00, 0x10, 0x10, 0x1C, 0x12, 0x12, 0x1C, 0x00], // b
+    [0x00, 0x00, 0x00, 0x06, 0x08, 0x08, 0x06, 0x00], // c
+    [0x00, 0x02, 0x02, 0x0E, 0x12, 0x12, 0x0E, 0x00], // d
+    [0x00, 0x00, 0x00, 0x0C, 0x16, 0x18, 0x0C, 0x00], // e
+    [0x00, 0x04, 0x0A, 0x08, 0x1C, 0x08, 0x08, 0x00], // f
+    [0x00, 0x00, 0x00, 0x0C, 0x12, 0x0E, 0x02, 0x0C], // g
+    [0x00, 0x10, 0x10, 0x1C, 0x12, 0x12, 0x12, 0x00], // h
+    [0x00, 0x04, 0x00, 0x0C, 0x04, 0x04, 0x0E, 0x00], // i
+    [0x00, 0x02, 0x00, 0x02, 0x02, 0x02, 0x0A, 0x04], // j
+    [0x00, 0x10, 0x10, 0x12, 0x1C, 0x12, 0x12, 0x00], // k
+    [0x00, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E, 0x00], // l
+    [0x00, 0x00, 0x00, 0x1A, 0x15, 0x15, 0x15, 0x00], // m
+    [0x00, 0x00, 0x00, 0x1C, 0x12, 0x12, 0x12, 0x00], // n
+    [0x00, 0x00, 0x00, 0x0C, 0x12, 0x12, 0x0C, 0x00], // o
+    [0x00, 0x00, 0x00, 0x1C, 0x12, 0x1C, 0x10, 0x10], // p
+    [0x00, 0x00, 0x00, 0x0E, 0x12, 0x0E, 0x02, 0x02], // q
+    [0x00, 0x00, 0x00, 0x14, 0x1A, 0x10, 0x10, 0x00], // r
+    [0x00, 0x00, 0x00, 0x06, 0x0C, 0x02, 0x0C, 0x00], // s
+    [0x00, 0x08, 0x08, 0x1C, 0x08, 0x0A, 0x04, 0x00], // t
+    [0x00, 0x00, 0x00, 0x12, 0x12, 0x12, 0x0E, 0x00], // u
+    [0x00, 0x00, 0x00, 0x0A, 0x0A, 0x0A, 0x04, 0x00], // v
+    [0x00, 0x00, 0x00, 0x11, 0x15, 0x15, 0x0A, 0x00], // w
+    [0x00, 0x00, 0x00, 0x12, 0x0C, 0x0C, 0x12, 0x00], // x
+    [0x00, 0x00, 0x00, 0x12, 0x12, 0x0E, 0x12, 0x0C], // y
+    [0x00, 0x00, 0x00, 0x1E, 0x04, 0x08, 0x1E, 0x00], // z
+    [0x06, 0x08, 0x04, 0x18, 0x04, 0x08, 0x06, 0x00], // {
+    [0x00, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x00], // |
+    [0x18, 0x04, 0x08, 0x06, 0x08, 0x04, 0x18, 0x00], // }
+    [0x00, 0x0A, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00], // ~
+];
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 8;
+
+/// Rows of the glyph of `c`, non-ASCII characters show as `?`.
+pub fn glyph(c: char) -> &'static [u8; 8] {
+    let index = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &GLYPHS[index]
+}
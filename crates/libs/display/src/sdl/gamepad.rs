@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Error};
+use chip8::core::controller::InputEvent;
+use chip8::core::input::EventQueue;
+use sdl2::controller::{Button, GameController};
+use sdl2::event::Event;
+use sdl2::{GameControllerSubsystem, JoystickSubsystem, Sdl};
+use shared::config::config::Gamepad;
+use tracing::{info, warn};
+
+struct Pad {
+    controller: GameController,
+    // Keys pressed through this pad, released when it is unplugged
+    held: [bool; 16],
+}
+
+/// Gamepads driving the keypad next to the keyboard. Pads are opened and closed as SDL reports
+/// them plugged in and out, including the ones connected at start.
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    joystick: JoystickSubsystem,
+    device: Option<String>,
+    buttons: HashMap<Button, u8>,
+    pads: HashMap<u32, Pad>,
+}
+
+impl Gamepads {
+    pub fn new(sdl: &Sdl, settings: &Gamepad) -> Result<Self, Error> {
+        let subsystem = sdl
+            .game_controller()
+            .map_err(|e| anyhow!("Failed to initialize SDL game controllers: {}", e))?;
+        let joystick = sdl
+            .joystick()
+            .map_err(|e| anyhow!("Failed to initialize SDL joysticks: {}", e))?;
+        let buttons = settings
+            .buttons
+            .iter()
+            .map(|(name, key)| {
+                let button = Button::from_string(name)
+                    .ok_or_else(|| anyhow!("Unknown gamepad button '{}'", name))?;
+                let key = key
+                    .to_digit(16)
+                    .ok_or_else(|| anyhow!("Invalid gamepad key '{}'", key))?;
+                Ok((button, key as u8))
+            })
+            .collect::<Result<_, Error>>()?;
+        if subsystem.num_joysticks().unwrap_or(0) == 0 {
+            info!("No gamepad connected, using the keyboard");
+        }
+        Ok(Self {
+            subsystem,
+            joystick,
+            device: settings.device.clone(),
+            buttons,
+            pads: HashMap::new(),
+        })
+    }
+
+    /// Whether the pad at `index` is the pinned one, any pad matches when none is pinned.
+    fn is_selected(&self, index: u32) -> bool {
+        let Some(device) = &self.device else {
+            return true;
+        };
+        let name = self.subsystem.name_for_index(index).ok();
+        let guid = self
+            .joystick
+            .device_guid(index)
+            .ok()
+            .map(|guid| guid.string());
+        name.as_deref() == Some(device.as_str())
+            || guid.is_some_and(|guid| guid.eq_ignore_ascii_case(device))
+    }
+
+    fn add(&mut self, index: u32) {
+        if !self.subsystem.is_game_controller(index) || !self.is_selected(index) {
+            return;
+        }
+        let guid = self
+            .joystick
+            .device_guid(index)
+            .map(|guid| guid.string())
+            .unwrap_or_default();
+        match self.subsystem.open(index) {
+            Ok(controller) => {
+                info!("Gamepad connected: {} ({})", controller.name(), guid);
+                self.pads.insert(
+                    controller.instance_id(),
+                    Pad {
+                        controller,
+                        held: [false; 16],
+                    },
+                );
+            }
+            Err(e) => warn!("Failed to open gamepad {}: {}", index, e),
+        }
+    }
+
+    fn remove(&mut self, id: u32, queue: &mut EventQueue) {
+        let Some(pad) = self.pads.remove(&id) else {
+            return;
+        };
+        info!("Gamepad disconnected: {}", pad.controller.name());
+        for key in (0..16).filter(|&key| pad.held[key as usize]) {
+            queue.push(InputEvent::KeyUp(key));
+        }
+        if self.pads.is_empty() {
+            info!("No gamepad left, using the keyboard");
+        }
+    }
+
+    fn button(&mut self, id: u32, button: Button, pressed: bool, queue: &mut EventQueue) {
+        let (Some(pad), Some(&key)) = (self.pads.get_mut(&id), self.buttons.get(&button)) else {
+            return;
+        };
+        pad.held[key as usize] = pressed;
+        queue.push(if pressed {
+            InputEvent::KeyDown(key)
+        } else {
+            InputEvent::KeyUp(key)
+        });
+    }
+
+    pub fn handle(&mut self, event: &Event, queue: &mut EventQueue) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => self.add(which),
+            Event::ControllerDeviceRemoved { which, .. } => self.remove(which, queue),
+            Event::ControllerButtonDown { which, button, .. } => {
+                self.button(which, button, true, queue)
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                self.button(which, button, false, queue)
+            }
+            _ => {}
+        }
+    }
+}
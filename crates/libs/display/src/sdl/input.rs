@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Error};
+use chip8::core::controller::{Hotkey, InputController, InputEvent};
+use chip8::core::input::EventQueue;
+use chip8::core::touch::{TouchPad, MOUSE_POINTER};
+
+use super::gamepad::Gamepads;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::{EventPump, Sdl};
+use shared::config::config::{ChipSettings, Player, Rotation, Turbo};
+
+/// `which` of the mouse events SDL synthesizes from touches, the touches are handled directly.
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+pub struct SdlInput {
+    event_pump: EventPump,
+    queue: EventQueue,
+    touch: Option<TouchPad>,
+    gamepads: Option<Gamepads>,
+    // Window size in the mouse coordinate space, to scale clicks to the touch grid
+    window_size: (u32, u32),
+}
+
+impl SdlInput {
+    pub fn new(sdl: &Sdl) -> Result<Self, Error> {
+        let event_pump = sdl
+            .event_pump()
+            .map_err(|e| anyhow!("Failed to obtain SDL event pump: {}", e))?;
+        Ok(Self {
+            event_pump,
+            queue: EventQueue::default(),
+            touch: None,
+            gamepads: None,
+            window_size: (1, 1),
+        })
+    }
+
+    pub fn set_gamepads(&mut self, gamepads: Gamepads) {
+        self.gamepads = Some(gamepads);
+    }
+
+    /// Moves the keypad to a player's keys, see `EventQueue::set_player`.
+    pub fn set_player(&mut self, player: &Player) -> Result<(), Error> {
+        self.queue.set_player(player)
+    }
+
+    /// Binds autofire keys, see `EventQueue::set_turbo`.
+    pub fn set_turbo(&mut self, bindings: &[Turbo]) -> Result<(), Error> {
+        self.queue.set_turbo(bindings)
+    }
+
+    /// Presses keys by touching or clicking the window, `window_size` is the current size of
+    /// the window, later resizes are followed.
+    pub fn set_touch_pad(&mut self, touch: TouchPad, window_size: (u32, u32)) {
+        self.touch = Some(touch);
+        self.window_size = window_size;
+    }
+
+    /// Remaps the direction keys for a rotated screen, see `input::rotate_key`.
+    pub fn set_key_rotation(&mut self, rotation: Rotation) {
+        self.queue.set_key_rotation(rotation);
+    }
+
+    /// Returns the character of single-character key names such as "Q" or "1". Scancodes are
+    /// named after the US layout, so the keypad stays on the same physical keys on any layout.
+    fn key_char(scancode: Scancode) -> Option<char> {
+        let name = scancode.name();
+        let mut chars = name.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    }
+}
+
+impl InputController for SdlInput {
+    fn set_keymap(&mut self, settings: &ChipSettings, rom: Option<&str>) -> Result<(), Error> {
+        self.queue.set_keymap(settings, rom)
+    }
+
+    fn poll(&mut self) -> Result<Vec<InputEvent>, Error> {
+        let queue = &mut self.queue;
+        for event in self.event_pump.poll_iter() {
+            if let Some(gamepads) = &mut self.gamepads {
+                gamepads.handle(&event, queue);
+            }
+            if let Some(touch) = &mut self.touch {
+                let window_size = self.window_size;
+                let mouse = |x: i32, y: i32| {
+                    (
+                        x as f32 / window_size.0.max(1) as f32,
+                        y as f32 / window_size.1.max(1) as f32,
+                    )
+                };
+                match event {
+                    Event::FingerDown {
+                        finger_id, x, y, ..
+                    } => touch.press(finger_id, x, y, queue),
+                    Event::FingerMotion {
+                        finger_id, x, y, ..
+                    } => touch.motion(finger_id, x, y, queue),
+                    Event::FingerUp { finger_id, .. } => touch.release(finger_id, queue),
+                    Event::MouseButtonDown {
+                        which,
+                        mouse_btn: MouseButton::Left,
+                        x,
+                        y,
+                        ..
+                    } if which != TOUCH_MOUSE_ID => {
+                        let (x, y) = mouse(x, y);
+                        touch.press(MOUSE_POINTER, x, y, queue);
+                    }
+                    Event::MouseMotion { which, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                        let (x, y) = mouse(x, y);
+                        touch.motion(MOUSE_POINTER, x, y, queue);
+                    }
+                    Event::MouseButtonUp {
+                        which,
+                        mouse_btn: MouseButton::Left,
+                        ..
+                    } if which != TOUCH_MOUSE_ID => touch.release(MOUSE_POINTER, queue),
+                    Event::Window {
+                        win_event: WindowEvent::SizeChanged(width, height),
+                        ..
+                    } => self.window_size = (width as u32, height as u32),
+                    _ => {}
+                }
+            }
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => queue.push(InputEvent::Quit),
+                Event::Window {
+                    win_event: WindowEvent::Exposed | WindowEvent::SizeChanged(..),
+                    ..
+                } => queue.push(InputEvent::Redraw),
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::PageUp | Keycode::PageDown | Keycode::End)),
+                    ..
+                } => queue.push(InputEvent::Hotkey(match keycode {
+                    Keycode::PageUp => Hotkey::ScrollLogUp,
+                    Keycode::PageDown => Hotkey::ScrollLogDown,
+                    _ => Hotkey::FollowLog,
+                })),
+                Event::KeyDown {
+                    keycode: Some(keycode @ (Keycode::F5 | Keycode::F9)),
+                    repeat: false,
+                    ..
+                } => queue.push(InputEvent::Hotkey(match keycode {
+                    Keycode::F5 => Hotkey::SaveToSlot,
+                    _ => Hotkey::LoadFromSlot,
+                })),
+                Event::KeyDown {
+                    scancode: Some(scancode),
+                    keymod,
+                    repeat,
+                    ..
+                } => {
+                    if let Some(key) = Self::key_char(scancode) {
+                        // Shift and a digit picks a save slot instead of pressing the keypad
+                        let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                        queue.push_key_with_shift(key, shift, true, repeat);
+                    }
+                }
+                Event::KeyUp {
+                    scancode: Some(scancode),
+                    ..
+                } => {
+                    if let Some(key) = Self::key_char(scancode) {
+                        queue.push_key(key, false, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+        queue.poll()
+    }
+}
@@ -1,2 +1,10 @@
+pub mod audio;
+pub mod console;
 pub mod context;
+pub mod controller;
+pub mod crt;
+pub mod font;
+pub mod gamepad;
+pub mod input;
+pub mod panels;
 pub mod window;
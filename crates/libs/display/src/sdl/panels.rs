@@ -0,0 +1,54 @@
+use anyhow::{anyhow, Error};
+use chip8::core::plugin::Panel;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+use super::console::draw_text;
+use super::font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+
+const BACKGROUND: Color = Color::RGBA(0x00, 0x00, 0x00, 0xB0);
+const TITLE_COLOR: Color = Color::RGB(0xFF, 0xFF, 0xFF);
+const LINE_COLOR: Color = Color::RGB(0x60, 0xE0, 0x60);
+
+/// Draws the plugin panels down the left side of the window, each a title and its lines on
+/// a dark box as wide as its longest line. Panels not fitting the window are cut off.
+pub fn draw_panels(
+    canvas: &mut Canvas<Window>,
+    panels: &[Panel],
+    (width, height): (u32, u32),
+) -> Result<(), Error> {
+    let scale = (height / 320).max(1);
+    let (char_width, line_height) = ((GLYPH_WIDTH + 1) * scale, (GLYPH_HEIGHT + 1) * scale);
+    let columns = (width / char_width) as usize;
+    let mut y = 0;
+    for panel in panels {
+        let rows = 1 + panel.lines.len() as u32;
+        let longest = panel
+            .lines
+            .iter()
+            .chain([&panel.title])
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(columns) as u32;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(BACKGROUND);
+        let result = canvas.fill_rect(Rect::new(0, y, longest * char_width, rows * line_height));
+        canvas.set_blend_mode(BlendMode::None);
+        result.map_err(|e| anyhow!("Failed to draw plugin panel: {}", e))?;
+
+        draw_text(canvas, &panel.title, y, columns, scale, TITLE_COLOR)?;
+        for line in &panel.lines {
+            y += line_height as i32;
+            draw_text(canvas, line, y, columns, scale, LINE_COLOR)?;
+        }
+        // A blank line between panels
+        y += 2 * line_height as i32;
+        if y >= height as i32 {
+            break;
+        }
+    }
+    Ok(())
+}
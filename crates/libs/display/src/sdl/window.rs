@@ -4,18 +4,20 @@ use sdl2::render::Canvas;
 use sdl2::surface::Surface;
 use sdl2::video::Window;
 use sdl2::Sdl;
-
-const TITLE: &str = "Chip-8 Emulator";
+use shared::config::config::{self, Palette, Rotation};
+use tracing::warn;
 
 pub struct CustomWindow<'a> {
     pub sdl: &'a Sdl,
     pub win_w: u32,
     pub win_h: u32,
     pub scale: u32,
+    pub title: String,
     pub canvas: Canvas<Window>,
     pub pixel_vec: Vec<u8>,
-    pub bg_color: Color,
-    pub pixel_color: Color,
+    pub palette: Palette,
+    /// SDL colors indexed by pixel value
+    pub colors: [Color; 4],
 }
 
 impl<'a> CustomWindow<'a> {
@@ -24,16 +26,19 @@ impl<'a> CustomWindow<'a> {
         win_w: u32,
         win_h: u32,
         scale: u32,
-        bg_color: Color,
-        pixel_color: Color,
+        title: &str,
+        palette: Palette,
+        rotation: Rotation,
     ) -> Self {
-        let win_w_scaled = win_w * scale;
-        let win_h_scaled = win_h * scale;
+        let (rotated_w, rotated_h) = rotation.size((win_w as usize, win_h as usize));
+        let win_w_scaled = rotated_w as u32 * scale;
+        let win_h_scaled = rotated_h as u32 * scale;
         let mut canvas = sdl
             .video()
             .unwrap()
-            .window(TITLE, win_w_scaled, win_h_scaled)
+            .window(title, win_w_scaled, win_h_scaled)
             .position_centered()
+            .resizable()
             .build()
             .unwrap()
             .into_canvas()
@@ -44,17 +49,48 @@ impl<'a> CustomWindow<'a> {
             canvas.window_mut().set_icon(win_icon);
         }
 
-        let pixel_vec = vec![0; win_w as usize * win_h as usize];
+        let colors = Self::sdl_colors(&palette);
+        let bg_color = colors[0];
+        // RGBA8888 staging buffer mirroring the screen texture, starts as background
+        let pixel_vec = [bg_color.r, bg_color.g, bg_color.b, bg_color.a]
+            .repeat(win_w as usize * win_h as usize);
 
         Self {
             sdl,
             win_w,
             win_h,
             scale,
+            title: title.to_string(),
             canvas,
             pixel_vec,
-            bg_color,
-            pixel_color,
+            palette,
+            colors,
+        }
+    }
+
+    /// Moves to the next built-in palette and refreshes the derived SDL colors.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.colors = Self::sdl_colors(&palette);
+        self.palette = palette;
+    }
+
+    pub fn cycle_palette(&mut self) {
+        self.palette.cycle_preset();
+        self.colors = Self::sdl_colors(&self.palette);
+    }
+
+    /// Shows `caption` in the title bar.
+    pub fn set_caption(&mut self, caption: &str) {
+        if let Err(e) = self.canvas.window_mut().set_title(caption) {
+            warn!("Failed to set window title: {}", e);
         }
     }
+
+    fn sdl_colors(palette: &Palette) -> [Color; 4] {
+        palette.colors().map(Self::sdl_color)
+    }
+
+    fn sdl_color(color: config::Color) -> Color {
+        Color::RGBA(color.r, color.g, color.b, color.a)
+    }
 }
@@ -0,0 +1,315 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::{RAM_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::plugin::{MachineView, Panel, Plugin};
+use tracing::info;
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, TypedFunc};
+
+/// Instructions a plugin may run per event before it is stopped, so a plugin stuck in a loop
+/// cannot freeze the emulator.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// The machine as last shown to the plugin and what the plugin produced.
+struct State {
+    name: String,
+    frame: u64,
+    ram: Vec<u8>,
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    dt: u8,
+    st: u8,
+    display: Vec<u8>,
+    panel: Option<Panel>,
+    export_path: PathBuf,
+    // Opened on the first export
+    export: Option<BufWriter<File>>,
+}
+
+impl State {
+    fn update(&mut self, machine: &MachineView) {
+        self.frame = machine.frame;
+        self.ram.copy_from_slice(machine.ram);
+        self.v = *machine.v;
+        self.i = machine.i;
+        self.pc = machine.pc;
+        self.dt = machine.dt;
+        self.st = machine.st;
+        self.display.copy_from_slice(machine.display);
+    }
+}
+
+/// Reads `len` bytes at `ptr` of the plugin's exported memory as text.
+fn read_text(caller: &mut Caller<'_, State>, ptr: i32, len: i32) -> Result<String, Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow!("The plugin exports no memory"))?;
+    let start = ptr as u32 as usize;
+    let bytes = memory
+        .data(&caller)
+        .get(start..start + len as u32 as usize)
+        .ok_or_else(|| anyhow!("The plugin passed text outside its memory"))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// A plugin compiled to WebAssembly, sandboxed by wasmtime: it sees the machine only through
+/// the read-only functions it imports from the `chip8` module and can only write its panel,
+/// its log lines and its export file. It exports `memory` and any of `init()`, `on_frame()`
+/// and `on_instruction(pc: i32, opcode: i32)`.
+pub struct WasmPlugin {
+    store: Store<State>,
+    on_frame: Option<TypedFunc<(), ()>>,
+    on_instruction: Option<TypedFunc<(i32, i32), ()>>,
+}
+
+impl WasmPlugin {
+    /// Loads the `.wasm` module, or `.wat` text, at `path` and runs its `init`. Its exports
+    /// are written to `export_path`.
+    pub fn load(path: &Path, export_path: PathBuf) -> Result<Self, Error> {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "plugin".to_string());
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine =
+            Engine::new(&config).map_err(|e| anyhow!("Failed to start the WASM engine: {}", e))?;
+        let module = Module::from_file(&engine, path)
+            .map_err(|e| anyhow!("Failed to load the plugin {}: {:#}", path.display(), e))?;
+        let linker = Self::linker(&engine)?;
+        let state = State {
+            name,
+            frame: 0,
+            ram: vec![0; RAM_SIZE],
+            v: [0; 16],
+            i: 0,
+            pc: 0,
+            dt: 0,
+            st: 0,
+            display: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            panel: None,
+            export_path,
+            export: None,
+        };
+        let mut store = Store::new(&engine, state);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| anyhow!("Failed to start the plugin {}: {:#}", path.display(), e))?;
+        let mut plugin = Self {
+            on_frame: Self::export(&instance, &mut store, "on_frame")?,
+            on_instruction: Self::export(&instance, &mut store, "on_instruction")?,
+            store,
+        };
+        if let Some(init) = Self::export::<(), ()>(&instance, &mut plugin.store, "init")? {
+            plugin.call(|store| init.call(store, ()))?;
+        }
+        info!("Loaded the plugin {}", path.display());
+        Ok(plugin)
+    }
+
+    /// Loads the `.wasm` and `.wat` plugins of `folder` in name order, each exporting to
+    /// `<name>.out` next to it.
+    pub fn load_folder(folder: &Path) -> Result<Vec<Self>, Error> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(folder)
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to read the plugin folder {}: {}",
+                    folder.display(),
+                    e
+                )
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "wasm" || extension == "wat")
+            })
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| Self::load(path, path.with_extension("out")))
+            .collect()
+    }
+
+    /// Looks up an exported function, `None` when the plugin leaves it out.
+    fn export<Params, Results>(
+        instance: &Instance,
+        store: &mut Store<State>,
+        name: &str,
+    ) -> Result<Option<TypedFunc<Params, Results>>, Error>
+    where
+        Params: wasmtime::WasmParams,
+        Results: wasmtime::WasmResults,
+    {
+        match instance.get_func(&mut *store, name) {
+            Some(func) => func
+                .typed(&*store)
+                .map(Some)
+                .map_err(|e| anyhow!("The plugin's {} has the wrong signature: {}", name, e)),
+            None => Ok(None),
+        }
+    }
+
+    /// The functions plugins import from the `chip8` module.
+    fn linker(engine: &Engine) -> Result<Linker<State>, Error> {
+        let mut linker = Linker::new(engine);
+        linker.func_wrap("chip8", "frame", |caller: Caller<'_, State>| {
+            caller.data().frame as i64
+        })?;
+        linker.func_wrap(
+            "chip8",
+            "peek",
+            |caller: Caller<'_, State>, address: i32| {
+                let ram = &caller.data().ram;
+                usize::try_from(address)
+                    .ok()
+                    .and_then(|address| ram.get(address))
+                    .map_or(-1, |&byte| byte as i32)
+            },
+        )?;
+        linker.func_wrap("chip8", "reg", |caller: Caller<'_, State>, n: i32| {
+            let v = &caller.data().v;
+            usize::try_from(n)
+                .ok()
+                .and_then(|n| v.get(n))
+                .map_or(-1, |&byte| byte as i32)
+        })?;
+        linker.func_wrap("chip8", "reg_i", |caller: Caller<'_, State>| {
+            caller.data().i as i32
+        })?;
+        linker.func_wrap("chip8", "pc", |caller: Caller<'_, State>| {
+            caller.data().pc as i32
+        })?;
+        linker.func_wrap("chip8", "dt", |caller: Caller<'_, State>| {
+            caller.data().dt as i32
+        })?;
+        linker.func_wrap("chip8", "st", |caller: Caller<'_, State>| {
+            caller.data().st as i32
+        })?;
+        linker.func_wrap(
+            "chip8",
+            "pixel",
+            |caller: Caller<'_, State>, x: i32, y: i32| {
+                if !(0..SCREEN_WIDTH as i32).contains(&x) || !(0..SCREEN_HEIGHT as i32).contains(&y)
+                {
+                    return -1;
+                }
+                caller.data().display[y as usize * SCREEN_WIDTH + x as usize] as i32
+            },
+        )?;
+        linker.func_wrap("chip8", "panel_clear", |mut caller: Caller<'_, State>| {
+            let state = caller.data_mut();
+            state.panel = Some(Panel {
+                title: state.name.clone(),
+                lines: Vec::new(),
+            });
+        })?;
+        linker.func_wrap(
+            "chip8",
+            "panel_line",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| -> Result<(), Error> {
+                let line = read_text(&mut caller, ptr, len)?;
+                let state = caller.data_mut();
+                let title = state.name.clone();
+                state
+                    .panel
+                    .get_or_insert_with(|| Panel {
+                        title,
+                        lines: Vec::new(),
+                    })
+                    .lines
+                    .push(line);
+                Ok(())
+            },
+        )?;
+        linker.func_wrap(
+            "chip8",
+            "log",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| -> Result<(), Error> {
+                let text = read_text(&mut caller, ptr, len)?;
+                info!("Plugin {}: {}", caller.data().name, text);
+                Ok(())
+            },
+        )?;
+        linker.func_wrap(
+            "chip8",
+            "export",
+            |mut caller: Caller<'_, State>, ptr: i32, len: i32| -> Result<(), Error> {
+                let text = read_text(&mut caller, ptr, len)?;
+                let state = caller.data_mut();
+                if state.export.is_none() {
+                    let file = File::create(&state.export_path).map_err(|e| {
+                        anyhow!(
+                            "Failed to create the export {}: {}",
+                            state.export_path.display(),
+                            e
+                        )
+                    })?;
+                    info!(
+                        "Plugin {} exports to {}",
+                        state.name,
+                        state.export_path.display()
+                    );
+                    state.export = Some(BufWriter::new(file));
+                }
+                if let Some(export) = &mut state.export {
+                    export
+                        .write_all(text.as_bytes())
+                        .map_err(|e| anyhow!("Failed to write the export: {}", e))?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(linker)
+    }
+
+    /// Runs `call` with a fresh allowance of fuel.
+    fn call(
+        &mut self,
+        call: impl FnOnce(&mut Store<State>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        call(&mut self.store).map_err(|e| anyhow!("{:#}", e))
+    }
+}
+
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.store.data().name
+    }
+
+    fn wants_instructions(&self) -> bool {
+        self.on_instruction.is_some()
+    }
+
+    fn on_instruction(&mut self, machine: &MachineView, pc: u16, opcode: u16) -> Result<(), Error> {
+        let Some(on_instruction) = self.on_instruction.clone() else {
+            return Ok(());
+        };
+        self.store.data_mut().update(machine);
+        self.call(|store| on_instruction.call(store, (pc as i32, opcode as i32)))
+    }
+
+    fn on_frame(&mut self, machine: &MachineView) -> Result<(), Error> {
+        let Some(on_frame) = self.on_frame.clone() else {
+            return Ok(());
+        };
+        self.store.data_mut().update(machine);
+        self.call(|store| on_frame.call(store, ()))?;
+        if let Some(export) = &mut self.store.data_mut().export {
+            export
+                .flush()
+                .map_err(|e| anyhow!("Failed to write the export: {}", e))?;
+        }
+        Ok(())
+    }
+
+    fn panel(&self) -> Option<&Panel> {
+        self.store.data().panel.as_ref()
+    }
+}
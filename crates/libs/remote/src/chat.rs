@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use chip8::core::emulator::Emulator;
+use chip8::core::service::Service;
+use shared::config::config::Chat;
+use tracing::{info, warn};
+
+/// Wait before joining again after the server dropped the connection.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A message of the chat, who sent it and its text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub user: String,
+    pub text: String,
+}
+
+impl ChatMessage {
+    /// Reads a `PRIVMSG` line of an IRC server, e.g.
+    /// `:viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :up`, with or without Twitch's
+    /// `@tags` in front. `None` for every other line.
+    pub fn from_irc(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let line = match line.strip_prefix('@') {
+            Some(tagged) => tagged.split_once(' ')?.1,
+            None => line,
+        };
+        let (prefix, command) = line.strip_prefix(':')?.split_once(' ')?;
+        let (_channel, text) = command.strip_prefix("PRIVMSG ")?.split_once(" :")?;
+        let user = prefix.split('!').next()?;
+        Some(Self {
+            user: user.to_string(),
+            text: text.to_string(),
+        })
+    }
+}
+
+/// Twitch-plays voting: chat messages naming a command vote for its key, the first vote opens
+/// a window and when it closes the command with the most votes is held for a few frames.
+/// Every user has one vote per window, their last message counts; ties go to the command
+/// voted for first.
+pub struct ChatVote {
+    commands: HashMap<String, u8>,
+    window: u64,
+    hold: u64,
+    ballots: HashMap<String, u8>,
+    // Keys in the order of their first vote in the window, to break ties
+    order: Vec<u8>,
+    // Frame the open window closes on
+    closes: Option<u64>,
+    // Key pressed by the last vote and the frame it is released on
+    held: Option<(u8, u64)>,
+}
+
+impl ChatVote {
+    pub fn new(settings: &Chat) -> Result<Self, Error> {
+        let commands = settings
+            .commands
+            .iter()
+            .map(|(command, key)| {
+                let key = key.to_digit(16).ok_or_else(|| {
+                    anyhow!("Invalid key '{}' for the chat command '{}'", key, command)
+                })?;
+                Ok((command.to_lowercase(), key as u8))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            commands,
+            window: settings.vote_frames.max(1) as u64,
+            hold: settings.hold_frames.max(1) as u64,
+            ballots: HashMap::new(),
+            order: Vec::new(),
+            closes: None,
+            held: None,
+        })
+    }
+
+    /// Counts `message` as a vote when its text is a command, e.g. `up` or `!up`, received
+    /// at `frame`.
+    pub fn vote(&mut self, message: &ChatMessage, frame: u64) -> bool {
+        let text = message.text.trim().to_lowercase();
+        let command = text.strip_prefix('!').unwrap_or(&text);
+        let Some(&key) = self.commands.get(command) else {
+            return false;
+        };
+        self.ballots.insert(message.user.to_lowercase(), key);
+        if !self.order.contains(&key) {
+            self.order.push(key);
+        }
+        self.closes.get_or_insert(frame + self.window);
+        true
+    }
+
+    /// The key presses and releases due before running `frame`.
+    pub fn tick(&mut self, frame: u64) -> Vec<(u8, bool)> {
+        let mut changes = Vec::new();
+        if let Some((key, _)) = self.held.filter(|&(_, release)| frame >= release) {
+            changes.push((key, false));
+            self.held = None;
+        }
+        if self.closes.is_some_and(|closes| frame >= closes) {
+            if let Some((key, votes)) = self.winner() {
+                info!(
+                    "Chat voted for key {:X} with {} of {} votes",
+                    key,
+                    votes,
+                    self.ballots.len()
+                );
+                if let Some((held, _)) = self.held.take() {
+                    changes.push((held, false));
+                }
+                changes.push((key, true));
+                self.held = Some((key, frame + self.hold));
+            }
+            self.ballots.clear();
+            self.order.clear();
+            self.closes = None;
+        }
+        changes
+    }
+
+    /// Key with the most votes in the window and its votes.
+    fn winner(&self) -> Option<(u8, usize)> {
+        self.order
+            .iter()
+            .map(|&key| {
+                let votes = self.ballots.values().filter(|&&vote| vote == key).count();
+                (key, votes)
+            })
+            .fold(None, |best, (key, votes)| match best {
+                Some((_, most)) if most >= votes => best,
+                _ => Some((key, votes)),
+            })
+    }
+}
+
+/// Twitch-plays as a service: counts the chat messages received as votes and presses or
+/// releases the keys the votes are due to, before every frame.
+pub struct ChatBridge {
+    messages: Receiver<ChatMessage>,
+    vote: ChatVote,
+}
+
+impl ChatBridge {
+    /// Lets the chat messages coming out of `messages`, see `join`, vote for keys.
+    pub fn new(messages: Receiver<ChatMessage>, vote: ChatVote) -> Self {
+        Self { messages, vote }
+    }
+}
+
+impl Service for ChatBridge {
+    fn name(&self) -> &str {
+        "chat vote"
+    }
+
+    fn apply(&mut self, emulator: &mut Emulator) -> Result<(), Error> {
+        let frame = emulator.frame();
+        for message in self.messages.try_iter() {
+            self.vote.vote(&message, frame);
+        }
+        for (key, pressed) in self.vote.tick(frame) {
+            if pressed {
+                emulator.key_press(key)?;
+            } else {
+                emulator.key_release(key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Who joins which channel of an IRC server.
+struct Login {
+    server: String,
+    channel: String,
+    nick: String,
+    token: Option<String>,
+}
+
+/// Joins `channel` on the IRC server at `server`, e.g. Twitch chat on
+/// `irc.chat.twitch.tv:6667`, and returns the messages posted to it. Fails when the first
+/// connection does, later drops are retried every few seconds.
+pub fn join(
+    server: &str,
+    channel: &str,
+    nick: &str,
+    token: Option<&str>,
+) -> Result<Receiver<ChatMessage>, Error> {
+    let login = Login {
+        server: server.to_string(),
+        channel: channel.trim_start_matches('#').to_lowercase(),
+        nick: nick.to_string(),
+        token: token.map(str::to_string),
+    };
+    if login.channel.is_empty() {
+        return Err(anyhow!("No chat channel set"));
+    }
+    let stream = connect(&login)?;
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("chat".to_string())
+        .spawn(move || {
+            let mut stream = Some(stream);
+            loop {
+                if let Some(stream) = stream.take() {
+                    match forward(stream, &sender) {
+                        // The emulator is gone
+                        Ok(false) => return,
+                        Ok(true) => warn!("The chat server closed the connection"),
+                        Err(e) => warn!("{}", e),
+                    }
+                }
+                thread::sleep(RECONNECT_DELAY);
+                stream = connect(&login).map_err(|e| warn!("{}", e)).ok();
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the chat thread: {}", e))?;
+    Ok(receiver)
+}
+
+fn connect(login: &Login) -> Result<TcpStream, Error> {
+    let mut stream = TcpStream::connect(&login.server)
+        .map_err(|e| anyhow!("Failed to connect to {}: {}", login.server, e))?;
+    let mut hello = String::new();
+    if let Some(token) = &login.token {
+        hello.push_str(&format!("PASS {}\r\n", token));
+    }
+    hello.push_str(&format!(
+        "NICK {}\r\nJOIN #{}\r\n",
+        login.nick, login.channel
+    ));
+    stream
+        .write_all(hello.as_bytes())
+        .map_err(|e| anyhow!("Failed to join the chat: {}", e))?;
+    info!("Reading chat #{} on {}", login.channel, login.server);
+    Ok(stream)
+}
+
+/// Sends the chat messages read from `stream` on until it ends, answering the server's
+/// pings. `false` once the receiver is gone.
+fn forward(stream: TcpStream, sender: &Sender<ChatMessage>) -> Result<bool, Error> {
+    let mut writer = stream
+        .try_clone()
+        .map_err(|e| anyhow!("Failed to set up the chat connection: {}", e))?;
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| anyhow!("Lost the chat connection: {}", e))?;
+        if let Some(payload) = line.strip_prefix("PING ") {
+            writer
+                .write_all(format!("PONG {}\r\n", payload).as_bytes())
+                .map_err(|e| anyhow!("Lost the chat connection: {}", e))?;
+        } else if let Some(message) = ChatMessage::from_irc(&line) {
+            if sender.send(message).is_err() {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,466 @@
+use std::io::{BufRead, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::service::Service;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use tungstenite::Message;
+
+/// Bytes `memory` reads when no length is given.
+const DEFAULT_LENGTH: u16 = 16;
+/// Instructions one `step` runs at most. The steps run between two frames, a larger count
+/// would hold up the display and input loop.
+pub const MAX_STEP: u32 = 10_000;
+/// How long a client thread waits for a message before sending the replies and frames queued
+/// for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A command of the remote control API, e.g. `{"command": "step", "count": 10}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Loads a ROM file and starts it from power-on
+    Load {
+        path: String,
+    },
+    /// Loads the ROM in `bytes`, named `name` in captions and per-ROM settings
+    Upload {
+        #[serde(default)]
+        name: Option<String>,
+        bytes: Vec<u8>,
+    },
+    Pause,
+    Resume,
+    /// Runs `count` instructions, up to `MAX_STEP`, pausing first
+    Step {
+        #[serde(default = "default_count")]
+        count: u32,
+    },
+    /// Presses or releases a key, 0 to 15
+    Key {
+        key: u8,
+        pressed: bool,
+    },
+    Registers,
+    /// The color index of every pixel, row by row
+    Display,
+    Memory {
+        address: u16,
+        #[serde(default = "default_length")]
+        length: u16,
+    },
+    /// Sends the pixels changed by every frame from now on, starting with the whole screen
+    Subscribe,
+    Unsubscribe,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_length() -> u16 {
+    DEFAULT_LENGTH
+}
+
+/// A command as a client sends it, with an optional `id` repeated in the reply.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(default)]
+    id: Option<Value>,
+    #[serde(flatten)]
+    command: ControlCommand,
+}
+
+/// A command received from a client, with where its reply and frame updates go.
+#[derive(Debug, Clone)]
+pub struct ControlRequest {
+    /// Tells apart the clients connected at the same time
+    pub client: u64,
+    pub id: Option<Value>,
+    pub command: ControlCommand,
+    pub replies: Sender<String>,
+}
+
+impl ControlRequest {
+    /// Parses a JSON message of `client`.
+    pub fn parse(client: u64, text: &str, replies: Sender<String>) -> Result<Self, Error> {
+        let envelope: Envelope =
+            serde_json::from_str(text).map_err(|e| anyhow!("Invalid command: {}", e))?;
+        if let ControlCommand::Step { count } = envelope.command {
+            if count > MAX_STEP {
+                return Err(anyhow!(
+                    "Invalid command: a step runs at most {} instructions, got {}",
+                    MAX_STEP,
+                    count
+                ));
+            }
+        }
+        Ok(Self {
+            client,
+            id: envelope.id,
+            command: envelope.command,
+            replies,
+        })
+    }
+
+    /// Sends the result of the command, an object of its fields or `{"error": "..."}`, with
+    /// the `id` of the request. Fails once the client is gone.
+    pub fn reply(&self, result: Result<Value, Error>) -> Result<(), Error> {
+        let mut reply = match result {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => serde_json::Map::new(),
+            Err(e) => {
+                let mut fields = serde_json::Map::new();
+                fields.insert("error".to_string(), Value::from(format!("{:#}", e)));
+                fields
+            }
+        };
+        if let Some(id) = &self.id {
+            reply.insert("id".to_string(), id.clone());
+        }
+        self.replies
+            .send(Value::Object(reply).to_string())
+            .map_err(|_| anyhow!("Remote control client {} disconnected", self.client))
+    }
+}
+
+/// The `frame` event listing the `(x, y, color index)` `pixels`, the changes since the last
+/// event or all the lit pixels when `full`. `None` for an update that changes nothing.
+pub fn frame_event(frame: u64, full: bool, pixels: &[(usize, usize, u8)]) -> Option<String> {
+    if !full && pixels.is_empty() {
+        return None;
+    }
+    Some(
+        json!({
+            "event": "frame",
+            "frame": frame,
+            "full": full,
+            "pixels": pixels,
+        })
+        .to_string(),
+    )
+}
+
+/// The remote control API as a service: runs the commands of every endpoint added, between
+/// frames, and sends the frame updates to the clients subscribed to them.
+#[derive(Default)]
+pub struct ControlHub {
+    // One receiver per endpoint, e.g. the WebSocket and the HTTP endpoint
+    sources: Vec<Receiver<ControlRequest>>,
+    subscribers: Vec<(u64, Sender<String>)>,
+}
+
+impl ControlHub {
+    /// Also takes the commands coming out of `requests`.
+    pub fn add(&mut self, requests: Receiver<ControlRequest>) {
+        self.sources.push(requests);
+    }
+
+    fn command(
+        &mut self,
+        emulator: &mut Emulator,
+        cpu: &CpuController,
+        request: &ControlRequest,
+    ) -> Result<Value, Error> {
+        let ok = json!({ "ok": true });
+        match request.command {
+            ControlCommand::Load { ref path } => {
+                emulator.start_rom_file(path)?;
+                Ok(ok)
+            }
+            ControlCommand::Upload {
+                ref name,
+                ref bytes,
+            } => {
+                info!("Loading a {} byte ROM sent by a remote client", bytes.len());
+                emulator.start_rom(bytes.clone(), name.clone())?;
+                Ok(ok)
+            }
+            ControlCommand::Pause => {
+                emulator.set_paused(true);
+                Ok(ok)
+            }
+            ControlCommand::Resume => {
+                emulator.set_paused(false);
+                Ok(ok)
+            }
+            ControlCommand::Step { count } => {
+                emulator.set_paused(true);
+                for _ in 0..count {
+                    cpu.tick(emulator)?;
+                }
+                Ok(registers(emulator))
+            }
+            ControlCommand::Key { key, pressed } => {
+                if pressed {
+                    emulator.take_over();
+                    emulator.key_press(key)?;
+                } else {
+                    emulator.key_release(key)?;
+                }
+                Ok(ok)
+            }
+            ControlCommand::Registers => Ok(registers(emulator)),
+            ControlCommand::Display => Ok(json!({
+                "width": SCREEN_WIDTH,
+                "height": SCREEN_HEIGHT,
+                "pixels": &emulator.get_display()[..],
+            })),
+            ControlCommand::Memory { address, length } => Ok(json!({
+                "address": address,
+                "bytes": emulator.read_ram(address, length as usize)?,
+            })),
+            ControlCommand::Subscribe => {
+                // Brings the other subscribers up to date, the updates of all of them then
+                // start from the screen sent whole to this one
+                self.publish_frame(emulator);
+                emulator.take_display_delta();
+                self.unsubscribe(request.client);
+                let lit: Vec<_> = (emulator.get_display().iter().enumerate())
+                    .filter(|&(_, &color)| color != 0)
+                    .map(|(index, &color)| (index % SCREEN_WIDTH, index / SCREEN_WIDTH, color))
+                    .collect();
+                if let Some(event) = frame_event(emulator.frame(), true, &lit) {
+                    request
+                        .replies
+                        .send(event)
+                        .map_err(|_| anyhow!("Remote control client disconnected"))?;
+                }
+                self.subscribers
+                    .push((request.client, request.replies.clone()));
+                Ok(ok)
+            }
+            ControlCommand::Unsubscribe => {
+                self.unsubscribe(request.client);
+                Ok(ok)
+            }
+        }
+    }
+
+    fn unsubscribe(&mut self, client: u64) {
+        self.subscribers
+            .retain(|(subscriber, _)| *subscriber != client);
+    }
+
+    /// Sends the pixels changed since the previous call to the subscribers. The changes come
+    /// from `Emulator::take_display_delta`, without subscribers they are left to pile up.
+    fn publish_frame(&mut self, emulator: &mut Emulator) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let delta = emulator.take_display_delta();
+        if let Some(event) = frame_event(emulator.frame(), false, &delta) {
+            self.subscribers
+                .retain(|(_, subscriber)| subscriber.send(event.clone()).is_ok());
+        }
+    }
+}
+
+impl Service for ControlHub {
+    fn name(&self) -> &str {
+        "remote control"
+    }
+
+    /// Runs the commands received since the previous call and replies to them. `cpu` runs the
+    /// `step` commands.
+    fn poll(&mut self, emulator: &mut Emulator, cpu: &CpuController) -> Result<(), Error> {
+        let sources = std::mem::take(&mut self.sources);
+        for request in sources.iter().flat_map(|requests| requests.try_iter()) {
+            let result = self.command(emulator, cpu, &request);
+            if request.reply(result).is_err() {
+                self.unsubscribe(request.client);
+            }
+        }
+        self.sources = sources;
+        Ok(())
+    }
+
+    fn publish(&mut self, emulator: &mut Emulator) -> Result<(), Error> {
+        self.publish_frame(emulator);
+        Ok(())
+    }
+}
+
+/// The registers, timers and stack as a reply, for the debug protocol as well.
+pub(crate) fn registers(emulator: &Emulator) -> Value {
+    let machine = emulator.machine_view();
+    json!({
+        "pc": machine.pc,
+        "i": machine.i,
+        "v": machine.v,
+        "dt": machine.dt,
+        "st": machine.st,
+        "stack": emulator.get_stack(),
+        "paused": emulator.is_paused(),
+        "frame": machine.frame,
+    })
+}
+
+/// Serves one client speaking the remote control API a JSON message per line, reading
+/// commands from `reader` until it ends and writing replies and frames to `writer` on a thread
+/// of its own, which stops once a write fails. Keys the client still holds are released when
+/// it goes. The same for local sockets, pipes or a test's buffers.
+pub fn serve(
+    client: u64,
+    reader: impl BufRead,
+    mut writer: impl Write + Send + 'static,
+    requests: Sender<ControlRequest>,
+) {
+    let (replies, outgoing) = mpsc::channel::<String>();
+    let spawned = thread::Builder::new()
+        .name(format!("control-{}", client))
+        .spawn(move || {
+            for message in outgoing {
+                if writeln!(writer, "{}", message)
+                    .and_then(|_| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start the control client thread: {}", e);
+        return;
+    }
+    let mut held = [false; 16];
+    for line in reader.lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match ControlRequest::parse(client, &line, replies.clone()) {
+            Ok(request) => {
+                if let ControlCommand::Key { key, pressed } = request.command {
+                    if let Some(held) = held.get_mut(key as usize) {
+                        *held = pressed;
+                    }
+                }
+                if requests.send(request).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = replies.send(json!({ "error": e.to_string() }).to_string());
+            }
+        }
+    }
+    for key in (0..16).filter(|&key| held[key as usize]) {
+        let (replies, _) = mpsc::channel();
+        let release = ControlRequest {
+            client,
+            id: None,
+            command: ControlCommand::Key {
+                key,
+                pressed: false,
+            },
+            replies,
+        };
+        let _ = requests.send(release);
+    }
+}
+
+/// Starts a WebSocket endpoint on `address` where clients send JSON commands, which come out
+/// of the returned receiver for `ControlHub::add`. Each client is served on its own thread,
+/// keys it still holds are released when it disconnects.
+pub fn listen(address: &str) -> Result<Receiver<ControlRequest>, Error> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+    info!("Remote control listening on ws://{}", address);
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("remote-control".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let client = crate::next_client();
+                        thread::spawn(move || serve_websocket(client, stream, sender));
+                    }
+                    Err(e) => warn!("Failed to accept remote control connection: {}", e),
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the remote control thread: {}", e))?;
+    Ok(receiver)
+}
+
+fn serve_websocket(client: u64, stream: TcpStream, sender: Sender<ControlRequest>) {
+    let peer = stream
+        .peer_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Rejected remote control connection from {}: {}", peer, e);
+            return;
+        }
+    };
+    // Reads time out so the replies and frames queued for the client go out in between
+    if let Err(e) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        warn!("Failed to set up remote control client {}: {}", peer, e);
+        return;
+    }
+    info!("Remote control client {} connected", peer);
+
+    let (replies, outgoing) = mpsc::channel();
+    let mut held = [false; 16];
+    'connection: loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                match ControlRequest::parse(client, &text, replies.clone()) {
+                    Ok(request) => {
+                        if let ControlCommand::Key { key, pressed } = request.command {
+                            if let Some(held) = held.get_mut(key as usize) {
+                                *held = pressed;
+                            }
+                        }
+                        if sender.send(request).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        let reply = json!({ "error": e.to_string() }).to_string();
+                        let _ = replies.send(reply);
+                    }
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+        for message in outgoing.try_iter() {
+            if socket.send(Message::text(message)).is_err() {
+                break 'connection;
+            }
+        }
+    }
+
+    // Dropping the reply channel tells the emulator the client is gone
+    drop(outgoing);
+    for key in (0..16).filter(|&key| held[key as usize]) {
+        let (replies, _) = mpsc::channel();
+        let release = ControlRequest {
+            client,
+            id: None,
+            command: ControlCommand::Key {
+                key,
+                pressed: false,
+            },
+            replies,
+        };
+        let _ = sender.send(release);
+    }
+    info!("Remote control client {} disconnected", peer);
+}
@@ -0,0 +1,412 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::service::Service;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tracing::{info, warn};
+
+use crate::control::{registers, MAX_STEP};
+
+/// Invalid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// Valid JSON, but not a JSON-RPC request.
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The emulator failed to run the method, e.g. an address outside the RAM.
+pub const EMULATOR_ERROR: i64 = -32000;
+
+/// Bytes `read_memory` reads when no length is given.
+const DEFAULT_LENGTH: u16 = 16;
+
+/// A method of the debug protocol with its params, e.g.
+/// `{"jsonrpc": "2.0", "id": 1, "method": "step", "params": {"count": 10}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DebugMethod {
+    /// Replaces the breakpoints, execution pauses before running an instruction at one
+    SetBreakpoints {
+        addresses: Vec<u16>,
+    },
+    /// Resumes, running over the breakpoint execution stopped at
+    Continue,
+    Pause,
+    /// Runs `count` instructions, up to `MAX_STEP`, pausing first and ignoring breakpoints
+    Step {
+        #[serde(default = "default_count")]
+        count: u32,
+    },
+    Registers,
+    /// Sets `v0` to `vf`, `i`, `pc`, `dt` or `st`
+    SetRegister {
+        register: String,
+        value: u16,
+    },
+    ReadMemory {
+        address: u16,
+        #[serde(default = "default_length")]
+        length: u16,
+    },
+    WriteMemory {
+        address: u16,
+        bytes: Vec<u8>,
+    },
+}
+
+impl DebugMethod {
+    pub const NAMES: &'static [&'static str] = &[
+        "set_breakpoints",
+        "continue",
+        "pause",
+        "step",
+        "registers",
+        "set_register",
+        "read_memory",
+        "write_memory",
+    ];
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_length() -> u16 {
+    DEFAULT_LENGTH
+}
+
+/// A request received from a debugger, with where its response and the events go.
+#[derive(Debug, Clone)]
+pub struct DebugRequest {
+    /// Tells apart the debuggers connected at the same time
+    pub client: u64,
+    /// `None` for notifications, which get no response
+    pub id: Option<Value>,
+    pub method: DebugMethod,
+    pub replies: Sender<String>,
+}
+
+impl DebugRequest {
+    /// Parses a JSON-RPC message of `client`. A message that is not a valid request fails
+    /// with the error response to send back.
+    pub fn parse(client: u64, text: &str, replies: Sender<String>) -> Result<Self, String> {
+        let message: Value = serde_json::from_str(text)
+            .map_err(|e| error_response(None, PARSE_ERROR, &format!("Invalid JSON: {}", e)))?;
+        let id = message.get("id").cloned();
+        let invalid = |code, message: &str| error_response(id.clone(), code, message);
+        let Value::Object(mut fields) = message else {
+            return Err(invalid(INVALID_REQUEST, "Expected a request object"));
+        };
+        if fields.get("jsonrpc") != Some(&Value::from("2.0")) {
+            return Err(invalid(INVALID_REQUEST, "Expected \"jsonrpc\": \"2.0\""));
+        }
+        let Some(name) = fields
+            .get("method")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        else {
+            return Err(invalid(INVALID_REQUEST, "Expected a method name"));
+        };
+        if !DebugMethod::NAMES.contains(&name.as_str()) {
+            return Err(invalid(
+                METHOD_NOT_FOUND,
+                &format!("Unknown method '{}'", name),
+            ));
+        }
+        // The params are read as the fields of the method, next to its name
+        let mut method = match fields.remove("params") {
+            None | Some(Value::Null) => Map::new(),
+            Some(Value::Object(params)) => params,
+            Some(_) => return Err(invalid(INVALID_PARAMS, "Expected named params")),
+        };
+        method.insert("method".to_string(), Value::from(name));
+        let method = serde_json::from_value(Value::Object(method))
+            .map_err(|e| invalid(INVALID_PARAMS, &format!("Invalid params: {}", e)))?;
+        if let DebugMethod::Step { count } = method {
+            if count > MAX_STEP {
+                return Err(invalid(
+                    INVALID_PARAMS,
+                    &format!("A step runs at most {} instructions", MAX_STEP),
+                ));
+            }
+        }
+        Ok(Self {
+            client,
+            id,
+            method,
+            replies,
+        })
+    }
+
+    /// Sends the response to the request, nothing for a notification. Fails once the client
+    /// is gone.
+    pub fn respond(&self, result: Result<Value, Error>) -> Result<(), Error> {
+        let Some(id) = &self.id else {
+            return Ok(());
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+            Err(e) => error_response(Some(id.clone()), EMULATOR_ERROR, &format!("{:#}", e)),
+        };
+        self.replies
+            .send(response)
+            .map_err(|_| anyhow!("Debugger {} disconnected", self.client))
+    }
+}
+
+/// A JSON-RPC error response, `id` being null when the request's could not be read.
+pub fn error_response(id: Option<Value>, code: i64, message: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id.unwrap_or(Value::Null),
+        "error": { "code": code, "message": message },
+    })
+    .to_string()
+}
+
+/// A JSON-RPC notification sent to every debugger, e.g. `stopped`.
+pub fn event(method: &str, params: Value) -> String {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string()
+}
+
+/// The debug protocol as a service: runs the requests of every endpoint added, between frames,
+/// and tells every debugger that sent a request when execution stops or continues.
+#[derive(Default)]
+pub struct DebugHub {
+    sources: Vec<Receiver<DebugRequest>>,
+    debuggers: Vec<(u64, Sender<String>)>,
+    // Whether the emulator was paused when the debuggers were last told, `None` before that
+    paused: Option<bool>,
+}
+
+impl DebugHub {
+    /// Also takes the requests coming out of `requests`.
+    pub fn add(&mut self, requests: Receiver<DebugRequest>) {
+        self.sources.push(requests);
+    }
+
+    fn method(
+        &mut self,
+        emulator: &mut Emulator,
+        cpu: &CpuController,
+        method: &DebugMethod,
+    ) -> Result<Value, Error> {
+        match *method {
+            DebugMethod::SetBreakpoints { ref addresses } => {
+                emulator.set_breakpoints(addresses.iter().copied());
+                Ok(json!({ "breakpoints": emulator.breakpoints() }))
+            }
+            DebugMethod::Continue => {
+                emulator.set_paused(false);
+                self.report(emulator);
+                Ok(json!({}))
+            }
+            DebugMethod::Pause => {
+                emulator.set_paused(true);
+                self.report(emulator);
+                Ok(json!({}))
+            }
+            DebugMethod::Step { count } => {
+                emulator.set_paused(true);
+                self.report(emulator);
+                for _ in 0..count {
+                    cpu.tick(emulator)?;
+                }
+                let pc = emulator.get_pc();
+                self.event("stopped", json!({ "reason": "step", "pc": pc }));
+                Ok(registers(emulator))
+            }
+            DebugMethod::Registers => Ok(registers(emulator)),
+            DebugMethod::SetRegister {
+                ref register,
+                value,
+            } => {
+                let byte = || {
+                    u8::try_from(value)
+                        .map_err(|_| anyhow!("{} holds a byte, {} is too large", register, value))
+                };
+                match register.to_ascii_lowercase().as_str() {
+                    "i" => emulator.set_i(value),
+                    "pc" => emulator.set_pc(value),
+                    "dt" => emulator.set_dt(byte()?),
+                    "st" => emulator.set_st(byte()?),
+                    name => {
+                        let index = name
+                            .strip_prefix('v')
+                            .filter(|digit| digit.len() == 1)
+                            .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                            .ok_or_else(|| anyhow!("Unknown register '{}'", register))?;
+                        emulator.set_v(index, byte()?)?;
+                    }
+                }
+                Ok(registers(emulator))
+            }
+            DebugMethod::ReadMemory { address, length } => Ok(json!({
+                "address": address,
+                "bytes": emulator.read_ram(address, length as usize)?,
+            })),
+            DebugMethod::WriteMemory { address, ref bytes } => {
+                emulator.write_ram(address, bytes)?;
+                Ok(json!({}))
+            }
+        }
+    }
+
+    /// Tells the debuggers when execution stopped or continued since they were last told,
+    /// whether by them, a breakpoint, the pause hotkey or another client.
+    fn report(&mut self, emulator: &Emulator) {
+        let paused = emulator.is_paused();
+        if self.paused.replace(paused).is_none_or(|was| was == paused) {
+            return;
+        }
+        if paused {
+            let reason = match emulator.stopped_at() {
+                Some(_) => "breakpoint",
+                None => "pause",
+            };
+            let pc = emulator.get_pc();
+            self.event("stopped", json!({ "reason": reason, "pc": pc }));
+        } else {
+            self.event("continued", json!({}));
+        }
+    }
+
+    /// Sends an event to the debuggers, dropping the disconnected ones.
+    fn event(&mut self, method: &str, params: Value) {
+        if self.debuggers.is_empty() {
+            return;
+        }
+        let event = event(method, params);
+        self.debuggers
+            .retain(|(_, replies)| replies.send(event.clone()).is_ok());
+    }
+}
+
+impl Service for DebugHub {
+    fn name(&self) -> &str {
+        "debug protocol"
+    }
+
+    /// Runs the requests received since the previous call and responds to them. Every client
+    /// that sent a request receives the events.
+    fn poll(&mut self, emulator: &mut Emulator, cpu: &CpuController) -> Result<(), Error> {
+        self.report(emulator);
+        let sources = std::mem::take(&mut self.sources);
+        for request in sources.iter().flat_map(|requests| requests.try_iter()) {
+            if !self
+                .debuggers
+                .iter()
+                .any(|(client, _)| *client == request.client)
+            {
+                self.debuggers
+                    .push((request.client, request.replies.clone()));
+            }
+            let result = self.method(emulator, cpu, &request.method);
+            if request.respond(result).is_err() {
+                self.debuggers
+                    .retain(|(client, _)| *client != request.client);
+            }
+        }
+        self.sources = sources;
+        Ok(())
+    }
+
+    /// Reports a breakpoint hit by the frames just run.
+    fn publish(&mut self, emulator: &mut Emulator) -> Result<(), Error> {
+        self.report(emulator);
+        Ok(())
+    }
+}
+
+/// Serves one debugger speaking the protocol a message per line, reading requests from
+/// `reader` until it ends and writing responses and events to `writer` on a thread of its
+/// own, which stops once a write fails. The same for stdio, TCP or a test's pipes.
+pub fn serve(
+    client: u64,
+    reader: impl BufRead,
+    mut writer: impl Write + Send + 'static,
+    requests: Sender<DebugRequest>,
+) {
+    let (replies, outgoing) = mpsc::channel::<String>();
+    let spawned = thread::Builder::new()
+        .name(format!("debugger-{}", client))
+        .spawn(move || {
+            for message in outgoing {
+                if writeln!(writer, "{}", message)
+                    .and_then(|_| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start the debugger thread: {}", e);
+        return;
+    }
+    for line in reader.lines().map_while(Result::ok) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match DebugRequest::parse(client, &line, replies.clone()) {
+            Ok(request) => {
+                if requests.send(request).is_err() {
+                    return;
+                }
+            }
+            Err(response) => {
+                let _ = replies.send(response);
+            }
+        }
+    }
+}
+
+/// Starts a TCP endpoint on `address` for the debug protocol, one JSON-RPC message per line,
+/// whose requests come out of the returned receiver for `DebugHub::add`. Each debugger is
+/// served on its own thread.
+pub fn listen(address: &str) -> Result<Receiver<DebugRequest>, Error> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+    info!("Debug server listening on tcp://{}", address);
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("debug-server".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept a debugger connection: {}", e);
+                        continue;
+                    }
+                };
+                let writer = match stream.try_clone() {
+                    Ok(writer) => writer,
+                    Err(e) => {
+                        warn!("Failed to set up a debugger connection: {}", e);
+                        continue;
+                    }
+                };
+                let sender = sender.clone();
+                let client = crate::next_client();
+                thread::spawn(move || {
+                    let peer = stream
+                        .peer_addr()
+                        .map(|address| address.to_string())
+                        .unwrap_or_default();
+                    info!("Debugger {} connected", peer);
+                    serve(client, BufReader::new(&stream), writer, sender);
+                    // Fails the pending writes, so the emulator drops the debugger
+                    let _ = stream.shutdown(Shutdown::Both);
+                    info!("Debugger {} disconnected", peer);
+                });
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the debug server thread: {}", e))?;
+    Ok(receiver)
+}
@@ -0,0 +1,115 @@
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use chip8::core::controller::InputEvent;
+use serde::Deserialize;
+use tracing::{info, warn};
+use tungstenite::Message;
+
+/// A key event sent by a client, e.g. `{"key": "A", "pressed": true}`.
+#[derive(Deserialize)]
+struct KeyMessage {
+    key: Key,
+    pressed: bool,
+}
+
+/// CHIP-8 key given as a number from 0 to 15 or as a hex digit string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Key {
+    Index(u8),
+    Digit(String),
+}
+
+impl KeyMessage {
+    fn parse(text: &str) -> Result<InputEvent, Error> {
+        let message: KeyMessage =
+            serde_json::from_str(text).map_err(|e| anyhow!("Invalid key event: {}", e))?;
+        let key = match &message.key {
+            Key::Index(index) => Some(*index).filter(|index| *index <= 0xF),
+            Key::Digit(digit) => u8::from_str_radix(digit, 16)
+                .ok()
+                .filter(|_| digit.len() == 1),
+        }
+        .ok_or_else(|| anyhow!("Invalid key, expected 0-15 or a hex digit"))?;
+        Ok(if message.pressed {
+            InputEvent::KeyDown(key)
+        } else {
+            InputEvent::KeyUp(key)
+        })
+    }
+}
+
+/// Starts a WebSocket endpoint on `address` where clients send JSON key events, which come
+/// out of the returned receiver. Each client is served on its own thread, keys it still
+/// holds are released when it disconnects.
+pub fn listen(address: &str) -> Result<Receiver<InputEvent>, Error> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+    info!("Remote input listening on ws://{}", address);
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("remote-input".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        thread::spawn(move || serve(stream, sender));
+                    }
+                    Err(e) => warn!("Failed to accept remote input connection: {}", e),
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the remote input thread: {}", e))?;
+    Ok(receiver)
+}
+
+fn serve(stream: TcpStream, sender: Sender<InputEvent>) {
+    let peer = stream
+        .peer_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_default();
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Rejected remote input connection from {}: {}", peer, e);
+            return;
+        }
+    };
+    info!("Remote input client {} connected", peer);
+
+    let mut held = [false; 16];
+    loop {
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        match KeyMessage::parse(&text) {
+            Ok(event) => {
+                match event {
+                    InputEvent::KeyDown(key) => held[key as usize] = true,
+                    InputEvent::KeyUp(key) => held[key as usize] = false,
+                    _ => {}
+                }
+                if sender.send(event).is_err() {
+                    return;
+                }
+            }
+            Err(e) => {
+                let reply = serde_json::json!({ "error": e.to_string() }).to_string();
+                if socket.send(Message::text(reply)).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    for key in (0..16).filter(|&key| held[key as usize]) {
+        let _ = sender.send(InputEvent::KeyUp(key));
+    }
+    info!("Remote input client {} disconnected", peer);
+}
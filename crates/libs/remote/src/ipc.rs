@@ -0,0 +1,74 @@
+use std::env;
+use std::fs;
+use std::io::BufReader;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use interprocess::local_socket::{
+    prelude::*, GenericFilePath, GenericNamespaced, ListenerOptions, Name,
+};
+use tracing::{info, warn};
+
+use crate::control::{self, ControlRequest};
+
+/// Starts a local control endpoint taking the remote control commands, one JSON message per
+/// line, which come out of the returned receiver for `ControlHub::add`. A `name` with
+/// a path separator is a Unix socket file, any other names an abstract socket on Linux, a
+/// named pipe `\\.\pipe\<name>` on Windows and a socket in the temp folder elsewhere. Unlike
+/// the WebSocket it opens no network port.
+pub fn listen(name: &str) -> Result<Receiver<ControlRequest>, Error> {
+    let socket = socket_name(name)?;
+    let listener = ListenerOptions::new()
+        .name(socket)
+        .reclaim_name(true)
+        .create_sync()
+        .map_err(|e| anyhow!("Failed to listen on {}: {}", name, e))?;
+    info!("Local control listening on {}", name);
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("local-control".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let client = crate::next_client();
+                        thread::spawn(move || {
+                            info!("Local control client {} connected", client);
+                            let (reader, writer) = stream.split();
+                            control::serve(client, BufReader::new(reader), writer, sender);
+                            info!("Local control client {} disconnected", client);
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept local control connection: {}", e),
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the local control thread: {}", e))?;
+    Ok(receiver)
+}
+
+fn socket_name(name: &str) -> Result<Name<'static>, Error> {
+    if name.contains(['/', '\\']) {
+        // A socket file left behind by an instance that crashed would fail the bind
+        if fs::metadata(name).is_ok() {
+            let _ = fs::remove_file(name);
+        }
+        return name
+            .to_string()
+            .to_fs_name::<GenericFilePath>()
+            .map_err(|e| anyhow!("Invalid socket path {}: {}", name, e));
+    }
+    if GenericNamespaced::is_supported() {
+        return name
+            .to_string()
+            .to_ns_name::<GenericNamespaced>()
+            .map_err(|e| anyhow!("Invalid socket name {}: {}", name, e));
+    }
+    let path = env::temp_dir().join(format!("{}.sock", name));
+    let _ = fs::remove_file(&path);
+    path.clone()
+        .to_fs_name::<GenericFilePath>()
+        .map_err(|e| anyhow!("Invalid socket path {}: {}", path.display(), e))
+}
@@ -0,0 +1,20 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub mod chat;
+pub mod control;
+pub mod debug;
+pub mod input;
+pub mod ipc;
+pub mod metrics;
+pub mod netplay;
+#[cfg(feature = "discord")]
+pub mod presence;
+#[cfg(feature = "rest")]
+pub mod rest;
+
+/// A client id no other remote control client has had, so the WebSocket, the local socket
+/// and the HTTP endpoint can share the emulator without mixing up their replies.
+pub(crate) fn next_client() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
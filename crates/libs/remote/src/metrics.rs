@@ -0,0 +1,107 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use chip8::core::metrics::Metrics;
+use tracing::{info, warn};
+
+/// Starts an HTTP endpoint on `address` serving the metrics published into the returned
+/// handle at `/metrics`, in the Prometheus text format.
+pub fn serve(address: &str) -> Result<Arc<Mutex<Metrics>>, Error> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+    info!("Metrics served on http://{}/metrics", address);
+    let metrics = Arc::new(Mutex::new(Metrics::default()));
+    let shared = metrics.clone();
+    thread::Builder::new()
+        .name("metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream
+                    .map_err(|e| anyhow!("Failed to accept a metrics connection: {}", e))
+                    .and_then(|stream| respond(stream, &shared));
+                if let Err(e) = result {
+                    warn!("{}", e);
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the metrics thread: {}", e))?;
+    Ok(metrics)
+}
+
+fn respond(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> Result<(), Error> {
+    let mut request_line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut request_line)
+        .map_err(|e| anyhow!("Failed to read a metrics request: {}", e))?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+    let response = if path == "/metrics" {
+        let body = render(&metrics.lock().unwrap());
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream
+        .write_all(response.as_bytes())
+        .map_err(|e| anyhow!("Failed to answer a metrics request: {}", e))
+}
+
+/// Formats the metrics in the Prometheus text exposition format.
+fn render(metrics: &Metrics) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+        for (labels, value) in samples {
+            let _ = writeln!(out, "{name}{labels} {value}");
+        }
+    };
+    metric(
+        "chip8_fps",
+        "gauge",
+        "Frames presented per second.",
+        &[("", metrics.fps)],
+    );
+    metric(
+        "chip8_emulated_fps",
+        "gauge",
+        "Emulated 60Hz frames per second.",
+        &[("", metrics.emulated_fps)],
+    );
+    metric(
+        "chip8_instructions_per_second",
+        "gauge",
+        "CHIP-8 instructions executed per second.",
+        &[("", metrics.ips)],
+    );
+    metric(
+        "chip8_frame_time_seconds",
+        "gauge",
+        "Time between presented frames over the last 120 frames.",
+        &[
+            ("{quantile=\"0.5\"}", metrics.frame_time_p50.as_secs_f64()),
+            ("{quantile=\"0.95\"}", metrics.frame_time_p95.as_secs_f64()),
+            ("{quantile=\"0.99\"}", metrics.frame_time_p99.as_secs_f64()),
+        ],
+    );
+    metric(
+        "chip8_frames_total",
+        "counter",
+        "Frames presented since start.",
+        &[("", metrics.frames as f64)],
+    );
+    metric(
+        "chip8_instructions_total",
+        "counter",
+        "CHIP-8 instructions executed since start.",
+        &[("", metrics.instructions as f64)],
+    );
+    out
+}
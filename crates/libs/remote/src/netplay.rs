@@ -0,0 +1,134 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{anyhow, Error};
+use chip8::core::netplay::{Lockstep, PeerFrame, Session};
+use chip8::core::replay::Quirks;
+use tracing::info;
+
+/// Signature of the handshake, followed by the protocol version.
+const MAGIC: &[u8; 3] = b"C8N";
+const VERSION: u8 = 1;
+/// Magic, version, ROM hash, seed, quirks, cycles per frame and input delay.
+const HELLO_LEN: usize = 4 + 8 + 8 + 1 + 4 + 1;
+/// Frame, keypad bitmask and state hash.
+const FRAME_LEN: usize = 8 + 2 + 8;
+
+/// Waits for a player to join on `listener`, who must run the same ROM and takes over the
+/// rest of `session`.
+pub fn accept(listener: &TcpListener, session: Session) -> Result<Lockstep, Error> {
+    let (mut stream, peer) = listener
+        .accept()
+        .map_err(|e| anyhow!("Failed to accept a netplay connection: {}", e))?;
+    info!("Netplay peer {} connected", peer);
+    let guest = read_hello(&mut stream)?;
+    write_hello(&mut stream, session)?;
+    if guest.rom != session.rom {
+        return Err(anyhow!("The peer runs a different ROM"));
+    }
+    start(stream, session)
+}
+
+/// Joins the game hosted on `address`, returning with the host's session.
+pub fn connect(address: &str, session: Session) -> Result<Lockstep, Error> {
+    let mut stream = TcpStream::connect(address)
+        .map_err(|e| anyhow!("Failed to connect to {}: {}", address, e))?;
+    info!("Joined the netplay host {}", address);
+    write_hello(&mut stream, session)?;
+    let host = read_hello(&mut stream)?;
+    if host.rom != session.rom {
+        return Err(anyhow!("The host runs a different ROM"));
+    }
+    start(stream, host)
+}
+
+fn write_hello(stream: &mut TcpStream, session: Session) -> Result<(), Error> {
+    let mut bytes = [0; HELLO_LEN];
+    bytes[..3].copy_from_slice(MAGIC);
+    bytes[3] = VERSION;
+    bytes[4..12].copy_from_slice(&session.rom.to_le_bytes());
+    bytes[12..20].copy_from_slice(&session.seed.to_le_bytes());
+    bytes[20] = session.quirks.to_bits();
+    bytes[21..25].copy_from_slice(&session.cycles_per_frame.to_le_bytes());
+    bytes[25] = session.input_delay;
+    stream
+        .write_all(&bytes)
+        .map_err(|e| anyhow!("Failed to send the netplay handshake: {}", e))
+}
+
+fn read_hello(stream: &mut TcpStream) -> Result<Session, Error> {
+    let mut bytes = [0; HELLO_LEN];
+    stream
+        .read_exact(&mut bytes)
+        .map_err(|e| anyhow!("Failed to read the netplay handshake: {}", e))?;
+    if &bytes[..3] != MAGIC {
+        return Err(anyhow!("The peer is not a CHIP-8 netplay instance"));
+    }
+    if bytes[3] != VERSION {
+        return Err(anyhow!(
+            "The peer speaks netplay version {}, this instance {}",
+            bytes[3],
+            VERSION
+        ));
+    }
+    Ok(Session {
+        rom: u64::from_le_bytes(bytes[4..12].try_into().unwrap()),
+        seed: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+        quirks: Quirks::from_bits(bytes[20]),
+        cycles_per_frame: u32::from_le_bytes(bytes[21..25].try_into().unwrap()),
+        input_delay: bytes[25],
+    })
+}
+
+/// Runs the connection on two threads, one writing the frames the `Lockstep` sends and one
+/// reading the peer's. Either reports a lost connection as an error frame.
+fn start(stream: TcpStream, session: Session) -> Result<Lockstep, Error> {
+    // Every frame waits for the peer, send its keys at once
+    stream
+        .set_nodelay(true)
+        .map_err(|e| anyhow!("Failed to set up the netplay connection: {}", e))?;
+    let mut reader = stream
+        .try_clone()
+        .map_err(|e| anyhow!("Failed to set up the netplay connection: {}", e))?;
+    let mut writer = stream;
+    let (outgoing, to_send) = mpsc::channel::<PeerFrame>();
+    let (received, incoming) = mpsc::channel();
+
+    let lost = received.clone();
+    thread::Builder::new()
+        .name("netplay-send".to_string())
+        .spawn(move || {
+            for frame in to_send {
+                let mut bytes = [0; FRAME_LEN];
+                bytes[..8].copy_from_slice(&frame.frame.to_le_bytes());
+                bytes[8..10].copy_from_slice(&frame.keys.to_le_bytes());
+                bytes[10..].copy_from_slice(&frame.hash.to_le_bytes());
+                if let Err(e) = writer.write_all(&bytes) {
+                    let _ = lost.send(Err(anyhow!("Failed to send to the netplay peer: {}", e)));
+                    return;
+                }
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the netplay thread: {}", e))?;
+    thread::Builder::new()
+        .name("netplay-receive".to_string())
+        .spawn(move || loop {
+            let mut bytes = [0; FRAME_LEN];
+            if let Err(e) = reader.read_exact(&mut bytes) {
+                let _ = received.send(Err(anyhow!("The netplay peer disconnected: {}", e)));
+                return;
+            }
+            let frame = PeerFrame {
+                frame: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+                keys: u16::from_le_bytes(bytes[8..10].try_into().unwrap()),
+                hash: u64::from_le_bytes(bytes[10..].try_into().unwrap()),
+            };
+            if received.send(Ok(frame)).is_err() {
+                return;
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the netplay thread: {}", e))?;
+    Ok(Lockstep::new(session, outgoing, incoming))
+}
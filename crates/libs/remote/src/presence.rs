@@ -0,0 +1,94 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Error};
+use chip8::core::controller::Status;
+use discord_rich_presence::activity::{Activity, Timestamps};
+use discord_rich_presence::{DiscordIpc, DiscordIpcClient};
+use tracing::{debug, info, warn};
+
+/// Wait before trying again to reach a Discord client that is not running.
+const RETRY_DELAY: Duration = Duration::from_secs(15);
+
+/// Shows the ROM being played, for how long and whether it is paused as the Discord activity
+/// of the user, for the Discord application `client_id`. Statuses sent to the returned sender
+/// update it; while Discord is closed the latest one waits until it is started.
+pub fn start(client_id: &str) -> Result<Sender<Status>, Error> {
+    if client_id.is_empty() {
+        return Err(anyhow!(
+            "Set chip8.discord.client_id to the application id of a Discord app"
+        ));
+    }
+    let mut client = DiscordIpcClient::new(client_id);
+    let (sender, receiver) = mpsc::channel::<Status>();
+    thread::Builder::new()
+        .name("discord".to_string())
+        .spawn(move || {
+            let mut connected = false;
+            let mut pending = None;
+            // ROM played and the Unix time in milliseconds it started at
+            let mut started: Option<(Option<String>, i64)> = None;
+            loop {
+                match receiver.recv_timeout(RETRY_DELAY) {
+                    Ok(status) => pending = receiver.try_iter().last().or(Some(status)),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                let Some(status) = &pending else {
+                    continue;
+                };
+                if started.as_ref().is_none_or(|(rom, _)| *rom != status.rom) {
+                    started = Some((status.rom.clone(), unix_millis()));
+                }
+                if !connected {
+                    connected = match client.connect() {
+                        Ok(()) => {
+                            info!("Connected to Discord");
+                            true
+                        }
+                        Err(e) => {
+                            debug!("Discord is not reachable: {}", e);
+                            continue;
+                        }
+                    };
+                }
+                let start = started.as_ref().map_or(0, |(_, start)| *start);
+                match client.set_activity(activity(status, start)) {
+                    Ok(()) => pending = None,
+                    Err(e) => {
+                        warn!("Failed to update the Discord activity: {}", e);
+                        connected = false;
+                    }
+                }
+            }
+            if connected {
+                let _ = client.close();
+            }
+        })
+        .map_err(|e| anyhow!("Failed to start the Discord thread: {}", e))?;
+    Ok(sender)
+}
+
+/// The activity for `status`, counting the time played from `start` while running.
+fn activity(status: &Status, start: i64) -> Activity<'_> {
+    let details = status.rom.as_deref().unwrap_or("Choosing a ROM");
+    let activity = Activity::new().details(details);
+    if status.paused {
+        activity.state("Paused")
+    } else if status.speed > 1 {
+        activity
+            .state(format!("Playing at {}x", status.speed))
+            .timestamps(Timestamps::new().start(start))
+    } else {
+        activity
+            .state("Playing")
+            .timestamps(Timestamps::new().start(start))
+    }
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
@@ -0,0 +1,169 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use chip8::core::export;
+use serde::Deserialize;
+use serde_json::Value;
+use shared::config::config::Palette;
+use tracing::{info, warn};
+
+use crate::control::{ControlCommand, ControlRequest};
+
+/// How long a request waits for the emulator to run its command, it only answers between
+/// frames.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct Endpoint {
+    commands: Sender<ControlRequest>,
+    palette: Palette,
+    scale: u32,
+}
+
+#[derive(Deserialize)]
+struct RomQuery {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScreenshotQuery {
+    scale: Option<u32>,
+}
+
+/// Starts an HTTP endpoint on `address` for one-shot control with curl and the like, whose
+/// commands come out of the returned receiver for `ControlHub::add`:
+/// `POST /rom` loads the ROM in the body, `POST /pause` and `POST /resume`, `GET /state`
+/// replies with the registers and `GET /screenshot.png` with the screen, `scale` times the
+/// CHIP-8 resolution unless `?scale=` says otherwise.
+pub fn serve(
+    address: &str,
+    palette: Palette,
+    scale: u32,
+) -> Result<Receiver<ControlRequest>, Error> {
+    let listener = std::net::TcpListener::bind(address)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+    info!("REST control listening on http://{}", address);
+    let (commands, receiver) = mpsc::channel();
+    let endpoint = Arc::new(Endpoint {
+        commands,
+        palette,
+        scale,
+    });
+    let app = Router::new()
+        .route("/rom", post(load_rom))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/state", get(state))
+        .route("/screenshot.png", get(screenshot))
+        .with_state(endpoint);
+    // The emulator owns the main thread, the endpoint gets a runtime of its own
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .map_err(|e| anyhow!("Failed to start the REST control runtime: {}", e))?;
+    thread::Builder::new()
+        .name("remote-rest".to_string())
+        .spawn(move || {
+            runtime.block_on(async move {
+                match tokio::net::TcpListener::from_std(listener) {
+                    Ok(listener) => {
+                        if let Err(e) = axum::serve(listener, app).await {
+                            warn!("REST control stopped: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("Failed to start the REST control endpoint: {}", e),
+                }
+            })
+        })
+        .map_err(|e| anyhow!("Failed to start the REST control thread: {}", e))?;
+    Ok(receiver)
+}
+
+async fn load_rom(
+    State(endpoint): State<Arc<Endpoint>>,
+    Query(query): Query<RomQuery>,
+    body: Bytes,
+) -> Response {
+    let command = ControlCommand::Upload {
+        name: query.name,
+        bytes: body.to_vec(),
+    };
+    json(run(&endpoint, command).await)
+}
+
+async fn pause(State(endpoint): State<Arc<Endpoint>>) -> Response {
+    json(run(&endpoint, ControlCommand::Pause).await)
+}
+
+async fn resume(State(endpoint): State<Arc<Endpoint>>) -> Response {
+    json(run(&endpoint, ControlCommand::Resume).await)
+}
+
+async fn state(State(endpoint): State<Arc<Endpoint>>) -> Response {
+    json(run(&endpoint, ControlCommand::Registers).await)
+}
+
+async fn screenshot(
+    State(endpoint): State<Arc<Endpoint>>,
+    Query(query): Query<ScreenshotQuery>,
+) -> Response {
+    let png = run(&endpoint, ControlCommand::Display)
+        .await
+        .and_then(|reply| {
+            let pixels: Vec<u8> = serde_json::from_value(reply["pixels"].clone())
+                .map_err(|e| anyhow!("Invalid display reply: {}", e))?;
+            let scale = query.scale.unwrap_or(endpoint.scale);
+            export::encode_png(&pixels, scale, &endpoint.palette)
+        });
+    match png {
+        Ok(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        Err(e) => json(Err(e)),
+    }
+}
+
+/// Sends `command` to the emulator and waits for its reply, an error when the reply is
+/// `{"error": "..."}`.
+async fn run(endpoint: &Endpoint, command: ControlCommand) -> Result<Value, Error> {
+    let (replies, received) = mpsc::channel();
+    let request = ControlRequest {
+        client: crate::next_client(),
+        id: None,
+        command,
+        replies,
+    };
+    endpoint
+        .commands
+        .send(request)
+        .map_err(|_| anyhow!("The emulator has stopped"))?;
+    // The reply comes from the emulator thread through a blocking channel
+    let reply = tokio::task::spawn_blocking(move || received.recv_timeout(REPLY_TIMEOUT))
+        .await
+        .map_err(|e| anyhow!("Failed to wait for the emulator: {}", e))?
+        .map_err(|_| anyhow!("The emulator did not answer in time"))?;
+    let reply: Value =
+        serde_json::from_str(&reply).map_err(|e| anyhow!("Invalid emulator reply: {}", e))?;
+    match reply["error"].as_str() {
+        Some(error) => Err(anyhow!("{}", error)),
+        None => Ok(reply),
+    }
+}
+
+fn json(result: Result<Value, Error>) -> Response {
+    match result {
+        Ok(reply) => (StatusCode::OK, axum::Json(reply)).into_response(),
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            axum::Json(serde_json::json!({ "error": format!("{:#}", e) })),
+        )
+            .into_response(),
+    }
+}
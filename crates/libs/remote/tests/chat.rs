@@ -0,0 +1,85 @@
+use std::sync::mpsc;
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::emulator::Emulator;
+use remote::chat::{ChatBridge, ChatMessage, ChatVote};
+use shared::config::config::Chat;
+
+fn message(user: &str, text: &str) -> ChatMessage {
+    ChatMessage {
+        user: user.to_string(),
+        text: text.to_string(),
+    }
+}
+
+fn settings() -> Chat {
+    Chat {
+        vote_frames: 10,
+        hold_frames: 3,
+        ..Chat::default()
+    }
+}
+
+#[test]
+fn test_irc_messages() {
+    assert_eq!(
+        ChatMessage::from_irc(":viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #chip8 :!up\r"),
+        Some(message("viewer", "!up"))
+    );
+    assert_eq!(
+        ChatMessage::from_irc(
+            "@badge-info=;color=#FF0000;display-name=Viewer :viewer!viewer@viewer.tmi.twitch.tv \
+             PRIVMSG #chip8 :left : right"
+        ),
+        Some(message("viewer", "left : right"))
+    );
+    assert_eq!(ChatMessage::from_irc("PING :tmi.twitch.tv"), None);
+    assert_eq!(
+        ChatMessage::from_irc(":tmi.twitch.tv 001 justinfan31337 :Welcome, GLHF!"),
+        None
+    );
+}
+
+#[test]
+fn test_votes_pick_the_majority() {
+    let mut vote = ChatVote::new(&settings()).unwrap();
+    assert!(vote.vote(&message("a", "left"), 5));
+    assert!(vote.vote(&message("b", "!UP"), 6));
+    assert!(vote.vote(&message("c", "up"), 7));
+    // Only the last vote of a user counts, other messages none
+    assert!(vote.vote(&message("a", "up"), 8));
+    assert!(vote.vote(&message("a", "left"), 8));
+    assert!(!vote.vote(&message("d", "left please"), 9));
+
+    assert!(vote.tick(14).is_empty());
+    // The window opened by the first vote closes 10 frames later
+    assert_eq!(vote.tick(15), vec![(2, true)]);
+    assert!(vote.tick(17).is_empty());
+    assert_eq!(vote.tick(18), vec![(2, false)]);
+
+    // Ties go to the command voted for first
+    vote.vote(&message("a", "fire"), 20);
+    vote.vote(&message("b", "down"), 21);
+    assert_eq!(vote.tick(30), vec![(5, true)]);
+}
+
+#[test]
+fn test_chat_presses_keys() {
+    let mut emulator = Emulator::new(CHIP8::default());
+    let (sender, messages) = mpsc::channel();
+    let vote = ChatVote::new(&settings()).unwrap();
+    emulator.add_service(Box::new(ChatBridge::new(messages, vote)));
+    sender.send(message("viewer", "right")).unwrap();
+    for _ in 0..10 {
+        emulator.apply_services();
+        emulator.advance_frame();
+    }
+    assert!(!emulator.is_key_pressed(6).unwrap());
+    emulator.apply_services();
+    assert!(emulator.is_key_pressed(6).unwrap());
+    for _ in 0..3 {
+        emulator.advance_frame();
+    }
+    emulator.apply_services();
+    assert!(!emulator.is_key_pressed(6).unwrap());
+}
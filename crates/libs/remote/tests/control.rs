@@ -0,0 +1,198 @@
+use std::io::{Cursor, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use remote::control::{self, ControlHub, ControlRequest};
+use serde_json::Value;
+
+// LD V0 5, LD I 0x20A, DRW V0 V0 1, JP 0x208, then the one row sprite
+const PROGRAM: [u8; 11] = [
+    0x60, 0x05, 0xA2, 0x0A, 0xD0, 0x01, 0x12, 0x06, 0x00, 0x00, 0xC0,
+];
+
+struct Client {
+    commands: Sender<ControlRequest>,
+    replies: Sender<String>,
+    received: Receiver<String>,
+}
+
+impl Client {
+    /// Runs the command, returning everything sent to the client meanwhile.
+    fn messages(&self, emulator: &mut Emulator, text: &str) -> Vec<Value> {
+        let request = ControlRequest::parse(1, text, self.replies.clone()).unwrap();
+        self.commands.send(request).unwrap();
+        emulator.poll_services(&CpuController);
+        self.received
+            .try_iter()
+            .map(|message| serde_json::from_str(&message).unwrap())
+            .collect()
+    }
+
+    fn send(&self, emulator: &mut Emulator, text: &str) -> Value {
+        self.messages(emulator, text).pop().unwrap()
+    }
+}
+
+fn connect() -> (Emulator, Client) {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(&PROGRAM).unwrap();
+    let (commands, requests) = mpsc::channel();
+    let mut hub = ControlHub::default();
+    hub.add(requests);
+    emulator.add_service(Box::new(hub));
+    let (replies, received) = mpsc::channel();
+    let client = Client {
+        commands,
+        replies,
+        received,
+    };
+    (emulator, client)
+}
+
+#[test]
+fn test_step_and_inspect() {
+    let (mut emulator, client) = connect();
+    let registers = client.send(&mut emulator, r#"{"command": "step", "count": 2, "id": 7}"#);
+    assert_eq!(registers["pc"], 0x204);
+    assert_eq!(registers["i"], 0x20A);
+    assert_eq!(registers["v"][0], 5);
+    assert_eq!(registers["paused"], true);
+    assert_eq!(registers["id"], 7);
+
+    let memory = client.send(
+        &mut emulator,
+        r#"{"command": "memory", "address": 522, "length": 1}"#,
+    );
+    assert_eq!(memory["bytes"], serde_json::json!([0xC0]));
+    let error = client.send(
+        &mut emulator,
+        r#"{"command": "memory", "address": 4095, "length": 2}"#,
+    );
+    assert!(error["error"].as_str().unwrap().contains("outside the RAM"));
+    assert!(ControlRequest::parse(1, r#"{"command": "fly"}"#, client.replies.clone()).is_err());
+
+    client.send(
+        &mut emulator,
+        r#"{"command": "key", "key": 10, "pressed": true}"#,
+    );
+    assert!(emulator.is_key_pressed(10).unwrap());
+}
+
+#[test]
+fn test_frame_updates() {
+    let (mut emulator, client) = connect();
+    client.send(&mut emulator, r#"{"command": "step", "count": 2}"#);
+    // The whole screen comes first, blank so far
+    let messages = client.messages(&mut emulator, r#"{"command": "subscribe"}"#);
+    assert_eq!(messages[0]["full"], true);
+    assert_eq!(messages[0]["pixels"], serde_json::json!([]));
+    assert_eq!(messages[1]["ok"], true);
+
+    client.send(&mut emulator, r#"{"command": "step"}"#);
+    // The window presenting the frame first takes nothing from the update
+    assert_eq!(emulator.take_dirty().len(), 2);
+    emulator.publish_services();
+    let frame: Value = serde_json::from_str(&client.received.try_recv().unwrap()).unwrap();
+    assert_eq!(frame["event"], "frame");
+    assert_eq!(frame["full"], false);
+    assert_eq!(frame["pixels"], serde_json::json!([[5, 5, 1], [6, 5, 1]]));
+    emulator.publish_services();
+    assert!(client.received.try_recv().is_err());
+
+    client.send(&mut emulator, r#"{"command": "unsubscribe"}"#);
+    client.send(&mut emulator, r#"{"command": "step"}"#);
+    emulator.publish_services();
+    assert!(client.received.try_recv().is_err());
+    let error = client.send(
+        &mut emulator,
+        r#"{"command": "load", "path": "missing.ch8"}"#,
+    );
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to read ROM file"));
+}
+
+#[test]
+fn test_step_count_is_capped() {
+    let (replies, _) = mpsc::channel();
+    let step = |count: u32| {
+        let text = serde_json::json!({ "command": "step", "count": count }).to_string();
+        ControlRequest::parse(1, &text, replies.clone())
+    };
+    assert!(step(control::MAX_STEP).is_ok());
+    assert!(step(u32::MAX).unwrap_err().to_string().contains("at most"));
+}
+
+#[test]
+fn test_upload_and_display() {
+    let (mut emulator, client) = connect();
+    let upload = serde_json::json!({ "command": "upload", "name": "dot", "bytes": PROGRAM });
+    assert_eq!(client.send(&mut emulator, &upload.to_string())["ok"], true);
+    assert_eq!(emulator.get_rom_name(), Some("dot"));
+
+    client.send(&mut emulator, r#"{"command": "step", "count": 3}"#);
+    let display = client.send(&mut emulator, r#"{"command": "display"}"#);
+    assert_eq!(display["width"], 64);
+    let pixels = display["pixels"].as_array().unwrap();
+    assert_eq!(pixels.len(), 64 * 32);
+    assert_eq!(pixels[5 * 64 + 5], 1);
+    assert_eq!(pixels[5 * 64 + 7], 0);
+
+    // Too large for RAM, the loaded ROM stays
+    let upload = serde_json::json!({ "command": "upload", "bytes": vec![0; 4000] });
+    assert!(client.send(&mut emulator, &upload.to_string())["error"].is_string());
+    assert_eq!(emulator.get_rom(), PROGRAM);
+}
+
+/// The write half of a local socket, handing what is written to the test.
+struct Pipe(Sender<Vec<u8>>);
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = self.0.send(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_line_protocol() {
+    let (mut emulator, _) = connect();
+    let (commands, requests) = mpsc::channel();
+    let mut hub = ControlHub::default();
+    hub.add(requests);
+    emulator.add_service(Box::new(hub));
+    let (pipe, written) = mpsc::channel();
+    let input = concat!(
+        r#"{"command": "pause", "id": 1}"#,
+        "\nnot json\n\n",
+        r#"{"command": "key", "key": 3, "pressed": true}"#,
+        "\n",
+    );
+    // Ends at the end of the input, like a client hanging up
+    control::serve(4, Cursor::new(input), Pipe(pipe), commands);
+    emulator.poll_services(&CpuController);
+    assert!(emulator.is_paused());
+    // Released as the client left
+    assert!(!emulator.is_key_pressed(3).unwrap());
+
+    let mut output = Vec::new();
+    while output.iter().filter(|&&byte| byte == b'\n').count() < 3 {
+        output.extend(written.recv_timeout(Duration::from_secs(5)).unwrap());
+    }
+    let replies: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(replies[0]["error"].is_string());
+    assert_eq!(replies[1], serde_json::json!({ "ok": true, "id": 1 }));
+    assert_eq!(replies[2], serde_json::json!({ "ok": true }));
+}
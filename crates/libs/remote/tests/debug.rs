@@ -0,0 +1,185 @@
+use std::io::Cursor;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use chip8::core::chip8::CHIP8;
+use chip8::core::controller;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::testing::{CapturingAudio, NullDisplay, ScriptedInput};
+use remote::debug::{self, DebugHub, DebugRequest};
+use serde_json::Value;
+
+// LD V0 1, ADD V0 1, JP 0x202
+const PROGRAM: [u8; 6] = [0x60, 0x01, 0x70, 0x01, 0x12, 0x02];
+
+struct Debugger {
+    requests: Sender<DebugRequest>,
+    replies: Sender<String>,
+    received: Receiver<String>,
+}
+
+impl Debugger {
+    /// Runs the request, returning everything sent to the debugger meanwhile.
+    fn call(&self, emulator: &mut Emulator, text: &str) -> Vec<Value> {
+        let request = DebugRequest::parse(1, text, self.replies.clone()).unwrap();
+        self.requests.send(request).unwrap();
+        emulator.poll_services(&CpuController);
+        self.messages()
+    }
+
+    fn messages(&self) -> Vec<Value> {
+        self.received
+            .try_iter()
+            .map(|message| serde_json::from_str(&message).unwrap())
+            .collect()
+    }
+}
+
+fn attach() -> (Emulator, Debugger) {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.init_ram_from_bytes(&PROGRAM).unwrap();
+    let (requests, received_requests) = mpsc::channel();
+    let mut hub = DebugHub::default();
+    hub.add(received_requests);
+    emulator.add_service(Box::new(hub));
+    let (replies, received) = mpsc::channel();
+    let debugger = Debugger {
+        requests,
+        replies,
+        received,
+    };
+    (emulator, debugger)
+}
+
+#[test]
+fn test_breakpoints_stop_and_continue() {
+    let (mut emulator, debugger) = attach();
+    let cpu = CpuController;
+    let set = debugger.call(
+        &mut emulator,
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "set_breakpoints", "params": {"addresses": [516]}}"#,
+    );
+    assert_eq!(set[0]["result"]["breakpoints"], serde_json::json!([516]));
+
+    // Stops before the jump, the rest of the frame is skipped
+    cpu.step_frame(&mut emulator, 10).unwrap();
+    assert!(emulator.is_paused());
+    assert_eq!(emulator.get_pc(), 0x204);
+    assert_eq!(emulator.get_v(0).unwrap(), 2);
+    emulator.publish_services();
+    let stopped = debugger.messages();
+    assert_eq!(stopped[0]["method"], "stopped");
+    assert_eq!(stopped[0]["params"]["reason"], "breakpoint");
+
+    // Continuing runs over the breakpoint once and stops at it on the next loop
+    let continued = debugger.call(
+        &mut emulator,
+        r#"{"jsonrpc": "2.0", "id": 2, "method": "continue"}"#,
+    );
+    assert_eq!(continued[0]["method"], "continued");
+    assert_eq!(continued[1]["id"], 2);
+    cpu.step_frame(&mut emulator, 10).unwrap();
+    assert_eq!(emulator.get_pc(), 0x204);
+    assert_eq!(emulator.get_v(0).unwrap(), 3);
+    emulator.publish_services();
+    assert_eq!(debugger.messages()[0]["params"]["pc"], 0x204);
+
+    let registers = debugger.call(
+        &mut emulator,
+        r#"{"jsonrpc": "2.0", "id": 3, "method": "set_register", "params": {"register": "vf", "value": 9}}"#,
+    );
+    assert_eq!(registers[0]["result"]["v"][15], 9);
+    let memory = debugger.call(
+        &mut emulator,
+        r#"{"jsonrpc": "2.0", "id": 4, "method": "read_memory", "params": {"address": 4095, "length": 2}}"#,
+    );
+    assert_eq!(memory[0]["error"]["code"], debug::EMULATOR_ERROR);
+}
+
+#[test]
+fn test_frames_stopped_at_a_breakpoint_are_not_counted() {
+    let (mut emulator, debugger) = attach();
+    let (mut display, mut input, mut audio) = (
+        NullDisplay::default(),
+        ScriptedInput::new(),
+        CapturingAudio::new(),
+    );
+    let mut run_frame = |emulator: &mut Emulator| {
+        controller::run_frame(
+            emulator,
+            &CpuController,
+            10,
+            &mut display,
+            &mut input,
+            &mut audio,
+        )
+        .unwrap()
+    };
+    // The metrics time frames from the previous one
+    run_frame(&mut emulator);
+    debugger.call(
+        &mut emulator,
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "set_breakpoints", "params": {"addresses": [516]}}"#,
+    );
+    emulator.set_speed(4).unwrap();
+
+    // The first of the four frames due stops at the breakpoint
+    run_frame(&mut emulator);
+    assert!(emulator.is_paused());
+    assert_eq!(emulator.metrics().instructions, 10);
+}
+
+#[test]
+fn test_invalid_messages_get_error_responses() {
+    let (replies, _) = mpsc::channel();
+    let code = |text: &str| {
+        let response = DebugRequest::parse(1, text, replies.clone()).unwrap_err();
+        serde_json::from_str::<Value>(&response).unwrap()["error"]["code"].clone()
+    };
+    assert_eq!(code("{"), debug::PARSE_ERROR);
+    assert_eq!(
+        code(r#"{"id": 1, "method": "pause"}"#),
+        debug::INVALID_REQUEST
+    );
+    assert_eq!(
+        code(r#"{"jsonrpc": "2.0", "id": 1, "method": "fly"}"#),
+        debug::METHOD_NOT_FOUND
+    );
+    assert_eq!(
+        code(r#"{"jsonrpc": "2.0", "id": 1, "method": "step", "params": {"count": "many"}}"#),
+        debug::INVALID_PARAMS
+    );
+    // A count this large would stall the frames for minutes
+    assert_eq!(
+        code(r#"{"jsonrpc": "2.0", "id": 1, "method": "step", "params": {"count": 4000000000}}"#),
+        debug::INVALID_PARAMS
+    );
+
+    // Over a line transport the error goes straight back, the valid request on to the emulator
+    let (writer, written) = mpsc::channel();
+    let (requests, received) = mpsc::channel();
+    let input = "nope\n{\"jsonrpc\": \"2.0\", \"method\": \"pause\"}\n";
+    debug::serve(1, Cursor::new(input), ChannelWriter(writer), requests);
+    assert!(received.try_recv().unwrap().id.is_none());
+    let response: Vec<u8> =
+        std::iter::from_fn(|| written.recv_timeout(std::time::Duration::from_secs(1)).ok())
+            .take_while(|&byte| byte != b'\n')
+            .collect();
+    assert!(String::from_utf8(response).unwrap().contains("-32700"));
+}
+
+/// Forwards written bytes to a channel, to read what `debug::serve` writes from its thread.
+struct ChannelWriter(Sender<u8>);
+
+impl std::io::Write for ChannelWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        for &byte in bytes {
+            let _ = self.0.send(byte);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use std::net::TcpListener;
+use std::thread;
+
+use anyhow::Error;
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::netplay::Session;
+use remote::netplay;
+
+// RND V0 FF, SKNP 5, ADD V1 1, JP 0x200
+const PROGRAM: [u8; 8] = [0xC0, 0xFF, 0xE5, 0xA1, 0x71, 0x01, 0x12, 0x00];
+
+fn emulator(seed: u64) -> Emulator {
+    let mut emulator = Emulator::new(CHIP8::default());
+    emulator.set_seed(seed);
+    emulator.init_ram_from_bytes(&PROGRAM).unwrap();
+    emulator
+}
+
+/// Connects an emulator seeded with 1 as the host to one seeded with 2.
+fn connect(input_delay: u8) -> (Emulator, Emulator) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let host = thread::spawn(move || {
+        let mut host = emulator(1);
+        let session = Session::new(&host, 10, input_delay);
+        host.start_netplay(netplay::accept(&listener, session).unwrap())
+            .unwrap();
+        host
+    });
+    let mut guest = emulator(2);
+    let session = Session::new(&guest, 20, 0);
+    guest
+        .start_netplay(netplay::connect(&address, session).unwrap())
+        .unwrap();
+    (host.join().unwrap(), guest)
+}
+
+/// Runs the next frame once the peer's keys for it arrived.
+fn step(emulator: &mut Emulator) -> Result<(), Error> {
+    while !emulator.apply_netplay()? {
+        thread::yield_now();
+    }
+    CpuController.step_frame(emulator, 10)
+}
+
+#[test]
+fn test_lockstep_shares_keys_and_seed() {
+    let (mut host, mut guest) = connect(2);
+    assert_eq!(guest.get_seed(), 1);
+    assert_eq!(guest.cycles_per_frame(), Some(10));
+
+    guest.key_press(5).unwrap();
+    for frame in 0..4 {
+        let guest_thread = thread::spawn(move || {
+            step(&mut guest).unwrap();
+            guest
+        });
+        step(&mut host).unwrap();
+        guest = guest_thread.join().unwrap();
+        assert_eq!(host.state_hash(), guest.state_hash(), "frame {}", frame);
+        // The guest's press reaches both keypads two frames after it was sent
+        assert_eq!(host.is_key_pressed(5).unwrap(), frame >= 2);
+        assert_eq!(guest.is_key_pressed(5).unwrap(), frame >= 2);
+    }
+    assert_eq!(host.get_v(0).unwrap(), guest.get_v(0).unwrap());
+}
+
+#[test]
+fn test_desync_is_reported() {
+    let (mut host, mut guest) = connect(0);
+    guest.set_to_ram(0x300, 1).unwrap();
+    let guest_thread = thread::spawn(move || step(&mut guest));
+    let host_error = step(&mut host).unwrap_err();
+    let guest_error = guest_thread.join().unwrap().unwrap_err();
+    assert!(host_error.to_string().contains("desync"));
+    assert!(guest_error.to_string().contains("desync"));
+}
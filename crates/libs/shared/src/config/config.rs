@@ -1,35 +1,55 @@
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
+use arc_swap::ArcSwap;
 use lazy_static::lazy_static;
 
+use super::dirs;
 use super::environment::Environment;
 use super::error::ConfigError;
+use super::migrate::migrate;
+use super::profile;
+use super::schema::describe;
 use crate::helper::renderer::render_string;
 use crate::logger::logger;
 
 lazy_static! {
-    static ref DEFAULT_FOLDER: PathBuf = PathBuf::from("config");
+    pub(crate) static ref DEFAULT_FOLDER: PathBuf = dirs::config_folder();
+}
+
+/// Prefix of the environment variables overriding config fields, the rest of the name is the
+/// field path split by `__`, e.g. `CHIP8__CHIP8__CYCLES_PER_FRAME=20`.
+pub const ENV_PREFIX: &str = "CHIP8__";
+
+/// A config reloaded from disk together with the one it replaces.
+pub struct ConfigUpdate {
+    pub previous: Config,
+    pub current: Config,
 }
 
 /// Main application configuration structure.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Config {
+    /// Format version of the file, older files are upgraded while loading
+    #[serde(default)]
+    pub version: u64,
     pub app: App,
     pub logger: Logger,
     pub chip8: ChipSettings,
 }
 
 /// App configuration
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
 pub struct App {
     pub name: String,
 }
 
 /// Logger configuration
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
 pub struct Logger {
     pub enable: bool,
     #[serde(default)]
@@ -38,9 +58,17 @@ pub struct Logger {
     pub format: logger::Format,
     pub override_filter: Option<String>,
     pub file_appender: Option<LoggerFileAppender>,
+    /// Log lines kept for the log console of the SDL window, toggled with the backtick key. 0
+    /// turns the console off
+    #[serde(default = "default_console_lines")]
+    pub console_lines: usize,
+}
+
+fn default_console_lines() -> usize {
+    300
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
 pub struct LoggerFileAppender {
     pub enable: bool,
     #[serde(default)]
@@ -55,17 +83,214 @@ pub struct LoggerFileAppender {
 }
 
 /// ChipSettings configuration
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ChipSettings {
     pub scale: u32,
     pub cycles_per_frame: u32,
+    /// Window title, the frontends append the ROM name, pause state and speed to it
+    #[serde(default = "default_title")]
+    pub title: String,
+    #[serde(default)]
+    pub backend: DisplayBackend,
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub crt: Crt,
+    #[serde(default)]
+    pub grid: Grid,
+    /// Presents the average of the last two frames, smoothing XOR flicker
+    #[serde(default)]
+    pub motion_blur: bool,
+    /// Runs the emulation on the render thread instead of its own, the SDL and terminal
+    /// backends only
+    #[serde(default)]
+    pub single_thread: bool,
+    #[serde(default)]
+    pub audio: Audio,
+    /// Lights a small LED in the corner of the screen while the buzzer sounds
+    #[serde(default)]
+    pub sound_led: bool,
+    /// Flashes the window border, or shows a speaker icon, while the buzzer sounds
+    #[serde(default)]
+    pub visual_bell: bool,
+    #[serde(default)]
+    pub scaling: Scaling,
+    #[serde(default = "default_letterbox_color")]
+    pub letterbox_color: Color,
+    #[serde(default)]
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub rotate_keys: bool,
+    #[serde(default)]
+    pub keyboard_layout: KeyboardLayout,
+    #[serde(default)]
+    pub touch: Touch,
+    /// Autofire bindings per ROM, keyed by ROM file name without extension
+    #[serde(default)]
+    pub turbo: HashMap<String, Vec<Turbo>>,
+    #[serde(default)]
+    pub remote_input: RemoteInput,
+    #[serde(default)]
+    pub remote_control: RemoteControl,
+    #[serde(default)]
+    pub ipc_control: IpcControl,
+    #[serde(default)]
+    pub rest_control: RestControl,
+    #[serde(default)]
+    pub debug_server: DebugServer,
+    #[serde(default)]
+    pub netplay: Netplay,
+    #[serde(default)]
+    pub chat: Chat,
+    #[serde(default)]
+    pub discord: Discord,
+    /// Script run before every frame, a `.rhai` file, see the README for its API
+    #[serde(default)]
+    pub script: Option<String>,
+    #[serde(default)]
+    pub plugins: Plugins,
+    #[serde(default)]
+    pub dynarec: Dynarec,
+    #[serde(default)]
+    pub rewind: Rewind,
+    #[serde(default)]
+    pub metrics: MetricsEndpoint,
+    #[serde(default)]
+    pub gamepad: Gamepad,
+    /// Keypad assignments for instances sharing a keyboard, `player` picks this instance's
+    #[serde(default = "default_players")]
+    pub players: Vec<Player>,
+    #[serde(default = "default_player")]
+    pub player: usize,
+    #[serde(default)]
+    pub terminal_mode: TerminalMode,
     pub default_ch8_folder: String,
+    /// Folder the K hotkey saves `.c8r` input recordings to
+    #[serde(default = "default_recording_folder")]
+    pub recording_folder: String,
+    /// Folder the F5 and F9 hotkeys save and load the numbered save states in
+    #[serde(default = "default_save_slot_folder")]
+    pub save_slot_folder: String,
+    /// Folder of the ROM profiles, settings files named by the SHA-1 of the ROM they apply to
+    #[serde(default = "default_profiles_folder")]
+    pub profiles_folder: String,
+    /// Folder of the cheat files, YAML lists of cheats named by the SHA-1 of the ROM they apply to
+    #[serde(default = "default_cheats_folder")]
+    pub cheats_folder: String,
+    /// ROM database file adding to and updating the builtin one, titles and recommended
+    /// settings by SHA-1
+    #[serde(default = "default_rom_database")]
+    pub rom_database: String,
+    /// Folder of a CHIP-8 Archive checkout, whose `programs.json` is added to the ROM database
+    #[serde(default = "default_chip8_archive")]
+    pub chip8_archive: String,
+    /// Folder crash reports are written to when the emulator stops on an error
+    #[serde(default = "default_crash_folder")]
+    pub crash_folder: String,
+    /// Folder the mobile frontend saves the state of the running ROM to when it is suspended,
+    /// to resume from on the next start
+    #[serde(default = "default_autosave_folder")]
+    pub autosave_folder: String,
     pub st_equals_buzzer: bool,
     pub bit_shift_instructions_use_vy: bool,
     pub store_read_instructions_change_i: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+fn default_title() -> String {
+    "Chip-8 Emulator".to_string()
+}
+
+fn default_profiles_folder() -> String {
+    "profiles".to_string()
+}
+
+fn default_rom_database() -> String {
+    "romdb.yaml".to_string()
+}
+
+fn default_chip8_archive() -> String {
+    "chip8Archive".to_string()
+}
+
+fn default_cheats_folder() -> String {
+    "cheats".to_string()
+}
+
+fn default_crash_folder() -> String {
+    "crashes".to_string()
+}
+
+fn default_autosave_folder() -> String {
+    "saves".to_string()
+}
+
+fn default_save_slot_folder() -> String {
+    "slots".to_string()
+}
+
+fn default_recording_folder() -> String {
+    "recordings".to_string()
+}
+
+/// Settings the running emulator picks up when the config file changes, see `watch`.
+const LIVE_SETTINGS: &[&str] = &[
+    "palette",
+    "cycles_per_frame",
+    "keyboard_layout",
+    "turbo",
+    "players",
+    "player",
+];
+
+impl ChipSettings {
+    /// Names the settings changed in `new` that only take effect after a restart, i.e. all
+    /// but `LIVE_SETTINGS` and the audio volume.
+    pub fn restart_required_changes(&self, new: &ChipSettings) -> Vec<String> {
+        let as_map = |settings: &ChipSettings| match serde_json::to_value(settings) {
+            Ok(serde_json::Value::Object(mut map)) => {
+                if let Some(serde_json::Value::Object(audio)) = map.get_mut("audio") {
+                    audio.remove("volume");
+                }
+                map
+            }
+            _ => serde_json::Map::new(),
+        };
+        let (old, new) = (as_map(self), as_map(new));
+        let mut changes: Vec<String> = new
+            .iter()
+            .filter(|(key, value)| {
+                !LIVE_SETTINGS.contains(&key.as_str()) && old.get(key.as_str()) != Some(value)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    /// Returns the input assignment of this instance, `None` when `player` has no entry.
+    pub fn active_player(&self) -> Option<&Player> {
+        self.player
+            .checked_sub(1)
+            .and_then(|index| self.players.get(index))
+    }
+
+    /// Returns the autofire bindings of `rom`, empty when it has none.
+    pub fn turbo_for(&self, rom: Option<&str>) -> &[Turbo] {
+        rom.and_then(|rom| self.turbo.get(rom))
+            .map_or(&[], |bindings| bindings.as_slice())
+    }
+}
+
+/// Autofire binding: while `host_key` is held, CHIP-8 key `chip8_key` (a hex digit) is
+/// pressed and released `rate` times per second, at most 30.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Turbo {
+    pub host_key: char,
+    pub chip8_key: char,
+    pub rate: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -73,35 +298,1092 @@ pub struct Color {
     pub a: u8,
 }
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    /// Averages two colors channel by channel.
+    pub fn mix(self, other: Color) -> Self {
+        let average = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        Self {
+            r: average(self.r, other.r),
+            g: average(self.g, other.g),
+            b: average(self.b, other.b),
+            a: average(self.a, other.a),
+        }
+    }
+}
+
+/// Palette configuration, the colors are used by the `custom` preset. XO-CHIP ROMs draw on
+/// two bit-planes: `foreground` shows plane 1, `foreground2` plane 2 and `blend` both.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub struct Palette {
+    #[serde(default)]
+    pub preset: PalettePreset,
+    pub background: Color,
+    pub foreground: Color,
+    #[serde(default = "default_foreground2")]
+    pub foreground2: Color,
+    #[serde(default = "default_blend")]
+    pub blend: Color,
+}
+
+fn default_foreground2() -> Color {
+    Color::rgb(0x55, 0x55, 0x55)
+}
+
+fn default_blend() -> Color {
+    Color::rgb(0xAA, 0xAA, 0xAA)
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            preset: PalettePreset::Custom,
+            background: Color::rgb(0, 0, 0),
+            foreground: Color::rgb(255, 255, 255),
+            foreground2: default_foreground2(),
+            blend: default_blend(),
+        }
+    }
+}
+
+impl Palette {
+    /// Returns the colors of the active preset, indexed by pixel value (the set plane bits).
+    pub fn colors(&self) -> [Color; 4] {
+        self.preset.colors().unwrap_or([
+            self.background,
+            self.foreground,
+            self.foreground2,
+            self.blend,
+        ])
+    }
+
+    /// Switches to the next preset, wrapping back to `custom` after the last built-in one.
+    pub fn cycle_preset(&mut self) {
+        let position = PalettePreset::ALL
+            .iter()
+            .position(|preset| *preset == self.preset)
+            .unwrap_or(0);
+        self.preset = PalettePreset::ALL[(position + 1) % PalettePreset::ALL.len()];
+    }
+}
+
+// Define an enumeration for the built-in palettes
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum PalettePreset {
+    #[serde(rename = "custom")]
+    #[default]
+    Custom,
+    #[serde(rename = "classic-green")]
+    ClassicGreen,
+    #[serde(rename = "amber")]
+    Amber,
+    #[serde(rename = "paper-white")]
+    PaperWhite,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 4] = [
+        PalettePreset::Custom,
+        PalettePreset::ClassicGreen,
+        PalettePreset::Amber,
+        PalettePreset::PaperWhite,
+    ];
+
+    /// Built-in colors indexed by pixel value, `None` for `custom`.
+    pub fn colors(&self) -> Option<[Color; 4]> {
+        match self {
+            PalettePreset::Custom => None,
+            PalettePreset::ClassicGreen => Some([
+                Color::rgb(0x0F, 0x38, 0x0F),
+                Color::rgb(0x9B, 0xBC, 0x0F),
+                Color::rgb(0x30, 0x62, 0x30),
+                Color::rgb(0x8B, 0xAC, 0x0F),
+            ]),
+            PalettePreset::Amber => Some([
+                Color::rgb(0x1A, 0x0F, 0x00),
+                Color::rgb(0xFF, 0xB0, 0x00),
+                Color::rgb(0x8A, 0x5A, 0x00),
+                Color::rgb(0xFF, 0xD2, 0x80),
+            ]),
+            PalettePreset::PaperWhite => Some([
+                Color::rgb(0xF5, 0xF2, 0xE8),
+                Color::rgb(0x22, 0x22, 0x22),
+                Color::rgb(0x9A, 0x94, 0x88),
+                Color::rgb(0x5A, 0x56, 0x50),
+            ]),
+        }
+    }
+}
+
+// Define an enumeration for the display backends a frontend can be built with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum DisplayBackend {
+    #[serde(rename = "sdl")]
+    #[default]
+    Sdl,
+    #[serde(rename = "wgpu")]
+    Wgpu,
+    #[serde(rename = "pixels")]
+    Pixels,
+    #[serde(rename = "terminal")]
+    Terminal,
+    #[serde(rename = "minifb")]
+    Minifb,
+    #[serde(rename = "mobile")]
+    Mobile,
+}
+
+// Define an enumeration for how the screen is scaled into windows of arbitrary size
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum Scaling {
+    /// Largest whole multiple of the screen size that fits, surrounded by letterbox bars.
+    #[serde(rename = "integer")]
+    #[default]
+    Integer,
+    /// Largest size that fits while keeping the 2:1 aspect ratio, may blur pixel edges.
+    #[serde(rename = "fit")]
+    Fit,
+    /// Fills the whole window, ignoring the aspect ratio.
+    #[serde(rename = "stretch")]
+    Stretch,
+}
+
+/// Destination rectangle of the screen inside a window, in physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Scaling {
+    /// Places a `source` sized image centered inside a `target` sized window.
+    pub fn viewport(self, source: (u32, u32), target: (u32, u32)) -> Viewport {
+        let (source_w, source_h) = (source.0.max(1), source.1.max(1));
+        let (target_w, target_h) = target;
+        let (width, height) = match self {
+            Scaling::Stretch => (target_w, target_h),
+            Scaling::Integer => {
+                let scale = (target_w / source_w).min(target_h / source_h).max(1);
+                (source_w * scale, source_h * scale)
+            }
+            Scaling::Fit => {
+                let scale =
+                    (target_w as f32 / source_w as f32).min(target_h as f32 / source_h as f32);
+                (
+                    (source_w as f32 * scale).round() as u32,
+                    (source_h as f32 * scale).round() as u32,
+                )
+            }
+        };
+        let (width, height) = (width.min(target_w), height.min(target_h));
+        Viewport {
+            x: (target_w - width) / 2,
+            y: (target_h - height) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+/// Clockwise screen rotation, written as 0, 90, 180 or 270 degrees in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(try_from = "u16", into = "u16")]
+#[schemars(rename = "ScreenRotation", extend("enum" = [0, 90, 180, 270]))]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::Deg0),
+            90 => Ok(Rotation::Deg90),
+            180 => Ok(Rotation::Deg180),
+            270 => Ok(Rotation::Deg270),
+            _ => Err(format!(
+                "invalid rotation {}, expected 0, 90, 180 or 270",
+                degrees
+            )),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+impl Rotation {
+    pub fn degrees(self) -> u16 {
+        self.quarter_turns() as u16 * 90
+    }
+
+    pub fn quarter_turns(self) -> u32 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 1,
+            Rotation::Deg180 => 2,
+            Rotation::Deg270 => 3,
+        }
+    }
+
+    /// Size of a `(width, height)` image after the rotation.
+    pub fn size(self, size: (usize, usize)) -> (usize, usize) {
+        match self {
+            Rotation::Deg90 | Rotation::Deg270 => (size.1, size.0),
+            Rotation::Deg0 | Rotation::Deg180 => size,
+        }
+    }
+
+    /// Position of the pixel `(x, y)` of a `(width, height)` image after the rotation.
+    pub fn rotate_point(self, x: usize, y: usize, size: (usize, usize)) -> (usize, usize) {
+        let (width, height) = size;
+        match self {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (height - 1 - y, x),
+            Rotation::Deg180 => (width - 1 - x, height - 1 - y),
+            Rotation::Deg270 => (y, width - 1 - x),
+        }
+    }
+
+    /// Row-major index of the pixel at `index` after the rotation.
+    pub fn rotate_index(self, index: usize, size: (usize, usize)) -> usize {
+        let (x, y) = self.rotate_point(index % size.0, index / size.0, size);
+        y * self.size(size).0 + x
+    }
+}
+
+fn default_letterbox_color() -> Color {
+    Color::rgb(0, 0, 0)
+}
+
+// Define an enumeration for the host keyboard layouts the keypad grid is found on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum KeyboardLayout {
+    #[serde(rename = "qwerty")]
+    #[default]
+    Qwerty,
+    #[serde(rename = "azerty")]
+    Azerty,
+    #[serde(rename = "qwertz")]
+    Qwertz,
+    #[serde(rename = "dvorak")]
+    Dvorak,
+}
+
+impl KeyboardLayout {
+    /// Characters typed on this layout paired with the US QWERTY key at the same position,
+    /// for the keys that differ.
+    fn remapped_keys(self) -> &'static [(char, char)] {
+        match self {
+            KeyboardLayout::Qwerty => &[],
+            KeyboardLayout::Azerty => &[
+                ('A', 'Q'),
+                ('Q', 'A'),
+                ('Z', 'W'),
+                ('W', 'Z'),
+                ('M', ';'),
+                (',', 'M'),
+                ('&', '1'),
+                ('\u{e9}', '2'),
+                ('"', '3'),
+                ('\'', '4'),
+                ('(', '5'),
+                ('-', '6'),
+                ('\u{e8}', '7'),
+                ('_', '8'),
+                ('\u{e7}', '9'),
+                ('\u{e0}', '0'),
+                (')', '-'),
+            ],
+            KeyboardLayout::Qwertz => &[('Z', 'Y'), ('Y', 'Z'), ('\u{df}', '-'), ('\u{b4}', '=')],
+            KeyboardLayout::Dvorak => &[
+                ('\'', 'Q'),
+                (',', 'W'),
+                ('.', 'E'),
+                ('P', 'R'),
+                ('Y', 'T'),
+                ('F', 'Y'),
+                ('G', 'U'),
+                ('C', 'I'),
+                ('R', 'O'),
+                ('L', 'P'),
+                ('O', 'S'),
+                ('E', 'D'),
+                ('U', 'F'),
+                ('I', 'G'),
+                ('D', 'H'),
+                ('H', 'J'),
+                ('T', 'K'),
+                ('N', 'L'),
+                ('S', ';'),
+                (';', 'Z'),
+                ('Q', 'X'),
+                ('J', 'C'),
+                ('K', 'V'),
+                ('X', 'B'),
+                ('B', 'N'),
+                ('[', '-'),
+                (']', '='),
+                ('/', '['),
+                ('=', ']'),
+                ('-', '\''),
+            ],
+        }
+    }
+
+    /// Returns the US QWERTY key at the position of `key` typed on this layout, so frontends
+    /// that only see typed characters keep the keypad grid on the same physical keys.
+    pub fn to_qwerty(self, key: char) -> char {
+        let key = key.to_ascii_uppercase();
+        self.remapped_keys()
+            .iter()
+            .find(|(typed, _)| *typed == key)
+            .map_or(key, |(_, qwerty)| *qwerty)
+    }
+}
+
+// Define an enumeration for the character cells the terminal backend draws with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum TerminalMode {
+    /// One half block per 1x2 pixels, 64x16 cells.
+    #[serde(rename = "half-block")]
+    #[default]
+    HalfBlock,
+    /// One Braille character per 2x4 pixels, 32x8 cells.
+    #[serde(rename = "braille")]
+    Braille,
+}
+
+/// CRT post-processing configuration, intensities range from 0.0 (off) to 1.0.
+/// `barrel_distortion` bends the screen like the glass of a tube, 0.0 keeps it flat.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Crt {
+    pub enable: bool,
+    pub scanline_intensity: f32,
+    pub vignette_intensity: f32,
+    #[serde(default)]
+    pub barrel_distortion: f32,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            scanline_intensity: 0.35,
+            vignette_intensity: 0.4,
+            barrel_distortion: 0.1,
+        }
+    }
+}
+
+/// Pixel grid configuration: 1px lines drawn between the CHIP-8 pixels once every pixel is
+/// at least `min_scale` physical pixels wide.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Grid {
+    pub enable: bool,
+    pub color: Color,
+    pub min_scale: u32,
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            color: Color::rgb(0x20, 0x20, 0x20),
+            min_scale: 4,
+        }
+    }
+}
+
+/// Touch and mouse keypad: the window is split into a grid with one row per `layout` entry,
+/// each character is the hex digit of the CHIP-8 key in that cell or `.` for no key.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Touch {
+    pub enable: bool,
+    pub layout: Vec<String>,
+}
+
+impl Default for Touch {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            layout: ["123C", "456D", "789E", "A0BF"]
+                .iter()
+                .map(|row| row.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Keypad of one player: `keys` lists the 16 host keys of the 4x4 grid row by row, as US
+/// layout characters, `gamepad` pins a pad by name or GUID.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Player {
+    pub keys: String,
+    #[serde(default)]
+    pub gamepad: Option<String>,
+}
+
+fn default_players() -> Vec<Player> {
+    ["1234QWERASDFZXCV", "7890UIOPJKL;M,./"]
+        .iter()
+        .map(|keys| Player {
+            keys: keys.to_string(),
+            gamepad: None,
+        })
+        .collect()
+}
+
+fn default_player() -> usize {
+    1
+}
+
+/// Gamepads opened as they are plugged in. `device` pins one pad by name or GUID, `buttons`
+/// maps SDL button names such as `a` or `dpup` to CHIP-8 keys given as hex digits.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Gamepad {
+    pub enable: bool,
+    #[serde(default)]
+    pub device: Option<String>,
+    #[serde(default = "default_gamepad_buttons")]
+    pub buttons: HashMap<String, char>,
+}
+
+impl Default for Gamepad {
+    fn default() -> Self {
+        Self {
+            enable: true,
+            device: None,
+            buttons: default_gamepad_buttons(),
+        }
+    }
+}
+
+fn default_gamepad_buttons() -> HashMap<String, char> {
+    [
+        ("dpup", '2'),
+        ("dpdown", '8'),
+        ("dpleft", '4'),
+        ("dpright", '6'),
+        ("a", '5'),
+        ("b", '0'),
+        ("x", 'A'),
+        ("y", 'B'),
+    ]
+    .into_iter()
+    .map(|(button, key)| (button.to_string(), key))
+    .collect()
+}
+
+/// WebSocket endpoint accepting JSON key events from other programs.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct RemoteInput {
+    pub enable: bool,
+    pub address: String,
+}
+
+impl Default for RemoteInput {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            address: "127.0.0.1:8765".to_string(),
+        }
+    }
+}
+
+/// WebSocket endpoint taking JSON commands to load, pause, step and inspect the emulator.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct RemoteControl {
+    pub enable: bool,
+    pub address: String,
+}
+
+impl Default for RemoteControl {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            address: "127.0.0.1:8766".to_string(),
+        }
+    }
+}
+
+/// Local socket or named pipe taking the remote control commands, one JSON message per line.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct IpcControl {
+    pub enable: bool,
+    pub name: String,
+}
+
+impl Default for IpcControl {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            name: "chip8-control".to_string(),
+        }
+    }
+}
+
+/// HTTP endpoint for one-shot control: load a ROM, pause, read the state, take a screenshot.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct RestControl {
+    pub enable: bool,
+    pub address: String,
+}
+
+impl Default for RestControl {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            address: "127.0.0.1:8767".to_string(),
+        }
+    }
+}
+
+/// TCP endpoint for the JSON-RPC debug protocol, one message per line.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct DebugServer {
+    pub enable: bool,
+    pub address: String,
+}
+
+impl Default for DebugServer {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            address: "127.0.0.1:8768".to_string(),
+        }
+    }
+}
+
+/// Lockstep netplay started with `--host` or `--join`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Netplay {
+    /// Frames between a key press and the frame it is applied on, on both instances; more
+    /// hides more network latency. The host's value is used.
+    pub input_delay: u8,
+}
+
+impl Default for Netplay {
+    fn default() -> Self {
+        Self { input_delay: 2 }
+    }
+}
+
+/// Twitch-plays input: chat messages naming a command in `commands` vote for its CHIP-8 key,
+/// given as a hex digit. The first vote opens a window of `vote_frames` emulated frames, after
+/// which the command with the most votes is held for `hold_frames`.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Chat {
+    pub enable: bool,
+    /// IRC server, Twitch chat by default
+    #[serde(default = "default_chat_server")]
+    pub server: String,
+    /// Channel to read, without the `#`
+    #[serde(default)]
+    pub channel: String,
+    /// The default `justinfan` nicks read Twitch chat anonymously
+    #[serde(default = "default_chat_nick")]
+    pub nick: String,
+    /// Password sent before the nick, e.g. `oauth:...`; reading needs none
+    #[serde(default)]
+    pub token: Option<String>,
+    #[serde(default = "default_vote_frames")]
+    pub vote_frames: u32,
+    #[serde(default = "default_hold_frames")]
+    pub hold_frames: u32,
+    #[serde(default = "default_chat_commands")]
+    pub commands: HashMap<String, char>,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            server: default_chat_server(),
+            channel: String::new(),
+            nick: default_chat_nick(),
+            token: None,
+            vote_frames: default_vote_frames(),
+            hold_frames: default_hold_frames(),
+            commands: default_chat_commands(),
+        }
+    }
+}
+
+fn default_chat_server() -> String {
+    "irc.chat.twitch.tv:6667".to_string()
+}
+
+fn default_chat_nick() -> String {
+    "justinfan31337".to_string()
+}
+
+fn default_vote_frames() -> u32 {
+    120
+}
+
+fn default_hold_frames() -> u32 {
+    8
+}
+
+fn default_chat_commands() -> HashMap<String, char> {
+    [
+        ("up", '2'),
+        ("down", '8'),
+        ("left", '4'),
+        ("right", '6'),
+        ("fire", '5'),
+    ]
+    .into_iter()
+    .map(|(command, key)| (command.to_string(), key))
+    .collect()
+}
+
+/// Discord Rich Presence showing the ROM played, for how long and whether it is paused.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct Discord {
+    pub enable: bool,
+    /// Application id of the Discord app the activity is shown as
+    #[serde(default)]
+    pub client_id: String,
+}
+
+/// WebAssembly plugins watching the emulator, e.g. debugger visualizations.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Plugins {
+    pub enable: bool,
+    /// Folder of the `.wasm` and `.wat` plugins, which write their exports next to them
+    #[serde(default = "default_plugins_folder")]
+    pub folder: String,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            folder: default_plugins_folder(),
+        }
+    }
+}
+
+fn default_plugins_folder() -> String {
+    "plugins".to_string()
+}
+
+/// Block compiler running straight-line runs of instructions as precompiled closures.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct Dynarec {
+    pub enable: bool,
+    /// Runs every compiled block through the interpreter too and stops on a difference
+    #[serde(default)]
+    pub verify: bool,
+}
+
+/// Holding the U hotkey steps back through the last frames emulated.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Rewind {
+    pub enable: bool,
+    /// Seconds of frames kept, the oldest are dropped a second at a time
+    #[serde(default = "default_rewind_seconds")]
+    pub seconds: u32,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            seconds: default_rewind_seconds(),
+        }
+    }
+}
+
+fn default_rewind_seconds() -> u32 {
+    120
+}
+
+/// HTTP endpoint serving the runtime metrics in the Prometheus text format.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct MetricsEndpoint {
+    pub enable: bool,
+    pub address: String,
+}
+
+impl Default for MetricsEndpoint {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            address: "127.0.0.1:9184".to_string(),
+        }
+    }
+}
+
+/// Buzzer configuration, a tone of `frequency` Hz. `duty_cycle` is the high part of a period
+/// of the square wave.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct Audio {
+    #[serde(default)]
+    pub backend: AudioBackend,
+    #[serde(default)]
+    pub waveform: Waveform,
+    pub frequency: f32,
+    /// Tone in Hz for specific ROMs, keyed by ROM file name without extension
+    #[serde(default)]
+    pub rom_frequencies: HashMap<String, f32>,
+    pub duty_cycle: f32,
+    /// Master volume from 0 to 100, the last value set at runtime is restored on start
+    #[serde(default = "default_volume")]
+    pub volume: u8,
+    /// WAV file the buzzer is recorded to while the emulator runs
+    #[serde(default)]
+    pub record: Option<String>,
+    /// Requested output rate in Hz, the device may pick another one
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u32,
+    /// Requested samples per buffer, smaller buffers make the beeps start sooner
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: u16,
+    /// Output device name, the system default when unset
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+impl Audio {
+    /// Returns the tone for `rom`, its own frequency if it has one and the global one otherwise.
+    pub fn frequency_for(&self, rom: Option<&str>) -> f32 {
+        rom.and_then(|rom| self.rom_frequencies.get(rom))
+            .copied()
+            .unwrap_or(self.frequency)
+    }
+}
+
+fn default_volume() -> u8 {
+    80
+}
+
+fn default_sample_rate() -> u32 {
+    44_100
+}
+
+fn default_buffer_size() -> u16 {
+    512
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self {
+            backend: AudioBackend::Auto,
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            rom_frequencies: HashMap::new(),
+            duty_cycle: 0.5,
+            volume: default_volume(),
+            record: None,
+            sample_rate: default_sample_rate(),
+            buffer_size: default_buffer_size(),
+            device: None,
+        }
+    }
+}
+
+// Define an enumeration for the sound outputs a frontend can be built with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum AudioBackend {
+    /// SDL audio with the SDL display backend, cpal with the others.
+    #[serde(rename = "auto")]
+    #[default]
+    Auto,
+    #[serde(rename = "sdl")]
+    Sdl,
+    #[serde(rename = "cpal")]
+    Cpal,
+    #[serde(rename = "none")]
+    None,
+    /// No audio device, every buzzer activation is logged instead.
+    #[serde(rename = "silent")]
+    Silent,
+}
+
+// Define an enumeration for the buzzer waveforms
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+pub enum Waveform {
+    #[serde(rename = "square")]
+    #[default]
+    Square,
+    #[serde(rename = "sine")]
+    Sine,
+    #[serde(rename = "triangle")]
+    Triangle,
+    #[serde(rename = "noise")]
+    Noise,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [
+        Waveform::Square,
+        Waveform::Sine,
+        Waveform::Triangle,
+        Waveform::Noise,
+    ];
+
+    /// Returns the next waveform, wrapping back to the square wave.
+    pub fn next(self) -> Self {
+        let position = Self::ALL
+            .iter()
+            .position(|waveform| *waveform == self)
+            .unwrap_or(0);
+        Self::ALL[(position + 1) % Self::ALL.len()]
+    }
+}
+
+/// A config field set on the command line, e.g. `--scale 20` or `--logger.level debug`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Override {
+    pub path: Vec<String>,
+    pub value: String,
+}
+
+impl Override {
+    /// Parses `--<field> <value>`, taking the value from `args`, or `--<field>=<value>`. Fields
+    /// outside the `app` and `logger` sections belong to `chip8`, and dashes in field names
+    /// stand for underscores.
+    pub fn from_args(
+        flag: &str,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<Self, ConfigError> {
+        let field = flag
+            .strip_prefix("--")
+            .ok_or_else(|| ConfigError::InvalidOverride(flag.to_string()))?;
+        let (field, value) = match field.split_once('=') {
+            Some((field, value)) => (field, value.to_string()),
+            None => (
+                field,
+                args.next()
+                    .ok_or_else(|| ConfigError::InvalidOverride(format!("{flag} needs a value")))?,
+            ),
+        };
+        let mut path: Vec<String> = field.split('.').map(|s| s.replace('-', "_")).collect();
+        if path.iter().any(|segment| segment.is_empty()) {
+            return Err(ConfigError::InvalidOverride(flag.to_string()));
+        }
+        if !["app", "logger", "chip8"].contains(&path[0].as_str()) {
+            path.insert(0, "chip8".to_string());
+        }
+        Ok(Self { path, value })
+    }
+}
+
+static CONFIG: OnceLock<ArcSwap<Config>> = OnceLock::new();
+// Kept for reloads, so the command line still wins over edited files
+static CLI_OVERRIDES: OnceLock<Vec<Override>> = OnceLock::new();
 impl Config {
-    pub fn new(env: &Environment) -> Result<Self, ConfigError> {
+    /// Loads the config of `env` with `overrides` from the command line on top. From lowest to
+    /// highest precedence the layers are the built-in defaults, `<env>.yaml`, `<env>.local.yaml`,
+    /// `CHIP8__` environment variables and the command line.
+    pub fn new(env: &Environment, overrides: Vec<Override>) -> Result<Self, ConfigError> {
+        CLI_OVERRIDES
+            .set(overrides)
+            .map_err(|_| ConfigError::SettingsAlreadyInitialized)?;
         let config = Self::from_folder(env, DEFAULT_FOLDER.as_path())?;
+        let fields = serde_yaml::to_value(&config)
+            .map_err(|e| ConfigError::YamlParseError(e.to_string()))?;
+        // Nothing rejects unknown fields, check the flags so a typo doesn't go unnoticed
+        if let Some(unknown) = CLI_OVERRIDES
+            .get()
+            .into_iter()
+            .flatten()
+            .find(|o| find_path(&fields, &o.path).is_none())
+        {
+            return Err(ConfigError::InvalidOverride(format!(
+                "unknown setting --{}",
+                unknown.path.join(".")
+            )));
+        }
         CONFIG
-            .set(config.clone())
+            .set(ArcSwap::from_pointee(config.clone()))
             .map_err(|_| ConfigError::SettingsAlreadyInitialized)?;
         Ok(config)
     }
 
-    pub fn get() -> &'static Config {
-        CONFIG.get().expect("SETTINGS has not been initialized!")
+    pub fn get() -> Arc<Config> {
+        CONFIG
+            .get()
+            .expect("SETTINGS has not been initialized!")
+            .load_full()
+    }
+
+    /// Reloads the config with the ROM profile `profile` merged over the files, below the
+    /// environment and command line overrides, and makes it the current one.
+    pub fn with_profile(
+        env: &Environment,
+        profile: serde_yaml::Value,
+    ) -> Result<Self, ConfigError> {
+        profile::activate(profile)?;
+        let config = Self::from_folder(env, DEFAULT_FOLDER.as_path())?;
+        Self::replace(config.clone());
+        Ok(config)
     }
+
+    /// Swaps in a reloaded config, returning the previous one.
+    pub fn replace(config: Config) -> Arc<Config> {
+        CONFIG
+            .get()
+            .expect("SETTINGS has not been initialized!")
+            .swap(Arc::new(config))
+    }
+
+    /// Reads `<env>.yaml` with `<env>.local.yaml` merged over it, either may be missing but
+    /// not both, then applies the environment and command line overrides.
     pub fn from_folder(env: &Environment, path: &Path) -> Result<Self, ConfigError> {
         let files = [
-            path.join(format!("{env}.local.yaml")),
             path.join(format!("{env}.yaml")),
+            path.join(format!("{env}.local.yaml")),
         ];
 
-        let selected_path = files
-            .iter()
-            .find(|p| p.exists())
-            .ok_or(ConfigError::NoConfigFileFound)?;
+        let mut value = None;
+        for file in files.iter().filter(|p| p.exists()) {
+            let content =
+                fs::read_to_string(file).map_err(|e| ConfigError::FileReadError(e.to_string()))?;
+            let rendered = render_string(&content, &serde_json::json!({}))
+                .map_err(|e| ConfigError::TemplateRenderError(e.to_string()))?;
+            let mut layer: serde_yaml::Value = serde_yaml::from_str(&rendered)
+                .map_err(|e| ConfigError::YamlParseError(e.to_string()))?;
+            migrate(&mut layer, file)?;
+            match &mut value {
+                Some(value) => merge(value, layer),
+                None => value = Some(layer),
+            }
+        }
+        let mut value = value.ok_or(ConfigError::NoConfigFileFound)?;
+        if let (Some(profile), Some(chip8)) = (profile::active(), value.get_mut("chip8")) {
+            merge(chip8, profile.clone());
+        }
+
+        apply_env_overrides(&mut value, std::env::vars());
+        for o in CLI_OVERRIDES.get().into_iter().flatten() {
+            set_path(
+                &mut value,
+                o.path.iter().map(String::as_str),
+                o.value.clone(),
+            );
+        }
+        serde_path_to_error::deserialize(value).map_err(|e| {
+            // Name the setting that failed and explain it from the schema
+            let path = e.path().clone();
+            let mut message = e.into_inner().to_string();
+            if path.iter().next().is_some() {
+                message = format!("{}: {}", path, message);
+                // Unknown variants are already listed by serde
+                if let Some(hint) = describe(&path).filter(|_| !message.contains("unknown variant"))
+                {
+                    message = format!("{} ({})", message, hint);
+                }
+            }
+            ConfigError::YamlParseError(message)
+        })
+    }
+}
+
+/// Merges `layer` into `base`, mappings field by field and anything else replaced whole.
+fn merge(base: &mut serde_yaml::Value, layer: serde_yaml::Value) {
+    match (base, layer) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Finds the key of `map` matching `segment` case-insensitively.
+fn find_key(map: &serde_yaml::Mapping, segment: &str) -> Option<serde_yaml::Value> {
+    map.keys()
+        .find(|key| {
+            key.as_str()
+                .is_some_and(|key| key.eq_ignore_ascii_case(segment))
+        })
+        .cloned()
+}
+
+fn find_path<'a>(value: &'a serde_yaml::Value, path: &[String]) -> Option<&'a serde_yaml::Value> {
+    path.iter().try_fold(value, |node, segment| {
+        let map = node.as_mapping()?;
+        map.get(find_key(map, segment)?)
+    })
+}
+
+/// Sets the field at `path`, creating the sections on the way. Values are parsed as YAML, so
+/// numbers, booleans and lists keep their types, and path segments match existing keys
+/// case-insensitively so maps keyed by ROM name can be reached too.
+fn set_path<'a>(config: &mut serde_yaml::Value, path: impl Iterator<Item = &'a str>, raw: String) {
+    let mut node = config;
+    for segment in path {
+        if !node.is_mapping() {
+            *node = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+        }
+        let map = node.as_mapping_mut().expect("node was just made a mapping");
+        let key = find_key(map, segment)
+            .unwrap_or_else(|| serde_yaml::Value::String(segment.to_string()));
+        node = map.entry(key).or_insert(serde_yaml::Value::Null);
+    }
+    *node = serde_yaml::from_str(&raw).unwrap_or(serde_yaml::Value::String(raw));
+}
+
+/// Sets the fields named by `ENV_PREFIX` variables in the parsed YAML.
+fn apply_env_overrides(
+    config: &mut serde_yaml::Value,
+    vars: impl Iterator<Item = (String, String)>,
+) {
+    for (name, raw) in vars {
+        if let Some(path) = name.strip_prefix(ENV_PREFIX) {
+            let path = path.to_ascii_lowercase();
+            set_path(config, path.split("__"), raw);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let content = fs::read_to_string(selected_path)
-            .map_err(|e| ConfigError::FileReadError(e.to_string()))?;
-        let rendered = render_string(&content, &serde_json::json!({}))
-            .map_err(|e| ConfigError::TemplateRenderError(e.to_string()))?;
+    #[test]
+    fn test_env_overrides() {
+        let mut config: serde_yaml::Value = serde_yaml::from_str(
+            "chip8:\n  scale: 10\n  audio:\n    rom_frequencies:\n      BRIX: 330.0\n",
+        )
+        .unwrap();
+        let vars = [
+            ("CHIP8__CHIP8__SCALE", "20"),
+            ("CHIP8__CHIP8__AUDIO__ROM_FREQUENCIES__BRIX", "220.5"),
+            ("CHIP8__LOGGER__LEVEL", "debug"),
+            ("OTHER__CHIP8__SCALE", "5"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env_overrides(&mut config, vars.into_iter());
 
-        serde_yaml::from_str(&rendered).map_err(|e| ConfigError::YamlParseError(e.to_string()))
+        assert_eq!(config["chip8"]["scale"], serde_yaml::Value::from(20));
+        assert_eq!(
+            config["chip8"]["audio"]["rom_frequencies"]["BRIX"],
+            serde_yaml::Value::from(220.5)
+        );
+        assert_eq!(config["logger"]["level"], serde_yaml::Value::from("debug"));
     }
 }
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Folder of the config files in a checkout of the repository, used instead of the platform
+/// folders when present so development runs keep everything in the working directory.
+const DEVELOPMENT_FOLDER: &str = "config";
+
+/// Per-user folders of an installed emulator, e.g. `~/.config/chip8-emulator` and
+/// `~/.local/share/chip8-emulator` on Linux.
+struct PlatformFolders {
+    config: PathBuf,
+    data: PathBuf,
+}
+
+/// The platform folders, `None` in development runs or where the platform has none.
+fn platform() -> Option<&'static PlatformFolders> {
+    static FOLDERS: OnceLock<Option<PlatformFolders>> = OnceLock::new();
+    FOLDERS
+        .get_or_init(|| {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let dirs = directories::ProjectDirs::from("", "", "chip8-emulator")?;
+                let config = dirs.config_dir().to_path_buf();
+                // Fall back to the checkout's folder until the platform one is set up
+                if !config.exists() && Path::new(DEVELOPMENT_FOLDER).is_dir() {
+                    return None;
+                }
+                Some(PlatformFolders {
+                    config,
+                    data: dirs.data_dir().to_path_buf(),
+                })
+            }
+            #[cfg(target_arch = "wasm32")]
+            None
+        })
+        .as_ref()
+}
+
+/// Folder holding the config files and the saved state.
+pub fn config_folder() -> PathBuf {
+    platform().map_or_else(|| PathBuf::from(DEVELOPMENT_FOLDER), |p| p.config.clone())
+}
+
+/// Resolves a relative path of files the emulator writes, such as recordings and logs, inside
+/// the platform data folder. Absolute paths and development runs keep the path as is.
+pub fn data_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match platform() {
+        Some(folders) if path.is_relative() => folders.data.join(path),
+        _ => path.to_path_buf(),
+    }
+}
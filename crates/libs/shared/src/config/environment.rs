@@ -1,7 +1,8 @@
 use std::env;
 use std::str::FromStr;
 
-use super::{config::Config, error::ConfigError};
+use super::config::{Config, Override};
+use super::error::ConfigError;
 use dotenvy::dotenv;
 use serde_derive::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
@@ -28,12 +29,16 @@ impl Environment {
     pub fn from_env() -> Self {
         dotenv().ok();
         let env_var = env::var("ENVIRONMENT").unwrap_or_else(|_| DEFAULT_ENVIRONMENT.to_string());
-        println!("env_var: {}", env_var.clone());
         Self::from(env_var)
     }
 
     pub fn load(&self) -> Result<Config, ConfigError> {
-        Config::new(self)
+        self.load_with(Vec::new())
+    }
+
+    /// Loads the config with fields set on the command line on top.
+    pub fn load_with(&self, overrides: Vec<Override>) -> Result<Config, ConfigError> {
+        Config::new(self, overrides)
     }
 }
 
@@ -4,12 +4,18 @@ use thiserror::Error;
 pub enum ConfigError {
     #[error("Failed to read configuration file: {0}")]
     FileReadError(String),
+    #[error("Failed to write state file: {0}")]
+    FileWriteError(String),
     #[error("Failed to render template: {0}")]
     TemplateRenderError(String),
     #[error("Failed to parse YAML configuration: {0}")]
     YamlParseError(String),
-    #[error("No configuration file found")]
+    #[error("Invalid config override: {0}")]
+    InvalidOverride(String),
+    #[error("No configuration file found, create one with `desktop config init`")]
     NoConfigFileFound,
+    #[error("Failed to watch the configuration files: {0}")]
+    WatchError(String),
     #[error("Settings already initialized")]
     SettingsAlreadyInitialized,
 }
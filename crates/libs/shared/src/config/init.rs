@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::config::DEFAULT_FOLDER;
+use super::environment::Environment;
+use super::error::ConfigError;
+
+/// The commented development config shipped with the repository, every setting at its default.
+pub const DEFAULT_CONFIG: &str = include_str!("../../../../../config/development.yaml");
+
+/// A file written by `init`, or left alone because it already existed.
+pub struct InitFile {
+    pub path: PathBuf,
+    pub written: bool,
+}
+
+/// Writes the default config as `config/<env>.yaml` and a `.env` selecting `env`, so the app
+/// runs without writing a config by hand. Existing files are kept unless `force` is set.
+pub fn init(env: &Environment, force: bool) -> Result<Vec<InitFile>, ConfigError> {
+    let env_file = format!("# Picks config/<ENVIRONMENT>.yaml\nENVIRONMENT={env}\n");
+    let files = [
+        (DEFAULT_FOLDER.join(format!("{env}.yaml")), DEFAULT_CONFIG),
+        (PathBuf::from(".env"), env_file.as_str()),
+    ];
+    files
+        .iter()
+        .map(|(path, content)| {
+            let written = force || !path.exists();
+            if written {
+                write(path, content)?;
+            }
+            Ok(InitFile {
+                path: path.clone(),
+                written,
+            })
+        })
+        .collect()
+}
+
+fn write(path: &Path, content: &str) -> Result<(), ConfigError> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .map_err(|e| ConfigError::FileWriteError(format!("{}: {}", parent.display(), e)))?;
+    }
+    fs::write(path, content)
+        .map_err(|e| ConfigError::FileWriteError(format!("{}: {}", path.display(), e)))
+}
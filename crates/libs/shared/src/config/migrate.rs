@@ -0,0 +1,123 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde_yaml::{Mapping, Value};
+
+use super::error::ConfigError;
+
+/// Version of the config format, bumped with each entry of `MIGRATIONS`. Files without a
+/// `version` field predate versioning and count as version 0.
+pub const CURRENT_VERSION: u64 = 1;
+
+/// Upgrades of the config format, `MIGRATIONS[n]` turns a version `n` file into version
+/// `n + 1` and describes each change it made.
+const MIGRATIONS: &[fn(&mut Mapping) -> Vec<String>] = &[palette_section];
+
+// Migrations run before the logger is up, their warnings wait here until it is
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Upgrades a parsed config file to `CURRENT_VERSION` in place. The changes are kept for
+/// `take_warnings`, so the user can update the file.
+pub fn migrate(config: &mut Value, file: &Path) -> Result<(), ConfigError> {
+    let Value::Mapping(root) = config else {
+        return Ok(());
+    };
+    let version = match root.get("version") {
+        None => 0,
+        Some(version) => version.as_u64().ok_or_else(|| {
+            ConfigError::YamlParseError(format!("{}: version must be a number", file.display()))
+        })?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(ConfigError::YamlParseError(format!(
+            "{} has config version {}, this build reads up to version {}",
+            file.display(),
+            version,
+            CURRENT_VERSION
+        )));
+    }
+
+    let changes: Vec<String> = MIGRATIONS[version as usize..]
+        .iter()
+        .flat_map(|migration| migration(root))
+        .collect();
+    root.insert("version".into(), CURRENT_VERSION.into());
+    if !changes.is_empty() {
+        WARNINGS.lock().unwrap().push(format!(
+            "{} uses config version {}, upgraded it while loading: {}. Update the file to \
+             version {} to silence this warning",
+            file.display(),
+            version,
+            changes.join(", "),
+            CURRENT_VERSION
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the migration warnings collected since the last call.
+pub fn take_warnings() -> Vec<String> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap())
+}
+
+/// Moves the field at `from` of the `chip8` section to `to`, creating the sections on the way.
+fn move_field(root: &mut Mapping, from: &str, to: &[&str], changes: &mut Vec<String>) {
+    let Some(Value::Mapping(chip8)) = root.get_mut("chip8") else {
+        return;
+    };
+    let Some(value) = chip8.remove(from) else {
+        return;
+    };
+    let (field, sections) = to.split_last().expect("the target path is not empty");
+    let mut node = &mut *chip8;
+    for section in sections {
+        let entry = node
+            .entry((*section).into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if !entry.is_mapping() {
+            *entry = Value::Mapping(Mapping::new());
+        }
+        node = entry
+            .as_mapping_mut()
+            .expect("entry was just made a mapping");
+    }
+    // A value already in the new place wins over the old field
+    if !node.contains_key(*field) {
+        node.insert((*field).into(), value);
+    }
+    changes.push(format!("chip8.{} moved to chip8.{}", from, to.join(".")));
+}
+
+/// Version 1 moved the screen colors into the `palette` section.
+fn palette_section(root: &mut Mapping) -> Vec<String> {
+    let mut changes = Vec::new();
+    move_field(root, "bg_color", &["palette", "background"], &mut changes);
+    move_field(
+        root,
+        "pixel_color",
+        &["palette", "foreground"],
+        &mut changes,
+    );
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_palette_section() {
+        let mut config: Value =
+            serde_yaml::from_str("chip8:\n  bg_color: {r: 1, g: 2, b: 3, a: 4}\n  scale: 10\n")
+                .unwrap();
+        migrate(&mut config, Path::new("old.yaml")).unwrap();
+
+        assert_eq!(config["version"], Value::from(CURRENT_VERSION));
+        assert_eq!(
+            config["chip8"]["palette"]["background"]["b"],
+            Value::from(3)
+        );
+        assert!(config["chip8"].get("bg_color").is_none());
+        assert_eq!(config["chip8"]["scale"], Value::from(10));
+    }
+}
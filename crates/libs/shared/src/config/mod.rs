@@ -1,3 +1,12 @@
 pub mod config;
+pub mod dirs;
 pub mod environment;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod init;
+pub mod migrate;
+pub mod profile;
+pub mod schema;
+pub mod state;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde_yaml::Value;
+use sha1::{Digest, Sha1};
+
+use super::error::ConfigError;
+
+/// `chip8` settings a ROM profile may override, anything else in a profile is rejected.
+pub const PROFILE_FIELDS: &[&str] = &[
+    "cycles_per_frame",
+    "palette",
+    "st_equals_buzzer",
+    "bit_shift_instructions_use_vy",
+    "store_read_instructions_change_i",
+];
+
+static ACTIVE: OnceLock<Value> = OnceLock::new();
+
+/// Lowercase hex SHA-1 of a ROM, the name of its profile files.
+pub fn rom_hash(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Reads the profile of the ROM with SHA-1 `hash` from `<hash>.yaml`, `<hash>.yml` or
+/// `<hash>.toml` in `folder`, returning the file it came from and the settings to merge into the
+/// `chip8` section.
+pub fn find(folder: &Path, hash: &str) -> Result<Option<(PathBuf, Value)>, ConfigError> {
+    let Some(path) = ["yaml", "yml", "toml"]
+        .iter()
+        .map(|extension| folder.join(format!("{hash}.{extension}")))
+        .find(|path| path.exists())
+    else {
+        return Ok(None);
+    };
+    let invalid = |e: String| ConfigError::YamlParseError(format!("{}: {}", path.display(), e));
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| ConfigError::FileReadError(e.to_string()))?;
+    let profile: Value = if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        let table: toml::Table = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        serde_yaml::to_value(table).map_err(|e| invalid(e.to_string()))?
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| invalid(e.to_string()))?
+    };
+
+    let fields = match &profile {
+        Value::Mapping(fields) => fields,
+        Value::Null => return Ok(None),
+        _ => return Err(invalid("expected a mapping of settings".to_string())),
+    };
+    if let Some(field) = fields.keys().find(|field| {
+        !field
+            .as_str()
+            .is_some_and(|field| PROFILE_FIELDS.contains(&field))
+    }) {
+        return Err(invalid(format!(
+            "{} can't be set by a profile, expected one of {}",
+            serde_yaml::to_string(field).unwrap_or_default().trim(),
+            PROFILE_FIELDS.join(", ")
+        )));
+    }
+    Ok(Some((path, profile)))
+}
+
+/// The profile of the running ROM, kept so reloads of the config still apply it.
+pub(crate) fn active() -> Option<&'static Value> {
+    ACTIVE.get()
+}
+
+pub(crate) fn activate(profile: Value) -> Result<(), ConfigError> {
+    ACTIVE
+        .set(profile)
+        .map_err(|_| ConfigError::SettingsAlreadyInitialized)
+}
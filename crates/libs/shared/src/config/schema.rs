@@ -0,0 +1,68 @@
+use schemars::schema_for;
+use serde_json::Value;
+use serde_path_to_error::{Path, Segment};
+
+use super::config::Config;
+
+/// JSON Schema of the config files, for editors to complete and check the YAML.
+pub fn schema() -> Value {
+    serde_json::to_value(schema_for!(Config)).expect("the config schema is valid JSON")
+}
+
+/// Describes the setting at `path` from the schema, with its doc comment and the accepted
+/// values of enums, to explain a config that failed to parse.
+pub fn describe(path: &Path) -> Option<String> {
+    let schema = schema();
+    let mut node = &schema;
+    // The field's own doc comment, falling back to the one of its type below
+    let mut description = None;
+    for segment in path.iter() {
+        let child = match segment {
+            Segment::Map { key } => node
+                .get("properties")
+                .and_then(|properties| properties.get(key))
+                .or_else(|| node.get("additionalProperties")),
+            Segment::Seq { .. } => node.get("items"),
+            _ => None,
+        }?;
+        description = child.get("description");
+        node = resolve(&schema, child);
+    }
+
+    let values: Vec<String> = match (node.get("enum"), node.get("oneOf")) {
+        (Some(Value::Array(values)), _) => values.iter().map(Value::to_string).collect(),
+        (_, Some(Value::Array(variants))) => variants
+            .iter()
+            .filter_map(|variant| variant.get("const").map(Value::to_string))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let description = description
+        .or_else(|| node.get("description"))
+        .and_then(Value::as_str)
+        .map(|description| description.trim_end_matches('.').replace('\n', " "));
+    match (description, values.is_empty()) {
+        (Some(description), true) => Some(description),
+        (Some(description), false) => Some(format!("{description}, one of {}", values.join(", "))),
+        (None, false) => Some(format!("one of {}", values.join(", "))),
+        (None, true) => None,
+    }
+}
+
+/// Follows `$ref`s into the definitions and optional fields into the schema of their value.
+fn resolve<'a>(schema: &'a Value, node: &'a Value) -> &'a Value {
+    let target = if let Some(reference) = node.get("$ref").and_then(Value::as_str) {
+        reference
+            .strip_prefix("#/$defs/")
+            .and_then(|name| schema.get("$defs")?.get(name))
+    } else {
+        node.get("anyOf")
+            .and_then(Value::as_array)
+            .and_then(|variants| {
+                variants
+                    .iter()
+                    .find(|v| v.get("type") != Some(&"null".into()))
+            })
+    };
+    target.map_or(node, |target| resolve(schema, target))
+}
@@ -0,0 +1,50 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+
+use super::config::DEFAULT_FOLDER;
+use super::error::ConfigError;
+
+/// File holding the runtime choices, next to the configuration files.
+fn state_file() -> PathBuf {
+    DEFAULT_FOLDER.join("state.yaml")
+}
+
+/// Runtime choices remembered between sessions, e.g. the last volume. Fields left unset fall
+/// back to the configuration.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct State {
+    #[serde(default)]
+    pub volume: Option<u8>,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+impl State {
+    /// Reads the saved state, a missing or broken file yields the defaults.
+    pub fn load() -> Self {
+        let path = state_file();
+        if !path.exists() {
+            return Self::default();
+        }
+        fs::read_to_string(path)
+            .map_err(|e| ConfigError::FileReadError(e.to_string()))
+            .and_then(|content| {
+                serde_yaml::from_str(&content)
+                    .map_err(|e| ConfigError::YamlParseError(e.to_string()))
+            })
+            .unwrap_or_else(|e| {
+                warn!("Ignoring saved state: {}", e);
+                Self::default()
+            })
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let content =
+            serde_yaml::to_string(self).map_err(|e| ConfigError::FileWriteError(e.to_string()))?;
+        fs::create_dir_all(DEFAULT_FOLDER.as_path())
+            .and_then(|_| fs::write(state_file(), content))
+            .map_err(|e| ConfigError::FileWriteError(e.to_string()))
+    }
+}
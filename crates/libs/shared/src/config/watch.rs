@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{info, warn};
+
+use super::config::{Config, ConfigUpdate, Logger, DEFAULT_FOLDER};
+use super::environment::Environment;
+use super::error::ConfigError;
+use super::migrate::take_warnings;
+use crate::logger::logger::{self, LogLevel};
+
+/// Keeps the file watch alive, dropping it stops the updates.
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// Watches the config files of `env` in the config folder and reloads them when they change.
+/// Reloads that fail to parse are logged and skipped. Each new config is swapped into
+/// `Config::get`, applies the log level and is sent to the receiver for the emulator to apply
+/// its live settings; changes to anything else are logged as needing a restart.
+pub fn watch(env: &Environment) -> Result<(ConfigWatcher, Receiver<ConfigUpdate>), ConfigError> {
+    let folder = DEFAULT_FOLDER.as_path();
+    let (sender, receiver) = mpsc::channel();
+    let names = [format!("{env}.local.yaml"), format!("{env}.yaml")];
+    let env = env.clone();
+    let path = folder.to_path_buf();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let touches_config = |path: &PathBuf| {
+            path.file_name()
+                .is_some_and(|name| names.iter().any(|config| name == config.as_str()))
+        };
+        if !(event.kind.is_modify() || event.kind.is_create())
+            || !event.paths.iter().any(touches_config)
+        {
+            return;
+        }
+        let current = match Config::from_folder(&env, &path) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Ignoring the changed config: {}", e);
+                return;
+            }
+        };
+        let warnings = take_warnings();
+        let previous = Config::get();
+        // Editors often report one save as several events
+        if serde_json::to_value(&*previous).ok() == serde_json::to_value(&current).ok() {
+            return;
+        }
+        Config::replace(current.clone());
+        info!("Config reloaded");
+        for warning in warnings {
+            warn!("{}", warning);
+        }
+
+        let mut restart = previous.chip8.restart_required_changes(&current.chip8);
+        // Only the log level and filter are swapped at runtime
+        let fixed_logger = |config: &Config| {
+            serde_json::to_value(Logger {
+                level: LogLevel::default(),
+                override_filter: None,
+                ..config.logger.clone()
+            })
+            .ok()
+        };
+        if fixed_logger(&previous) != fixed_logger(&current) {
+            restart.push("logger".to_string());
+        }
+        if !restart.is_empty() {
+            warn!(
+                "Changes to {} take effect after a restart",
+                restart.join(", ")
+            );
+        }
+        logger::set_level(&current.logger);
+        let _ = sender.send(ConfigUpdate {
+            previous: (*previous).clone(),
+            current,
+        });
+    })
+    .map_err(|e| ConfigError::WatchError(e.to_string()))?;
+    watcher
+        .watch(folder, RecursiveMode::NonRecursive)
+        .map_err(|e| ConfigError::WatchError(e.to_string()))?;
+    Ok((ConfigWatcher { _watcher: watcher }, receiver))
+}
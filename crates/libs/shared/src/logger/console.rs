@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write as _};
+use std::sync::{Mutex, OnceLock};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+// Lines kept for the in-app log console, set up by `logger::init`
+static LINES: OnceLock<Mutex<VecDeque<LogLine>>> = OnceLock::new();
+
+/// A formatted log event, e.g. `chip8::core::emulator: Resetting the machine`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLine {
+    pub level: Level,
+    pub text: String,
+}
+
+/// Keeps the last `capacity` events it receives in memory for `recent`.
+pub struct ConsoleLayer {
+    capacity: usize,
+}
+
+impl ConsoleLayer {
+    pub fn new(capacity: usize) -> Self {
+        LINES.get_or_init(|| Mutex::new(VecDeque::with_capacity(capacity)));
+        Self { capacity }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut text = format!("{}: ", event.metadata().target());
+        event.record(&mut LineVisitor(&mut text));
+        let Some(lines) = LINES.get() else {
+            return;
+        };
+        let mut lines = lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            level: *event.metadata().level(),
+            text,
+        });
+    }
+}
+
+/// Writes the message followed by the other fields as `name=value`.
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Returns up to `count` of the kept lines, oldest first, ending `skip` lines before the
+/// newest one. Empty when the console layer is not installed.
+pub fn recent(count: usize, skip: usize) -> Vec<LogLine> {
+    let Some(lines) = LINES.get() else {
+        return Vec::new();
+    };
+    let lines = lines.lock().unwrap();
+    let end = lines.len().saturating_sub(skip);
+    lines
+        .range(end.saturating_sub(count)..end)
+        .cloned()
+        .collect()
+}
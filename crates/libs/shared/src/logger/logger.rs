@@ -1,17 +1,23 @@
 use std::sync::OnceLock;
 
+use anyhow::{anyhow, Error};
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_variant::to_variant_name;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer, Registry};
+
+use crate::config::config::{self, Config, DisplayBackend};
+use crate::config::{dirs, migrate};
 
-use crate::config::config::Config;
+use super::console::ConsoleLayer;
 
 // Define an enumeration for log levels
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
 pub enum LogLevel {
     /// The "off" level.
     #[serde(rename = "off")]
@@ -35,7 +41,7 @@ pub enum LogLevel {
 }
 
 // Define an enumeration for log formats
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
 pub enum Format {
     #[serde(rename = "compact")]
     #[default]
@@ -47,7 +53,7 @@ pub enum Format {
 }
 
 // Define an enumeration for log file appender rotation
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, Deserialize, Serialize, JsonSchema)]
 pub enum Rotation {
     #[serde(rename = "minutely")]
     Minutely,
@@ -68,99 +74,168 @@ impl std::fmt::Display for LogLevel {
 }
 
 // Function to initialize the logger based on the provided configuration
-const MODULE_WHITELIST: &[&str] = &["desktop", "shared", "chip8", "display"];
+const MODULE_WHITELIST: &[&str] = &["desktop", "shared", "chip8", "display", "remote"];
+
+// Log file names when the config leaves them out, e.g. `chip8.2024-01-01-10.log`
+const DEFAULT_FILENAME_PREFIX: &str = "chip8";
+const DEFAULT_FILENAME_SUFFIX: &str = "log";
+
+pub type BoxedLayer = Box<dyn Layer<Registry> + Sync + Send>;
+pub type Layers = Vec<BoxedLayer>;
 
-// Keep nonblocking file appender work guard
-static NONBLOCKING_WORK_GUARD_KEEP: OnceLock<WorkerGuard> = OnceLock::new();
+// Swaps the console filter when the config is reloaded
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 ///
 /// Tracing filtering rules:
 /// 1. if `RUST_LOG`, use that filter
-/// 2. if we have a config, and in it `override_filter` use that filter (ignore
-///    all else)
-/// 3. take `MODULE_WHITELIST` and filter only events from these modules, use
+/// 2. take `MODULE_WHITELIST` and filter only events from these modules, use
 ///    `config.level` on each to filter their events
+/// 3. if we have a config, and in it `override_filter`, add its directives on
+///    top of (2); the most specific target wins, so `chip8::core::cpu=warn`
+///    quiets the instruction traces while the other modules keep `config.level`
 ///
 /// use cases:
 /// 1. mostly, people will set the level and will trust *us* to decide which
 ///    modules to stream events from
 /// 2. people who will disagree with us, will set the `override_filter`
-///    permanently, or make up their own whitelist filtering (or suggest it to
-///    use via PR)
+///    permanently, e.g. `chip8::core=trace,display=warn`, or a bare level such
+///    as `trace` to see third party libraries too
 /// 3. regardless of (1) and (2) operators in production, or elsewhere can
 ///    always use `RUST_LOG` to quickly diagnose a service
-pub fn init() {
+///
+/// The console and the file appender filter separately, each with its own level. The returned
+/// guard flushes a non-blocking file appender when dropped, so keep it until the app exits.
+pub fn init() -> Result<Option<WorkerGuard>, Error> {
+    init_with(Vec::new())
+}
+
+/// Like `init`, with `extra` layers such as a profiler receiving every event and span
+/// unfiltered.
+pub fn init_with(extra: Layers) -> Result<Option<WorkerGuard>, Error> {
     let config = Config::get();
-    let mut layers: Vec<Box<dyn Layer<Registry> + Sync + Send>> = Vec::new();
+    let mut layers: Layers = extra;
+    let mut guard = None;
 
     if let Some(file_appender_config) = config.logger.file_appender.as_ref() {
         if file_appender_config.enable {
-            let dir = file_appender_config
-                .dir
-                .as_ref()
-                .map_or_else(|| "./logs".to_string(), ToString::to_string);
-
-            let mut rolling_builder = tracing_appender::rolling::Builder::default()
-                .max_log_files(file_appender_config.max_log_files);
-
-            rolling_builder = match file_appender_config.rotation {
-                Rotation::Minutely => {
-                    rolling_builder.rotation(tracing_appender::rolling::Rotation::MINUTELY)
-                }
-                Rotation::Hourly => {
-                    rolling_builder.rotation(tracing_appender::rolling::Rotation::HOURLY)
-                }
-                Rotation::Daily => {
-                    rolling_builder.rotation(tracing_appender::rolling::Rotation::DAILY)
-                }
-                Rotation::Never => {
-                    rolling_builder.rotation(tracing_appender::rolling::Rotation::NEVER)
-                }
-            };
-
-            let file_appender = rolling_builder
-                .filename_prefix(
-                    file_appender_config
-                        .filename_prefix
-                        .as_ref()
-                        .map_or_else(String::new, ToString::to_string),
-                )
-                .filename_suffix(
-                    file_appender_config
-                        .filename_suffix
-                        .as_ref()
-                        .map_or_else(String::new, ToString::to_string),
-                )
-                .build(dir)
-                .expect("logger file appender initialization failed");
-
-            let file_appender_layer = if file_appender_config.non_blocking {
-                let (non_blocking_file_appender, work_guard) =
-                    tracing_appender::non_blocking(file_appender);
-                NONBLOCKING_WORK_GUARD_KEEP.set(work_guard).unwrap();
-                init_layer(non_blocking_file_appender, &config.logger.format, false)
-            } else {
-                init_layer(file_appender, &config.logger.format, false)
-            };
-            layers.push(file_appender_layer);
+            let (layer, work_guard) = init_file_layer(file_appender_config)?;
+            let filter = init_env_filter(
+                config.logger.override_filter.as_ref(),
+                &file_appender_config.level,
+                &config.app.name,
+            )?;
+            layers.push(layer.with_filter(filter).boxed());
+            guard = work_guard;
         }
     }
 
-    if config.logger.enable {
+    // The terminal frontend draws on stdout, its logs can only go to the file appender.
+    if config.logger.enable && config.chip8.backend != DisplayBackend::Terminal {
+        let filter = init_env_filter(
+            config.logger.override_filter.as_ref(),
+            &config.logger.level,
+            &config.app.name,
+        )?;
+        let (filter, handle) = reload::Layer::new(filter);
+        FILTER_HANDLE.set(handle).ok();
         let stdout_layer = init_layer(std::io::stdout, &config.logger.format, true);
-        layers.push(stdout_layer);
+        layers.push(stdout_layer.with_filter(filter).boxed());
     }
 
-    if !layers.is_empty() {
-        let env_filter = init_env_filter(
+    // Feeds the log console of the SDL window, filtered like the console at start
+    if config.logger.console_lines > 0 && config.chip8.backend == DisplayBackend::Sdl {
+        let filter = init_env_filter(
             config.logger.override_filter.as_ref(),
             &config.logger.level,
             &config.app.name,
+        )?;
+        let layer = ConsoleLayer::new(config.logger.console_lines);
+        layers.push(layer.with_filter(filter).boxed());
+    }
+
+    if !layers.is_empty() {
+        tracing_subscriber::registry().with(layers).init();
+    }
+    for warning in migrate::take_warnings() {
+        tracing::warn!("{}", warning);
+    }
+    Ok(guard)
+}
+
+/// Builds the layer writing to the rolling log files, with the guard flushing the background
+/// writer of a non-blocking appender.
+fn init_file_layer(
+    file_appender_config: &config::LoggerFileAppender,
+) -> Result<(BoxedLayer, Option<WorkerGuard>), Error> {
+    let dir = file_appender_config
+        .dir
+        .as_ref()
+        .map_or_else(|| dirs::data_path("logs"), dirs::data_path);
+
+    let rotation = match file_appender_config.rotation {
+        Rotation::Minutely => tracing_appender::rolling::Rotation::MINUTELY,
+        Rotation::Hourly => tracing_appender::rolling::Rotation::HOURLY,
+        Rotation::Daily => tracing_appender::rolling::Rotation::DAILY,
+        Rotation::Never => tracing_appender::rolling::Rotation::NEVER,
+    };
+    let mut rolling_builder = tracing_appender::rolling::Builder::default()
+        .rotation(rotation)
+        .filename_prefix(
+            file_appender_config
+                .filename_prefix
+                .as_deref()
+                .unwrap_or(DEFAULT_FILENAME_PREFIX),
+        )
+        .filename_suffix(
+            file_appender_config
+                .filename_suffix
+                .as_deref()
+                .unwrap_or(DEFAULT_FILENAME_SUFFIX),
         );
-        tracing_subscriber::registry()
-            .with(layers)
-            .with(env_filter)
-            .init();
+    // 0 keeps every file
+    if file_appender_config.max_log_files > 0 {
+        rolling_builder = rolling_builder.max_log_files(file_appender_config.max_log_files);
+    }
+    let file_appender = rolling_builder
+        .build(&dir)
+        .map_err(|e| anyhow!("Failed to open the log folder {}: {}", dir.display(), e))?;
+
+    Ok(if file_appender_config.non_blocking {
+        let (non_blocking_file_appender, work_guard) =
+            tracing_appender::non_blocking(file_appender);
+        (
+            init_layer(
+                non_blocking_file_appender,
+                &file_appender_config.format,
+                false,
+            ),
+            Some(work_guard),
+        )
+    } else {
+        (
+            init_layer(file_appender, &file_appender_config.format, false),
+            None,
+        )
+    })
+}
+
+/// Applies the level and `override_filter` of a reloaded config to the console, `RUST_LOG` still
+/// wins. The file appender keeps the filter it started with.
+pub fn set_level(logger: &config::Logger) {
+    let Some(handle) = FILTER_HANDLE.get() else {
+        return;
+    };
+    let app_name = Config::get().app.name.clone();
+    let filter = match init_env_filter(logger.override_filter.as_ref(), &logger.level, &app_name) {
+        Ok(filter) => filter,
+        Err(e) => {
+            tracing::warn!("Keeping the log level: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = handle.reload(filter) {
+        tracing::warn!("Failed to change the log level: {}", e);
     }
 }
 
@@ -168,31 +243,28 @@ fn init_env_filter(
     override_filter: Option<&String>,
     level: &LogLevel,
     app_name: &str,
-) -> EnvFilter {
-    EnvFilter::try_from_default_env()
-        .or_else(|_| {
-            override_filter.map_or_else(
-                || {
-                    EnvFilter::try_new(
-                        MODULE_WHITELIST
-                            .iter()
-                            .map(|m| format!("{m}={level}"))
-                            .chain(std::iter::once(format!("{}={}", app_name, level)))
-                            .collect::<Vec<_>>()
-                            .join(","),
-                    )
-                },
-                EnvFilter::try_new,
-            )
-        })
-        .expect("logger initialization failed")
+) -> Result<EnvFilter, Error> {
+    if let Ok(rust_log) = std::env::var(EnvFilter::DEFAULT_ENV) {
+        return EnvFilter::try_new(&rust_log)
+            .map_err(|e| anyhow!("Invalid RUST_LOG filter '{}': {}", rust_log, e));
+    }
+    let directives = MODULE_WHITELIST
+        .iter()
+        .map(|m| format!("{m}={level}"))
+        .chain(std::iter::once(format!("{}={}", app_name, level)))
+        .chain(override_filter.cloned())
+        .collect::<Vec<_>>()
+        .join(",");
+    EnvFilter::try_new(&directives).map_err(|e| {
+        anyhow!(
+            "Invalid logger.override_filter '{}': {}",
+            override_filter.map_or("", |filter| filter.as_str()),
+            e
+        )
+    })
 }
 
-fn init_layer<W2>(
-    make_writer: W2,
-    format: &Format,
-    ansi: bool,
-) -> Box<dyn Layer<Registry> + Sync + Send>
+fn init_layer<W2>(make_writer: W2, format: &Format, ansi: bool) -> BoxedLayer
 where
     W2: for<'writer> MakeWriter<'writer> + Sync + Send + 'static,
 {
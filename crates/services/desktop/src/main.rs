@@ -1,18 +1,1245 @@
-use anyhow::Error;
-use log::{debug, info, trace, warn};
+use anyhow::{anyhow, Error};
+use chip8::core::audio::{AudioSink, Silence, SoundLog};
+use chip8::core::bench::{self, BenchSettings};
+use chip8::core::cheats::Cheats;
+use chip8::core::chip8::CHIP8;
+use chip8::core::compat::{self, CompatSettings};
+use chip8::core::cpu::CpuController;
+use chip8::core::crash;
+use chip8::core::disasm::Listing;
+use chip8::core::emulator::Emulator;
+use chip8::core::gallery::GALLERY;
+use chip8::core::golden::{check_suite, Outcome};
+use chip8::core::identify::Identity;
+use chip8::core::lint;
+use chip8::core::octo;
+use chip8::core::replay::{Quirks, Recording};
+use chip8::core::romdb::RomDatabase;
+use chip8::core::script;
+use chip8::core::sprites::{find_sprites, Sprite};
+use chip8::core::trace::{self, Trace};
+use chip8::core::wav::WavRecorder;
+use shared::config::config::{AudioBackend, ChipSettings, Config, DisplayBackend, Override};
+use shared::config::dirs::{config_folder, data_path};
+use shared::config::init::init;
+use shared::config::profile::{find, rom_hash};
+use shared::config::schema::schema;
+use shared::config::state::State;
+use shared::config::watch::watch;
 use shared::{config::environment::Environment, logger::logger};
+use std::io::{IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tracing::{error, info, warn};
+use tracing_chrome::ChromeLayerBuilder;
+use tracing_subscriber::Layer;
 
-//TODO: LOG NOT WORKING
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    Environment::from_env().load()?;
-    logger::init();
-    info!("Environment loaded successfully");
-    println!("SA");
+    match std::env::args().nth(1).as_deref() {
+        Some("config") => return config_command(std::env::args().skip(2)),
+        Some("check") => return check_command(std::env::args().skip(2)),
+        Some("disasm") => return disasm_command(std::env::args().skip(2)),
+        Some("decompile") => return decompile_command(std::env::args().skip(2)),
+        Some("sprites") => return sprites_command(std::env::args().skip(2)),
+        Some("lint") => return lint_command(std::env::args().skip(2)),
+        Some("id") => return id_command(std::env::args().skip(2)),
+        Some("bench") => return bench_command(std::env::args().skip(2)),
+        _ => {}
+    }
+    let args = parse_args(std::env::args().skip(1))?;
+    let environment = Environment::from_env();
+    let mut config = environment.load_with(args.overrides)?;
+    // Dropping the guards flushes the log file and writes the trace, keep them until the
+    // emulator exits
+    let (profiler, _trace_guard) = match &args.trace_out {
+        Some(path) => {
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .file(path)
+                .include_args(true)
+                .build();
+            (vec![layer.boxed()], Some(guard))
+        }
+        None => (Vec::new(), None),
+    };
+    let _log_guard = logger::init_with(profiler)?;
+    info!(
+        "Environment loaded successfully from {}",
+        config_folder().display()
+    );
+
+    let mut emulator = Emulator::new(CHIP8::default());
+    match &args.rom {
+        Rom::Path(path) => emulator.init_ram(path)?,
+        Rom::Builtin(name) => load_builtin(&mut emulator, name)?,
+        Rom::Choose => choose_rom(&mut emulator, &config.chip8)?,
+    }
+    let hash = rom_hash(emulator.get_rom());
+    info!("ROM SHA-1: {}", hash);
+    let mut profile = match find(&data_path(&config.chip8.profiles_folder), &hash)? {
+        Some((path, profile)) => {
+            info!("Applying the ROM profile {}", path.display());
+            Some(profile)
+        }
+        None => None,
+    };
+    let database = RomDatabase::with_updates(
+        &data_path(&config.chip8.chip8_archive),
+        &data_path(&config.chip8.rom_database),
+    )?;
+    if let Some(rom) = database.find(&hash) {
+        info!(
+            "Recognised {} by {}, written for the {} platform",
+            rom.title,
+            rom.author.as_deref().unwrap_or("an unknown author"),
+            rom.platform
+        );
+        emulator.set_rom_title(Some(rom.title.clone()));
+        // The ROM profile wins over the recommended settings
+        profile = Some(rom.profile_with(profile)?);
+    }
+    if let Some(profile) = profile {
+        config = Config::with_profile(&environment, profile)?;
+    }
+    emulator.set_quirks(Quirks::from(&config.chip8));
+    if let Some((path, cheats)) = Cheats::find(&data_path(&config.chip8.cheats_folder), &hash)? {
+        info!(
+            "Loaded {} cheats from {}",
+            cheats.list().len(),
+            path.display()
+        );
+        emulator.set_cheats(cheats);
+    }
+    emulator.set_recording_folder(Some(data_path(&config.chip8.recording_folder)));
+    emulator.set_save_slot_folder(Some(data_path(&config.chip8.save_slot_folder)));
+    if config.chip8.rewind.enable {
+        emulator.set_rewind(config.chip8.rewind.seconds);
+    }
+    if config.chip8.remote_input.enable {
+        start_remote_input(&mut emulator, &config.chip8)?;
+    }
+    if config.chip8.chat.enable {
+        start_chat(&mut emulator, &config.chip8)?;
+    }
+    if config.chip8.remote_control.enable
+        || config.chip8.ipc_control.enable
+        || config.chip8.rest_control.enable
+    {
+        start_control(&mut emulator, &config.chip8)?;
+    }
+    if config.chip8.debug_server.enable || args.debug_stdio {
+        start_debugging(&mut emulator, &config.chip8, args.debug_stdio)?;
+    }
+    if config.chip8.metrics.enable {
+        start_metrics(&mut emulator, &config.chip8)?;
+    }
+    if config.chip8.discord.enable {
+        start_discord(&mut emulator, &config.chip8)?;
+    }
+    if let Some(path) = &config.chip8.script {
+        emulator.add_service(Box::new(script::load(&data_path(path))?));
+    }
+    if config.chip8.plugins.enable {
+        start_plugins(&mut emulator, &config.chip8)?;
+    }
+    if config.chip8.dynarec.enable {
+        start_dynarec(&mut emulator, &config.chip8)?;
+    }
+    if let Some(role) = &args.netplay {
+        start_netplay(&mut emulator, &config.chip8, role)?;
+    }
+    if let Some(path) = args.replay {
+        emulator.start_replay(Recording::load(&path)?)?;
+    }
+    if args.search {
+        start_search_console(&mut emulator);
+    }
+    let audio = &mut config.chip8.audio;
+    audio.frequency = audio.frequency_for(emulator.get_rom_name());
+    info!("Buzzer tone set to {} Hz", audio.frequency);
+    let cpu = CpuController;
+
+    // Dropping the watcher stops the reloads, keep it until the emulator exits
+    let _config_watcher = match watch(&environment) {
+        Ok((watcher, updates)) => {
+            emulator.set_config_updates(updates);
+            Some(watcher)
+        }
+        Err(e) => {
+            warn!("Config changes need a restart: {}", e);
+            None
+        }
+    };
+
     info!("Starting the application");
-    trace!("This is a trace message");
-    debug!("This is a debug message");
-    info!("This is an info message");
-    warn!("This is a warning message");
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_frontend(&mut emulator, &cpu, &config.chip8)
+    }))
+    .unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow!("Emulator panicked: {}", message))
+    });
+    if let Err(e) = &result {
+        let folder = data_path(&config.chip8.crash_folder);
+        match crash::write(&emulator, &format!("{:#}", e), &folder) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => error!("{}", e),
+        }
+    }
+    // Keep an input recording still running when the window is closed
+    emulator.stop_recording()?;
+    result
+}
+
+/// Feeds the lines typed on stdin to the emulator's memory search and prints its replies.
+fn start_search_console(emulator: &mut Emulator) {
+    let (commands, received) = mpsc::channel();
+    let (replies, printed) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if commands.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    std::thread::spawn(move || {
+        for reply in printed {
+            println!("{}", reply);
+        }
+    });
+    println!("{}", chip8::core::search::HELP);
+    emulator.set_search_console(received, replies);
+}
+
+/// Waits for the other player or joins them, then restarts the ROM in lockstep with them.
+#[cfg(feature = "remote")]
+fn start_netplay(
+    emulator: &mut Emulator,
+    settings: &ChipSettings,
+    role: &NetplayRole,
+) -> Result<(), Error> {
+    use chip8::core::netplay::Session;
+    use std::net::TcpListener;
+
+    let session = Session::new(
+        emulator,
+        settings.cycles_per_frame,
+        settings.netplay.input_delay,
+    );
+    let lockstep = match role {
+        NetplayRole::Host(address) => {
+            let listener = TcpListener::bind(address)
+                .map_err(|e| anyhow!("Failed to bind {}: {}", address, e))?;
+            println!("Waiting for a player to join on {}", address);
+            remote::netplay::accept(&listener, session)?
+        }
+        NetplayRole::Join(address) => remote::netplay::connect(address, session)?,
+    };
+    emulator.start_netplay(lockstep)
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_netplay(
+    _emulator: &mut Emulator,
+    _settings: &ChipSettings,
+    _role: &NetplayRole,
+) -> Result<(), Error> {
+    Err(anyhow!(
+        "Netplay is not compiled into this build, enable the remote cargo feature"
+    ))
+}
+
+/// Serves the debug protocol from `chip8.debug_server` and, with `--debug-stdio`, on stdin and
+/// stdout, which `--debug-stdio` keeps free of logs.
+#[cfg(feature = "remote")]
+fn start_debugging(
+    emulator: &mut Emulator,
+    settings: &ChipSettings,
+    stdio: bool,
+) -> Result<(), Error> {
+    let mut hub = remote::debug::DebugHub::default();
+    if settings.debug_server.enable {
+        hub.add(remote::debug::listen(&settings.debug_server.address)?);
+    }
+    if stdio {
+        let (requests, received) = mpsc::channel();
+        std::thread::spawn(move || {
+            // Remote debuggers are numbered from 1
+            remote::debug::serve(0, std::io::stdin().lock(), std::io::stdout(), requests)
+        });
+        hub.add(received);
+    }
+    emulator.add_service(Box::new(hub));
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_debugging(
+    _emulator: &mut Emulator,
+    _settings: &ChipSettings,
+    _stdio: bool,
+) -> Result<(), Error> {
+    Err(anyhow!(
+        "The debug protocol is not compiled into this build, enable the remote cargo feature"
+    ))
+}
+
+/// Serves the enabled control endpoints, which share one hub and so one view of the screen.
+#[cfg(feature = "remote")]
+fn start_control(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    let mut hub = remote::control::ControlHub::default();
+    if settings.remote_control.enable {
+        hub.add(remote::control::listen(&settings.remote_control.address)?);
+    }
+    if settings.ipc_control.enable {
+        hub.add(remote::ipc::listen(&settings.ipc_control.name)?);
+    }
+    if settings.rest_control.enable {
+        hub.add(start_rest_control(settings)?);
+    }
+    emulator.add_service(Box::new(hub));
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_control(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "Remote control is not compiled into this build, enable the remote cargo feature"
+    ))
+}
+
+#[cfg(feature = "rest")]
+fn start_rest_control(
+    settings: &ChipSettings,
+) -> Result<mpsc::Receiver<remote::control::ControlRequest>, Error> {
+    remote::rest::serve(
+        &settings.rest_control.address,
+        settings.palette.clone(),
+        settings.scale,
+    )
+}
+
+#[cfg(all(feature = "remote", not(feature = "rest")))]
+fn start_rest_control(
+    _settings: &ChipSettings,
+) -> Result<mpsc::Receiver<remote::control::ControlRequest>, Error> {
+    Err(anyhow!(
+        "REST control is not compiled into this build, enable the rest cargo feature"
+    ))
+}
+
+#[cfg(feature = "remote")]
+fn start_remote_input(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    emulator.set_remote_input(remote::input::listen(&settings.remote_input.address)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_remote_input(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "Remote input is not compiled into this build, enable the remote cargo feature"
+    ))
+}
+
+#[cfg(feature = "remote")]
+fn start_chat(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    let chat = &settings.chat;
+    let vote = remote::chat::ChatVote::new(chat)?;
+    let messages = remote::chat::join(
+        &chat.server,
+        &chat.channel,
+        &chat.nick,
+        chat.token.as_deref(),
+    )?;
+    emulator.add_service(Box::new(remote::chat::ChatBridge::new(messages, vote)));
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+fn start_chat(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "Chat input is not compiled into this build, enable the remote cargo feature"
+    ))
+}
+
+#[cfg(feature = "discord")]
+fn start_discord(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    emulator.set_status_target(remote::presence::start(&settings.discord.client_id)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "discord"))]
+fn start_discord(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "Discord Rich Presence is not compiled into this build, enable the discord cargo feature"
+    ))
+}
+
+#[cfg(feature = "plugins")]
+fn start_plugins(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    let folder = data_path(&settings.plugins.folder);
+    for plugin in plugins::wasm::WasmPlugin::load_folder(&folder)? {
+        emulator.add_plugin(Box::new(plugin));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+fn start_plugins(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "Plugins are not compiled into this build, enable the plugins cargo feature"
+    ))
+}
+
+#[cfg(feature = "dynarec")]
+fn start_dynarec(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    use chip8::core::dynarec::DynarecMode;
+
+    emulator.set_dynarec(match settings.dynarec.verify {
+        true => DynarecMode::Verify,
+        false => DynarecMode::On,
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "dynarec"))]
+fn start_dynarec(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "The block compiler is not compiled into this build, enable the dynarec cargo feature"
+    ))
+}
+
+#[cfg(feature = "prometheus")]
+fn start_metrics(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    emulator.set_metrics_target(remote::metrics::serve(&settings.metrics.address)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "prometheus"))]
+fn start_metrics(_emulator: &mut Emulator, _settings: &ChipSettings) -> Result<(), Error> {
+    Err(anyhow!(
+        "The metrics endpoint is not compiled into this build, enable the prometheus cargo feature"
+    ))
+}
+
+/// Runs `disasm <rom> [--xref]`, which prints the ROM as labelled assembly, with a table of
+/// the instructions referring to each label when `--xref` is given.
+fn disasm_command(args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop disasm <rom> [--xref]");
+    let (mut xref, mut path) = (false, None);
+    for arg in args {
+        match arg.as_str() {
+            "--xref" => xref = true,
+            _ if !arg.starts_with("--") && path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(usage()),
+        }
+    }
+    let path = path.ok_or_else(usage)?;
+    let rom =
+        std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    print!("{}", Listing::new(&rom).render(xref));
+    Ok(())
+}
+
+/// Runs `decompile <rom>`, which prints the ROM as Octo source.
+fn decompile_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop decompile <rom>");
+    let path = match (args.next(), args.next()) {
+        (Some(path), None) if !path.starts_with("--") => PathBuf::from(path),
+        _ => return Err(usage()),
+    };
+    let rom =
+        std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    print!("{}", octo::decompile(&rom));
+    Ok(())
+}
+
+/// Runs `id <rom>`, which prints what identifies the ROM in an issue report: size, SHA-1,
+/// CRC32, its title when the builtin database knows it and the platform its opcodes suggest.
+fn id_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop id <rom>");
+    let path = match (args.next(), args.next()) {
+        (Some(path), None) if !path.starts_with("--") => PathBuf::from(path),
+        _ => return Err(usage()),
+    };
+    let rom =
+        std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let identity = Identity::new(&rom);
+    println!("File      {}", path.display());
+    println!("Size      {} bytes", identity.size);
+    println!("SHA-1     {}", identity.sha1);
+    println!("CRC32     {:08x}", identity.crc32);
+    if let Some(known) = RomDatabase::builtin().find(&identity.sha1) {
+        println!("Title     {}", known.title);
+    }
+    println!("Platform  {}", identity.platform().name());
+    for extension in &identity.extensions {
+        println!(
+            "          0x{:03X}  {:04X}  {} ({})",
+            extension.address,
+            extension.opcode,
+            extension.description,
+            extension.platform.name()
+        );
+    }
+    Ok(())
+}
+
+/// Runs `bench [rom] [--frames N] [--cycles N,N,...]`, which runs the ROM, the bundled Brix
+/// by default, headlessly under each engine and cycles per frame, draws sprites into both
+/// framebuffer layouts and prints how long each took.
+fn bench_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop bench [rom] [--frames N] [--cycles N,N,...]");
+    let number = |value: &str, name: &str| -> Result<u32, Error> {
+        value
+            .parse()
+            .map_err(|e| anyhow!("Invalid {} '{}': {}", name, value, e))
+    };
+    let (mut settings, mut path) = (BenchSettings::default(), None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => settings.frames = number(&args.next().ok_or_else(usage)?, "frame count")?,
+            "--cycles" => {
+                settings.cycles_per_frame = args
+                    .next()
+                    .ok_or_else(usage)?
+                    .split(',')
+                    .map(|cycles| number(cycles, "cycles per frame"))
+                    .collect::<Result<_, _>>()?
+            }
+            _ if !arg.starts_with("--") && path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(usage()),
+        }
+    }
+    let (name, rom) = match path {
+        Some(path) => {
+            let rom = std::fs::read(&path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            (path.display().to_string(), rom)
+        }
+        None => (GALLERY[0].name.to_string(), GALLERY[0].bytes.to_vec()),
+    };
+    println!(
+        "{}, {} frames per configuration, fastest of {} runs\n",
+        name,
+        settings.frames,
+        bench::ROUNDS
+    );
+    print!("{}", bench::table(&bench::run(&rom, &settings)?));
+    Ok(())
+}
+
+/// Runs `lint <rom>`, which prints the suspicious patterns found in the ROM's code and fails
+/// when there are any.
+fn lint_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop lint <rom>");
+    let path = match (args.next(), args.next()) {
+        (Some(path), None) if !path.starts_with("--") => PathBuf::from(path),
+        _ => return Err(usage()),
+    };
+    let rom =
+        std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let findings = lint::lint(&rom);
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    if !findings.is_empty() {
+        return Err(anyhow!(
+            "{} issue(s) found in {}",
+            findings.len(),
+            path.display()
+        ));
+    }
+    println!("No issues found in {}", path.display());
+    Ok(())
+}
+
+/// Runs `sprites <rom> [--rows N] [--sheet <sheet.png>] [--scale N]`, which prints the
+/// plausible sprites of the ROM, `--rows` tall when no `DXYN` tells, 8 by default, and writes
+/// them to a PNG sheet with `--sheet`.
+fn sprites_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage =
+        || anyhow!("Usage: desktop sprites <rom> [--rows N] [--sheet <sheet.png>] [--scale N]");
+    let number = |value: Option<String>, name: &str| -> Result<u32, Error> {
+        let value = value.ok_or_else(usage)?;
+        value
+            .parse()
+            .map_err(|e| anyhow!("Invalid {} '{}': {}", name, value, e))
+    };
+    let (mut rows, mut sheet, mut scale, mut path) = (8, None, 8, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rows" => rows = number(args.next(), "row count")?,
+            "--sheet" => sheet = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--scale" => scale = number(args.next(), "scale")?,
+            _ if !arg.starts_with("--") && path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(usage()),
+        }
+    }
+    let path = path.ok_or_else(usage)?;
+    let rom =
+        std::fs::read(&path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let sprites = find_sprites(&rom, rows.min(u8::MAX as u32) as u8);
+    for sprite in &sprites {
+        let kind = if sprite.referenced {
+            "sprite"
+        } else {
+            "candidate"
+        };
+        println!(
+            "0x{:03X} {}, {} rows\n{}\n",
+            sprite.address,
+            kind,
+            sprite.rows.len(),
+            sprite.to_art()
+        );
+    }
+    match sheet {
+        Some(sheet) => write_sheet(&sprites, &sheet, scale),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "image")]
+fn write_sheet(sprites: &[Sprite], path: &Path, scale: u32) -> Result<(), Error> {
+    chip8::core::export::write_sprite_sheet(sprites, path, scale, &Default::default())?;
+    println!("Wrote {} sprites to {}", sprites.len(), path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "image"))]
+fn write_sheet(_sprites: &[Sprite], _path: &Path, _scale: u32) -> Result<(), Error> {
+    Err(anyhow!(
+        "Sprite sheets are not compiled into this build, enable the image cargo feature"
+    ))
+}
+
+/// Runs `check --suite [folder] [--bless]`, which runs the ROMs of a golden-test suite
+/// headlessly and compares their screens to the known-good captures, `tests/golden` by
+/// default, `check --trace <trace> <rom> [--bless [--steps N]]`, which runs a ROM against a
+/// reference trace and stops at the first instruction that differs, or `check --dir <folder>`,
+/// which writes a compatibility report of every ROM in a folder. `--bless` rewrites the
+/// captures or the trace from this build instead.
+fn check_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || {
+        anyhow!(
+            "Usage: desktop check --suite [folder] [--bless] | \
+             desktop check --trace <trace> <rom> [--bless [--steps N]] | \
+             desktop check --dir <folder> [--quirks <preset>] [--seconds N] [--cycles N] \
+             [--report <report.md|report.html>]"
+        )
+    };
+    let number = |value: Option<String>, name: &str| -> Result<u32, Error> {
+        let value = value.ok_or_else(usage)?;
+        value
+            .parse()
+            .map_err(|e| anyhow!("Invalid {} '{}': {}", name, value, e))
+    };
+    let (mut suite, mut trace, mut dir, mut bless, mut steps) = (false, None, None, false, None);
+    let (mut compat, mut report) = (CompatSettings::default(), None);
+    let mut paths = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--suite" => suite = true,
+            "--trace" => trace = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--dir" => dir = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--bless" => bless = true,
+            "--steps" => steps = Some(number(args.next(), "step count")? as usize),
+            "--quirks" => compat.preset = args.next().ok_or_else(usage)?,
+            "--seconds" => compat.seconds = number(args.next(), "seconds")?,
+            "--cycles" => compat.cycles_per_frame = number(args.next(), "cycles per frame")?,
+            "--report" => report = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            _ if !arg.starts_with("--") => paths.push(PathBuf::from(arg)),
+            _ => return Err(usage()),
+        }
+    }
+    match (suite, trace, dir) {
+        (true, None, None) if paths.len() <= 1 && steps.is_none() => {
+            let folder = paths.pop().unwrap_or_else(|| PathBuf::from("tests/golden"));
+            check_suite_command(&folder, bless)
+        }
+        (false, Some(trace), None) if paths.len() == 1 && (bless || steps.is_none()) => {
+            check_trace_command(&trace, &paths[0], bless, steps)
+        }
+        (false, None, Some(dir)) if paths.is_empty() && !bless && steps.is_none() => {
+            check_dir_command(&dir, &compat, report.as_deref())
+        }
+        _ => Err(usage()),
+    }
+}
+
+fn check_suite_command(folder: &Path, bless: bool) -> Result<(), Error> {
+    let mut failed = 0;
+    for (golden, outcome) in check_suite(folder, bless)? {
+        match outcome {
+            Outcome::Pass => println!("pass     {}", golden.name),
+            Outcome::Blessed => println!("blessed  {}", golden.name),
+            Outcome::MissingRom => {
+                println!("skipped  {} (no {})", golden.name, golden.rom.display())
+            }
+            Outcome::MissingCapture => {
+                failed += 1;
+                println!("fail     {} (no capture, run with --bless)", golden.name);
+            }
+            Outcome::Fail(diff) => {
+                failed += 1;
+                println!("fail     {}\n{}", golden.name, diff);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} ROMs of the suite failed", failed));
+    }
+    Ok(())
+}
+
+/// Prints how every ROM of `folder` runs, or writes it to `report` as Markdown or, for an
+/// `.html` file, HTML.
+fn check_dir_command(
+    folder: &Path,
+    settings: &CompatSettings,
+    report: Option<&Path>,
+) -> Result<(), Error> {
+    let reports = compat::check_dir(folder, settings)?;
+    for rom in &reports {
+        eprintln!("{:<15} {}", rom.verdict.label(), rom.name);
+    }
+    let Some(path) = report else {
+        print!("{}", compat::markdown(&reports, settings));
+        return Ok(());
+    };
+    let text = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html" | "htm") => compat::html(&reports, settings),
+        _ => compat::markdown(&reports, settings),
+    };
+    std::fs::write(path, text).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    println!(
+        "Wrote the report of {} ROMs to {}",
+        reports.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn check_trace_command(
+    path: &Path,
+    rom_path: &Path,
+    bless: bool,
+    steps: Option<usize>,
+) -> Result<(), Error> {
+    let rom = std::fs::read(rom_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", rom_path.display(), e))?;
+    if bless {
+        // Keeps the settings of an existing trace, so only the steps are rewritten
+        let settings = if path.exists() {
+            Trace::load(path)?
+        } else {
+            Trace::default()
+        };
+        let steps = steps.unwrap_or(settings.steps.len().max(1000));
+        let trace = trace::record(
+            &rom,
+            steps,
+            settings.cycles_per_frame,
+            settings.seed,
+            settings.quirks,
+        )?;
+        trace.save(path)?;
+        println!("Wrote {} steps to {}", trace.steps.len(), path.display());
+        return Ok(());
+    }
+    let trace = Trace::load(path)?;
+    match trace::conform(&rom, &trace)? {
+        None => {
+            println!(
+                "All {} steps of {} match",
+                trace.steps.len(),
+                path.display()
+            );
+            Ok(())
+        }
+        Some(divergence) => {
+            print!("{}", divergence);
+            Err(anyhow!(
+                "{} of {} steps of {} match",
+                divergence.step,
+                trace.steps.len(),
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Runs `config init [--force]`, which writes the default config and `.env` for the current
+/// environment, or `config schema`, which prints the JSON Schema of the config files.
+fn config_command(mut args: impl Iterator<Item = String>) -> Result<(), Error> {
+    let usage = || anyhow!("Usage: desktop config init [--force] | desktop config schema");
+    match args.next().as_deref() {
+        Some("init") => {}
+        Some("schema") if args.next().is_none() => {
+            println!("{}", serde_json::to_string_pretty(&schema())?);
+            return Ok(());
+        }
+        _ => return Err(usage()),
+    }
+    let force = match args.next().as_deref() {
+        None => false,
+        Some("--force") => true,
+        Some(_) => return Err(usage()),
+    };
+    for file in init(&Environment::from_env(), force)? {
+        if file.written {
+            println!("Wrote {}", file.path.display());
+        } else {
+            println!(
+                "Kept the existing {}, pass --force to overwrite it",
+                file.path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+enum Rom {
+    Path(String),
+    /// Name of a ROM compiled in with the test-roms feature
+    Builtin(String),
+    /// None given, picked from the ROM folder or the gallery
+    Choose,
+}
+
+// The addresses are only read when netplay is compiled in
+#[cfg_attr(not(feature = "remote"), allow(dead_code))]
+enum NetplayRole {
+    /// Address to wait for the other player on
+    Host(String),
+    /// Address of the host
+    Join(String),
+}
+
+struct Args {
+    rom: Rom,
+    replay: Option<PathBuf>,
+    /// Chrome trace file recording the spans of the run, for chrome://tracing or Perfetto
+    trace_out: Option<PathBuf>,
+    /// Reads memory search commands from stdin while running
+    search: bool,
+    /// Speaks the debug protocol on stdin and stdout
+    debug_stdio: bool,
+    netplay: Option<NetplayRole>,
+    overrides: Vec<Override>,
+}
+
+/// Reads `<path-to-rom> | --builtin <name>` followed by `[--replay <recording.c8r>]
+/// [--trace-out <trace.json>] [--search] [--debug-stdio] [--host <address> | --join <address>]
+/// [--<setting> <value>...]`, any other flag overrides a config setting, e.g. `--scale 20`.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, Error> {
+    let usage = || {
+        anyhow!(
+            "Usage: desktop [<path-to-rom> | --builtin <name>] [--replay <recording.c8r>] \
+             [--trace-out <trace.json>] [--search] [--debug-stdio] \
+             [--host <address> | --join <address>] [--<setting> <value>...]"
+        )
+    };
+    let mut rom_path = None;
+    let mut builtin = None;
+    let mut replay = None;
+    let mut trace_out = None;
+    let mut search = false;
+    let mut debug_stdio = false;
+    let mut netplay = None;
+    let mut overrides = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--builtin" => builtin = Some(args.next().ok_or_else(usage)?),
+            "--replay" => replay = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--trace-out" => trace_out = Some(PathBuf::from(args.next().ok_or_else(usage)?)),
+            "--search" => search = true,
+            "--host" if netplay.is_none() => {
+                netplay = Some(NetplayRole::Host(args.next().ok_or_else(usage)?))
+            }
+            "--join" if netplay.is_none() => {
+                netplay = Some(NetplayRole::Join(args.next().ok_or_else(usage)?))
+            }
+            "--host" | "--join" => return Err(usage()),
+            "--debug-stdio" => {
+                debug_stdio = true;
+                // Stdout carries the protocol, the logs can only go to the file appender
+                overrides.push(Override {
+                    path: vec!["logger".to_string(), "enable".to_string()],
+                    value: "false".to_string(),
+                });
+            }
+            _ if arg.starts_with("--") => overrides.push(Override::from_args(&arg, &mut args)?),
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+    let rom = match (rom_path, builtin) {
+        (Some(path), None) => Rom::Path(path),
+        (None, Some(name)) => Rom::Builtin(name),
+        (None, None) => Rom::Choose,
+        _ => return Err(usage()),
+    };
+    Ok(Args {
+        rom,
+        replay,
+        trace_out,
+        search,
+        debug_stdio,
+        netplay,
+        overrides,
+    })
+}
+
+/// Asks which ROM to run when none is given: one of the `default_ch8_folder` when it has any,
+/// one of the gallery compiled in otherwise. Without a terminal to ask in, the first is run.
+fn choose_rom(emulator: &mut Emulator, settings: &ChipSettings) -> Result<(), Error> {
+    let folder = data_path(&settings.default_ch8_folder);
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file())
+                .collect()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    if !paths.is_empty() {
+        let names: Vec<String> = paths
+            .iter()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        println!("ROMs in {}:", folder.display());
+        let path = &paths[choose(&names)?];
+        return emulator.init_ram(&path.to_string_lossy());
+    }
+
+    let names: Vec<String> = GALLERY
+        .iter()
+        .map(|rom| format!("{:<14}{}", rom.name, rom.description))
+        .collect();
+    println!(
+        "No ROM given and no ROMs in {}, pick one of the gallery:",
+        folder.display()
+    );
+    let rom = &GALLERY[choose(&names)?];
+    info!("Loading {} from the gallery", rom.name);
+    emulator.init_ram_from_bytes(rom.bytes)?;
+    emulator.set_rom_name(Some(rom.name.to_string()));
+    Ok(())
+}
+
+/// Lists `choices` numbered from 1 and reads the number of one from the terminal.
+fn choose(choices: &[String]) -> Result<usize, Error> {
+    for (number, choice) in choices.iter().enumerate() {
+        println!("{:>3}  {}", number + 1, choice);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(0);
+    }
+    loop {
+        print!("Number of the ROM to run: ");
+        std::io::stdout()
+            .flush()
+            .map_err(|e| anyhow!("Failed to write the prompt: {}", e))?;
+        let mut line = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| anyhow!("Failed to read the choice: {}", e))?;
+        if read == 0 {
+            return Err(anyhow!("No ROM chosen"));
+        }
+        match line.trim().parse::<usize>() {
+            Ok(number) if (1..=choices.len()).contains(&number) => return Ok(number - 1),
+            _ => println!("Enter a number from 1 to {}", choices.len()),
+        }
+    }
+}
+
+#[cfg(feature = "test-roms")]
+fn load_builtin(emulator: &mut Emulator, name: &str) -> Result<(), Error> {
+    let rom = chip8::core::roms::builtin(name)?;
+    info!("Loading the builtin ROM {}: {}", rom.name, rom.description);
+    emulator.init_ram_from_bytes(rom.bytes)?;
+    emulator.set_rom_name(Some(rom.name.to_string()));
     Ok(())
 }
+
+#[cfg(not(feature = "test-roms"))]
+fn load_builtin(_emulator: &mut Emulator, _name: &str) -> Result<(), Error> {
+    Err(anyhow!(
+        "Builtin ROMs are not compiled into this build, enable the test-roms cargo feature"
+    ))
+}
+
+#[allow(unreachable_patterns)]
+fn run_frontend(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+) -> Result<(), Error> {
+    info!("Using {:?} display backend", settings.backend);
+    match settings.backend {
+        #[cfg(feature = "sdl")]
+        DisplayBackend::Sdl => run_sdl(emulator, cpu, settings),
+        #[cfg(feature = "wgpu")]
+        DisplayBackend::Wgpu => with_audio(settings, open_audio(settings), |audio| {
+            display_wgpu::window::run(emulator, cpu, settings, audio)
+        }),
+        #[cfg(feature = "pixels")]
+        DisplayBackend::Pixels => with_audio(settings, open_audio(settings), |audio| {
+            display_pixels::window::run(emulator, cpu, settings, audio)
+        }),
+        #[cfg(feature = "terminal")]
+        DisplayBackend::Terminal => with_audio(settings, open_audio(settings), |audio| {
+            run_terminal(emulator, cpu, settings, audio)
+        }),
+        #[cfg(feature = "minifb")]
+        DisplayBackend::Minifb => with_audio(settings, open_audio(settings), |audio| {
+            display_minifb::window::run(emulator, cpu, settings, audio)
+        }),
+        #[cfg(feature = "mobile")]
+        DisplayBackend::Mobile => with_audio(settings, open_audio(settings), |audio| {
+            let autosave = data_path(&settings.autosave_folder)
+                .join(format!("{}.c8s", rom_hash(emulator.get_rom())));
+            display_mobile::window::run(emulator, cpu, settings, audio, Some(autosave))
+        }),
+        backend => Err(anyhow!(
+            "The {:?} display backend is not compiled into this build, enable its cargo feature",
+            backend
+        )),
+    }
+}
+
+/// Resolves `audio.backend`: `auto` plays through SDL with the SDL display and through cpal,
+/// when compiled in, otherwise.
+fn audio_backend(settings: &ChipSettings) -> AudioBackend {
+    match settings.audio.backend {
+        AudioBackend::Auto if settings.backend == DisplayBackend::Sdl => AudioBackend::Sdl,
+        AudioBackend::Auto if cfg!(feature = "cpal") => AudioBackend::Cpal,
+        AudioBackend::Auto => AudioBackend::None,
+        backend => backend,
+    }
+}
+
+/// Opens the configured sound output, `None` when sound is disabled. SDL audio is opened by
+/// `run_sdl` from its own context.
+#[allow(unreachable_patterns)]
+fn open_audio(settings: &ChipSettings) -> Result<Option<Box<dyn AudioSink>>, Error> {
+    match audio_backend(settings) {
+        AudioBackend::None => Ok(None),
+        AudioBackend::Silent => Ok(Some(Box::new(SoundLog::new(settings.audio.volume)))),
+        #[cfg(feature = "cpal")]
+        AudioBackend::Cpal => Ok(Some(Box::new(audio_cpal::buzzer::CpalBuzzer::new(
+            &settings.audio,
+        )?))),
+        AudioBackend::Sdl => Err(anyhow!(
+            "The sdl audio backend needs the sdl display backend"
+        )),
+        backend => Err(anyhow!(
+            "The {:?} audio backend is not compiled into this build, enable its cargo feature",
+            backend
+        )),
+    }
+}
+
+/// Restores the saved volume into `audio` and runs `frontend` with it, silently when sound is
+/// disabled or failed to open, then saves the volume set at runtime.
+fn with_audio<F>(
+    settings: &ChipSettings,
+    audio: Result<Option<Box<dyn AudioSink>>, Error>,
+    frontend: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn AudioSink) -> Result<(), Error>,
+{
+    // Sound is optional, keep running silently when no audio device can be opened.
+    let mut audio = audio.unwrap_or_else(|e| {
+        warn!("Running without sound: {:?}", e);
+        None
+    });
+    let Some(audio) = audio.as_deref_mut() else {
+        return with_recording(settings, &mut Silence, frontend);
+    };
+
+    let state = State::load();
+    if let Some(volume) = state.volume {
+        audio.set_volume(volume);
+    }
+    audio.set_muted(state.muted);
+    let result = with_recording(settings, audio, frontend);
+
+    let state = State {
+        volume: Some(audio.volume()),
+        muted: audio.is_muted(),
+    };
+    if let Err(e) = state.save() {
+        warn!("Failed to save the volume: {}", e);
+    }
+    result
+}
+
+/// Runs `frontend` with `audio`, also recording it to a WAV file when `audio.record` is set.
+fn with_recording<F>(
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+    frontend: F,
+) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn AudioSink) -> Result<(), Error>,
+{
+    let Some(path) = &settings.audio.record else {
+        return frontend(audio);
+    };
+    info!("Recording audio to {}", path);
+    let mut recorder = WavRecorder::create(Path::new(path), &settings.audio, audio)?;
+    let result = frontend(&mut recorder);
+    let finished = recorder.finish();
+    result.and(finished)
+}
+
+#[cfg(feature = "sdl")]
+fn run_sdl(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+) -> Result<(), Error> {
+    use chip8::core::chip8::{REFRESH_RATE, SCREEN_HEIGHT, SCREEN_WIDTH};
+    use chip8::core::controller;
+    use chip8::core::pacer::FramePacer;
+    use chip8::core::threaded;
+    use chip8::core::touch::TouchPad;
+    use display::sdl::audio::SdlBuzzer;
+    use display::sdl::context::SdlContext;
+    use display::sdl::controller::Controller;
+    use display::sdl::gamepad::Gamepads;
+    use display::sdl::input::SdlInput;
+    use display::sdl::window::CustomWindow;
+
+    let sdl = SdlContext::init()?;
+    let mut input = SdlInput::new(&sdl)?;
+    if settings.rotate_keys {
+        input.set_key_rotation(settings.rotation);
+    }
+    let mut window = CustomWindow::new(
+        &sdl,
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+        settings.scale,
+        &settings.title,
+        settings.palette.clone(),
+        settings.rotation,
+    );
+    input.set_turbo(settings.turbo_for(emulator.get_rom_name()))?;
+    let mut gamepad = settings.gamepad.clone();
+    if let Some(player) = settings.active_player() {
+        input.set_player(player)?;
+        gamepad.device = player.gamepad.clone().or(gamepad.device);
+    }
+    if gamepad.enable {
+        match Gamepads::new(&sdl, &gamepad) {
+            Ok(gamepads) => input.set_gamepads(gamepads),
+            Err(e) => warn!("{}, using the keyboard only", e),
+        }
+    }
+    if settings.touch.enable {
+        input.set_touch_pad(
+            TouchPad::new(&settings.touch)?,
+            window.canvas.window().size(),
+        );
+    }
+    let texture_creator = window.canvas.texture_creator();
+    let mut display = Controller::new(&mut window, &texture_creator, settings)?;
+    let audio = match audio_backend(settings) {
+        AudioBackend::Sdl => SdlBuzzer::new(&sdl, &settings.audio)
+            .map(|buzzer| Some(Box::new(buzzer) as Box<dyn AudioSink>)),
+        _ => open_audio(settings),
+    };
+
+    with_audio(settings, audio, |audio| {
+        if let Some(scope) = audio.scope() {
+            display.set_scope(scope);
+        }
+        if !settings.single_thread {
+            return threaded::run(
+                emulator,
+                cpu,
+                settings.cycles_per_frame,
+                &mut display,
+                &mut input,
+                audio,
+            );
+        }
+        let mut pacer = FramePacer::new(REFRESH_RATE);
+        loop {
+            let frames = pacer.advance();
+            let running = controller::run_frames(
+                emulator,
+                cpu,
+                settings.cycles_per_frame,
+                frames,
+                &mut display,
+                &mut input,
+                audio,
+            )?;
+            if !running {
+                return Ok(());
+            }
+            pacer.wait();
+        }
+    })
+}
+
+#[cfg(feature = "terminal")]
+fn run_terminal(
+    emulator: &mut Emulator,
+    cpu: &CpuController,
+    settings: &ChipSettings,
+    audio: &mut dyn AudioSink,
+) -> Result<(), Error> {
+    use chip8::core::{controller, threaded};
+    use display_term::input::TermInput;
+    use display_term::renderer::TermRenderer;
+    use display_term::terminal::TerminalGuard;
+    use shared::config::config::Rotation;
+
+    if settings.rotation != Rotation::Deg0 {
+        warn!("The terminal backend does not support screen rotation, ignoring it");
+    }
+    if settings.motion_blur {
+        warn!("The terminal backend does not support motion blur, ignoring it");
+    }
+    let terminal = TerminalGuard::enter()?;
+    let mut input = TermInput::new(terminal.key_releases());
+    input.set_keyboard_layout(settings.keyboard_layout);
+    if let Some(player) = settings.active_player() {
+        input.set_player(player)?;
+    }
+    let mut display = TermRenderer::new(settings.palette.clone(), settings.terminal_mode);
+    display.set_visual_bell(settings.visual_bell);
+
+    let run = if settings.single_thread {
+        controller::run
+    } else {
+        threaded::run
+    };
+    run(
+        emulator,
+        cpu,
+        settings.cycles_per_frame,
+        &mut display,
+        &mut input,
+        audio,
+    )
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use chip8::core::audio::{AudioSink, Silence};
+use chip8::core::chip8::CHIP8;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::gallery::GALLERY;
+use chip8::core::replay::Quirks;
+use shared::config::config::{ChipSettings, Config};
+use shared::config::environment::Environment;
+use shared::config::profile::rom_hash;
+use tracing::{info, warn};
+use winit::event_loop::EventLoop;
+
+#[cfg(target_os = "android")]
+use winit::platform::android::activity::AndroidApp;
+
+/// The development config, written to the app's storage on the first start so it can be
+/// edited there, e.g. with `adb shell run-as`.
+const DEFAULT_CONFIG: &str = include_str!("../../../../config/development.yaml");
+
+/// Loads the config from `folder`, writing the bundled one there first when it is missing.
+pub fn load_config(folder: &Path) -> Result<Config, Error> {
+    let env = Environment::Development;
+    let path = folder.join(format!("{env}.yaml"));
+    if !path.exists() {
+        fs::create_dir_all(folder)
+            .and_then(|_| fs::write(&path, DEFAULT_CONFIG))
+            .map_err(|e| anyhow!("Failed to write the config {}: {}", path.display(), e))?;
+    }
+    Config::from_folder(&env, folder)
+        .map_err(|e| anyhow!("Failed to load the config {}: {}", path.display(), e))
+}
+
+/// Loads `rom.ch8` from `folder`, pushed there with `adb`, or the first gallery ROM when
+/// there is none.
+pub fn load_rom(emulator: &mut Emulator, folder: &Path) -> Result<(), Error> {
+    let path = folder.join("rom.ch8");
+    match fs::read(&path) {
+        Ok(bytes) => {
+            info!("Loading the ROM {}", path.display());
+            emulator.init_ram_from_bytes(&bytes)?;
+            emulator.set_rom_name(Some("rom".to_string()));
+        }
+        Err(_) => {
+            let rom = GALLERY
+                .first()
+                .ok_or_else(|| anyhow!("No gallery ROM to start with"))?;
+            info!("Loading the gallery ROM {}: {}", rom.name, rom.description);
+            emulator.init_ram_from_bytes(rom.bytes)?;
+            emulator.set_rom_name(Some(rom.name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cpal")]
+fn open_audio(settings: &ChipSettings) -> Result<Box<dyn AudioSink>, Error> {
+    Ok(Box::new(audio_cpal::buzzer::CpalBuzzer::new(
+        &settings.audio,
+    )?))
+}
+
+#[cfg(not(feature = "cpal"))]
+fn open_audio(_settings: &ChipSettings) -> Result<Box<dyn AudioSink>, Error> {
+    Err(anyhow!(
+        "Sound is not compiled into this build, enable the cpal cargo feature"
+    ))
+}
+
+/// Runs the emulator on `event_loop` with its config, ROM, autosave and save slots kept in
+/// `folder`, the app's internal storage on Android.
+pub fn run(event_loop: EventLoop<()>, folder: &Path) -> Result<(), Error> {
+    let config = load_config(&folder.join("config"))?;
+    let settings = &config.chip8;
+    let mut emulator = Emulator::new(CHIP8::default());
+    load_rom(&mut emulator, folder)?;
+    emulator.set_quirks(Quirks::from(settings));
+    emulator.set_save_slot_folder(Some(folder.join(&settings.save_slot_folder)));
+    let autosave = folder
+        .join(&settings.autosave_folder)
+        .join(format!("{}.c8s", rom_hash(emulator.get_rom())));
+
+    let mut audio = open_audio(settings).unwrap_or_else(|e| {
+        warn!("Running without sound: {:?}", e);
+        Box::new(Silence)
+    });
+    display_mobile::window::run_with(
+        event_loop,
+        &mut emulator,
+        &CpuController,
+        settings,
+        audio.as_mut(),
+        Some(autosave),
+    )
+}
+
+#[cfg(target_os = "android")]
+#[no_mangle]
+fn android_main(app: AndroidApp) {
+    use winit::platform::android::EventLoopBuilderExtAndroid;
+
+    android_logger::init_once(
+        android_logger::Config::default()
+            .with_max_level(log::LevelFilter::Info)
+            .with_tag("chip8"),
+    );
+    let Some(folder) = app.internal_data_path() else {
+        log::error!("No internal storage to keep the config and saves in");
+        return;
+    };
+    let result = EventLoop::builder()
+        .with_android_app(app)
+        .build()
+        .map_err(|e| anyhow!("Failed to create event loop: {}", e))
+        .and_then(|event_loop| run(event_loop, &folder));
+    if let Err(e) = result {
+        log::error!("{:?}", e);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Error};
+use chip8::core::audio::{self, AudioSink};
+use shared::config::config::Audio;
+use web_sys::{AudioContext, GainNode, OscillatorType};
+
+const BUZZER_FREQUENCY: f32 = 440.0;
+const BUZZER_VOLUME: f32 = 0.1;
+
+/// WebAudio buzzer: a square oscillator that runs continuously behind a gain node which is
+/// opened while the sound timer is non-zero.
+pub struct Buzzer {
+    context: AudioContext,
+    gain: GainNode,
+    active: bool,
+    volume: u8,
+    muted: bool,
+}
+
+impl Buzzer {
+    pub fn new() -> Result<Self, Error> {
+        let context =
+            AudioContext::new().map_err(|e| anyhow!("Failed to create audio context: {:?}", e))?;
+        let oscillator = context
+            .create_oscillator()
+            .map_err(|e| anyhow!("Failed to create oscillator: {:?}", e))?;
+        let gain = context
+            .create_gain()
+            .map_err(|e| anyhow!("Failed to create gain node: {:?}", e))?;
+
+        oscillator.set_type(OscillatorType::Square);
+        oscillator.frequency().set_value(BUZZER_FREQUENCY);
+        gain.gain().set_value(0.0);
+        oscillator
+            .connect_with_audio_node(&gain)
+            .and_then(|_| gain.connect_with_audio_node(&context.destination()))
+            .map_err(|e| anyhow!("Failed to connect audio nodes: {:?}", e))?;
+        oscillator
+            .start()
+            .map_err(|e| anyhow!("Failed to start oscillator: {:?}", e))?;
+
+        Ok(Self {
+            context,
+            gain,
+            active: false,
+            volume: Audio::default().volume,
+            muted: false,
+        })
+    }
+
+    /// Browsers keep audio suspended until a user gesture, call this from an input handler.
+    pub fn resume(&self) {
+        let _ = self.context.resume();
+    }
+
+    fn update_gain(&self) {
+        let gain = if self.active {
+            BUZZER_VOLUME * audio::gain(self.volume, self.muted)
+        } else {
+            0.0
+        };
+        self.gain.gain().set_value(gain);
+    }
+}
+
+/// The tone is a WebAudio oscillator, pushed samples are not played.
+impl AudioSink for Buzzer {
+    fn set_beep(&mut self, on: bool) {
+        if self.active != on {
+            self.active = on;
+            self.update_gain();
+        }
+    }
+
+    fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    fn set_volume(&mut self, volume: u8) {
+        self.volume = volume.min(100);
+        self.update_gain();
+    }
+
+    fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.update_gain();
+    }
+}
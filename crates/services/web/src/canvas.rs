@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Error};
+use chip8::core::blend::{self, FrameBlend};
+use chip8::core::chip8::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8::core::controller::{DisplayController, Hotkey, Status};
+use shared::config::config::Palette;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+const BYTES_PER_PIXEL: usize = 4;
+const TITLE: &str = "Chip-8 Emulator";
+/// CSS outline of the canvas while the visual bell rings.
+const BELL_OUTLINE: &str = "4px solid #ff3030";
+
+/// Draws the framebuffer into a 64x32 canvas, the page scales it up with CSS.
+pub struct CanvasDisplay {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    pixels: Vec<u8>,
+    palette: Palette,
+    blend: Option<FrameBlend>,
+    visual_bell: bool,
+    buzzing: bool,
+    needs_repaint: bool,
+}
+
+impl CanvasDisplay {
+    pub fn new(canvas_id: &str, palette: Palette) -> Result<Self, Error> {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(canvas_id))
+            .ok_or_else(|| anyhow!("No element with id '{}' in the page", canvas_id))?
+            .dyn_into::<HtmlCanvasElement>()
+            .map_err(|_| anyhow!("Element '{}' is not a canvas", canvas_id))?;
+        canvas.set_width(SCREEN_WIDTH as u32);
+        canvas.set_height(SCREEN_HEIGHT as u32);
+
+        let context = canvas
+            .get_context("2d")
+            .map_err(|e| anyhow!("Failed to get the canvas 2d context: {:?}", e))?
+            .ok_or_else(|| anyhow!("Canvas 2d context is not available"))?
+            .dyn_into::<CanvasRenderingContext2d>()
+            .map_err(|_| anyhow!("Unexpected canvas context type"))?;
+
+        Ok(Self {
+            canvas,
+            context,
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * BYTES_PER_PIXEL],
+            palette,
+            blend: None,
+            visual_bell: false,
+            buzzing: false,
+            needs_repaint: true,
+        })
+    }
+
+    /// Forces a full repaint on the next frame, e.g. after a new ROM was loaded.
+    pub fn invalidate(&mut self) {
+        self.needs_repaint = true;
+    }
+
+    /// Averages every frame with the previous one to smooth XOR flicker.
+    pub fn set_motion_blur(&mut self, enable: bool) {
+        self.blend = enable.then(FrameBlend::default);
+        self.needs_repaint = true;
+    }
+
+    /// Outlines the canvas while the buzzer sounds.
+    pub fn set_visual_bell(&mut self, enable: bool) {
+        self.visual_bell = enable;
+        if !enable {
+            let _ = self.show_sound(false);
+        }
+    }
+
+    fn paint_pixel(&mut self, display: &[u8], index: usize) {
+        let colors = self.palette.colors();
+        let color = match &self.blend {
+            Some(blend) => blend.color(&colors, index),
+            None => colors[display[index] as usize],
+        };
+        let offset = index * BYTES_PER_PIXEL;
+        self.pixels[offset..offset + BYTES_PER_PIXEL]
+            .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+    }
+}
+
+impl DisplayController for CanvasDisplay {
+    fn present(&mut self, display: &[u8], dirty: &[usize]) -> Result<(), Error> {
+        if self.needs_repaint {
+            // The blend history may belong to a previous ROM
+            if let Some(blend) = self.blend.as_mut() {
+                blend.sync(display);
+            }
+        }
+        let dirty = blend::repaint_list(self.blend.as_mut(), display, dirty);
+        if self.needs_repaint {
+            for index in 0..display.len() {
+                self.paint_pixel(display, index);
+            }
+            self.needs_repaint = false;
+        } else if dirty.is_empty() {
+            return Ok(());
+        } else {
+            for &index in dirty.iter() {
+                self.paint_pixel(display, index);
+            }
+        }
+
+        let image = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(&self.pixels),
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+        )
+        .map_err(|e| anyhow!("Failed to create image data: {:?}", e))?;
+        self.context
+            .put_image_data(&image, 0.0, 0.0)
+            .map_err(|e| anyhow!("Failed to draw to the canvas: {:?}", e))
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) -> Result<(), Error> {
+        if hotkey == Hotkey::CyclePalette {
+            self.palette.cycle_preset();
+            self.needs_repaint = true;
+        }
+        Ok(())
+    }
+
+    /// Shows the ROM name and state in the page title.
+    fn show_status(&mut self, status: &Status) -> Result<(), Error> {
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            document.set_title(&status.caption(TITLE));
+        }
+        Ok(())
+    }
+
+    fn show_sound(&mut self, on: bool) -> Result<(), Error> {
+        let on = on && self.visual_bell;
+        if on != self.buzzing {
+            self.buzzing = on;
+            self.canvas
+                .style()
+                .set_property("outline", if on { BELL_OUTLINE } else { "none" })
+                .map_err(|e| anyhow!("Failed to style the canvas: {:?}", e))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+pub mod audio;
+pub mod canvas;
+
+use chip8::core::audio::{AudioSink, Silence};
+use chip8::core::chip8::CHIP8;
+use chip8::core::controller;
+use chip8::core::cpu::CpuController;
+use chip8::core::emulator::Emulator;
+use chip8::core::input::{map_hotkey, map_key, EventQueue};
+use chip8::core::touch::TouchPad;
+use shared::config::config::{Palette, Touch};
+use wasm_bindgen::prelude::*;
+
+use crate::audio::Buzzer;
+use crate::canvas::CanvasDisplay;
+
+/// Instructions per frame, about 600 per second at 60 frames per second.
+const DEFAULT_CYCLES_PER_FRAME: u32 = 10;
+
+/// Converts a `KeyboardEvent.code` to the character printed on it in the US layout,
+/// e.g. "KeyQ" -> 'Q'.
+fn key_char(code: &str) -> Option<char> {
+    let key = code
+        .strip_prefix("Key")
+        .or_else(|| code.strip_prefix("Digit"))?;
+    let mut chars = key.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// Emulator handle exported to JavaScript. The page owns the animation loop and calls
+/// `frame` 60 times per second, keyboard events are forwarded with `key_down`/`key_up`.
+#[wasm_bindgen]
+pub struct WebEmulator {
+    emulator: Emulator,
+    cpu: CpuController,
+    display: CanvasDisplay,
+    input: EventQueue,
+    touch: TouchPad,
+    buzzer: Option<Buzzer>,
+    cycles_per_frame: u32,
+    running: bool,
+}
+
+#[wasm_bindgen]
+impl WebEmulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas_id: &str) -> Result<WebEmulator, JsError> {
+        let display = CanvasDisplay::new(canvas_id, Palette::default())
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Self {
+            emulator: Emulator::new(CHIP8::default()),
+            cpu: CpuController,
+            display,
+            input: EventQueue::default(),
+            touch: TouchPad::new(&Touch::default()).map_err(|e| JsError::new(&e.to_string()))?,
+            // Sound is optional, the emulator still runs where WebAudio is unavailable.
+            buzzer: Buzzer::new().ok(),
+            cycles_per_frame: DEFAULT_CYCLES_PER_FRAME,
+            running: false,
+        })
+    }
+
+    /// Resets the machine and loads a ROM read from a file input or fetched by the page,
+    /// `name` is shown in the page title.
+    pub fn load_rom(&mut self, rom: &[u8], name: Option<String>) -> Result<(), JsError> {
+        let mut emulator = Emulator::new(CHIP8::default());
+        emulator
+            .init_ram_from_bytes(rom)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        emulator.set_rom_name(name);
+        self.emulator = emulator;
+        self.display.invalidate();
+        self.running = true;
+        Ok(())
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles_per_frame: u32) {
+        self.cycles_per_frame = cycles_per_frame;
+    }
+
+    pub fn set_motion_blur(&mut self, enable: bool) {
+        self.display.set_motion_blur(enable);
+    }
+
+    /// Outlines the canvas while the buzzer sounds, for players who can't hear it.
+    pub fn set_visual_bell(&mut self, enable: bool) {
+        self.display.set_visual_bell(enable);
+    }
+
+    /// Takes a `KeyboardEvent.code`, returns whether the key is used by the emulator so the
+    /// page can prevent its default action.
+    pub fn key_down(&mut self, code: &str, repeat: bool) -> bool {
+        if let Some(buzzer) = &self.buzzer {
+            buzzer.resume();
+        }
+        self.push_key(code, true, repeat)
+    }
+
+    pub fn key_up(&mut self, code: &str) -> bool {
+        self.push_key(code, false, false)
+    }
+
+    /// Replaces the 4x4 touch keypad, one string per row with a hex digit or `.` per cell.
+    pub fn set_touch_layout(&mut self, rows: Vec<String>) -> Result<(), JsError> {
+        let settings = Touch {
+            enable: true,
+            layout: rows,
+        };
+        self.touch = TouchPad::new(&settings).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(())
+    }
+
+    /// Pointer events over the canvas, `x` and `y` are fractions of its size. Each
+    /// `PointerEvent.pointerId` holds its own key, so multi-touch presses chorded keys.
+    pub fn pointer_down(&mut self, id: i32, x: f32, y: f32) {
+        if let Some(buzzer) = &self.buzzer {
+            buzzer.resume();
+        }
+        self.touch.press(id as i64, x, y, &mut self.input);
+    }
+
+    pub fn pointer_move(&mut self, id: i32, x: f32, y: f32) {
+        self.touch.motion(id as i64, x, y, &mut self.input);
+    }
+
+    pub fn pointer_up(&mut self, id: i32) {
+        self.touch.release(id as i64, &mut self.input);
+    }
+
+    /// Runs one frame of emulation and redraws the canvas.
+    pub fn frame(&mut self) -> Result<(), JsError> {
+        if !self.running {
+            return Ok(());
+        }
+        let audio: &mut dyn AudioSink = match self.buzzer.as_mut() {
+            Some(buzzer) => buzzer,
+            None => &mut Silence,
+        };
+        controller::run_frame(
+            &mut self.emulator,
+            &self.cpu,
+            self.cycles_per_frame,
+            &mut self.display,
+            &mut self.input,
+            audio,
+        )
+        .map_err(|e| {
+            self.running = false;
+            JsError::new(&e.to_string())
+        })?;
+        Ok(())
+    }
+
+    fn push_key(&mut self, code: &str, pressed: bool, repeat: bool) -> bool {
+        let Some(key) =
+            key_char(code).filter(|&key| map_key(key).is_some() || map_hotkey(key).is_some())
+        else {
+            return false;
+        };
+        self.input.push_key(key, pressed, repeat);
+        true
+    }
+}